@@ -27,6 +27,7 @@
           "src"
           "themes"
           "syntaxes"
+          "fonts"
         ];
 
         buildSrc = flakeboxLib.filterSubPaths {
@@ -0,0 +1,75 @@
+#!/usr/bin/env python3
+
+# Generates src/markdown/emoji/shortcodes.rs out of the tables in this directory:
+#
+# * github.tsv: shortcodes named the way GitHub names them. These take precedence.
+# * rich-13.7.1.tsv: the table shipped with rich 13.7.1, which contains older GitHub names and Unicode CLDR names.
+#
+# Emojis that are displayed as text by default get a U+FE0F variation selector so they're displayed as emojis, the
+# same way GitHub does it. This needs the `regex` package to look up the emoji presentation property.
+#
+# Usage: python3 scripts/emoji/generate.py > src/markdown/emoji/shortcodes.rs
+
+import os
+import re
+import sys
+import unicodedata
+
+import regex
+
+DIRECTORY = os.path.dirname(os.path.abspath(__file__))
+VALID_NAME = re.compile(r"[a-z0-9+\-][a-z0-9_+\-]*")
+EMOJI = regex.compile(r"\p{Emoji}")
+EMOJI_PRESENTATION = regex.compile(r"\p{Emoji_Presentation}")
+VARIATION_SELECTOR = "\ufe0f"
+
+
+def read_table(file_name: str):
+    with open(os.path.join(DIRECTORY, file_name), encoding="utf-8") as table:
+        for line in table:
+            line = line.rstrip("\n")
+            if line and not line.startswith("#"):
+                name, emoji = line.split("\t")
+                yield name, emoji
+
+
+def normalize_name(name: str) -> str:
+    # e.g. "côte_d’ivoire" -> "cote_divoire", "st._kitts_&_nevis" -> "st_kitts_and_nevis".
+    name = unicodedata.normalize("NFKD", name).encode("ascii", "ignore").decode()
+    name = name.replace("&", "and")
+    name = re.sub(r"[().!'’,:]", "", name)
+    return re.sub("_+", "_", name).lower()
+
+
+def with_emoji_presentation(emoji: str) -> str:
+    if len(emoji) == 1 and EMOJI.fullmatch(emoji) and not EMOJI_PRESENTATION.fullmatch(emoji):
+        return emoji + VARIATION_SELECTOR
+    return emoji
+
+
+def main():
+    shortcodes = {}
+    for name, emoji in read_table("github.tsv"):
+        if not VALID_NAME.fullmatch(name):
+            sys.exit(f"invalid GitHub shortcode: {name}")
+        shortcodes[name] = emoji
+    for name, emoji in read_table("rich-13.7.1.tsv"):
+        name = normalize_name(name)
+        # Entries that don't make for a valid shortcode, like "__1", are skipped rather than mangled into one.
+        if VALID_NAME.fullmatch(name):
+            shortcodes.setdefault(name, emoji)
+
+    print("//! The emoji shortcodes we know about.")
+    print("//!")
+    print("//! GitHub's names are used where there is one, along with each emoji's Unicode CLDR name.")
+    print("//!")
+    print("//! This file is generated by `scripts/emoji/generate.py`, don't edit it by hand.")
+    print()
+    print("/// Every shortcode and the emoji it represents, sorted by shortcode.")
+    print("pub(super) const SHORTCODES: &[(&str, &str)] = &[")
+    for name in sorted(shortcodes):
+        print(f'    ("{name}", "{with_emoji_presentation(shortcodes[name])}"),')
+    print("];")
+
+
+main()
@@ -1,31 +1,152 @@
 use crate::{
-    execute::{CodeExecuter, ExecutionHandle, ExecutionState, ProcessStatus},
+    execute::{CodeExecuter, ExecutionHandle, ExecutionState, ProcessExit, ProcessStatus},
+    input::source::WidgetInput,
     markdown::{
         elements::{
-            Code, ListItem, ListItemType, MarkdownElement, ParagraphElement, SourcePosition, StyledText, Table,
-            TableRow, Text,
+            Code, CodeLanguage, ColumnAlignment, LineGroup, ListItem, ListItemType, MarkdownElement, ParagraphElement,
+            SourcePosition, StyledText, Table, TableRow, Text, ThematicBreakMarker,
         },
-        text::{WeightedLine, WeightedText},
+        parse::{LineBreaks, MarkdownParser, ParseError},
+        text::{char_width, TextWidth, WeightedLine, WeightedText},
     },
     presentation::{
-        AsRenderOperations, MarginProperties, PreformattedLine, Presentation, PresentationMetadata,
-        PresentationThemeMetadata, RenderOnDemand, RenderOnDemandState, RenderOperation, Slide, SlideChunk,
+        AsRenderOperations, ChunkMutator, ClosingSlideMetadata, ColumnLayout, ImagePosition, MarginProperties,
+        PreformattedLine, Presentation, PresentationMetadata, PresentationThemeMetadata, RenderOnDemand,
+        RenderOnDemandState, RenderOperation, Slide, SlideChunk, SlideNumbering, TerminalSnippet,
     },
     render::{
-        highlighting::{CodeHighlighter, CodeLine},
+        ansi::AnsiSplitter,
+        chart::{Chart, ChartLine, ChartPart, ParseChartError},
+        figlet::big_text,
+        highlighting::{CodeHighlighter, CodeLine, LineStyle},
         properties::WindowSize,
+        qr::QrCode,
+        text::{styled_line_width, wrap_styled_line},
     },
-    resource::{LoadImageError, Resources},
-    style::{Colors, TextStyle},
-    theme::{Alignment, AuthorPositioning, ElementType, FooterStyle, LoadThemeError, Margin, PresentationTheme},
+    resource::{LoadFileError, LoadImageError, Resources},
+    style::{hyperlinks_supported, Color, Colors, TextStyle},
+    theme::{
+        Alignment, AuthorPositioning, ColumnLayoutStyle, ElementType, ExecutionOutputBlockStyle, FooterStyle,
+        LineHighlightMode, LoadThemeError, Margin, OutputOverflow, PresentationTheme, ProgressBarPosition, TableBorder,
+        VerticalAlignment,
+    },
+    third_party::{RenderRequest, ThirdPartyRender, ThirdPartyRenderError},
+    timer::{parse_duration, CountdownTimer},
+    typewriter::Typewriter,
+    variables::{current_time, Variables},
 };
-use itertools::Itertools;
+use comrak::Arena;
 use serde::Deserialize;
-use std::{borrow::Cow, cell::RefCell, fmt::Display, iter, mem, path::PathBuf, rc::Rc, str::FromStr};
-use unicode_width::UnicodeWidthStr;
+use std::{
+    borrow::Cow,
+    cell::{Cell, RefCell},
+    collections::{hash_map::DefaultHasher, HashMap},
+    env,
+    fmt::Display,
+    fs,
+    hash::{Hash, Hasher},
+    iter, mem,
+    path::{Path, PathBuf},
+    rc::Rc,
+    str::FromStr,
+    time::{Duration, Instant},
+};
 
 // TODO: move to a theme config.
 static DEFAULT_BOTTOM_SLIDE_MARGIN: u16 = 3;
+static ELISION_MARKER: &str = "…\n";
+static BACKGROUND_IMAGE_BRIGHTNESS: f64 = 0.35;
+static SPINNER_FRAMES: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+/// Options that affect how a presentation is built.
+#[derive(Clone, Debug)]
+pub struct PresentationBuilderOptions {
+    /// The timeout to use for executed code that doesn't specify one.
+    pub execution_timeout: Option<Duration>,
+
+    /// The directory to run executed code that doesn't specify one from.
+    pub execution_working_directory: Option<PathBuf>,
+
+    /// The executer used to run code marked for execution.
+    pub code_executer: Rc<CodeExecuter>,
+
+    /// Renders code marked to be rendered into images.
+    pub third_party: Rc<ThirdPartyRender>,
+
+    /// Whether to replace emoji shortcodes in included markdown files.
+    pub emoji_shortcodes: bool,
+
+    /// Whether to use typographic punctuation in included markdown files.
+    pub smart_punctuation: bool,
+
+    /// How single line breaks within paragraphs in included markdown files are handled.
+    pub line_breaks: LineBreaks,
+
+    /// Whether to render links as hyperlinks the terminal can open.
+    ///
+    /// If this isn't set, they're used only if the terminal supports them. Otherwise the URL they point to is
+    /// displayed instead.
+    pub hyperlinks: Option<bool>,
+
+    /// The thematic breaks that end the current slide rather than rendering a separator.
+    pub slide_separators: Vec<ThematicBreakMarker>,
+
+    /// The heading level at or above which headings start a new slide, using the heading as its title.
+    pub slide_level: Option<u8>,
+
+    /// Whether to add a pause in between every top level item in lists.
+    pub incremental_lists: bool,
+
+    /// The time after which the chunk following a pause is revealed automatically.
+    ///
+    /// If this isn't set, chunks are only revealed when moving forward in the presentation.
+    pub reveal_delay: Option<Duration>,
+
+    /// The time after which the next item in an incremental list is revealed automatically.
+    ///
+    /// If this isn't set, `reveal_delay` is used.
+    pub list_reveal_delay: Option<Duration>,
+
+    /// Whether slides that use the `skip_slide` command are left out of the presentation.
+    ///
+    /// This is meant to be set only when presenting so skipped slides can still be worked on.
+    pub skip_slides: bool,
+}
+
+impl Default for PresentationBuilderOptions {
+    fn default() -> Self {
+        Self {
+            execution_timeout: None,
+            execution_working_directory: None,
+            code_executer: Default::default(),
+            third_party: Default::default(),
+            emoji_shortcodes: true,
+            smart_punctuation: false,
+            line_breaks: LineBreaks::default(),
+            hyperlinks: None,
+            slide_separators: Vec::new(),
+            slide_level: None,
+            incremental_lists: false,
+            reveal_delay: None,
+            list_reveal_delay: None,
+            skip_slides: false,
+        }
+    }
+}
+
+/// Keeps the state of executed code so it survives reloading a presentation.
+///
+/// Code is identified by a hash of its contents so any modifications to it will cause its state to be discarded.
+#[derive(Default)]
+pub(crate) struct ExecutionCache {
+    entries: HashMap<ExecutionKey, Rc<RefCell<RunCodeOperationInner>>>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+struct ExecutionKey {
+    hash: u64,
+    occurrence: usize,
+}
 
 /// Builds a presentation.
 ///
@@ -34,12 +155,29 @@ static DEFAULT_BOTTOM_SLIDE_MARGIN: u16 = 3;
 pub(crate) struct PresentationBuilder<'a> {
     slide_chunks: Vec<SlideChunk>,
     chunk_operations: Vec<RenderOperation>,
+    chunk_code_blocks: Vec<String>,
+    chunk_terminal_snippets: Vec<TerminalSnippet>,
+    chunk_mutators: Vec<Rc<dyn ChunkMutator>>,
+    chunk_links: Vec<String>,
     slides: Vec<Slide>,
+    anchors: HashMap<String, usize>,
     highlighter: CodeHighlighter,
     theme: Cow<'a, PresentationTheme>,
+    slide_theme_backup: Option<(Cow<'a, PresentationTheme>, CodeHighlighter)>,
     resources: &'a mut Resources,
+    execution_cache: &'a mut ExecutionCache,
+    claimed_executions: HashMap<ExecutionKey, Rc<RefCell<RunCodeOperationInner>>>,
+    code_chains: HashMap<String, String>,
     slide_state: SlideState,
+    include_stack: Vec<PathBuf>,
     footer_context: Rc<RefCell<FooterContext>>,
+    section: String,
+    shows_clock: bool,
+    closing_slide: Option<ClosingSlideMetadata>,
+    variables: Variables,
+    code_executer: Rc<CodeExecuter>,
+    third_party: Rc<ThirdPartyRender>,
+    options: PresentationBuilderOptions,
 }
 
 impl<'a> PresentationBuilder<'a> {
@@ -48,16 +186,35 @@ impl<'a> PresentationBuilder<'a> {
         default_highlighter: CodeHighlighter,
         default_theme: &'a PresentationTheme,
         resources: &'a mut Resources,
+        execution_cache: &'a mut ExecutionCache,
+        options: PresentationBuilderOptions,
     ) -> Self {
         Self {
             slide_chunks: Vec::new(),
             chunk_operations: Vec::new(),
+            chunk_code_blocks: Vec::new(),
+            chunk_terminal_snippets: Vec::new(),
+            chunk_mutators: Vec::new(),
+            chunk_links: Vec::new(),
             slides: Vec::new(),
+            anchors: Default::default(),
             highlighter: default_highlighter,
             theme: Cow::Borrowed(default_theme),
+            slide_theme_backup: None,
             resources,
+            execution_cache,
+            claimed_executions: Default::default(),
+            code_chains: Default::default(),
             slide_state: Default::default(),
+            include_stack: Default::default(),
             footer_context: Default::default(),
+            section: String::new(),
+            shows_clock: false,
+            closing_slide: None,
+            variables: Variables::new(&Default::default()),
+            code_executer: options.code_executer.clone(),
+            third_party: options.third_party.clone(),
+            options,
         }
     }
 
@@ -71,6 +228,26 @@ impl<'a> PresentationBuilder<'a> {
         if self.chunk_operations.is_empty() {
             self.push_slide_prelude();
         }
+        self.process_elements(elements)?;
+        if !self.chunk_operations.is_empty() || !self.slide_chunks.is_empty() {
+            self.terminate_slide();
+        }
+        if self.slides.is_empty() {
+            // Every slide was skipped so display an empty one instead.
+            self.terminate_slide();
+        }
+        if let Some(metadata) = self.closing_slide.take() {
+            self.push_closing_slide(metadata);
+        }
+        self.footer_context.borrow_mut().total_slides = self.slides.len();
+        // Anything that wasn't claimed belongs to code that's no longer part of the presentation.
+        self.execution_cache.entries = mem::take(&mut self.claimed_executions);
+
+        let presentation = Presentation::new(self.slides).with_anchors(self.anchors).with_clock(self.shows_clock);
+        Ok(presentation)
+    }
+
+    fn process_elements(&mut self, elements: Vec<MarkdownElement>) -> Result<(), BuildError> {
         for element in elements {
             self.slide_state.ignore_element_line_break = false;
             self.process_element(element)?;
@@ -79,13 +256,7 @@ impl<'a> PresentationBuilder<'a> {
                 self.push_line_break();
             }
         }
-        if !self.chunk_operations.is_empty() || !self.slide_chunks.is_empty() {
-            self.terminate_slide();
-        }
-        self.footer_context.borrow_mut().total_slides = self.slides.len();
-
-        let presentation = Presentation::new(self.slides);
-        Ok(presentation)
+        Ok(())
     }
 
     fn validate_last_operation(&mut self) -> Result<(), BuildError> {
@@ -99,55 +270,130 @@ impl<'a> PresentationBuilder<'a> {
             return Ok(());
         }
         self.slide_state.needs_enter_column = false;
-        let last_valid = matches!(last, RenderOperation::EnterColumn { .. } | RenderOperation::ExitLayout);
+        let last_valid = matches!(
+            last,
+            RenderOperation::EnterColumn { .. } | RenderOperation::ExitLayout | RenderOperation::ExitNestedLayout
+        );
         if last_valid { Ok(()) } else { Err(BuildError::NotInsideColumn) }
     }
 
     fn push_slide_prelude(&mut self) {
-        let colors = self.theme.default_style.colors.clone();
+        let style = &self.theme.default_style;
+        // Leave at least one row between the slide's contents and the footer.
+        let bottom_slide_margin = DEFAULT_BOTTOM_SLIDE_MARGIN.max(self.theme.footer.bottom_rows() + 1);
+        let top_rows = self.theme.footer.top_rows().max(1) + style.padding_top.unwrap_or(0) as u16;
         self.chunk_operations.extend([
-            RenderOperation::SetColors(colors),
+            RenderOperation::SetColors(style.colors.clone()),
             RenderOperation::ClearScreen,
             RenderOperation::ApplyMargin(MarginProperties {
-                horizontal_margin: self.theme.default_style.margin.clone().unwrap_or_default(),
-                bottom_slide_margin: DEFAULT_BOTTOM_SLIDE_MARGIN,
+                horizontal_margin: style.margin.clone().unwrap_or_default(),
+                max_width: style.max_width.clone(),
+                bottom_slide_margin: bottom_slide_margin + style.padding_bottom.unwrap_or(0) as u16,
             }),
         ]);
-        self.push_line_break();
+        for _ in 0..top_rows {
+            self.push_line_break();
+        }
     }
 
     fn process_element(&mut self, element: MarkdownElement) -> Result<(), BuildError> {
         let should_clear_last = !matches!(element, MarkdownElement::List(_) | MarkdownElement::Comment { .. });
+        let starts_slide = match &element {
+            MarkdownElement::FrontMatter(_) => false,
+            MarkdownElement::Comment { comment, .. } => {
+                let comment = comment.trim_start();
+                !Self::should_ignore_comment(comment)
+                    && !comment.starts_with("slide_theme:")
+                    && !comment.starts_with("speaker_note:")
+            }
+            _ => true,
+        };
+        let slide_has_content = self.slide_state.started || !self.slide_chunks.is_empty();
+        // This is set before processing the element so anything that ends the slide resets it.
+        if starts_slide {
+            self.slide_state.started = true;
+        }
+        let typewriter = match &element {
+            MarkdownElement::SetexHeading { .. }
+            | MarkdownElement::Heading { .. }
+            | MarkdownElement::Paragraph(_)
+            | MarkdownElement::List(_)
+            | MarkdownElement::BlockQuote { .. } => self.slide_state.typewriter.take(),
+            _ => None,
+        };
+        let start = (self.slides.len(), self.slide_chunks.len(), self.chunk_operations.len());
         match element {
             // This one is processed before everything else as it affects how the rest of the
             // elements is rendered.
             MarkdownElement::FrontMatter(_) => self.slide_state.ignore_element_line_break = true,
-            MarkdownElement::SetexHeading { text } => self.push_slide_title(text),
+            MarkdownElement::SetexHeading { text, level } | MarkdownElement::Heading { level, text }
+                if self.options.slide_level.is_some_and(|slide_level| level <= slide_level) =>
+            {
+                if slide_has_content {
+                    self.terminate_slide();
+                    self.slide_state.started = true;
+                }
+                self.push_slide_title(text);
+            }
+            MarkdownElement::SetexHeading { text, .. } => self.push_slide_title(text),
             MarkdownElement::Heading { level, text } => self.push_heading(level, text),
             MarkdownElement::Paragraph(elements) => self.push_paragraph(elements)?,
             MarkdownElement::List(elements) => self.push_list(elements),
-            MarkdownElement::Code(code) => self.push_code(code),
+            MarkdownElement::Code(code) => self.push_code(*code)?,
             MarkdownElement::Table(table) => self.push_table(table),
-            MarkdownElement::ThematicBreak => self.push_separator(),
+            MarkdownElement::ThematicBreak(marker) if self.options.slide_separators.contains(&marker) => {
+                self.terminate_slide();
+                self.slide_state.ignore_element_line_break = true;
+            }
+            MarkdownElement::ThematicBreak(_) => self.push_separator(),
             MarkdownElement::Comment { comment, source_position } => self.process_comment(comment, source_position)?,
-            MarkdownElement::BlockQuote(lines) => self.push_block_quote(lines),
-            MarkdownElement::Image { path, .. } => self.push_image(path)?,
+            MarkdownElement::BlockQuote { lines, attribution } => self.push_block_quote(lines, attribution),
+            MarkdownElement::Image { path, alt_text, .. } => self.push_image_element(path, alt_text)?,
         };
+        if let Some(speed) = typewriter {
+            self.push_typewriter(start, speed);
+        }
         if should_clear_last {
             self.slide_state.last_element = Default::default();
         }
         Ok(())
     }
 
+    fn push_typewriter(&mut self, start: (usize, usize, usize), speed: u32) {
+        let (slides, chunks, operations) = start;
+        // Elements that were split into several chunks, like lists with pauses in them, are displayed as they are.
+        if slides != self.slides.len() || chunks != self.slide_chunks.len() {
+            return;
+        }
+        let operations = self.chunk_operations.split_off(operations);
+        let typewriter = Typewriter::new(operations, speed);
+        self.chunk_operations.push(RenderOperation::RenderOnDemand(Rc::new(typewriter)));
+    }
+
     fn process_front_matter(&mut self, contents: &str) -> Result<(), BuildError> {
         let metadata: PresentationMetadata =
             serde_yaml::from_str(contents).map_err(|e| BuildError::InvalidMetadata(e.to_string()))?;
 
         self.footer_context.borrow_mut().author = metadata.author.clone().unwrap_or_default();
+        self.variables = Variables::new(&metadata);
+        self.footer_context.borrow_mut().variables = self.variables.clone();
         self.set_theme(&metadata.theme)?;
+        if let Some(incremental_lists) = metadata.options.incremental_lists {
+            self.options.incremental_lists = incremental_lists;
+        }
+        self.footer_context.borrow_mut().numbering = metadata.options.slide_numbers.clone();
+        self.closing_slide = metadata.closing_slide.clone();
+        if let Some(destination) = &metadata.execution.ssh {
+            let executer = self.code_executer.as_ref().clone().with_ssh_destination(destination.clone());
+            self.code_executer = Rc::new(executer);
+        }
         if metadata.title.is_some() || metadata.sub_title.is_some() || metadata.author.is_some() {
             self.push_slide_prelude();
+            if let Some(path) = &metadata.background_image {
+                self.push_background_image(path)?;
+            }
             self.push_intro_slide(metadata);
+            self.footer_context.borrow_mut().has_intro = true;
         }
         Ok(())
     }
@@ -174,11 +420,33 @@ impl<'a> PresentationBuilder<'a> {
         Ok(())
     }
 
+    fn set_slide_theme(&mut self, overrides: serde_yaml::Value) -> Result<(), BuildError> {
+        if self.slide_state.started || !self.slide_chunks.is_empty() {
+            return Err(BuildError::SlideThemeNotAtStart);
+        }
+        let overrides: PresentationTheme =
+            serde_yaml::from_value(overrides).map_err(|e| BuildError::InvalidSlideTheme(e.to_string()))?;
+        let theme = merge_struct::merge(self.theme.as_ref(), &overrides)
+            .map_err(|e| BuildError::InvalidSlideTheme(e.to_string()))?;
+        // The presentation's theme is restored once this slide is over.
+        let previous_theme = mem::replace(&mut self.theme, Cow::Owned(theme));
+        self.slide_theme_backup = Some((previous_theme, self.highlighter.clone()));
+        self.set_code_theme()?;
+
+        // Start the slide over so it uses this theme's colors and margins.
+        self.chunk_operations.clear();
+        self.push_slide_prelude();
+        Ok(())
+    }
+
     fn set_code_theme(&mut self) -> Result<(), BuildError> {
         if let Some(theme) = &self.theme.code.theme_name {
             let highlighter = CodeHighlighter::new(theme).map_err(|_| BuildError::InvalidCodeTheme)?;
             self.highlighter = highlighter;
         }
+        if let Some(color) = self.theme.code.background_color {
+            self.highlighter = self.highlighter.clone().with_background(color);
+        }
         Ok(())
     }
 
@@ -219,6 +487,31 @@ impl<'a> PresentationBuilder<'a> {
         self.terminate_slide();
     }
 
+    fn push_closing_slide(&mut self, metadata: ClosingSlideMetadata) {
+        let styles = &self.theme.closing_slide;
+        let message = StyledText::new(
+            metadata.message.unwrap_or_else(|| "Thanks!".into()),
+            TextStyle::default().bold().colors(styles.message.colors.clone()),
+        );
+        let author = Some(self.footer_context.borrow().author.clone()).filter(|author| !author.is_empty());
+        let details: Vec<_> = [author, metadata.handle, metadata.url]
+            .into_iter()
+            .flatten()
+            .map(|text| StyledText::new(text, TextStyle::default().colors(styles.details.colors.clone())))
+            .collect();
+        self.chunk_operations.push(RenderOperation::JumpToVerticalCenter);
+        self.push_text(Text::from(message), ElementType::ClosingSlideMessage);
+        self.push_line_break();
+        if !details.is_empty() {
+            self.push_line_break();
+        }
+        for text in details {
+            self.push_text(Text::from(text), ElementType::ClosingSlideDetails);
+            self.push_line_break();
+        }
+        self.terminate_slide();
+    }
+
     fn process_comment(&mut self, comment: String, source_position: SourcePosition) -> Result<(), BuildError> {
         if Self::should_ignore_comment(&comment) {
             return Ok(());
@@ -228,31 +521,50 @@ impl<'a> PresentationBuilder<'a> {
             Err(error) => return Err(BuildError::CommandParse { line: source_position.start.line + 1, error }),
         };
         match comment {
-            CommentCommand::Pause => self.process_pause(),
+            CommentCommand::Pause => self.process_pause(self.reveal_delay(false)),
             CommentCommand::EndSlide => self.terminate_slide(),
             CommentCommand::InitColumnLayout(columns) => {
-                Self::validate_column_layout(&columns)?;
-                self.slide_state.layout = LayoutState::InLayout { columns_count: columns.len() };
-                self.chunk_operations.push(RenderOperation::InitColumnLayout { columns });
-                self.slide_state.needs_enter_column = true;
-            }
-            CommentCommand::ResetLayout => {
-                self.slide_state.layout = LayoutState::Default;
-                self.chunk_operations.extend([RenderOperation::ExitLayout, RenderOperation::RenderLineBreak]);
+                self.init_column_layout(columns, self.theme.column_layout.clone())?
             }
-            CommentCommand::Column(column) => {
-                let (current_column, columns_count) = match self.slide_state.layout {
-                    LayoutState::InColumn { column, columns_count } => (Some(column), columns_count),
-                    LayoutState::InLayout { columns_count } => (None, columns_count),
-                    LayoutState::Default => return Err(BuildError::NoLayout),
-                };
-                if current_column == Some(column) {
-                    return Err(BuildError::AlreadyInColumn);
-                } else if column >= columns_count {
-                    return Err(BuildError::ColumnIndexTooLarge);
+            CommentCommand::ResetLayout => match self.slide_state.parent_layouts.pop() {
+                Some(parent) => {
+                    self.slide_state.layout = parent;
+                    self.chunk_operations.extend([RenderOperation::ExitNestedLayout, RenderOperation::RenderLineBreak]);
+                }
+                None => {
+                    self.slide_state.layout = LayoutState::Default;
+                    self.slide_state.regions.clear();
+                    self.chunk_operations.extend([RenderOperation::ExitLayout, RenderOperation::RenderLineBreak]);
                 }
-                self.slide_state.layout = LayoutState::InColumn { column, columns_count };
-                self.chunk_operations.push(RenderOperation::EnterColumn { column });
+            },
+            CommentCommand::JumpToMiddle => self.chunk_operations.push(RenderOperation::JumpToVerticalCenter),
+            CommentCommand::Alignment(alignment) => {
+                self.slide_state.alignment = Some(alignment.into());
+            }
+            CommentCommand::SkipSlide => self.slide_state.skip_slide = true,
+            CommentCommand::SlideTheme(overrides) => self.set_slide_theme(overrides)?,
+            CommentCommand::Include(path) => self.include_file(path)?,
+            CommentCommand::SpeakerNote(note) => self.slide_state.speaker_notes.push(note.trim_end().to_string()),
+            CommentCommand::Column(column) => self.enter_column(column)?,
+            CommentCommand::Layout(name) => self.apply_layout(&name)?,
+            CommentCommand::RevealDelay(milliseconds) => self.slide_state.reveal_delay = Some(milliseconds),
+            CommentCommand::ImageAlignment(position) => self.slide_state.image_position = Some(position),
+            CommentCommand::ImageFloat(float) => self.slide_state.image_float = Some(float),
+            CommentCommand::ImageCaption(enabled) => self.slide_state.image_caption = Some(enabled),
+            CommentCommand::BackgroundImage(path) => self.push_background_image(&path)?,
+            CommentCommand::Timer(duration) => self.push_timer(&duration)?,
+            CommentCommand::BigText(text) => self.push_big_text(&text),
+            CommentCommand::Qr(data) => self.push_qr_code(data.trim())?,
+            CommentCommand::Typewriter(speed) => self.slide_state.typewriter = Some(speed),
+            CommentCommand::VerticalAlignment(alignment) => self.slide_state.vertical_alignment = Some(alignment),
+            CommentCommand::Region(name) => {
+                let column = self
+                    .slide_state
+                    .regions
+                    .iter()
+                    .position(|region| region == &name)
+                    .ok_or(BuildError::UnknownRegion(name))?;
+                self.enter_column(column)?;
             }
         };
         // Don't push line breaks for any comments.
@@ -260,9 +572,40 @@ impl<'a> PresentationBuilder<'a> {
         Ok(())
     }
 
+    fn include_file(&mut self, path: PathBuf) -> Result<(), BuildError> {
+        let full_path = self.resources.base_path().join(self.resolve_path(&path));
+        let full_path = fs::canonicalize(&full_path).unwrap_or(full_path);
+        if self.include_stack.contains(&full_path) {
+            return Err(BuildError::IncludeCycle(path));
+        }
+        // This keeps track of the file so any changes to it trigger a reload.
+        let contents = self.resources.external_text_file(&full_path).map_err(BuildError::LoadInclude)?;
+        let arena = Arena::new();
+        let parser = MarkdownParser::new(&arena)
+            .with_emoji_shortcodes(self.options.emoji_shortcodes)
+            .with_smart_punctuation(self.options.smart_punctuation)
+            .with_line_breaks(self.options.line_breaks);
+        let elements = parser.parse(&contents).map_err(|e| BuildError::IncludeParse(path, e))?;
+
+        self.include_stack.push(full_path);
+        self.process_elements(elements)?;
+        self.include_stack.pop();
+        Ok(())
+    }
+
+    /// Resolve a path used in the file being processed, which is relative to the directory that file is in.
+    fn resolve_path(&self, path: &Path) -> PathBuf {
+        match self.include_stack.last().and_then(|file| file.parent()) {
+            Some(directory) => directory.join(path),
+            None => path.into(),
+        }
+    }
+
     fn should_ignore_comment(comment: &str) -> bool {
-        // Ignore any multi line comment; those are assumed to be user comments
-        if comment.contains('\n') {
+        // Ignore any multi line comment other than slide themes and speaker notes; those are assumed to be user comments
+        let is_command =
+            ["slide_theme:", "speaker_note:"].iter().any(|prefix| comment.trim_start().starts_with(prefix));
+        if comment.contains('\n') && !is_command {
             return true;
         }
         // Ignore vim-like code folding tags
@@ -270,24 +613,151 @@ impl<'a> PresentationBuilder<'a> {
         comment == "{{{" || comment == "}}}"
     }
 
-    fn validate_column_layout(columns: &[u8]) -> Result<(), BuildError> {
-        if columns.is_empty() {
-            Err(BuildError::InvalidLayout("need at least one column"))
-        } else if columns.iter().any(|column| column == &0) {
-            Err(BuildError::InvalidLayout("can't have zero sized columns"))
+    /// Start a column layout, nesting it in the current column if there is one.
+    fn init_column_layout(&mut self, columns: ColumnLayout, style: ColumnLayoutStyle) -> Result<(), BuildError> {
+        let columns_count = Self::validate_column_layout(&columns)?;
+        let layout = LayoutState::InLayout { columns_count };
+        // Layouts defined inside a column are nested in it.
+        if matches!(self.slide_state.layout, LayoutState::InColumn { .. }) {
+            let parent = mem::replace(&mut self.slide_state.layout, layout);
+            self.slide_state.parent_layouts.push(parent);
         } else {
-            Ok(())
+            self.slide_state.layout = layout;
+        }
+        self.chunk_operations.push(RenderOperation::InitColumnLayout { columns, style });
+        self.slide_state.needs_enter_column = true;
+        Ok(())
+    }
+
+    fn enter_column(&mut self, column: usize) -> Result<(), BuildError> {
+        let (current_column, columns_count) = match self.slide_state.layout {
+            LayoutState::InColumn { column, columns_count } => (Some(column), columns_count),
+            LayoutState::InLayout { columns_count } => (None, columns_count),
+            LayoutState::Default => return Err(BuildError::NoLayout),
+        };
+        if current_column == Some(column) {
+            return Err(BuildError::AlreadyInColumn);
+        } else if column >= columns_count {
+            return Err(BuildError::ColumnIndexTooLarge);
+        }
+        self.slide_state.layout = LayoutState::InColumn { column, columns_count };
+        self.chunk_operations.push(RenderOperation::EnterColumn { column });
+        Ok(())
+    }
+
+    fn apply_layout(&mut self, name: &str) -> Result<(), BuildError> {
+        let layout = self.theme.layouts.get(name).cloned().ok_or_else(|| BuildError::UnknownLayout(name.into()))?;
+        if layout.jump_to_middle() {
+            self.chunk_operations.push(RenderOperation::JumpToVerticalCenter);
+        }
+        if let Some(alignment) = layout.alignment {
+            self.slide_state.alignment = Some(alignment);
+        }
+        let regions = layout.regions.unwrap_or_default();
+        match layout.columns {
+            Some(columns) => {
+                if regions.len() > Self::validate_column_layout(&columns)? {
+                    return Err(BuildError::InvalidLayout("can't have more regions than columns"));
+                }
+                let mut style = self.theme.column_layout.clone();
+                if let Some(column_styles) = layout.column_styles {
+                    style.columns = Some(column_styles);
+                }
+                self.init_column_layout(columns, style)?;
+            }
+            None if !regions.is_empty() => return Err(BuildError::InvalidLayout("regions require columns")),
+            None => (),
+        };
+        self.slide_state.regions = regions;
+        Ok(())
+    }
+
+    /// Validate a column layout, returning the number of columns in it.
+    fn validate_column_layout(layout: &ColumnLayout) -> Result<usize, BuildError> {
+        let mut columns_count = None;
+        for columns in layout.iter_widths() {
+            if columns.is_empty() {
+                return Err(BuildError::InvalidLayout("need at least one column"));
+            } else if columns.iter().any(|column| column == &0) {
+                return Err(BuildError::InvalidLayout("can't have zero sized columns"));
+            } else if columns_count.is_some_and(|count| count != columns.len()) {
+                return Err(BuildError::InvalidLayout("all terminal sizes must have the same number of columns"));
+            }
+            columns_count = Some(columns.len());
         }
+        columns_count.ok_or(BuildError::InvalidLayout("need at least one column"))
     }
 
-    fn process_pause(&mut self) {
+    fn process_pause(&mut self, reveal_delay: Option<Duration>) {
         self.slide_state.last_chunk_ended_in_list = matches!(self.slide_state.last_element, LastElement::List { .. });
 
-        let chunk_operations = mem::take(&mut self.chunk_operations);
-        self.slide_chunks.push(SlideChunk::new(chunk_operations));
+        let chunk = self.take_chunk().with_reveal_delay(reveal_delay);
+        self.slide_chunks.push(chunk);
+    }
+
+    /// Get the time after which the chunk following a pause is revealed automatically.
+    fn reveal_delay(&self, in_list: bool) -> Option<Duration> {
+        match self.slide_state.reveal_delay {
+            Some(0) => None,
+            Some(milliseconds) => Some(Duration::from_millis(milliseconds)),
+            None if in_list => self.options.list_reveal_delay.or(self.options.reveal_delay),
+            None => self.options.reveal_delay,
+        }
+    }
+
+    fn push_timer(&mut self, duration: &str) -> Result<(), BuildError> {
+        let duration = parse_duration(duration).ok_or_else(|| BuildError::InvalidTimer(duration.into()))?;
+        let timer = CountdownTimer::new(duration, &self.theme.timer);
+        self.chunk_operations.push(RenderOperation::RenderOnDemand(Rc::new(timer)));
+        self.push_line_break();
+        Ok(())
+    }
+
+    fn push_big_text(&mut self, text: &str) {
+        let alignment = self.alignment(&ElementType::BigText);
+        let colors = self.theme.big_text.colors.clone();
+        for (index, line) in text.lines().map(str::trim).filter(|line| !line.is_empty()).enumerate() {
+            if index > 0 {
+                self.push_line_break();
+            }
+            let text = RenderBigText { text: line.into(), colors: colors.clone(), alignment: alignment.clone() };
+            self.chunk_operations.push(RenderOperation::RenderDynamic(Rc::new(text)));
+        }
+        self.push_line_break();
+    }
+
+    fn push_qr_code(&mut self, data: &str) -> Result<(), BuildError> {
+        let code = QrCode::encode(data)?;
+        let style = &self.theme.qr;
+        let colors = Colors {
+            foreground: style.colors.foreground.or(Some(Color::new(0, 0, 0))),
+            background: style.colors.background.or(Some(Color::new(255, 255, 255))),
+        };
+        let alignment = self.alignment(&ElementType::QrCode);
+        let code = RenderQrCode { code, data: data.into(), colors, alignment };
+        self.chunk_operations.push(RenderOperation::RenderDynamic(Rc::new(code)));
+        self.push_line_break();
+        Ok(())
+    }
+
+    fn push_chart(&mut self, contents: &str) -> Result<(), BuildError> {
+        let chart = Chart::parse(contents)?;
+        let style = self.theme.chart.clone();
+        let alignment = self.alignment(&ElementType::Chart);
+        let chart = RenderChart {
+            chart,
+            colors: style.colors,
+            axis_colors: style.axis_colors,
+            height: style.height.unwrap_or(10),
+            alignment,
+        };
+        self.chunk_operations.push(RenderOperation::RenderDynamic(Rc::new(chart)));
+        self.push_line_break();
+        Ok(())
     }
 
     fn push_slide_title(&mut self, mut text: Text) {
+        self.slide_state.anchors.push(anchor_for(&text));
         let style = self.theme.slide_title.clone();
         text.apply_style(&TextStyle::default().bold().colors(style.colors.clone()));
 
@@ -317,6 +787,15 @@ impl<'a> PresentationBuilder<'a> {
             6 => (ElementType::Heading6, &self.theme.headings.h6),
             other => panic!("unexpected heading level {other}"),
         };
+        self.slide_state.anchors.push(anchor_for(&text));
+        if level == 1 {
+            self.section = text.chunks.iter().map(|chunk| chunk.text.as_str()).collect();
+            let slide = self.slides.len();
+            let mut context = self.footer_context.borrow_mut();
+            if context.section_starts.last() != Some(&slide) {
+                context.section_starts.push(slide);
+            }
+        }
         if let Some(prefix) = &style.prefix {
             let mut prefix = prefix.clone();
             prefix.push(' ');
@@ -348,13 +827,65 @@ impl<'a> PresentationBuilder<'a> {
         self.chunk_operations.extend([RenderSeparator::default().into(), RenderOperation::RenderLineBreak]);
     }
 
+    fn push_image_element(&mut self, path: PathBuf, alt_text: String) -> Result<(), BuildError> {
+        let (image_column, text_column, columns) = match self.slide_state.image_float.take() {
+            Some(ImageFloat::Left) => (0, 1, vec![1, 2]),
+            Some(ImageFloat::Right) => (1, 0, vec![2, 1]),
+            None => {
+                self.push_image(path)?;
+                self.push_image_caption(alt_text);
+                return Ok(());
+            }
+        };
+        if !matches!(self.slide_state.layout, LayoutState::Default) {
+            return Err(BuildError::InvalidLayout("images can't be floated inside a column layout"));
+        }
+        // Floating images sit in their own column while everything after them goes into the one next to it.
+        self.init_column_layout(columns.into(), Default::default())?;
+        self.enter_column(image_column)?;
+        self.push_image(path)?;
+        self.push_image_caption(alt_text);
+        self.enter_column(text_column)?;
+        self.slide_state.ignore_element_line_break = true;
+        Ok(())
+    }
+
     fn push_image(&mut self, path: PathBuf) -> Result<(), BuildError> {
-        let image = self.resources.image(&path)?;
-        self.chunk_operations.push(RenderOperation::RenderImage(image));
+        let image = self.resources.image(self.resolve_path(&path))?;
+        let position = self.slide_state.image_position.take().unwrap_or_default();
+        self.chunk_operations.push(RenderOperation::RenderImage(image, position));
         self.chunk_operations.push(RenderOperation::SetColors(self.theme.default_style.colors.clone()));
         Ok(())
     }
 
+    fn push_background_image(&mut self, path: &Path) -> Result<(), BuildError> {
+        let image = self.resources.image(self.resolve_path(path))?.dimmed(BACKGROUND_IMAGE_BRIGHTNESS);
+        // This goes right after the screen is cleared so it's drawn before anything else in the slide.
+        let operations = match self.slide_chunks.first_mut() {
+            Some(chunk) => chunk.operations_mut(),
+            None => &mut self.chunk_operations,
+        };
+        let index = operations.iter().position(|op| matches!(op, RenderOperation::ClearScreen)).map_or(0, |i| i + 1);
+        operations.insert(index, RenderOperation::RenderBackgroundImage(image));
+        Ok(())
+    }
+
+    fn push_image_caption(&mut self, alt_text: String) {
+        let style = &self.theme.image.caption;
+        let enabled = self.slide_state.image_caption.take().unwrap_or_else(|| style.enabled());
+        let alt_text = alt_text.trim();
+        if !enabled || alt_text.is_empty() {
+            return;
+        }
+        let mut text_style = TextStyle::default().colors(style.colors.clone());
+        if style.italics() {
+            text_style = text_style.italics();
+        }
+        let alignment = Alignment::Center { minimum_margin: Margin::Fixed(0), minimum_size: 0 };
+        self.push_aligned_text(Text::from(StyledText::new(alt_text.to_string(), text_style)), alignment);
+        self.push_line_break();
+    }
+
     fn push_list(&mut self, list: Vec<ListItem>) {
         let last_chunk_operation = self.slide_chunks.last().and_then(|chunk| chunk.iter_operations().last());
         // If the last chunk ended in a list, pop the newline so we get them all next to each
@@ -371,36 +902,55 @@ impl<'a> PresentationBuilder<'a> {
             _ => 0,
         };
 
-        let iter = ListIterator::new(list, start_index);
-        for item in iter {
-            self.push_list_item(item.index, item.item);
+        let items: Vec<_> = ListIterator::new(list, start_index).collect();
+        // Numbers are right aligned so every item's text in a level starts at the same column.
+        let mut number_widths: HashMap<u8, usize> = HashMap::new();
+        for item in &items {
+            if let Some(number) = self.list_item_number(item.index, &item.item) {
+                let width = number_widths.entry(item.item.depth).or_default();
+                *width = number.width().max(*width);
+            }
+        }
+        for (position, item) in items.into_iter().enumerate() {
+            if self.options.incremental_lists && position > 0 && item.item.depth == 0 {
+                self.process_pause(self.reveal_delay(true));
+            }
+            let number_width = number_widths.get(&item.item.depth).copied().unwrap_or(0);
+            self.push_list_item(item.index, item.item, number_width);
         }
     }
 
-    fn push_list_item(&mut self, index: usize, item: ListItem) {
+    /// Get the number displayed before an item in an ordered list, along with its suffix.
+    fn list_item_number(&self, index: usize, item: &ListItem) -> Option<String> {
+        let suffix = match item.item_type {
+            ListItemType::Unordered => return None,
+            ListItemType::OrderedParens => ")",
+            ListItemType::OrderedPeriod => ".",
+        };
+        let style = &self.theme.list;
+        let suffix = style.number_suffix.as_deref().unwrap_or(suffix);
+        Some(format!("{}{suffix}", style.numbering(item.depth).format(index + 1)))
+    }
+
+    fn push_list_item(&mut self, index: usize, item: ListItem, number_width: usize) {
         let padding_length = (item.depth as usize + 1) * 3;
-        let mut prefix: String = " ".repeat(padding_length);
-        match item.item_type {
-            ListItemType::Unordered => {
-                let delimiter = match item.depth {
-                    0 => '•',
-                    1 => '◦',
-                    _ => '▪',
-                };
-                prefix.push(delimiter);
-            }
-            ListItemType::OrderedParens => {
-                prefix.push_str(&(index + 1).to_string());
-                prefix.push_str(") ");
-            }
-            ListItemType::OrderedPeriod => {
-                prefix.push_str(&(index + 1).to_string());
-                prefix.push_str(". ");
-            }
+        let padding: String = " ".repeat(padding_length);
+        let number = self.list_item_number(index, &item).map(|number| format!("{number:>number_width$} "));
+        if let Some(checked) = item.checked {
+            return self.push_task_list_item(index, item, padding + &number.unwrap_or_default(), checked);
+        }
+        let (marker, gap) = match number {
+            Some(number) => (number, 0),
+            // Leave a 2 column gap between the bullet and the text.
+            None => (self.theme.list.bullet(item.depth).to_string(), 2),
         };
 
-        let prefix_length = prefix.len() as u16;
-        self.push_text(prefix.into(), ElementType::List);
+        let prefix_length = (padding.width() + marker.width() + gap) as u16;
+        let colors = self.theme.list.colors(item.depth);
+        let prefix = Text {
+            chunks: vec![StyledText::from(padding), StyledText::new(marker, TextStyle::default().colors(colors))],
+        };
+        self.push_text(prefix, ElementType::List);
 
         let text = item.contents;
         self.push_aligned_text(text, Alignment::Left { margin: Margin::Fixed(prefix_length) });
@@ -410,20 +960,79 @@ impl<'a> PresentationBuilder<'a> {
         }
     }
 
-    fn push_block_quote(&mut self, lines: Vec<String>) {
-        let prefix = self.theme.block_quote.prefix.clone().unwrap_or_default();
-        let block_length = lines.iter().map(|line| line.width() + prefix.width()).max().unwrap_or(0);
+    fn push_task_list_item(&mut self, index: usize, item: ListItem, prefix: String, checked: bool) {
+        // The checkbox takes the place of the bullet in unordered lists and goes after the number in ordered ones.
+        let style = &self.theme.task_list;
+        let (glyph, colors) = match checked {
+            true => (style.checked_glyph(), &style.checked.colors),
+            false => (style.unchecked_glyph(), &style.unchecked.colors),
+        };
+        // Leave the same 2 column gap between the checkbox and the text that there is after a bullet.
+        let prefix_length = (prefix.width() + glyph.width() + 2) as u16;
+        let prefix = Text {
+            chunks: vec![StyledText::from(prefix), StyledText::new(glyph, TextStyle::default().colors(colors.clone()))],
+        };
+        self.push_text(prefix, ElementType::List);
+        self.push_aligned_text(item.contents, Alignment::Left { margin: Margin::Fixed(prefix_length) });
+        self.push_line_break();
+        if item.depth == 0 {
+            self.slide_state.last_element = LastElement::List { last_index: index };
+        }
+    }
+
+    fn push_block_quote(&mut self, lines: Vec<String>, attribution: Option<String>) {
+        let lines: Vec<_> = lines.iter().map(|line| self.variables.interpolate(line)).collect();
+        let attribution = attribution.map(|attribution| self.variables.interpolate(&attribution));
+        let style = self.theme.block_quote.clone();
+        let colors = style.colors.clone();
+        let with_fallback = |override_colors: &Colors| Colors {
+            background: override_colors.background.or(colors.background),
+            foreground: override_colors.foreground.or(colors.foreground),
+        };
+        let prefix = style.prefix.clone().unwrap_or_default();
+        let prefix_style = TextStyle::default().colors(with_fallback(&style.prefix_colors));
+        let mut text_style = TextStyle::default().colors(colors.clone());
+        if style.italics() {
+            text_style = text_style.italics();
+        }
+        let attribution = attribution.map(|author| {
+            let attribution_prefix = style.attribution.prefix.as_deref().unwrap_or("— ");
+            let text = format!("{attribution_prefix}{author}");
+            StyledText::new(text, TextStyle::default().colors(with_fallback(&style.attribution.colors)))
+        });
+        let block_length = lines
+            .iter()
+            .map(String::as_str)
+            .chain(attribution.iter().map(|attribution| attribution.text.as_str()))
+            .map(|line| line.width() + prefix.width())
+            .max()
+            .unwrap_or(0);
 
-        self.chunk_operations.push(RenderOperation::SetColors(self.theme.block_quote.colors.clone()));
-        for mut line in lines {
-            line.insert_str(0, &prefix);
+        let mut styled_lines: Vec<_> = lines
+            .into_iter()
+            .map(|line| {
+                vec![StyledText::new(prefix.clone(), prefix_style.clone()), StyledText::new(line, text_style.clone())]
+            })
+            .collect();
+        if let Some(attribution) = attribution {
+            // Right align the attribution within the quote.
+            let padding = block_length - prefix.width() - attribution.text.width();
+            styled_lines.push(vec![
+                StyledText::new(prefix.clone(), prefix_style.clone()),
+                StyledText::new(" ".repeat(padding), TextStyle::default().colors(colors.clone())),
+                attribution,
+            ]);
+        }
 
-            let line_length = line.width();
+        self.chunk_operations.push(RenderOperation::SetColors(colors.clone()));
+        for spans in styled_lines {
             self.chunk_operations.push(RenderOperation::RenderPreformattedLine(PreformattedLine {
-                text: line,
-                unformatted_length: line_length,
+                unformatted_length: styled_line_width(&spans),
+                spans,
                 block_length,
-                alignment: self.theme.alignment(&ElementType::BlockQuote).clone(),
+                alignment: self.alignment(&ElementType::BlockQuote),
+                overflow: None,
+                raw: false,
             }));
             self.push_line_break();
         }
@@ -431,15 +1040,45 @@ impl<'a> PresentationBuilder<'a> {
     }
 
     fn push_text(&mut self, text: Text, element_type: ElementType) {
-        let alignment = self.theme.alignment(&element_type);
+        let alignment = self.alignment(&element_type);
         self.push_aligned_text(text, alignment);
     }
 
     fn push_aligned_text(&mut self, text: Text, alignment: Alignment) {
+        let hyperlinks = self.options.hyperlinks.unwrap_or_else(hyperlinks_supported);
         let mut texts: Vec<WeightedText> = Vec::new();
+        let mut last_link = None;
+        // The slide number is the same one the footer uses.
+        let current_slide = self.footer_context.borrow().slide_number(self.slides.len());
+        self.variables.insert("current_slide", current_slide.map(|number| number.to_string()).unwrap_or_default());
         for mut chunk in text.chunks {
+            if !chunk.style.is_code() {
+                chunk.text = self.variables.interpolate(&chunk.text);
+            }
+            let link = mem::replace(&mut last_link, chunk.link.clone());
+            if chunk.anchor().is_none() && chunk.link.is_some() && !hyperlinks {
+                // Display the URL once instead of the text in the link.
+                if link == chunk.link {
+                    continue;
+                }
+                let url = chunk.link.take().unwrap_or_default();
+                chunk = StyledText::new(url, TextStyle::default().link());
+            }
             if chunk.style.is_code() {
                 chunk.style.colors = self.theme.inline_code.colors.clone();
+                if let Some(padding) = &self.theme.inline_code.padding {
+                    chunk.text = format!("{padding}{}{padding}", chunk.text);
+                }
+            }
+            if chunk.style.is_strikethrough() {
+                let colors = &self.theme.strikethrough.colors;
+                chunk.style.colors.background = chunk.style.colors.background.or(colors.background);
+                chunk.style.colors.foreground = chunk.style.colors.foreground.or(colors.foreground);
+            }
+            if let Some(anchor) = chunk.anchor() {
+                if !self.chunk_links.iter().any(|link| link == anchor) {
+                    self.chunk_links.push(anchor.to_string());
+                }
             }
             texts.push(chunk.into());
         }
@@ -453,75 +1092,439 @@ impl<'a> PresentationBuilder<'a> {
         self.chunk_operations.push(RenderOperation::RenderLineBreak);
     }
 
-    fn push_code(&mut self, code: Code) {
-        let Code { contents, language, flags } = code;
-        let mut code = String::new();
+    fn push_code(&mut self, mut code: Code) -> Result<(), BuildError> {
+        if let Some(path) = &code.flags.external_file {
+            code.contents = self.resources.external_text_file(self.resolve_path(path))?;
+            if code.flags.title.is_none() && self.theme.code.title.file_names() {
+                code.flags.title = Some(path.display().to_string());
+            }
+        }
+        match code.language {
+            CodeLanguage::BigText => {
+                self.push_big_text(&code.contents);
+                return Ok(());
+            }
+            CodeLanguage::Qr => return self.push_qr_code(code.contents.trim()),
+            CodeLanguage::Chart => return self.push_chart(&code.contents),
+            _ => (),
+        };
+        if code.flags.raw {
+            self.push_raw_code(&code.contents);
+            return Ok(());
+        }
+        let elisions = code.select_lines();
+        // If the tool used to render isn't installed the code is displayed as is.
+        if code.flags.render {
+            if let Some(path) = self.render_code(&code)? {
+                return self.push_image(path);
+            }
+        }
+        let mut contents = String::new();
+        let mut line_count = 0;
+        for (index, line) in code.contents.lines().enumerate() {
+            if code.flags.elision_markers && elisions.contains(&index) {
+                contents.push_str(ELISION_MARKER);
+            }
+            if !code.is_hidden(line) {
+                contents.push_str(line);
+                contents.push('\n');
+            }
+            line_count += 1;
+        }
+        if code.flags.elision_markers && elisions.contains(&line_count) {
+            contents.push_str(ELISION_MARKER);
+        }
+        // Code that continues another one is executed along with it.
+        if let Some(id) = &code.flags.continues {
+            let previous = self.code_chains.get(id).ok_or_else(|| BuildError::UnknownCodeId(id.clone()))?;
+            code.contents = format!("{previous}\n{}", code.contents);
+        }
+        if let Some(id) = &code.flags.id {
+            self.code_chains.insert(id.clone(), code.contents.clone());
+        }
+        // If code can't be executed this is displayed as is so it's clear what would have run.
+        if code.flags.execute_replace && self.code_executer.is_enabled() {
+            self.push_code_output(code);
+            return Ok(());
+        }
+        self.chunk_code_blocks.push(code.visible_lines().collect::<Vec<_>>().join("\n"));
+        let mut padded = String::new();
         let horizontal_padding = self.theme.code.padding.horizontal.unwrap_or(0);
         let vertical_padding = self.theme.code.padding.vertical.unwrap_or(0);
         if horizontal_padding == 0 && vertical_padding == 0 {
-            code = contents.clone();
+            padded = contents;
         } else {
             if vertical_padding > 0 {
-                code.push('\n');
+                padded.push('\n');
             }
             if horizontal_padding > 0 {
                 let padding = " ".repeat(horizontal_padding as usize);
                 for line in contents.lines() {
-                    code.push_str(&padding);
-                    code.push_str(line);
-                    code.push('\n');
+                    padded.push_str(&padding);
+                    padded.push_str(line);
+                    padded.push('\n');
                 }
             } else {
-                code.push_str(&contents);
+                padded.push_str(&contents);
             }
             if vertical_padding > 0 {
-                code.push('\n');
+                padded.push('\n');
             }
         }
-        let block_length = code.lines().map(|line| line.width()).max().unwrap_or(0) + horizontal_padding as usize;
-        for code_line in self.highlighter.highlight(&code, &language) {
-            let CodeLine { formatted, original } = code_line;
-            let trimmed = formatted.trim_end();
-            let original_length = original.width() - (formatted.width() - trimmed.width());
+        // Padding lines aren't part of the code so they don't count when figuring out which lines to highlight.
+        let first_line_index = if vertical_padding > 0 { 1 } else { 0 };
+        match code.flags.highlight_groups.as_slice() {
+            [] | [_] => {
+                let operations =
+                    self.highlight_code(&padded, &code, code.flags.highlight_groups.first(), first_line_index);
+                self.chunk_operations.extend(operations);
+            }
+            groups => {
+                // Each group is displayed in turn as the presentation moves forward.
+                let group_operations = groups
+                    .iter()
+                    .map(|group| self.highlight_code(&padded, &code, Some(group), first_line_index))
+                    .collect();
+                let operation = Rc::new(HighlightGroupsOperation { group_operations, current: Default::default() });
+                self.chunk_operations.push(RenderOperation::RenderDynamic(operation.clone()));
+                self.chunk_mutators.push(operation);
+            }
+        }
+        if code.flags.acquire_terminal {
+            self.push_terminal_snippet(code);
+        } else if code.flags.execute {
+            self.push_code_execution(code);
+        }
+        Ok(())
+    }
+
+    /// Display code exactly as it is, so things like ASCII art keep their shape.
+    ///
+    /// Tabs are expanded to the next multiple of 8 columns as terminals do, and lines are never wrapped.
+    fn push_raw_code(&mut self, contents: &str) {
+        self.chunk_code_blocks.push(contents.trim_end_matches('\n').to_string());
+        let lines: Vec<_> = contents.lines().map(expand_tabs).collect();
+        let block_length = lines.iter().map(|line| line.width()).max().unwrap_or(0);
+        self.chunk_operations.push(RenderOperation::SetColors(self.highlighter.default_colors()));
+        for line in lines {
             self.chunk_operations.push(RenderOperation::RenderPreformattedLine(PreformattedLine {
-                text: trimmed.into(),
-                unformatted_length: original_length,
+                unformatted_length: line.width(),
+                spans: vec![StyledText::from(line)],
                 block_length,
-                alignment: self.theme.alignment(&ElementType::Code),
+                alignment: self.alignment(&ElementType::Code),
+                overflow: None,
+                raw: true,
             }));
             self.push_line_break();
         }
-        if flags.execute {
-            self.push_code_execution(Code { contents, language, flags });
+        self.chunk_operations.push(RenderOperation::SetColors(self.theme.default_style.colors.clone()));
+    }
+
+    /// Render code into an image, returning `None` if the tool used to render it isn't installed.
+    fn render_code(&self, code: &Code) -> Result<Option<PathBuf>, BuildError> {
+        let default_colors = &self.theme.default_style.colors;
+        let typst_colors = &self.theme.typst.colors;
+        let colors = Colors {
+            background: typst_colors.background.or(default_colors.background),
+            foreground: typst_colors.foreground.or(default_colors.foreground),
+        };
+        let request = match &code.language {
+            CodeLanguage::Latex => RenderRequest::Latex { input: &code.contents, colors: &colors },
+            CodeLanguage::Typst => RenderRequest::Typst { input: &code.contents, colors: &colors },
+            CodeLanguage::Mermaid => RenderRequest::Mermaid { input: &code.contents, style: &self.theme.mermaid },
+            CodeLanguage::Dot => RenderRequest::Graphviz { input: &code.contents },
+            CodeLanguage::D2 => RenderRequest::D2 { input: &code.contents },
+            other => return Err(BuildError::UnsupportedRender(format!("{other:?}"))),
+        };
+        match self.third_party.render(request) {
+            Ok(path) => Ok(Some(path)),
+            Err(e) if e.is_missing_tool() => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn highlight_code(
+        &self,
+        padded: &str,
+        code: &Code,
+        group: Option<&LineGroup>,
+        first_line_index: usize,
+    ) -> Vec<RenderOperation> {
+        let overflow = code.flags.overflow.clone().unwrap_or_else(|| self.theme.code.overflow());
+        if code.language == CodeLanguage::Ansi {
+            return self.render_ansi_code(padded, code, &overflow);
+        }
+        let horizontal_padding = self.theme.code.padding.horizontal.unwrap_or(0);
+        let block_length = self.code_block_length(padded.lines().map(|line| line.width()), code);
+        let dim = self.theme.code.highlight_mode() == LineHighlightMode::Dim;
+        let diff_colors = &self.theme.code.diff_colors;
+        let lines: Vec<_> = padded.lines().collect();
+        let line_style = |index: usize| {
+            // Diff markers are right after the padding.
+            let marker = match code.flags.diff {
+                true => lines.get(index).and_then(|line| line.get(horizontal_padding as usize..)?.chars().next()),
+                false => None,
+            };
+            match (marker, group) {
+                (Some('+'), _) => LineStyle::Changed(diff_colors.added()),
+                (Some('-'), _) => LineStyle::Changed(diff_colors.removed()),
+                (_, Some(group)) if index >= first_line_index && group.contains(index - first_line_index + 1) => {
+                    LineStyle::Emphasized
+                }
+                (_, Some(_)) if dim => LineStyle::Dimmed,
+                _ => LineStyle::Normal,
+            }
+        };
+        let mut operations = Vec::new();
+        if let Some(title) = &code.flags.title {
+            operations.extend(self.render_code_title(title, block_length));
+        }
+        for code_line in self.highlighter.highlight(padded, &code.language, line_style) {
+            let CodeLine { mut spans, original } = code_line;
+            // Trailing whitespace is dropped but not the colors it uses, as the line is padded with them.
+            for span in spans.iter_mut().rev() {
+                let trimmed_length = span.text.trim_end().len();
+                span.text.truncate(trimmed_length);
+                if !span.text.is_empty() {
+                    break;
+                }
+            }
+            operations.push(RenderOperation::RenderPreformattedLine(PreformattedLine {
+                spans,
+                unformatted_length: original.trim_end().width(),
+                block_length,
+                alignment: self.alignment(&ElementType::Code),
+                overflow: Some(overflow.clone()),
+                raw: false,
+            }));
+            operations.push(RenderOperation::RenderLineBreak);
+        }
+        operations
+    }
+
+    /// Render code that already contains escape codes to style it rather than highlighting it.
+    fn render_ansi_code(&self, padded: &str, code: &Code, overflow: &OutputOverflow) -> Vec<RenderOperation> {
+        let colors = self.highlighter.default_colors();
+        let mut splitter = AnsiSplitter::new(colors.clone());
+        let lines: Vec<_> = padded.lines().map(|line| splitter.split_line(line)).collect();
+        let block_length = self.code_block_length(lines.iter().map(|spans| styled_line_width(spans)), code);
+        let mut operations = Vec::new();
+        if let Some(title) = &code.flags.title {
+            operations.extend(self.render_code_title(title, block_length));
+        }
+        operations.push(RenderOperation::SetColors(colors));
+        for spans in lines {
+            operations.push(RenderOperation::RenderPreformattedLine(PreformattedLine {
+                unformatted_length: styled_line_width(&spans),
+                spans,
+                block_length,
+                alignment: self.alignment(&ElementType::Code),
+                overflow: Some(overflow.clone()),
+                raw: false,
+            }));
+            operations.push(RenderOperation::RenderLineBreak);
         }
+        operations.push(RenderOperation::SetColors(self.theme.default_style.colors.clone()));
+        operations
+    }
+
+    /// Render the bar that goes above a code block that has a title.
+    fn render_code_title(&self, title: &str, block_length: usize) -> Vec<RenderOperation> {
+        let code_colors = self.highlighter.default_colors();
+        let title_colors = &self.theme.code.title.colors;
+        let colors = Colors {
+            foreground: title_colors.foreground.or(code_colors.foreground),
+            background: title_colors.background.or(code_colors.background),
+        };
+        let horizontal_padding = self.theme.code.padding.horizontal.unwrap_or(0) as usize;
+        let spans = vec![
+            StyledText::new(" ".repeat(horizontal_padding), TextStyle::default().colors(colors.clone())),
+            StyledText::new(title, TextStyle::default().bold().colors(colors.clone())),
+        ];
+        vec![
+            RenderOperation::SetColors(colors),
+            RenderOperation::RenderPreformattedLine(PreformattedLine {
+                unformatted_length: styled_line_width(&spans),
+                spans,
+                block_length,
+                alignment: self.alignment(&ElementType::Code),
+                overflow: Some(OutputOverflow::Truncate),
+                raw: false,
+            }),
+            RenderOperation::RenderLineBreak,
+            RenderOperation::SetColors(self.theme.default_style.colors.clone()),
+        ]
     }
 
-    fn push_code_execution(&mut self, code: Code) {
+    /// Get the width of a code block given the width of each of its lines, which already include its left padding.
+    ///
+    /// Code blocks with a title are made wide enough to fit it.
+    fn code_block_length<I: Iterator<Item = usize>>(&self, line_lengths: I, code: &Code) -> usize {
+        let horizontal_padding = self.theme.code.padding.horizontal.unwrap_or(0) as usize;
+        let title_length = code.flags.title.as_ref().map(|title| horizontal_padding + title.width()).unwrap_or(0);
+        line_lengths.max().unwrap_or(0).max(title_length) + horizontal_padding
+    }
+
+    fn push_code_execution(&mut self, mut code: Code) {
+        self.apply_execution_defaults(&mut code);
+        let state = self.claim_execution_state(&code);
         let operation = RunCodeOperation::new(
             code,
+            self.code_executer.clone(),
             self.theme.default_style.colors.clone(),
-            self.theme.execution_output.colors.clone(),
+            &self.theme.execution_output,
+            state,
         );
         let operation = RenderOperation::RenderOnDemand(Rc::new(operation));
         self.chunk_operations.push(operation);
     }
 
-    fn terminate_slide(&mut self) {
-        let footer = self.generate_footer();
-
-        let operations = mem::take(&mut self.chunk_operations);
-        self.slide_chunks.push(SlideChunk::new(operations));
+    /// Add a piece of code that takes over the terminal when executed.
+    ///
+    /// These are run by the presenter as they need the terminal for themselves, so all that's displayed in the
+    /// slide is the error from the last time they were run, if any.
+    fn push_terminal_snippet(&mut self, mut code: Code) {
+        self.apply_execution_defaults(&mut code);
+        let error: Rc<RefCell<Option<String>>> = Default::default();
+        let operation = RenderTerminalSnippetError {
+            error: error.clone(),
+            colors: self.theme.execution_output.stderr_colors(),
+            default_colors: self.theme.default_style.colors.clone(),
+            alignment: self.alignment(&ElementType::Code),
+        };
+        self.chunk_operations.push(RenderOperation::RenderDynamic(Rc::new(operation)));
+        self.chunk_terminal_snippets.push(TerminalSnippet { code, error });
+    }
 
-        let chunks = mem::take(&mut self.slide_chunks);
-        self.slides.push(Slide::new(chunks, footer));
+    /// Display the output of a piece of code instead of the code itself.
+    ///
+    /// The code runs as soon as the slide it's in is displayed.
+    fn push_code_output(&mut self, mut code: Code) {
+        self.apply_execution_defaults(&mut code);
+        // Nobody can type anything while this runs.
+        code.flags.interactive = false;
+        // Reuse the output from the last build if the code didn't change so reloading doesn't run it again.
+        let state = self.claim_execution_state(&code);
+        let operation = RunCodeOperation::new(
+            code,
+            self.code_executer.clone(),
+            self.theme.default_style.colors.clone(),
+            &self.theme.execution_output,
+            state,
+        )
+        .replacing_code(self.alignment(&ElementType::Code));
+        self.chunk_operations.push(RenderOperation::RenderOnDemand(Rc::new(operation)));
+    }
+
+    /// Fill in the execution attributes that weren't set in a piece of code.
+    fn apply_execution_defaults(&self, code: &mut Code) {
+        if code.flags.timeout.is_none() {
+            code.flags.timeout = self.options.execution_timeout;
+        }
+        if code.flags.working_directory.is_none() {
+            code.flags.working_directory = self.options.execution_working_directory.clone();
+        }
+        // Paths in a remote host can't be relative to the presentation.
+        if !self.code_executer.is_remote() {
+            if let Some(path) = &mut code.flags.working_directory {
+                let full_path = self.resources.base_path().join(&*path);
+                // Docker takes relative paths to be volume names so this needs to be absolute.
+                *path = env::current_dir().map(|directory| directory.join(&full_path)).unwrap_or(full_path);
+            }
+        }
+    }
+
+    /// Get the execution state for a piece of code, reusing the one from the previous build if there was one.
+    fn claim_execution_state(&mut self, code: &Code) -> Rc<RefCell<RunCodeOperationInner>> {
+        let mut hasher = DefaultHasher::new();
+        code.hash(&mut hasher);
+        let hash = hasher.finish();
+        // The same code can show up more than once so differentiate them by their order.
+        let occurrence = self.claimed_executions.keys().filter(|key| key.hash == hash).count();
+        let key = ExecutionKey { hash, occurrence };
+        let state = self.execution_cache.entries.get(&key).cloned().unwrap_or_default();
+        self.claimed_executions.insert(key, state.clone());
+        state
+    }
+
+    fn terminate_slide(&mut self) {
+        let footer = self.generate_footer();
+
+        let chunk = self.take_chunk();
+        self.slide_chunks.push(chunk);
+        self.align_slide_vertically();
+
+        let chunks = mem::take(&mut self.slide_chunks);
+        if !self.slide_state.skip_slide || !self.options.skip_slides {
+            // The first slide to use an anchor is the one links to it point to.
+            for anchor in mem::take(&mut self.slide_state.anchors) {
+                self.anchors.entry(anchor).or_insert(self.slides.len());
+            }
+            let speaker_notes = mem::take(&mut self.slide_state.speaker_notes);
+            self.slides.push(Slide::new(chunks, footer).with_speaker_notes(speaker_notes));
+        }
+        if let Some((theme, highlighter)) = self.slide_theme_backup.take() {
+            self.theme = theme;
+            self.highlighter = highlighter;
+        }
         self.push_slide_prelude();
         self.slide_state = Default::default();
     }
 
+    fn align_slide_vertically(&mut self) {
+        let alignment =
+            self.slide_state.vertical_alignment.unwrap_or_else(|| self.theme.default_style.vertical_alignment());
+        // Slides that jump to the middle on their own are already placed where they want to be.
+        let jumps = self
+            .slide_chunks
+            .iter()
+            .flat_map(|chunk| chunk.iter_operations())
+            .any(|operation| matches!(operation, RenderOperation::JumpToVerticalCenter));
+        if alignment == VerticalAlignment::Top || jumps {
+            return;
+        }
+        // Align everything that comes after the slide's prelude.
+        let Some((first, rest)) = self.slide_chunks.split_first_mut() else {
+            return;
+        };
+        let operations = first.operations_mut();
+        let Some(margin) = operations.iter().position(|operation| matches!(operation, RenderOperation::ApplyMargin(_)))
+        else {
+            return;
+        };
+        let breaks = operations[margin + 1..]
+            .iter()
+            .take_while(|operation| matches!(operation, RenderOperation::RenderLineBreak))
+            .count();
+        let index = margin + 1 + breaks;
+        // Chunks that aren't visible yet are measured too so the contents don't move around as they're revealed.
+        let contents =
+            operations[index..].iter().chain(rest.iter().flat_map(|chunk| chunk.iter_operations())).cloned().collect();
+        operations.insert(index, RenderOperation::AlignVertically { alignment, contents: Rc::new(contents) });
+    }
+
+    /// Get the alignment for an element, taking into account any alignment set for the current slide.
+    fn alignment(&self, element: &ElementType) -> Alignment {
+        self.slide_state.alignment.clone().unwrap_or_else(|| self.theme.alignment(element))
+    }
+
+    fn take_chunk(&mut self) -> SlideChunk {
+        SlideChunk::new(mem::take(&mut self.chunk_operations))
+            .with_code_blocks(mem::take(&mut self.chunk_code_blocks))
+            .with_terminal_snippets(mem::take(&mut self.chunk_terminal_snippets))
+            .with_mutators(mem::take(&mut self.chunk_mutators))
+            .with_links(mem::take(&mut self.chunk_links))
+    }
+
     fn generate_footer(&mut self) -> Vec<RenderOperation> {
+        if let FooterStyle::Template { left, center, right, .. } = &self.theme.footer {
+            let templates = [left, center, right].into_iter().flatten();
+            self.shows_clock |= templates.into_iter().any(|template| Variables::is_used(template.template(), "clock"));
+        }
         let generator = FooterGenerator {
             style: self.theme.footer.clone(),
             current_slide: self.slides.len(),
+            section: self.section.clone(),
             context: self.footer_context.clone(),
         };
         vec![
@@ -537,51 +1540,141 @@ impl<'a> PresentationBuilder<'a> {
         let widths: Vec<_> = (0..table.columns())
             .map(|column| table.iter_column(column).map(|text| text.width()).max().unwrap_or(0))
             .collect();
-        let flattened_header = Self::prepare_table_row(table.header, &widths);
-        self.push_text(flattened_header, ElementType::Table);
-        self.push_line_break();
+        let style = &self.theme.table;
+        let characters = TableCharacters::new(style.border());
+        let border_style = TextStyle::default().colors(style.border_colors.clone());
+        let header_style = TextStyle::default().bold().colors(style.header.colors.clone());
+
+        if let Some(edges) = &characters.top {
+            self.push_table_border(edges, &characters, &widths, &border_style);
+        }
+        let mut header = table.header;
+        for text in &mut header.0 {
+            for chunk in &mut text.chunks {
+                chunk.style.merge(&header_style);
+            }
+        }
+        self.push_table_row(header, &table.alignments, &characters, &widths, &border_style);
+        self.push_table_border(&characters.middle, &characters, &widths, &border_style);
+        for row in table.rows {
+            self.push_table_row(row, &table.alignments, &characters, &widths, &border_style);
+        }
+        if let Some(edges) = &characters.bottom {
+            self.push_table_border(edges, &characters, &widths, &border_style);
+        }
+    }
 
-        let mut separator = Text { chunks: Vec::new() };
+    fn push_table_border(
+        &mut self,
+        edges: &BorderEdges,
+        characters: &TableCharacters,
+        widths: &[usize],
+        style: &TextStyle,
+    ) {
+        let outer_padding = if characters.vertical_edges { 1 } else { 0 };
+        let mut line = String::new();
+        if characters.vertical_edges {
+            line.push(edges.left);
+        }
         for (index, width) in widths.iter().enumerate() {
-            let mut contents = String::new();
-            let mut margin = 1;
             if index > 0 {
-                contents.push('┼');
-                // Append an extra dash to have 1 column margin on both sides
-                if index < widths.len() - 1 {
-                    margin += 1;
-                }
+                line.push(edges.middle);
             }
-            contents.extend(iter::repeat("─").take(*width + margin));
-            separator.chunks.push(StyledText::from(contents));
+            // Cells have a 1 column margin on each side, except on the outer edges if there's no border there.
+            let left_margin = if index > 0 { 1 } else { outer_padding };
+            let right_margin = if index < widths.len() - 1 { 1 } else { outer_padding };
+            line.push_str(&characters.horizontal.to_string().repeat(width + left_margin + right_margin));
         }
-
-        self.push_text(separator, ElementType::Table);
-        self.push_line_break();
-
-        for row in table.rows {
-            let flattened_row = Self::prepare_table_row(row, &widths);
-            self.push_text(flattened_row, ElementType::Table);
-            self.push_line_break();
+        if characters.vertical_edges {
+            line.push(edges.right);
         }
+        self.push_text(Text { chunks: vec![StyledText::new(line, style.clone())] }, ElementType::Table);
+        self.push_line_break();
     }
 
-    fn prepare_table_row(row: TableRow, widths: &[usize]) -> Text {
+    fn push_table_row(
+        &mut self,
+        row: TableRow,
+        alignments: &[ColumnAlignment],
+        characters: &TableCharacters,
+        widths: &[usize],
+        style: &TextStyle,
+    ) {
+        let separator = |text: String| StyledText::new(text, style.clone());
         let mut flattened_row = Text { chunks: Vec::new() };
+        if characters.vertical_edges {
+            flattened_row.chunks.push(separator(format!("{} ", characters.vertical)));
+        }
         for (column, text) in row.0.into_iter().enumerate() {
             if column > 0 {
-                flattened_row.chunks.push(StyledText::from(" │ "));
+                flattened_row.chunks.push(separator(format!(" {} ", characters.vertical)));
+            }
+            let padding = widths[column].saturating_sub(text.width());
+            let (left_padding, right_padding) = match alignments.get(column).copied().unwrap_or_default() {
+                ColumnAlignment::Left => (0, padding),
+                ColumnAlignment::Center => (padding / 2, padding - padding / 2),
+                ColumnAlignment::Right => (padding, 0),
+            };
+            if left_padding > 0 {
+                flattened_row.chunks.push(StyledText::from(" ".repeat(left_padding)));
             }
-            let text_length = text.width();
             flattened_row.chunks.extend(text.chunks.into_iter());
+            if right_padding > 0 {
+                flattened_row.chunks.push(StyledText::from(" ".repeat(right_padding)));
+            }
+        }
+        if characters.vertical_edges {
+            flattened_row.chunks.push(separator(format!(" {}", characters.vertical)));
+        }
+        self.push_text(flattened_row, ElementType::Table);
+        self.push_line_break();
+    }
+}
+
+/// The characters used to draw a table.
+struct TableCharacters {
+    horizontal: char,
+    vertical: char,
+    top: Option<BorderEdges>,
+    middle: BorderEdges,
+    bottom: Option<BorderEdges>,
+    vertical_edges: bool,
+}
 
-            let cell_width = widths[column];
-            if text_length < cell_width {
-                let padding = " ".repeat(cell_width - text_length);
-                flattened_row.chunks.push(StyledText::from(padding));
+/// The characters used at the start, between columns, and at the end of a horizontal border.
+struct BorderEdges {
+    left: char,
+    middle: char,
+    right: char,
+}
+
+impl TableCharacters {
+    fn new(border: TableBorder) -> Self {
+        let edges = |[left, middle, right]: [char; 3]| BorderEdges { left, middle, right };
+        let (horizontal, vertical, [top, middle, bottom]) = match border {
+            TableBorder::Sharp => ('─', '│', [['┌', '┬', '┐'], ['├', '┼', '┤'], ['└', '┴', '┘']]),
+            TableBorder::Rounded => ('─', '│', [['╭', '┬', '╮'], ['├', '┼', '┤'], ['╰', '┴', '╯']]),
+            TableBorder::Double => ('═', '║', [['╔', '╦', '╗'], ['╠', '╬', '╣'], ['╚', '╩', '╝']]),
+            TableBorder::Ascii => ('-', '|', [['+', '+', '+'], ['+', '+', '+'], ['+', '+', '+']]),
+            TableBorder::Minimal => {
+                return Self {
+                    horizontal: '─',
+                    vertical: '│',
+                    top: None,
+                    middle: edges([' ', '┼', ' ']),
+                    bottom: None,
+                    vertical_edges: false,
+                };
             }
+        };
+        Self {
+            horizontal,
+            vertical,
+            top: Some(edges(top)),
+            middle: edges(middle),
+            bottom: Some(edges(bottom)),
+            vertical_edges: true,
         }
-        flattened_row
     }
 }
 
@@ -592,6 +1685,19 @@ struct SlideState {
     last_chunk_ended_in_list: bool,
     last_element: LastElement,
     layout: LayoutState,
+    parent_layouts: Vec<LayoutState>,
+    alignment: Option<Alignment>,
+    skip_slide: bool,
+    started: bool,
+    anchors: Vec<String>,
+    speaker_notes: Vec<String>,
+    regions: Vec<String>,
+    reveal_delay: Option<u64>,
+    image_position: Option<ImagePosition>,
+    image_float: Option<ImageFloat>,
+    image_caption: Option<bool>,
+    vertical_alignment: Option<VerticalAlignment>,
+    typewriter: Option<u32>,
 }
 
 #[derive(Debug, Default)]
@@ -619,12 +1725,27 @@ enum LastElement {
 #[derive(Debug, Default)]
 struct FooterContext {
     total_slides: usize,
+    section_starts: Vec<usize>,
     author: String,
+    variables: Variables,
+    numbering: SlideNumbering,
+    has_intro: bool,
+}
+
+impl FooterContext {
+    fn slide_number(&self, index: usize) -> Option<usize> {
+        self.numbering.number(index, self.has_intro)
+    }
+
+    fn last_slide_number(&self) -> usize {
+        self.numbering.last(self.total_slides, self.has_intro)
+    }
 }
 
 #[derive(Debug)]
 struct FooterGenerator {
     current_slide: usize,
+    section: String,
     context: Rc<RefCell<FooterContext>>,
     style: FooterStyle,
 }
@@ -632,15 +1753,28 @@ struct FooterGenerator {
 impl FooterGenerator {
     fn render_template(
         template: &str,
-        current_slide: &str,
+        current_slide: Option<usize>,
+        section: &str,
         context: &FooterContext,
         colors: Colors,
         alignment: Alignment,
     ) -> RenderOperation {
-        let contents = template
-            .replace("{current_slide}", current_slide)
-            .replace("{total_slides}", &context.total_slides.to_string())
-            .replace("{author}", &context.author);
+        // Slides that aren't counted, like an uncounted introduction slide, don't display a number.
+        let slide_number = current_slide
+            .map(|number| context.numbering.format(number, context.last_slide_number()))
+            .unwrap_or_default();
+        let mut variables = context.variables.clone();
+        variables.insert("current_slide", current_slide.map(|number| number.to_string()).unwrap_or_default());
+        variables.insert("total_slides", context.last_slide_number().to_string());
+        variables.insert("slide_number", slide_number);
+        variables.insert("author", context.author.clone());
+        variables.insert("section", section);
+        // The time is only looked up when it's going to be displayed.
+        if Variables::is_used(template, "clock") {
+            variables.insert("clock", current_time());
+        }
+        // Footer templates used to only support these as `{name}` so that keeps working.
+        let contents = variables.interpolate_with_legacy(template, &["current_slide", "total_slides", "author"]);
         let text = WeightedText::from(StyledText::new(contents, TextStyle::default().colors(colors)));
         RenderOperation::RenderText { line: vec![text].into(), alignment }
     }
@@ -651,7 +1785,7 @@ impl AsRenderOperations for FooterGenerator {
         let context = self.context.borrow();
         match &self.style {
             FooterStyle::Template { left, center, right, colors } => {
-                let current_slide = (self.current_slide + 1).to_string();
+                let current_slide = context.slide_number(self.current_slide);
                 // We print this one row below the bottom so there's one row of padding.
                 let mut operations = vec![RenderOperation::JumpToBottomRow { index: 1 }];
                 let margin = Margin::Fixed(1);
@@ -660,33 +1794,90 @@ impl AsRenderOperations for FooterGenerator {
                     Alignment::Center { minimum_size: 0, minimum_margin: margin.clone() },
                     Alignment::Right { margin: margin.clone() },
                 ];
-                for (text, alignment) in [left, center, right].iter().zip(alignments) {
-                    if let Some(text) = text {
+                for (template, alignment) in [left, center, right].iter().zip(alignments) {
+                    if let Some(template) = template {
                         operations.push(Self::render_template(
-                            text,
-                            &current_slide,
+                            template.template(),
+                            current_slide,
+                            &self.section,
                             &context,
-                            colors.clone(),
+                            template.colors(colors),
                             alignment,
                         ));
                     }
                 }
                 operations
             }
-            FooterStyle::ProgressBar { character, colors } => {
-                let character = character.unwrap_or('█').to_string();
-                let total_columns = dimensions.columns as usize / character.width();
+            FooterStyle::ProgressBar {
+                character,
+                remaining_character,
+                section_tick,
+                thickness,
+                position,
+                colors,
+                remaining_colors,
+            } => {
+                let character = character.unwrap_or('█');
+                let cell_width = [Some(character), *remaining_character, *section_tick]
+                    .into_iter()
+                    .flatten()
+                    .map(|c| char_width(c, None).max(1))
+                    .max()
+                    .unwrap_or(1);
+                let total_columns = dimensions.columns as usize / cell_width;
                 let progress_ratio = (self.current_slide + 1) as f64 / context.total_slides as f64;
-                let columns_ratio = (total_columns as f64 * progress_ratio).ceil();
-                let bar = character.repeat(columns_ratio as usize);
-                let bar = vec![WeightedText::from(StyledText::new(bar, TextStyle::default().colors(colors.clone())))];
-                vec![
-                    RenderOperation::JumpToBottomRow { index: 0 },
-                    RenderOperation::RenderText {
-                        line: bar.into(),
-                        alignment: Alignment::Left { margin: Margin::Fixed(0) },
-                    },
-                ]
+                let filled_columns = (total_columns as f64 * progress_ratio).ceil() as usize;
+                let ticks: Vec<usize> = match section_tick {
+                    Some(_) => context
+                        .section_starts
+                        .iter()
+                        .map(|slide| slide * total_columns / context.total_slides.max(1))
+                        .filter(|column| *column > 0)
+                        .collect(),
+                    None => Vec::new(),
+                };
+                // Without a character for the remaining part, the bar only goes as far as we need it to.
+                let end_column = match remaining_character {
+                    Some(_) => total_columns,
+                    None => ticks.iter().map(|column| column + 1).fold(filled_columns, usize::max).min(total_columns),
+                };
+
+                let mut bar: Vec<(String, &Colors)> = Vec::new();
+                for column in 0..end_column {
+                    let cell_colors = if column < filled_columns { colors } else { remaining_colors };
+                    let cell = match section_tick {
+                        Some(tick) if ticks.contains(&column) => *tick,
+                        _ if column < filled_columns => character,
+                        _ => remaining_character.unwrap_or(' '),
+                    };
+                    match bar.last_mut() {
+                        Some((text, last_colors)) if *last_colors == cell_colors => text.push(cell),
+                        _ => bar.push((cell.to_string(), cell_colors)),
+                    };
+                }
+                let line: Vec<_> = bar
+                    .into_iter()
+                    .map(|(text, colors)| {
+                        WeightedText::from(StyledText::new(text, TextStyle::default().colors(colors.clone())))
+                    })
+                    .collect();
+
+                let thickness = thickness.unwrap_or(1);
+                let mut operations = Vec::new();
+                for row in 0..thickness {
+                    let index = match position {
+                        ProgressBarPosition::Top => dimensions.rows.saturating_sub(row + 1),
+                        ProgressBarPosition::Bottom => thickness - row - 1,
+                    };
+                    operations.extend([
+                        RenderOperation::JumpToBottomRow { index },
+                        RenderOperation::RenderText {
+                            line: line.clone().into(),
+                            alignment: Alignment::Left { margin: Margin::Fixed(0) },
+                        },
+                    ]);
+                }
+                operations
             }
             FooterStyle::Empty => vec![],
         }
@@ -699,6 +1890,33 @@ pub enum BuildError {
     #[error("loading image: {0}")]
     LoadImage(#[from] LoadImageError),
 
+    #[error("loading code: {0}")]
+    LoadCode(#[from] LoadFileError),
+
+    #[error("code continues unknown code id '{0}'")]
+    UnknownCodeId(String),
+
+    #[error("rendering code: {0}")]
+    RenderCode(#[from] ThirdPartyRenderError),
+
+    #[error("invalid slide theme: {0}")]
+    InvalidSlideTheme(String),
+
+    #[error("slide themes must be at the start of a slide")]
+    SlideThemeNotAtStart,
+
+    #[error("loading included file: {0}")]
+    LoadInclude(LoadFileError),
+
+    #[error("file {0} includes itself")]
+    IncludeCycle(PathBuf),
+
+    #[error("failed to parse included file {0}: {1}")]
+    IncludeParse(PathBuf, ParseError),
+
+    #[error("code in this language can't be rendered: {0}")]
+    UnsupportedRender(String),
+
     #[error("invalid presentation metadata: {0}")]
     InvalidMetadata(String),
 
@@ -723,6 +1941,21 @@ pub enum BuildError {
     #[error("need to enter layout column explicitly using `column` command")]
     NotInsideColumn,
 
+    #[error("layout '{0}' is not defined in the theme")]
+    UnknownLayout(String),
+
+    #[error("region '{0}' is not defined in the slide's layout")]
+    UnknownRegion(String),
+
+    #[error("invalid timer duration '{0}'")]
+    InvalidTimer(String),
+
+    #[error("can't generate QR code: {0}")]
+    InvalidQrCode(#[from] qrcode::types::QrError),
+
+    #[error("invalid chart: {0}")]
+    InvalidChart(#[from] ParseChartError),
+
     #[error("error parsing command at line {line}: {error}")]
     CommandParse { line: usize, error: CommandParseError },
 }
@@ -733,9 +1966,56 @@ enum CommentCommand {
     Pause,
     EndSlide,
     #[serde(rename = "column_layout")]
-    InitColumnLayout(Vec<u8>),
+    InitColumnLayout(ColumnLayout),
     Column(usize),
     ResetLayout,
+    JumpToMiddle,
+    Alignment(CommentCommandAlignment),
+    SkipSlide,
+    SlideTheme(serde_yaml::Value),
+    Include(PathBuf),
+    // These are taken as is rather than parsed as YAML so they can contain anything.
+    #[serde(skip)]
+    SpeakerNote(String),
+    Layout(String),
+    Region(String),
+    RevealDelay(u64),
+    ImageAlignment(ImagePosition),
+    ImageFloat(ImageFloat),
+    ImageCaption(bool),
+    BackgroundImage(PathBuf),
+    Timer(String),
+    VerticalAlignment(VerticalAlignment),
+    BigText(String),
+    Qr(String),
+    Typewriter(u32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum ImageFloat {
+    Left,
+    Right,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum CommentCommandAlignment {
+    Left,
+    Center,
+    Right,
+    Justify,
+}
+
+impl From<CommentCommandAlignment> for Alignment {
+    fn from(alignment: CommentCommandAlignment) -> Self {
+        match alignment {
+            CommentCommandAlignment::Left => Self::Left { margin: Margin::Fixed(0) },
+            CommentCommandAlignment::Center => Self::Center { minimum_margin: Margin::Fixed(0), minimum_size: 0 },
+            CommentCommandAlignment::Right => Self::Right { margin: Margin::Fixed(0) },
+            CommentCommandAlignment::Justify => Self::Justify { margin: Margin::Fixed(0) },
+        }
+    }
 }
 
 impl FromStr for CommentCommand {
@@ -745,6 +2025,16 @@ impl FromStr for CommentCommand {
         #[derive(Deserialize)]
         struct CommandWrapper(#[serde(with = "serde_yaml::with::singleton_map")] CommentCommand);
 
+        if let Some(note) = s.trim_start().strip_prefix("speaker_note:") {
+            let note = note.trim();
+            // Notes spanning multiple lines use a YAML block.
+            let note = match note.starts_with(['|', '>']) {
+                true => serde_yaml::from_str(note)?,
+                false => note.to_string(),
+            };
+            return Ok(Self::SpeakerNote(note));
+        }
+
         let wrapper = serde_yaml::from_str::<CommandWrapper>(s)?;
         Ok(wrapper.0)
     }
@@ -763,65 +2053,225 @@ impl Display for CommandParseError {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 struct RunCodeOperationInner {
     handle: Option<ExecutionHandle>,
     output_lines: Vec<String>,
+    error_lines: Vec<String>,
+    exit: Option<ProcessExit>,
+    status: ProcessStatus,
+    input_line: String,
+    scroll_offset: usize,
+    follow_output: bool,
+    started_at: Option<Instant>,
     state: RenderOnDemandState,
 }
 
+/// A piece of code that highlights a different group of lines each time the presentation moves forward.
+#[derive(Debug)]
+struct HighlightGroupsOperation {
+    group_operations: Vec<Vec<RenderOperation>>,
+    current: Cell<usize>,
+}
+
+impl AsRenderOperations for HighlightGroupsOperation {
+    fn as_render_operations(&self, _dimensions: &WindowSize) -> Vec<RenderOperation> {
+        self.group_operations[self.current.get()].clone()
+    }
+}
+
+impl ChunkMutator for HighlightGroupsOperation {
+    fn mutate_next(&self) -> bool {
+        let current = self.current.get();
+        if current + 1 < self.group_operations.len() {
+            self.current.set(current + 1);
+            true
+        } else {
+            false
+        }
+    }
+
+    fn mutate_previous(&self) -> bool {
+        let current = self.current.get();
+        if current > 0 {
+            self.current.set(current - 1);
+            true
+        } else {
+            false
+        }
+    }
+
+    fn reset_mutations(&self) {
+        self.current.set(0);
+    }
+
+    fn apply_all_mutations(&self) {
+        self.current.set(self.group_operations.len() - 1);
+    }
+}
+
 #[derive(Debug)]
 pub(crate) struct RunCodeOperation {
     code: Code,
+    executer: Rc<CodeExecuter>,
     default_colors: Colors,
     block_colors: Colors,
+    error_colors: Colors,
+    max_height: Option<u16>,
+    overflow: OutputOverflow,
+    max_scroll_offset: Cell<usize>,
+    /// The alignment of the output when it's displayed instead of the code rather than in a block below it.
+    replace_alignment: Option<Alignment>,
     inner: Rc<RefCell<RunCodeOperationInner>>,
 }
 
 impl RunCodeOperation {
-    fn new(code: Code, default_colors: Colors, block_colors: Colors) -> Self {
-        let inner =
-            RunCodeOperationInner { handle: None, output_lines: Vec::new(), state: RenderOnDemandState::default() };
-        Self { code, default_colors, block_colors, inner: Rc::new(RefCell::new(inner)) }
+    fn new(
+        code: Code,
+        executer: Rc<CodeExecuter>,
+        default_colors: Colors,
+        style: &ExecutionOutputBlockStyle,
+        inner: Rc<RefCell<RunCodeOperationInner>>,
+    ) -> Self {
+        Self {
+            code,
+            executer,
+            default_colors,
+            block_colors: style.colors.clone(),
+            error_colors: style.stderr_colors(),
+            max_height: style.max_height,
+            overflow: style.overflow(),
+            max_scroll_offset: Default::default(),
+            replace_alignment: None,
+            inner,
+        }
+    }
+
+    /// Display the output instead of the code that generates it, starting as soon as it's visible.
+    fn replacing_code(mut self, alignment: Alignment) -> Self {
+        self.replace_alignment = Some(alignment);
+        self
+    }
+
+    fn render_replacement(&self, inner: &RunCodeOperationInner, alignment: &Alignment) -> Vec<RenderOperation> {
+        let mut lines = Vec::new();
+        for (source, colors) in [(&inner.output_lines, &self.block_colors), (&inner.error_lines, &self.error_colors)] {
+            let mut splitter = AnsiSplitter::new(colors.clone());
+            for line in source {
+                let spans = splitter.split_line(line);
+                lines.push((styled_line_width(&spans), spans, colors));
+            }
+        }
+        // There's no heading to show failures in so they're displayed along with the output.
+        if let Some(exit) = inner.exit.as_ref().filter(|exit| !exit.is_success()) {
+            let text = format!("[{exit}]");
+            lines.push((text.width(), vec![StyledText::from(text)], &self.error_colors));
+        }
+        let block_length = lines.iter().map(|(length, _, _)| *length).max().unwrap_or(0);
+        let mut operations = Vec::new();
+        for (length, spans, colors) in lines {
+            operations.push(RenderOperation::SetColors(colors.clone()));
+            operations.push(RenderOperation::RenderPreformattedLine(PreformattedLine {
+                spans,
+                unformatted_length: length,
+                block_length,
+                alignment: alignment.clone(),
+                overflow: None,
+                raw: false,
+            }));
+            operations.push(RenderOperation::RenderLineBreak);
+        }
+        operations.push(RenderOperation::SetColors(self.default_colors.clone()));
+        operations
     }
 
     fn render_line(&self, line: String) -> RenderOperation {
-        let line_len = line.len();
+        let line_len = line.width();
+        self.render_styled_line(vec![StyledText::from(line)], line_len)
+    }
+
+    fn render_styled_line(&self, spans: Vec<StyledText>, length: usize) -> RenderOperation {
         RenderOperation::RenderPreformattedLine(PreformattedLine {
-            text: line,
-            unformatted_length: line_len,
-            block_length: line_len,
+            spans,
+            unformatted_length: length,
+            block_length: length,
             alignment: Default::default(),
+            overflow: None,
+            raw: false,
         })
     }
 }
 
 impl AsRenderOperations for RunCodeOperation {
     fn as_render_operations(&self, dimensions: &WindowSize) -> Vec<RenderOperation> {
-        let inner = self.inner.borrow();
+        let captures_input = self.captures_input();
+        let mut inner = self.inner.borrow_mut();
         if matches!(inner.state, RenderOnDemandState::NotStarted) {
             return Vec::new();
         }
-        let state = match inner.state {
-            RenderOnDemandState::Rendered => "done",
-            _ => "running",
+        if let Some(alignment) = &self.replace_alignment {
+            return self.render_replacement(&inner, alignment);
+        }
+        let state: Cow<str> = match inner.state {
+            RenderOnDemandState::Rendered => match inner.status {
+                ProcessStatus::Aborted => "aborted".into(),
+                ProcessStatus::TimedOut => "timed out".into(),
+                _ => "done".into(),
+            },
+            _ => {
+                // Let the audience know something is happening while this runs.
+                let elapsed = inner.started_at.map(|start| start.elapsed()).unwrap_or_default();
+                let frame = SPINNER_FRAMES[(elapsed.as_millis() / 100) as usize % SPINNER_FRAMES.len()];
+                format!("running {frame} {}s", elapsed.as_secs()).into()
+            }
+        };
+        let mut lines = Vec::new();
+        for (source, colors) in [(&inner.output_lines, &self.block_colors), (&inner.error_lines, &self.error_colors)] {
+            let mut splitter = AnsiSplitter::new(colors.clone());
+            for line in source {
+                let chunks = splitter.split_line(line);
+                let columns = dimensions.columns as usize;
+                for (line, length) in wrap_styled_line(chunks, columns, &self.overflow) {
+                    lines.push((line, length, colors));
+                }
+            }
+        }
+        // Only render the window of lines that fits within the max height.
+        let max_height = self.max_height.map(usize::from).unwrap_or(usize::MAX);
+        let max_scroll_offset = lines.len().saturating_sub(max_height);
+        self.max_scroll_offset.set(max_scroll_offset);
+        if inner.follow_output {
+            inner.scroll_offset = max_scroll_offset;
+        }
+        let start = inner.scroll_offset.min(max_scroll_offset);
+        let end = lines.len().min(start.saturating_add(max_height));
+
+        let heading = match max_scroll_offset {
+            0 => format!(" [{state}] "),
+            _ => format!(" [{state}] [{}-{}/{}] ", start + 1, end, lines.len()),
         };
-        let heading = format!(" [{state}] ");
         let separator = RenderSeparator::new(heading);
         let mut operations = vec![
             RenderOperation::RenderLineBreak,
             RenderOperation::RenderDynamic(Rc::new(separator)),
             RenderOperation::RenderLineBreak,
             RenderOperation::RenderLineBreak,
-            RenderOperation::SetColors(self.block_colors.clone()),
         ];
-
-        for line in &inner.output_lines {
-            let chunks = line.chars().chunks(dimensions.columns as usize);
-            for chunk in &chunks {
-                operations.push(self.render_line(chunk.collect()));
-                operations.push(RenderOperation::RenderLineBreak);
-            }
+        for (line, length, colors) in lines.drain(start..end) {
+            operations.push(RenderOperation::SetColors(colors.clone()));
+            operations.push(self.render_styled_line(line, length));
+            operations.push(RenderOperation::RenderLineBreak);
+        }
+        if captures_input {
+            operations.push(RenderOperation::SetColors(self.block_colors.clone()));
+            operations.push(self.render_line(format!("{}_", inner.input_line)));
+            operations.push(RenderOperation::RenderLineBreak);
+        }
+        if let Some(exit) = &inner.exit {
+            let colors = if exit.is_success() { &self.block_colors } else { &self.error_colors };
+            operations.push(RenderOperation::SetColors(colors.clone()));
+            operations.push(self.render_line(format!("[{exit}]")));
+            operations.push(RenderOperation::RenderLineBreak);
         }
         operations.push(RenderOperation::SetColors(self.default_colors.clone()));
         operations
@@ -833,15 +2283,19 @@ impl RenderOnDemand for RunCodeOperation {
         let mut inner = self.inner.borrow_mut();
         if let Some(handle) = inner.handle.as_mut() {
             let state = handle.state();
-            let ExecutionState { output, status } = state;
+            let ExecutionState { output, stderr, status, exit } = state;
             if status.is_finished() {
                 inner.handle.take();
                 inner.state = RenderOnDemandState::Rendered;
             }
             inner.output_lines = output;
-            if matches!(status, ProcessStatus::Failure) {
-                inner.output_lines.push("[finished with error]".to_string());
+            inner.error_lines = stderr;
+            inner.exit = exit;
+            // If we couldn't figure out how the process exited, at least let the user know it failed.
+            if matches!(status, ProcessStatus::Failure) && exit.is_none() {
+                inner.error_lines.push("[finished with error]".to_string());
             }
+            inner.status = status;
         }
         inner.state.clone()
     }
@@ -851,19 +2305,110 @@ impl RenderOnDemand for RunCodeOperation {
         if !matches!(inner.state, RenderOnDemandState::NotStarted) {
             return false;
         }
-        match CodeExecuter::execute(&self.code) {
+        match self.executer.execute(&self.code) {
             Ok(handle) => {
                 inner.handle = Some(handle);
+                inner.follow_output = true;
+                inner.started_at = Some(Instant::now());
                 inner.state = RenderOnDemandState::Rendering;
                 true
             }
             Err(e) => {
-                inner.output_lines = vec![e.to_string()];
+                inner.error_lines = vec![e.to_string()];
+                inner.status = ProcessStatus::Failure;
                 inner.state = RenderOnDemandState::Rendered;
                 true
             }
         }
     }
+
+    fn renders_automatically(&self) -> bool {
+        self.code.flags.auto_execute || self.replace_alignment.is_some()
+    }
+
+    fn runs_code(&self) -> bool {
+        true
+    }
+
+    fn render_step(&self) -> Option<u32> {
+        self.code.flags.step
+    }
+
+    fn abort_render(&self) -> bool {
+        let inner = self.inner.borrow();
+        let Some(handle) = inner.handle.as_ref() else {
+            return false;
+        };
+        handle.kill().is_ok()
+    }
+
+    fn reset_render(&self) -> bool {
+        let mut inner = self.inner.borrow_mut();
+        if let Some(handle) = inner.handle.take() {
+            let _ = handle.kill();
+        }
+        let was_started = !matches!(inner.state, RenderOnDemandState::NotStarted);
+        *inner = RunCodeOperationInner::default();
+        was_started
+    }
+
+    fn render_error(&self) -> Option<String> {
+        let inner = self.inner.borrow();
+        let summary = match (&inner.status, &inner.exit) {
+            (ProcessStatus::Success | ProcessStatus::Running, _) => return None,
+            (ProcessStatus::Aborted, _) => "aborted".to_string(),
+            (ProcessStatus::TimedOut, _) => "timed out".to_string(),
+            (ProcessStatus::Failure, Some(exit)) => exit.to_string(),
+            (ProcessStatus::Failure, None) => "failed".to_string(),
+        };
+        let lines = iter::once(summary).chain(inner.error_lines.iter().cloned());
+        Some(lines.collect::<Vec<_>>().join("\n"))
+    }
+
+    fn scroll(&self, lines: isize) -> bool {
+        let mut inner = self.inner.borrow_mut();
+        let current = inner.scroll_offset.min(self.max_scroll_offset.get());
+        let next = current.saturating_add_signed(lines).min(self.max_scroll_offset.get());
+        inner.scroll_offset = next;
+        // Scrolling away from the bottom stops following the output and going back to it resumes it.
+        inner.follow_output = next == self.max_scroll_offset.get() && lines > 0;
+        current != next
+    }
+
+    fn toggle_follow(&self) -> bool {
+        let mut inner = self.inner.borrow_mut();
+        if matches!(inner.state, RenderOnDemandState::NotStarted) {
+            return false;
+        }
+        inner.follow_output = !inner.follow_output;
+        true
+    }
+
+    fn captures_input(&self) -> bool {
+        let inner = self.inner.borrow();
+        inner.handle.as_ref().map(|handle| handle.is_interactive()).unwrap_or(false)
+    }
+
+    fn send_input(&self, input: &WidgetInput) -> bool {
+        let mut inner = self.inner.borrow_mut();
+        let inner = &mut *inner;
+        let Some(handle) = inner.handle.as_mut() else {
+            return false;
+        };
+        match input {
+            WidgetInput::Char(c) => inner.input_line.push(*c),
+            WidgetInput::Backspace => {
+                inner.input_line.pop();
+            }
+            WidgetInput::Enter => {
+                let line = mem::take(&mut inner.input_line);
+                // If the process is gone there's nothing else to do.
+                let _ = handle.send_line(&line);
+            }
+            WidgetInput::EndOfInput => handle.close_input(),
+        };
+        true
+    }
 }
 
 #[derive(Clone, Debug, Default)]
@@ -889,7 +2434,7 @@ impl AsRenderOperations for RenderSeparator {
         let separator = match self.heading.is_empty() {
             true => character.repeat(dimensions.columns as usize),
             false => {
-                let dashes_len = (dimensions.columns as usize).saturating_sub(self.heading.len()) / 2;
+                let dashes_len = (dimensions.columns as usize).saturating_sub(self.heading.width()) / 2;
                 let dashes = character.repeat(dashes_len);
                 let heading = &self.heading;
                 format!("{dashes}{heading}{dashes}")
@@ -899,6 +2444,135 @@ impl AsRenderOperations for RenderSeparator {
     }
 }
 
+/// The error that happened the last time a piece of code that takes over the terminal was executed.
+#[derive(Debug)]
+struct RenderTerminalSnippetError {
+    error: Rc<RefCell<Option<String>>>,
+    colors: Colors,
+    default_colors: Colors,
+    alignment: Alignment,
+}
+
+impl AsRenderOperations for RenderTerminalSnippetError {
+    fn as_render_operations(&self, _dimensions: &WindowSize) -> Vec<RenderOperation> {
+        let Some(error) = self.error.borrow().clone() else {
+            return Vec::new();
+        };
+        let mut operations = vec![RenderOperation::RenderLineBreak, RenderOperation::SetColors(self.colors.clone())];
+        for line in error.lines() {
+            operations.extend([
+                RenderOperation::RenderText { line: line.to_string().into(), alignment: self.alignment.clone() },
+                RenderOperation::RenderLineBreak,
+            ]);
+        }
+        operations.push(RenderOperation::SetColors(self.default_colors.clone()));
+        operations
+    }
+}
+
+/// A piece of text drawn using large glyphs, or as is if it doesn't fit in the screen or the font can't draw it.
+#[derive(Debug)]
+struct RenderBigText {
+    text: String,
+    colors: Colors,
+    alignment: Alignment,
+}
+
+impl AsRenderOperations for RenderBigText {
+    fn as_render_operations(&self, dimensions: &WindowSize) -> Vec<RenderOperation> {
+        let style = TextStyle::default().colors(self.colors.clone());
+        let (rows, style) = match big_text(&self.text) {
+            Some(rows) if rows.iter().all(|row| row.width() <= dimensions.columns as usize) => (rows, style),
+            _ => (vec![self.text.clone()], style.bold()),
+        };
+        let mut operations = Vec::new();
+        for row in rows {
+            let text = WeightedText::from(StyledText::new(row, style.clone()));
+            operations.extend([
+                RenderOperation::RenderText { line: vec![text].into(), alignment: self.alignment.clone() },
+                RenderOperation::RenderLineBreak,
+            ]);
+        }
+        operations
+    }
+}
+
+/// A QR code that's as big as it can be, or the data in it as text if it doesn't fit in the screen.
+#[derive(Debug)]
+struct RenderQrCode {
+    code: QrCode,
+    data: String,
+    colors: Colors,
+    alignment: Alignment,
+}
+
+impl AsRenderOperations for RenderQrCode {
+    fn as_render_operations(&self, dimensions: &WindowSize) -> Vec<RenderOperation> {
+        // Leave some room for whatever else is in the slide.
+        let max_rows = dimensions.rows as usize * 2 / 3;
+        let (rows, style) = match self.code.fitting_scale(dimensions.columns as usize, max_rows) {
+            Some(scale) => (self.code.rows(scale), TextStyle::default().colors(self.colors.clone())),
+            None => (vec![self.data.clone()], TextStyle::default()),
+        };
+        let mut operations = Vec::new();
+        for row in rows {
+            let text = WeightedText::from(StyledText::new(row, style.clone()));
+            operations.extend([
+                RenderOperation::RenderText { line: vec![text].into(), alignment: self.alignment.clone() },
+                RenderOperation::RenderLineBreak,
+            ]);
+        }
+        operations
+    }
+}
+
+/// A chart that takes up the whole width of the screen.
+#[derive(Debug)]
+struct RenderChart {
+    chart: Chart,
+    colors: Colors,
+    axis_colors: Colors,
+    height: u16,
+    alignment: Alignment,
+}
+
+impl RenderChart {
+    fn render_line(&self, line: ChartLine) -> RenderOperation {
+        let texts: Vec<_> = line
+            .0
+            .into_iter()
+            .map(|(text, part)| {
+                let colors = match part {
+                    ChartPart::Axis => self.axis_colors.clone(),
+                    ChartPart::Plot => self.colors.clone(),
+                };
+                WeightedText::from(StyledText::new(text, TextStyle::default().colors(colors)))
+            })
+            .collect();
+        RenderOperation::RenderText { line: WeightedLine::from(texts), alignment: self.alignment.clone() }
+    }
+}
+
+impl AsRenderOperations for RenderChart {
+    fn as_render_operations(&self, dimensions: &WindowSize) -> Vec<RenderOperation> {
+        // Don't let line charts push everything else off the screen.
+        let rows = self.height.min(dimensions.rows / 2) as usize;
+        let mut operations = Vec::new();
+        if let Some(title) = self.chart.title() {
+            let style = TextStyle::default().bold().colors(self.axis_colors.clone());
+            let text = WeightedText::from(StyledText::new(title, style));
+            operations.extend([
+                RenderOperation::RenderText { line: vec![text].into(), alignment: self.alignment.clone() },
+                RenderOperation::RenderLineBreak,
+            ]);
+        }
+        for line in self.chart.render(dimensions.columns as usize, rows) {
+            operations.extend([self.render_line(line), RenderOperation::RenderLineBreak]);
+        }
+        operations
+    }
+}
+
 struct ListIterator<I> {
     remaining: I,
     next_index: usize,
@@ -949,22 +2623,77 @@ struct IndexedListItem {
     item: ListItem,
 }
 
+/// Get the anchor links use to point to a title, the same way GitHub generates them.
+///
+/// e.g. `Architecture Overview!` turns into `architecture-overview`.
+fn anchor_for(text: &Text) -> String {
+    let mut anchor = String::new();
+    for c in text.chunks.iter().flat_map(|chunk| chunk.text.chars()) {
+        if c.is_alphanumeric() || c == '_' || c == '-' {
+            anchor.extend(c.to_lowercase());
+        } else if c == ' ' {
+            anchor.push('-');
+        }
+    }
+    anchor
+}
+
+/// Replace the tabs in a line with spaces up to the next multiple of 8 columns.
+fn expand_tabs(line: &str) -> String {
+    let mut expanded = String::new();
+    let mut previous = None;
+    let mut width = 0;
+    for c in line.chars() {
+        if c == '\t' {
+            let spaces = 8 - width % 8;
+            expanded.push_str(&" ".repeat(spaces));
+            width += spaces;
+        } else {
+            expanded.push(c);
+            width += char_width(c, previous);
+        }
+        previous = Some(c);
+    }
+    expanded
+}
+
 #[cfg(test)]
 mod test {
     use rstest::rstest;
 
     use super::*;
-    use crate::{markdown::elements::CodeLanguage, presentation::PreformattedLine};
+    use crate::{
+        config::LatexConfig, markdown::elements::CodeFlags, presentation::PreformattedLine, style::Color,
+        theme::ListNumbering,
+    };
+    use std::{io::Write, thread};
 
     fn build_presentation(elements: Vec<MarkdownElement>) -> Presentation {
         try_build_presentation(elements).expect("build failed")
     }
 
     fn try_build_presentation(elements: Vec<MarkdownElement>) -> Result<Presentation, BuildError> {
+        try_build_presentation_with(&Default::default(), Default::default(), elements)
+    }
+
+    fn build_presentation_with(
+        theme: &PresentationTheme,
+        options: PresentationBuilderOptions,
+        elements: Vec<MarkdownElement>,
+    ) -> Presentation {
+        try_build_presentation_with(theme, options, elements).expect("build failed")
+    }
+
+    fn try_build_presentation_with(
+        theme: &PresentationTheme,
+        options: PresentationBuilderOptions,
+        elements: Vec<MarkdownElement>,
+    ) -> Result<Presentation, BuildError> {
         let highlighter = CodeHighlighter::new("base16-ocean.dark").unwrap();
-        let theme = PresentationTheme::default();
-        let mut resources = Resources::new("/tmp");
-        let builder = PresentationBuilder::new(highlighter, &theme, &mut resources);
+        // Images used in tests are taken from the examples directory.
+        let mut resources = Resources::new("examples");
+        let mut execution_cache = ExecutionCache::default();
+        let builder = PresentationBuilder::new(highlighter, theme, &mut resources, &mut execution_cache, options);
         builder.build(elements)
     }
 
@@ -990,15 +2719,18 @@ mod test {
             ClearScreen
             | SetColors(_)
             | JumpToVerticalCenter
+            | AlignVertically { .. }
             | JumpToBottomRow { .. }
             | InitColumnLayout { .. }
             | EnterColumn { .. }
             | ExitLayout { .. }
+            | ExitNestedLayout
             | ApplyMargin(_)
             | PopMargin => false,
             RenderText { .. }
             | RenderLineBreak
-            | RenderImage(_)
+            | RenderImage(..)
+            | RenderBackgroundImage(_)
             | RenderPreformattedLine(_)
             | RenderDynamic(_)
             | RenderOnDemand(_) => true,
@@ -1031,6 +2763,37 @@ mod test {
         extract_text_lines(&operations)
     }
 
+    /// Get the text in a preformatted line without any of its styling.
+    fn plain_text(line: &PreformattedLine) -> String {
+        line.spans.iter().map(|span| span.text.as_str()).collect()
+    }
+
+    /// Render every dynamic and on demand operation and get the lines of text each of them produces.
+    fn render_dynamic_lines<'a>(
+        operations: impl IntoIterator<Item = &'a RenderOperation>,
+        dimensions: &WindowSize,
+    ) -> Vec<Vec<String>> {
+        operations
+            .into_iter()
+            .filter_map(|operation| match operation {
+                RenderOperation::RenderDynamic(generator) => Some(generator.as_render_operations(dimensions)),
+                RenderOperation::RenderOnDemand(generator) => Some(generator.as_render_operations(dimensions)),
+                _ => None,
+            })
+            .map(|operations| {
+                operations
+                    .iter()
+                    .filter_map(|operation| match operation {
+                        RenderOperation::RenderText { line, .. } => {
+                            Some(line.iter_texts().map(|text| text.text.text.clone()).collect())
+                        }
+                        _ => None,
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
     #[test]
     fn prelude_appears_once() {
         let elements = vec![
@@ -1072,16 +2835,73 @@ mod test {
         }
     }
 
+    #[test]
+    fn hidden_code_lines() {
+        let contents = "/// cd /tmp\n///\necho hi\n".into();
+        let flags = CodeFlags { execute: true, ..Default::default() };
+        let code = Code { contents, language: CodeLanguage::Shell("sh".into()), flags };
+        let presentation = build_presentation(vec![MarkdownElement::Code(code.into())]);
+        let slides = presentation.into_slides();
+        let lengths: Vec<_> = slides[0]
+            .iter_operations()
+            .filter_map(|op| match op {
+                RenderOperation::RenderPreformattedLine(line) => Some(line.unformatted_length),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(lengths, &["echo hi".len()]);
+    }
+
+    #[test]
+    fn terminal_snippets() {
+        let flags = CodeFlags { execute: true, acquire_terminal: true, ..Default::default() };
+        let code = Code { contents: "vim".into(), language: CodeLanguage::Shell("sh".into()), flags };
+        let presentation = build_presentation(vec![MarkdownElement::Code(code.into())]);
+        let snippets = presentation.current_terminal_snippets();
+        assert_eq!(snippets.len(), 1);
+
+        let on_demand = presentation
+            .current_slide()
+            .iter_operations()
+            .filter(|op| matches!(op, RenderOperation::RenderOnDemand(_)))
+            .count();
+        assert_eq!(on_demand, 0);
+
+        // Errors from running them are displayed in the slide.
+        let dimensions = WindowSize { rows: 10, columns: 40, width: 0, height: 0, has_pixels: false };
+        let lines = || render_dynamic_lines(presentation.current_slide().iter_operations(), &dimensions).concat();
+        assert!(!lines().contains(&"[exit: 1]".to_string()));
+        *snippets[0].error.borrow_mut() = Some("[exit: 1]".into());
+        assert!(lines().contains(&"[exit: 1]".to_string()));
+    }
+
+    #[test]
+    fn last_visible_code_block() {
+        let build_code = |contents: &str| {
+            MarkdownElement::Code(Box::new(Code {
+                contents: contents.into(),
+                language: CodeLanguage::Shell("sh".into()),
+                flags: Default::default(),
+            }))
+        };
+        let elements = vec![build_code("echo hi\n"), build_pause(), build_code("echo bye\n")];
+        let mut presentation = build_presentation(elements);
+        assert_eq!(presentation.last_visible_code_block(), Some("echo hi"));
+
+        presentation.jump_next_slide();
+        assert_eq!(presentation.last_visible_code_block(), Some("echo bye"));
+    }
+
     #[test]
     fn preformatted_blocks_account_for_unicode_widths() {
         let text = "苹果".to_string();
         let elements = vec![
-            MarkdownElement::BlockQuote(vec![text.clone()]),
-            MarkdownElement::Code(Code {
+            MarkdownElement::BlockQuote { lines: vec![text.clone()], attribution: None },
+            MarkdownElement::Code(Box::new(Code {
                 contents: text.clone(),
-                language: CodeLanguage::Unknown,
+                language: CodeLanguage::Unknown(String::new()),
                 flags: Default::default(),
-            }),
+            })),
         ];
         let presentation = build_presentation(elements);
         let slides = presentation.into_slides();
@@ -1100,18 +2920,116 @@ mod test {
         assert_eq!(lengths[1], (width, width));
     }
 
+    #[test]
+    fn ansi_code() {
+        let code = Code {
+            contents: "\x1b[31mred\x1b[0m plain\x1b[2K".into(),
+            language: CodeLanguage::Ansi,
+            flags: Default::default(),
+        };
+        let presentation = build_presentation(vec![MarkdownElement::Code(code.into())]);
+        let slides = presentation.into_slides();
+        let lines: Vec<_> = slides[0]
+            .iter_operations()
+            .filter_map(|op| match op {
+                RenderOperation::RenderPreformattedLine(line) => Some(line.clone()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0].unformatted_length, "red plain".len());
+        assert_eq!(lines[0].block_length, "red plain".len());
+        assert_eq!(plain_text(&lines[0]), "red plain");
+        assert_eq!(lines[0].spans[0].text, "red");
+        assert_eq!(lines[0].spans[0].style.colors.foreground, Some(Color::new(205, 0, 0)));
+    }
+
+    #[test]
+    fn render_unsupported_language() {
+        let flags = CodeFlags { render: true, ..Default::default() };
+        let code = Code { contents: "a".into(), language: CodeLanguage::Rust, flags };
+        let result = try_build_presentation(vec![MarkdownElement::Code(code.into())]);
+        assert!(matches!(result, Err(BuildError::UnsupportedRender(_))));
+    }
+
+    #[test]
+    fn render_missing_tool() {
+        let latex = LatexConfig { command: Some(vec!["presenterm-surely-not-installed".into()]) };
+        let third_party = ThirdPartyRender::new(Default::default(), latex, Default::default());
+        let options = PresentationBuilderOptions { third_party: Rc::new(third_party), ..Default::default() };
+        let flags = CodeFlags { render: true, ..Default::default() };
+        let code = Code { contents: "x^2".into(), language: CodeLanguage::Latex, flags };
+
+        let presentation =
+            build_presentation_with(&Default::default(), options, vec![MarkdownElement::Code(code.into())]);
+
+        // The code is displayed as is instead.
+        let slides = presentation.into_slides();
+        let code_lines = slides[0]
+            .iter_operations()
+            .filter(
+                |op| matches!(op, RenderOperation::RenderPreformattedLine(line) if plain_text(line).contains("x^2")),
+            )
+            .count();
+        assert_eq!(code_lines, 1);
+    }
+
+    #[test]
+    fn code_title() {
+        let flags = CodeFlags { title: Some("src/main.rs".into()), ..Default::default() };
+        let code = Code { contents: "a".into(), language: CodeLanguage::Rust, flags };
+        let presentation = build_presentation(vec![MarkdownElement::Code(code.into())]);
+        let slides = presentation.into_slides();
+        let lines: Vec<_> = slides[0]
+            .iter_operations()
+            .filter_map(|op| match op {
+                RenderOperation::RenderPreformattedLine(line) => Some(line.clone()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert!(plain_text(&lines[0]).contains("src/main.rs"));
+        assert_eq!(lines[0].unformatted_length, "src/main.rs".len());
+        // The block is as wide as the title so the code lines up with it.
+        assert_eq!(lines[0].block_length, "src/main.rs".len());
+        assert_eq!(lines[1].block_length, "src/main.rs".len());
+    }
+
     #[test]
     fn table() {
         let elements = vec![MarkdownElement::Table(Table {
             header: TableRow(vec![Text::from("key"), Text::from("value"), Text::from("other")]),
             rows: vec![TableRow(vec![Text::from("potato"), Text::from("bar"), Text::from("yes")])],
+            alignments: vec![ColumnAlignment::Left, ColumnAlignment::Center, ColumnAlignment::Right],
         })];
         let slides = build_presentation(elements).into_slides();
         let lines = extract_slide_text_lines(slides.into_iter().next().unwrap());
-        let expected_lines = &["key    │ value │ other", "───────┼───────┼──────", "potato │ bar   │ yes  "];
+        let expected_lines = &[
+            "┌────────┬───────┬───────┐",
+            "│ key    │ value │ other │",
+            "├────────┼───────┼───────┤",
+            "│ potato │  bar  │   yes │",
+            "└────────┴───────┴───────┘",
+        ];
         assert_eq!(lines, expected_lines);
     }
 
+    #[rstest]
+    #[case::ascii(TableBorder::Ascii, &["+--------+-------+", "| key    | value |", "+--------+-------+", "| potato | bar   |", "+--------+-------+"])]
+    #[case::minimal(TableBorder::Minimal, &["key    │ value", "───────┼──────", "potato │ bar  "])]
+    fn table_borders(#[case] border: TableBorder, #[case] expected: &[&str]) {
+        let elements = vec![MarkdownElement::Table(Table {
+            header: TableRow(vec![Text::from("key"), Text::from("value")]),
+            rows: vec![TableRow(vec![Text::from("potato"), Text::from("bar")])],
+            alignments: Vec::new(),
+        })];
+        let mut theme = PresentationTheme::default();
+        theme.table.border = Some(border);
+        let slides = build_presentation_with(&theme, Default::default(), elements).into_slides();
+        let lines = extract_slide_text_lines(slides.into_iter().next().unwrap());
+        assert_eq!(lines, expected);
+    }
+
     #[test]
     fn layout_without_init() {
         let elements = vec![build_column(0)];
@@ -1155,128 +3073,1835 @@ mod test {
     fn operation_without_enter_column() {
         let elements = vec![
             MarkdownElement::Comment { comment: "column_layout: [1]".into(), source_position: Default::default() },
-            MarkdownElement::ThematicBreak,
+            MarkdownElement::ThematicBreak(Default::default()),
         ];
         let result = try_build_presentation(elements);
         assert!(result.is_err());
     }
 
+    #[test]
+    fn thematic_break_slide_separators() {
+        let options =
+            PresentationBuilderOptions { slide_separators: vec![ThematicBreakMarker::Asterisks], ..Default::default() };
+        let elements = vec![
+            MarkdownElement::Paragraph(vec![ParagraphElement::Text("hi".into())]),
+            MarkdownElement::ThematicBreak(ThematicBreakMarker::Asterisks),
+            MarkdownElement::Paragraph(vec![ParagraphElement::Text("bye".into())]),
+            MarkdownElement::ThematicBreak(ThematicBreakMarker::Dashes),
+        ];
+        let slides = build_presentation_with(&Default::default(), options, elements).into_slides();
+        assert_eq!(slides.len(), 2);
+        let separators = |slide: &Slide| {
+            slide.iter_operations().filter(|op| matches!(op, RenderOperation::RenderDynamic(_))).count()
+        };
+        // The first slide's footer is the only dynamic operation in it while the second one also has the separator.
+        assert_eq!(separators(&slides[1]) - separators(&slides[0]), 1);
+        assert_eq!(extract_slide_text_lines(slides[1].clone())[0], "bye");
+    }
+
     #[rstest]
-    #[case::pause("pause", CommentCommand::Pause)]
-    #[case::pause(" pause ", CommentCommand::Pause)]
-    #[case::end_slide("end_slide", CommentCommand::EndSlide)]
-    #[case::column_layout("column_layout: [1, 2]", CommentCommand::InitColumnLayout(vec![1, 2]))]
-    #[case::column("column: 1", CommentCommand::Column(1))]
-    #[case::reset_layout("reset_layout", CommentCommand::ResetLayout)]
-    fn command_formatting(#[case] input: &str, #[case] expected: CommentCommand) {
-        let parsed: CommentCommand = input.parse().expect("deserialization failed");
-        assert_eq!(parsed, expected);
+    #[case::content_before(true, 3)]
+    #[case::heading_first(false, 2)]
+    fn headings_start_slides(#[case] content_before: bool, #[case] expected_slides: usize) {
+        let options = PresentationBuilderOptions { slide_level: Some(2), ..Default::default() };
+        let mut elements = vec![
+            MarkdownElement::Comment { comment: "{{{".into(), source_position: Default::default() },
+            MarkdownElement::Heading { text: "One".into(), level: 1 },
+            MarkdownElement::Paragraph(vec![ParagraphElement::Text("hi".into())]),
+            MarkdownElement::Heading { text: "Details".into(), level: 3 },
+            MarkdownElement::SetexHeading { text: "Two".into(), level: 2 },
+            MarkdownElement::Paragraph(vec![ParagraphElement::Text("bye".into())]),
+        ];
+        if content_before {
+            elements.insert(0, MarkdownElement::Paragraph(vec![ParagraphElement::Text("intro".into())]));
+        }
+        let slides = build_presentation_with(&Default::default(), options, elements).into_slides();
+        assert_eq!(slides.len(), expected_slides);
+        let lines = extract_slide_text_lines(slides.into_iter().last().unwrap());
+        assert_eq!(lines, &["Two", "bye"]);
     }
 
-    #[test]
-    fn end_slide_inside_layout() {
-        let elements = vec![build_column_layout(1), build_end_slide()];
-        let presentation = build_presentation(elements);
-        assert_eq!(presentation.iter_slides().count(), 2);
+    #[rstest]
+    #[case::default(None, "pause", " a doge ", &["a doge", "a doge"])]
+    #[case::disabled(Some(false), "pause", "a doge", &[])]
+    #[case::no_alt_text(None, "pause", "", &[])]
+    #[case::disabled_for_image(None, "image_caption: false", "a doge", &["a doge"])]
+    #[case::enabled_for_image(Some(false), "image_caption: true", "a doge", &["a doge"])]
+    fn image_captions(
+        #[case] enabled: Option<bool>,
+        #[case] comment: &str,
+        #[case] alt_text: &str,
+        #[case] expected: &[&str],
+    ) {
+        let mut theme = PresentationTheme::default();
+        theme.image.caption.enabled = enabled;
+        let image = || MarkdownElement::Image {
+            path: "doge.png".into(),
+            alt_text: alt_text.into(),
+            source_position: Default::default(),
+        };
+        // The comment command only applies to the image right after it.
+        let elements = vec![
+            MarkdownElement::Comment { comment: comment.into(), source_position: Default::default() },
+            image(),
+            image(),
+        ];
+        let slides = build_presentation_with(&theme, Default::default(), elements).into_slides();
+        let lines = extract_slide_text_lines(slides.into_iter().next().unwrap());
+        assert_eq!(lines, expected);
     }
 
     #[test]
-    fn end_slide_inside_column() {
-        let elements = vec![build_column_layout(1), build_column(0), build_end_slide()];
-        let presentation = build_presentation(elements);
-        assert_eq!(presentation.iter_slides().count(), 2);
+    fn variables() {
+        let elements = vec![
+            MarkdownElement::FrontMatter("author: bob\nvariables:\n  event: RustConf".into()),
+            MarkdownElement::Paragraph(vec![ParagraphElement::Text(Text {
+                chunks: vec![
+                    StyledText::from("{{event}} by {{ author }} on slide {{current_slide}} "),
+                    StyledText::new("{{author}}", TextStyle::default().code()),
+                ],
+            })]),
+        ];
+        let slides = build_presentation(elements).into_slides();
+        // The intro slide is the first one.
+        let lines = extract_slide_text_lines(slides.into_iter().nth(1).unwrap());
+        assert_eq!(lines, &["RustConf by bob on slide 2 {{author}}"]);
+    }
+
+    #[rstest]
+    #[case::defaults("closing_slide: {}", &["Thanks!", "bob"])]
+    #[case::everything(
+        "closing_slide:\n  message: Thanks for coming to {{event}}\n  handle: '@bob'\n  url: https://example.com",
+        &["Thanks for coming to RustConf", "bob", "@bob", "https://example.com"]
+    )]
+    fn closing_slide(#[case] metadata: &str, #[case] expected: &[&str]) {
+        let front_matter = format!("author: bob\nvariables:\n  event: RustConf\n{metadata}");
+        let elements = vec![
+            MarkdownElement::FrontMatter(front_matter),
+            MarkdownElement::Paragraph(vec![ParagraphElement::Text(Text::from("hi"))]),
+        ];
+        let slides = build_presentation(elements).into_slides();
+        assert_eq!(slides.len(), 3);
+        let lines = extract_slide_text_lines(slides.into_iter().nth(2).unwrap());
+        assert_eq!(lines, expected);
     }
 
     #[test]
-    fn pause_inside_layout() {
-        let elements = vec![build_column_layout(1), build_pause(), build_column(0)];
-        let presentation = build_presentation(elements);
-        assert_eq!(presentation.iter_slides().count(), 1);
+    fn no_closing_slide() {
+        let elements = vec![
+            MarkdownElement::FrontMatter("author: bob".into()),
+            MarkdownElement::Paragraph(vec![ParagraphElement::Text(Text::from("hi"))]),
+        ];
+        assert_eq!(build_presentation(elements).into_slides().len(), 2);
     }
 
     #[test]
-    fn iterate_list() {
-        let iter = ListIterator::new(
-            vec![
-                ListItem { depth: 0, contents: "0".into(), item_type: ListItemType::Unordered },
-                ListItem { depth: 0, contents: "1".into(), item_type: ListItemType::Unordered },
-                ListItem { depth: 1, contents: "00".into(), item_type: ListItemType::Unordered },
-                ListItem { depth: 1, contents: "01".into(), item_type: ListItemType::Unordered },
-                ListItem { depth: 1, contents: "02".into(), item_type: ListItemType::Unordered },
-                ListItem { depth: 2, contents: "001".into(), item_type: ListItemType::Unordered },
-                ListItem { depth: 0, contents: "2".into(), item_type: ListItemType::Unordered },
-            ],
-            0,
-        );
-        let expected_indexes = [0, 1, 0, 1, 2, 0, 2];
-        let indexes: Vec<_> = iter.map(|item| item.index).collect();
-        assert_eq!(indexes, expected_indexes);
+    fn raw_code() {
+        // Raw code ignores the theme's padding.
+        let front_matter = "theme:\n  override:\n    code:\n      padding:\n        horizontal: 2\n        vertical: 1";
+        let flags = CodeFlags { raw: true, ..Default::default() };
+        let code = Code { contents: "\n+--+  \n|\tx|\n".into(), language: CodeLanguage::Unknown("text".into()), flags };
+        let elements = vec![MarkdownElement::FrontMatter(front_matter.into()), MarkdownElement::Code(code.into())];
+        let slides = build_presentation(elements).into_slides();
+        let lines: Vec<_> = slides[0]
+            .iter_operations()
+            .filter_map(|operation| match operation {
+                RenderOperation::RenderPreformattedLine(line) => Some(line.clone()),
+                _ => None,
+            })
+            .collect();
+        let texts: Vec<_> = lines.iter().map(plain_text).collect();
+        assert_eq!(texts, &["", "+--+  ", "|       x|"]);
+        assert!(lines.iter().all(|line| line.block_length == 10 && line.overflow.is_none() && line.raw));
     }
 
     #[test]
-    fn iterate_list_starting_from_other() {
-        let list = ListIterator::new(
-            vec![
-                ListItem { depth: 0, contents: "0".into(), item_type: ListItemType::Unordered },
-                ListItem { depth: 0, contents: "1".into(), item_type: ListItemType::Unordered },
-            ],
-            3,
-        );
-        let expected_indexes = [3, 4];
-        let indexes: Vec<_> = list.into_iter().map(|item| item.index).collect();
-        assert_eq!(indexes, expected_indexes);
+    fn typewriter() {
+        let elements = vec![
+            MarkdownElement::Comment { comment: "typewriter: 20".into(), source_position: Default::default() },
+            MarkdownElement::Paragraph(vec![ParagraphElement::Text(Text::from("hello"))]),
+            MarkdownElement::Paragraph(vec![ParagraphElement::Text(Text::from("bye"))]),
+        ];
+        let slides = build_presentation(elements).into_slides();
+        let dimensions = WindowSize { rows: 20, columns: 40, width: 0, height: 0, has_pixels: false };
+        let typewriters = slides[0].iter_operations().filter(|op| matches!(op, RenderOperation::RenderOnDemand(_)));
+        assert_eq!(typewriters.count(), 1);
+        // Until it starts, the typewriter displays all of the paragraph that follows it.
+        let lines = render_dynamic_lines(slides[0].iter_operations(), &dimensions);
+        assert_eq!(lines[0], &["hello"]);
     }
 
     #[test]
-    fn ordered_list_with_pauses() {
+    fn typewriter_split_list() {
+        let item = |text: &str| ListItem {
+            depth: 0,
+            contents: Text::from(text),
+            item_type: ListItemType::Unordered,
+            checked: None,
+        };
         let elements = vec![
-            MarkdownElement::List(vec![
-                ListItem { depth: 0, contents: "one".into(), item_type: ListItemType::OrderedPeriod },
-                ListItem { depth: 1, contents: "one_one".into(), item_type: ListItemType::OrderedPeriod },
-                ListItem { depth: 1, contents: "one_two".into(), item_type: ListItemType::OrderedPeriod },
-            ]),
-            build_pause(),
-            MarkdownElement::List(vec![ListItem {
-                depth: 0,
-                contents: "two".into(),
-                item_type: ListItemType::OrderedPeriod,
-            }]),
+            MarkdownElement::FrontMatter("options:\n  incremental_lists: true".into()),
+            MarkdownElement::Comment { comment: "typewriter: 20".into(), source_position: Default::default() },
+            MarkdownElement::List(vec![item("one"), item("two")]),
         ];
         let slides = build_presentation(elements).into_slides();
-        let lines = extract_slide_text_lines(slides.into_iter().next().unwrap());
-        let expected_lines = &["   1. one", "      1. one_one", "      2. one_two", "   2. two"];
-        assert_eq!(lines, expected_lines);
+        let typewriters = slides[0].iter_operations().filter(|op| matches!(op, RenderOperation::RenderOnDemand(_)));
+        assert_eq!(typewriters.count(), 0);
     }
 
     #[test]
-    fn pause_after_list() {
+    fn current_slide_variable_numbering() {
+        let front_matter = "author: bob\noptions:\n  slide_numbers:\n    count_intro: false\n    start: 5";
         let elements = vec![
-            MarkdownElement::List(vec![ListItem {
-                depth: 0,
-                contents: "one".into(),
-                item_type: ListItemType::OrderedPeriod,
-            }]),
-            build_pause(),
-            MarkdownElement::Heading { level: 1, text: "hi".into() },
-            MarkdownElement::List(vec![ListItem {
-                depth: 0,
-                contents: "two".into(),
-                item_type: ListItemType::OrderedPeriod,
-            }]),
+            MarkdownElement::FrontMatter(front_matter.into()),
+            MarkdownElement::Paragraph(vec![ParagraphElement::Text(Text::from("slide {{current_slide}}"))]),
         ];
         let slides = build_presentation(elements).into_slides();
-        let first_chunk = &slides[0];
-        let operations = first_chunk.iter_operations().collect::<Vec<_>>();
-        // This is pretty easy to break, refactor soon
-        let last_operation = &operations[operations.len() - 4];
-        assert!(matches!(last_operation, RenderOperation::RenderLineBreak), "last operation is {last_operation:?}");
+        let lines = extract_slide_text_lines(slides.into_iter().nth(1).unwrap());
+        assert_eq!(lines, &["slide 5"]);
+    }
+
+    #[test]
+    fn footer_variables() {
+        let mut variables = Variables::new(&Default::default());
+        variables.insert("event", "RustConf");
+        let context = FooterContext { total_slides: 5, author: "bob".into(), variables, ..Default::default() };
+        let template = "{{event}} {author} {{current_slide}}/{total_slides}";
+        let operation =
+            FooterGenerator::render_template(template, Some(2), "", &context, Default::default(), Default::default());
+        let RenderOperation::RenderText { line, .. } = operation else { panic!("not text: {operation:?}") };
+        let text: String = line.iter_texts().map(|text| text.text.text.as_str()).collect();
+        assert_eq!(text, "RustConf bob 2/5");
     }
 
     #[rstest]
-    #[case::multiline("hello\nworld")]
-    #[case::many_open_braces("{{{")]
-    #[case::many_close_braces("}}}")]
-    fn ignore_comments(#[case] comment: &str) {
-        assert!(PresentationBuilder::should_ignore_comment(comment));
+    #[case::counted_intro("{}", &["1/3", "2/3", "3/3"])]
+    #[case::uncounted_intro("{count_intro: false, format: padded}", &["", "01", "02"])]
+    #[case::offset("{start: 10, format: number}", &["10", "11", "12"])]
+    fn footer_slide_number(#[case] numbering: &str, #[case] expected: &[&str]) {
+        let numbering: SlideNumbering = serde_yaml::from_str(numbering).expect("invalid numbering");
+        let context = FooterContext { total_slides: 3, numbering, has_intro: true, ..Default::default() };
+        let numbers: Vec<String> = (0..3)
+            .map(|index| {
+                let current_slide = context.slide_number(index);
+                let operation = FooterGenerator::render_template(
+                    "{{slide_number}}",
+                    current_slide,
+                    "",
+                    &context,
+                    Default::default(),
+                    Default::default(),
+                );
+                let RenderOperation::RenderText { line, .. } = operation else { panic!("not text: {operation:?}") };
+                line.iter_texts().map(|text| text.text.text.as_str()).collect()
+            })
+            .collect();
+        assert_eq!(numbers, expected);
+    }
+
+    #[test]
+    fn footer_placeholders() {
+        let metadata = "title: Rust\nauthor: bob\ndate: 2024-09-10";
+        let metadata: PresentationMetadata = serde_yaml::from_str(metadata).expect("invalid metadata");
+        let context = FooterContext {
+            total_slides: 5,
+            author: "bob".into(),
+            variables: Variables::new(&metadata),
+            ..Default::default()
+        };
+        let template = "{{title}} | {{date}} | {{section}} | {section}";
+        let operation = FooterGenerator::render_template(
+            template,
+            Some(2),
+            "Intro",
+            &context,
+            Default::default(),
+            Default::default(),
+        );
+        let RenderOperation::RenderText { line, .. } = operation else { panic!("not text: {operation:?}") };
+        let text: String = line.iter_texts().map(|text| text.text.text.as_str()).collect();
+        assert_eq!(text, "Rust | 2024-09-10 | Intro | {section}");
+    }
+
+    #[test]
+    fn footer_segment_colors() {
+        let footer = r#"
+style: template
+left: "{current_slide}"
+right:
+  template: "{total_slides}"
+  colors:
+    foreground: "ff0000"
+colors:
+  foreground: "00ff00"
+  background: "0000ff"
+"#;
+        let style: FooterStyle = serde_yaml::from_str(footer).expect("invalid footer");
+        let generator =
+            FooterGenerator { current_slide: 0, section: String::new(), context: Default::default(), style };
+        let colors: Vec<_> = generator
+            .as_render_operations(&WindowSize { rows: 10, columns: 10, width: 0, height: 0, has_pixels: false })
+            .into_iter()
+            .filter_map(|operation| match operation {
+                RenderOperation::RenderText { line, .. } => {
+                    line.iter_texts().next().map(|text| text.text.style.colors.clone())
+                }
+                _ => None,
+            })
+            .collect();
+        let expected = [
+            Colors { foreground: Some(Color::new(0, 255, 0)), background: Some(Color::new(0, 0, 255)) },
+            Colors { foreground: Some(Color::new(255, 0, 0)), background: Some(Color::new(0, 0, 255)) },
+        ];
+        assert_eq!(colors, expected);
+    }
+
+    fn render_progress_bar(footer: &str, current_slide: usize, section_starts: Vec<usize>) -> Vec<(u16, String)> {
+        let style: FooterStyle = serde_yaml::from_str(footer).expect("invalid footer");
+        let context = FooterContext { total_slides: 4, section_starts, ..Default::default() };
+        let generator =
+            FooterGenerator { current_slide, section: String::new(), context: Rc::new(RefCell::new(context)), style };
+        let dimensions = WindowSize { rows: 10, columns: 8, width: 0, height: 0, has_pixels: false };
+        let mut rows = Vec::new();
+        let mut row = None;
+        for operation in generator.as_render_operations(&dimensions) {
+            match operation {
+                RenderOperation::JumpToBottomRow { index } => row = Some(index),
+                RenderOperation::RenderText { line, .. } => {
+                    let text = line.iter_texts().map(|text| text.text.text.as_str()).collect();
+                    rows.push((row.expect("no jump"), text));
+                }
+                _ => panic!("unexpected operation: {operation:?}"),
+            };
+        }
+        rows
+    }
+
+    #[rstest]
+    #[case::default("style: progress_bar", 1, vec![], &[(0, "████")])]
+    #[case::remaining("{style: progress_bar, character: '#', remaining_character: '-'}", 0, vec![], &[(0, "##------")])]
+    #[case::ticks(
+        "{style: progress_bar, character: '#', remaining_character: '-', section_tick: '|'}",
+        1,
+        vec![0, 2],
+        &[(0, "####|---")]
+    )]
+    #[case::ticks_past_progress("{style: progress_bar, character: '#', section_tick: '|'}", 0, vec![0, 2], &[(0, "##  |")])]
+    #[case::thick_bottom("{style: progress_bar, character: '#', thickness: 2}", 3, vec![], &[(1, "########"), (0, "########")])]
+    #[case::thick_top(
+        "{style: progress_bar, character: '#', thickness: 2, position: top}",
+        3,
+        vec![],
+        &[(9, "########"), (8, "########")]
+    )]
+    fn progress_bar(
+        #[case] footer: &str,
+        #[case] current_slide: usize,
+        #[case] section_starts: Vec<usize>,
+        #[case] expected: &[(u16, &str)],
+    ) {
+        let rows = render_progress_bar(footer, current_slide, section_starts);
+        let expected: Vec<_> = expected.iter().map(|(row, text)| (*row, text.to_string())).collect();
+        assert_eq!(rows, expected);
+    }
+
+    #[rstest]
+    #[case::clock("style: template\nright: '{{ clock }}'", true)]
+    #[case::single_braces("style: template\nright: '{clock}'", false)]
+    #[case::no_clock("style: template\nright: '{current_slide}'", false)]
+    #[case::progress_bar("style: progress_bar", false)]
+    fn footer_clock(#[case] footer: &str, #[case] expected: bool) {
+        let theme =
+            PresentationTheme { footer: serde_yaml::from_str(footer).expect("invalid footer"), ..Default::default() };
+        let elements = vec![MarkdownElement::Paragraph(vec![ParagraphElement::Text("hi".into())])];
+        let presentation = build_presentation_with(&theme, Default::default(), elements);
+        assert_eq!(presentation.shows_clock(), expected);
+    }
+
+    #[test]
+    fn footer_section() {
+        let elements = vec![
+            MarkdownElement::Heading { text: "Basics".into(), level: 1 },
+            MarkdownElement::Comment { comment: "end_slide".into(), source_position: Default::default() },
+            MarkdownElement::Heading { text: "Details".into(), level: 2 },
+            MarkdownElement::Comment { comment: "end_slide".into(), source_position: Default::default() },
+            MarkdownElement::Heading { text: "Advanced".into(), level: 1 },
+        ];
+        let theme = PresentationTheme {
+            footer: serde_yaml::from_str("style: template\nleft: '{{section}}'").expect("invalid footer"),
+            ..Default::default()
+        };
+        let slides = build_presentation_with(&theme, Default::default(), elements).into_slides();
+        let dimensions = WindowSize { rows: 10, columns: 10, width: 0, height: 0, has_pixels: false };
+        let operations = slides.iter().flat_map(|slide| slide.iter_operations());
+        let sections: Vec<String> = render_dynamic_lines(operations, &dimensions).into_iter().flatten().collect();
+        assert_eq!(sections, &["Basics", "Basics", "Advanced"]);
+    }
+
+    fn try_build_with_layouts(elements: Vec<MarkdownElement>) -> Result<Presentation, BuildError> {
+        let layouts = r#"
+title:
+  alignment: center
+  jump_to_middle: true
+two_column:
+  columns: [1, 1]
+  regions: [left, right]
+styled_columns:
+  columns: [1, 1]
+  column_styles:
+    - padding: 2
+"#;
+        let theme = PresentationTheme {
+            layouts: serde_yaml::from_str(layouts).expect("invalid layouts"),
+            column_layout: serde_yaml::from_str("separator: \"|\"\ncolumns:\n  - background: \"ff0000\"")
+                .expect("invalid column layout"),
+            ..Default::default()
+        };
+        try_build_presentation_with(&theme, Default::default(), elements)
+    }
+
+    #[test]
+    fn named_layout() {
+        let elements = vec![
+            MarkdownElement::Comment { comment: "layout: title".into(), source_position: Default::default() },
+            MarkdownElement::Paragraph(vec![ParagraphElement::Text("hi".into())]),
+        ];
+        let slides = try_build_with_layouts(elements).expect("build failed").into_slides();
+        let operations: Vec<_> = slides[0].iter_operations().collect();
+        assert!(operations.iter().any(|op| matches!(op, RenderOperation::JumpToVerticalCenter)));
+        let alignment = operations.iter().find_map(|op| match op {
+            RenderOperation::RenderText { alignment, .. } => Some(alignment.clone()),
+            _ => None,
+        });
+        assert!(matches!(alignment, Some(Alignment::Center { .. })), "unexpected alignment: {alignment:?}");
+    }
+
+    #[test]
+    fn named_layout_regions() {
+        let elements = vec![
+            MarkdownElement::Comment { comment: "layout: two_column".into(), source_position: Default::default() },
+            MarkdownElement::Comment { comment: "region: right".into(), source_position: Default::default() },
+            MarkdownElement::Paragraph(vec![ParagraphElement::Text("right".into())]),
+            MarkdownElement::Comment { comment: "region: left".into(), source_position: Default::default() },
+            MarkdownElement::Paragraph(vec![ParagraphElement::Text("left".into())]),
+        ];
+        let slides = try_build_with_layouts(elements).expect("build failed").into_slides();
+        let columns: Vec<_> = slides[0]
+            .iter_operations()
+            .filter_map(|op| match op {
+                RenderOperation::EnterColumn { column } => Some(*column),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(columns, &[1, 0]);
+    }
+
+    #[rstest]
+    #[case::theme(&["column_layout: [1, 1]"], Some(Color::new(255, 0, 0)), None)]
+    #[case::named_layout(&["layout: styled_columns"], None, Some(2))]
+    fn column_layout_styles(
+        #[case] comments: &[&str],
+        #[case] expected_background: Option<Color>,
+        #[case] expected_padding: Option<u8>,
+    ) {
+        let elements = comments
+            .iter()
+            .map(|comment| MarkdownElement::Comment {
+                comment: comment.to_string(),
+                source_position: Default::default(),
+            })
+            .chain([MarkdownElement::Comment { comment: "column: 0".into(), source_position: Default::default() }])
+            .collect();
+        let slides = try_build_with_layouts(elements).expect("build failed").into_slides();
+        let style = slides[0]
+            .iter_operations()
+            .find_map(|op| match op {
+                RenderOperation::InitColumnLayout { style, .. } => Some(style.clone()),
+                _ => None,
+            })
+            .expect("no layout");
+        assert_eq!(style.separator.as_deref(), Some("|"));
+        assert_eq!(style.column(0).background, expected_background);
+        assert_eq!(style.column(0).padding, expected_padding);
+    }
+
+    #[rstest]
+    #[case::unknown_layout(&["layout: potato"])]
+    #[case::unknown_region(&["layout: two_column", "region: potato"])]
+    #[case::region_without_layout(&["region: left"])]
+    fn invalid_named_layouts(#[case] comments: &[&str]) {
+        let elements = comments
+            .iter()
+            .map(|comment| MarkdownElement::Comment {
+                comment: comment.to_string(),
+                source_position: Default::default(),
+            })
+            .collect();
+        let result = try_build_with_layouts(elements);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn reveal_delays() {
+        let elements = vec![
+            MarkdownElement::Paragraph(vec![ParagraphElement::Text("hi".into())]),
+            build_pause(),
+            MarkdownElement::List(vec![
+                ListItem { depth: 0, contents: "one".into(), item_type: ListItemType::Unordered, checked: None },
+                ListItem { depth: 0, contents: "two".into(), item_type: ListItemType::Unordered, checked: None },
+            ]),
+            MarkdownElement::Comment { comment: "reveal_delay: 0".into(), source_position: Default::default() },
+            build_pause(),
+            MarkdownElement::Paragraph(vec![ParagraphElement::Text("bye".into())]),
+        ];
+        let options = PresentationBuilderOptions {
+            incremental_lists: true,
+            reveal_delay: Some(Duration::from_secs(2)),
+            list_reveal_delay: Some(Duration::from_secs(1)),
+            ..Default::default()
+        };
+        let mut presentation = build_presentation_with(&Default::default(), options, elements);
+        let mut delays = vec![presentation.reveal_delay()];
+        while presentation.reveal_next_chunk() {
+            delays.push(presentation.reveal_delay());
+        }
+        let expected = [Some(Duration::from_secs(2)), Some(Duration::from_secs(1)), None, None];
+        assert_eq!(delays, expected);
+    }
+
+    fn build_image_slide(comment: &str) -> Result<Presentation, BuildError> {
+        let elements = vec![
+            MarkdownElement::Comment { comment: comment.into(), source_position: Default::default() },
+            MarkdownElement::Image {
+                path: "doge.png".into(),
+                alt_text: String::new(),
+                source_position: Default::default(),
+            },
+            MarkdownElement::Paragraph(vec![ParagraphElement::Text("hi".into())]),
+        ];
+        try_build_presentation_with(&Default::default(), Default::default(), elements)
+    }
+
+    #[rstest]
+    #[case::left("image_alignment: left", ImagePosition::Left)]
+    #[case::right("image_alignment: right", ImagePosition::Right)]
+    #[case::other_command("pause", ImagePosition::Center)]
+    fn image_alignment(#[case] comment: &str, #[case] expected: ImagePosition) {
+        let slides = build_image_slide(comment).expect("build failed").into_slides();
+        let position = slides[0].iter_chunks().flat_map(|chunk| chunk.iter_operations()).find_map(|op| match op {
+            RenderOperation::RenderImage(_, position) => Some(*position),
+            _ => None,
+        });
+        assert_eq!(position, Some(expected));
+    }
+
+    #[test]
+    fn image_alignment_applies_once() {
+        let image = || MarkdownElement::Image {
+            path: "doge.png".into(),
+            alt_text: String::new(),
+            source_position: Default::default(),
+        };
+        let elements = vec![
+            MarkdownElement::Comment { comment: "image_alignment: left".into(), source_position: Default::default() },
+            image(),
+            image(),
+        ];
+        let slides = build_presentation_with(&Default::default(), Default::default(), elements).into_slides();
+        let positions: Vec<_> = slides[0]
+            .iter_operations()
+            .filter_map(|op| match op {
+                RenderOperation::RenderImage(_, position) => Some(*position),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(positions, &[ImagePosition::Left, ImagePosition::Center]);
+    }
+
+    #[rstest]
+    #[case::left("image_float: left", vec![1, 2], [0, 1])]
+    #[case::right("image_float: right", vec![2, 1], [1, 0])]
+    fn image_float(#[case] comment: &str, #[case] expected_layout: Vec<u8>, #[case] expected_columns: [usize; 2]) {
+        let slides = build_image_slide(comment).expect("build failed").into_slides();
+        let operations: Vec<_> = slides[0].iter_operations().collect();
+        let layout = operations.iter().find_map(|op| match op {
+            RenderOperation::InitColumnLayout { columns, .. } => Some(columns.clone()),
+            _ => None,
+        });
+        assert_eq!(layout, Some(expected_layout.into()));
+
+        // The image goes into its own column and the text into the other one.
+        let image_index = operations.iter().position(|op| matches!(op, RenderOperation::RenderImage(..))).unwrap();
+        let text_index = operations.iter().position(|op| matches!(op, RenderOperation::RenderText { .. })).unwrap();
+        let column_before = |index: usize| {
+            operations[..index].iter().rev().find_map(|op| match op {
+                RenderOperation::EnterColumn { column } => Some(*column),
+                _ => None,
+            })
+        };
+        assert_eq!([column_before(image_index), column_before(text_index)], expected_columns.map(Some));
+    }
+
+    #[rstest]
+    #[case::start_of_slide(false)]
+    #[case::after_pause(true)]
+    fn background_image(#[case] after_pause: bool) {
+        let mut elements = vec![
+            MarkdownElement::Paragraph(vec![ParagraphElement::Text("hi".into())]),
+            MarkdownElement::Comment {
+                comment: "background_image: doge.png".into(),
+                source_position: Default::default(),
+            },
+        ];
+        if after_pause {
+            elements.insert(1, build_pause());
+        }
+        let slides = build_presentation_with(&Default::default(), Default::default(), elements).into_slides();
+        let operations = slides[0].iter_chunks().next().unwrap().iter_operations();
+        // The background goes right after the screen is cleared.
+        let clear_index = operations.clone().position(|op| matches!(op, RenderOperation::ClearScreen)).unwrap();
+        let background_index =
+            operations.clone().position(|op| matches!(op, RenderOperation::RenderBackgroundImage(_))).unwrap();
+        assert_eq!(background_index, clear_index + 1);
+    }
+
+    #[test]
+    fn intro_slide_background_image() {
+        let elements = vec![MarkdownElement::FrontMatter("title: hi\nbackground_image: doge.png".into())];
+        let slides = build_presentation_with(&Default::default(), Default::default(), elements).into_slides();
+        assert!(slides[0].iter_operations().any(|op| matches!(op, RenderOperation::RenderBackgroundImage(_))));
+    }
+
+    #[test]
+    fn image_float_inside_layout() {
+        let elements = vec![
+            build_column_layout(1),
+            build_column(0),
+            MarkdownElement::Comment { comment: "image_float: left".into(), source_position: Default::default() },
+            MarkdownElement::Image {
+                path: "doge.png".into(),
+                alt_text: String::new(),
+                source_position: Default::default(),
+            },
+        ];
+        let result = try_build_presentation(elements);
+        assert!(matches!(result, Err(BuildError::InvalidLayout(_))));
+    }
+
+    #[test]
+    fn timer() {
+        let elements =
+            vec![MarkdownElement::Comment { comment: "timer: 1m30s".into(), source_position: Default::default() }];
+        let mut presentation = build_presentation(elements);
+        let widgets = presentation
+            .current_slide()
+            .iter_operations()
+            .filter(|operation| matches!(operation, RenderOperation::RenderOnDemand(_)))
+            .count();
+        assert_eq!(widgets, 1);
+        assert!(presentation.render_slide_widgets());
+        assert!(presentation.widgets_rendering());
+        assert!(presentation.reset_slide_widgets());
+    }
+
+    #[test]
+    fn invalid_timer() {
+        let elements =
+            vec![MarkdownElement::Comment { comment: "timer: soon".into(), source_position: Default::default() }];
+        let result = try_build_presentation(elements);
+        assert!(matches!(result, Err(BuildError::InvalidTimer(_))));
+    }
+
+    #[test]
+    fn slide_padding() {
+        let mut theme = PresentationTheme::default();
+        theme.default_style.padding_top = Some(2);
+        theme.default_style.padding_bottom = Some(4);
+        theme.default_style.max_width = Some(Margin::Percent(80));
+        let elements = vec![MarkdownElement::Paragraph(vec![ParagraphElement::Text("hi".into())])];
+        let slides = build_presentation_with(&theme, Default::default(), elements).into_slides();
+        let operations: Vec<_> = slides[0].iter_operations().collect();
+
+        let margin = operations.iter().position(|op| matches!(op, RenderOperation::ApplyMargin(_))).expect("no margin");
+        let RenderOperation::ApplyMargin(properties) = operations[margin] else { unreachable!() };
+        assert_eq!(properties.bottom_slide_margin, DEFAULT_BOTTOM_SLIDE_MARGIN + 4);
+        assert!(matches!(properties.max_width, Some(Margin::Percent(80))));
+        let breaks =
+            operations[margin + 1..].iter().take_while(|op| matches!(op, RenderOperation::RenderLineBreak)).count();
+        assert_eq!(breaks, 3);
+    }
+
+    #[rstest]
+    #[case::fits(40, 5)]
+    #[case::too_narrow(6, 1)]
+    fn big_text(#[case] columns: u16, #[case] expected_rows: usize) {
+        let code = Code { contents: "Hi\n\nthere".into(), language: CodeLanguage::BigText, flags: Default::default() };
+        let slides = build_presentation(vec![MarkdownElement::Code(code.into())]).into_slides();
+        let dimensions = WindowSize { rows: 20, columns, width: 0, height: 0, has_pixels: false };
+        // Anything after the slide's margin is popped is part of the footer.
+        let operations =
+            slides[0].iter_operations().take_while(|operation| !matches!(operation, RenderOperation::PopMargin));
+        let blocks = render_dynamic_lines(operations, &dimensions);
+        assert_eq!(blocks.len(), 2);
+        assert!(blocks.iter().all(|rows| rows.len() == expected_rows), "unexpected rows: {blocks:?}");
+    }
+
+    #[rstest]
+    #[case::fits(80, 40, 15)]
+    #[case::too_narrow(20, 40, 1)]
+    fn qr_code(#[case] columns: u16, #[case] rows: u16, #[case] expected_rows: usize) {
+        let code =
+            Code { contents: "https://example.com\n".into(), language: CodeLanguage::Qr, flags: Default::default() };
+        let slides = build_presentation(vec![MarkdownElement::Code(code.into())]).into_slides();
+        let dimensions = WindowSize { rows, columns, width: 0, height: 0, has_pixels: false };
+        let lines =
+            render_dynamic_lines(slides[0].iter_operations(), &dimensions).into_iter().next().expect("no qr code");
+        assert_eq!(lines.len(), expected_rows);
+        if expected_rows == 1 {
+            assert_eq!(lines[0], "https://example.com");
+        }
+    }
+
+    #[test]
+    fn qr_code_too_long() {
+        let comment = format!("qr: {}", "a".repeat(5000));
+        let result =
+            try_build_presentation(vec![MarkdownElement::Comment { comment, source_position: Default::default() }]);
+        assert!(matches!(result, Err(BuildError::InvalidQrCode(_))));
+    }
+
+    #[test]
+    fn chart() {
+        let contents = "month,visits\njan,10\nfeb,20".into();
+        let code = Code { contents, language: CodeLanguage::Chart, flags: Default::default() };
+        let slides = build_presentation(vec![MarkdownElement::Code(code.into())]).into_slides();
+        let dimensions = WindowSize { rows: 20, columns: 20, width: 0, height: 0, has_pixels: false };
+        let lines =
+            render_dynamic_lines(slides[0].iter_operations(), &dimensions).into_iter().next().expect("no chart");
+        assert_eq!(lines, &["visits", "jan ██████▌       10", "feb █████████████ 20"]);
+    }
+
+    #[test]
+    fn invalid_chart() {
+        let code = Code { contents: "jan,potato".into(), language: CodeLanguage::Chart, flags: Default::default() };
+        let result = try_build_presentation(vec![MarkdownElement::Code(code.into())]);
+        assert!(matches!(result, Err(BuildError::InvalidChart(_))));
+    }
+
+    fn vertical_alignments(
+        theme: &PresentationTheme,
+        elements: Vec<MarkdownElement>,
+    ) -> Vec<Option<VerticalAlignment>> {
+        let slides = build_presentation_with(theme, Default::default(), elements).into_slides();
+        slides
+            .iter()
+            .map(|slide| {
+                let operations: Vec<_> = slide.iter_operations().collect();
+                let align = operations.iter().position(|op| matches!(op, RenderOperation::AlignVertically { .. }))?;
+                let text = operations.iter().position(|op| matches!(op, RenderOperation::RenderText { .. }));
+                assert!(Some(align) < text, "alignment after text");
+                match operations[align] {
+                    RenderOperation::AlignVertically { alignment, .. } => Some(*alignment),
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn vertical_alignment() {
+        let elements = vec![
+            MarkdownElement::Paragraph(vec![ParagraphElement::Text("hi".into())]),
+            MarkdownElement::Comment {
+                comment: "vertical_alignment: center".into(),
+                source_position: Default::default(),
+            },
+            MarkdownElement::Comment { comment: "end_slide".into(), source_position: Default::default() },
+            MarkdownElement::Paragraph(vec![ParagraphElement::Text("hi".into())]),
+            MarkdownElement::Comment { comment: "end_slide".into(), source_position: Default::default() },
+            MarkdownElement::Comment { comment: "vertical_alignment: top".into(), source_position: Default::default() },
+            MarkdownElement::Paragraph(vec![ParagraphElement::Text("hi".into())]),
+            MarkdownElement::Comment { comment: "end_slide".into(), source_position: Default::default() },
+            MarkdownElement::Comment { comment: "jump_to_middle".into(), source_position: Default::default() },
+            MarkdownElement::Paragraph(vec![ParagraphElement::Text("hi".into())]),
+        ];
+        let alignments = vertical_alignments(&Default::default(), elements.clone());
+        assert_eq!(alignments, &[Some(VerticalAlignment::Center), None, None, None]);
+
+        let mut theme = PresentationTheme::default();
+        theme.default_style.vertical_alignment = Some(VerticalAlignment::Bottom);
+        let alignments = vertical_alignments(&theme, elements);
+        assert_eq!(alignments, &[Some(VerticalAlignment::Center), Some(VerticalAlignment::Bottom), None, None]);
+    }
+
+    #[test]
+    fn vertical_alignment_measures_hidden_chunks() {
+        let elements = vec![
+            MarkdownElement::Comment {
+                comment: "vertical_alignment: center".into(),
+                source_position: Default::default(),
+            },
+            MarkdownElement::Paragraph(vec![ParagraphElement::Text("first".into())]),
+            build_pause(),
+            MarkdownElement::Paragraph(vec![ParagraphElement::Text("second".into())]),
+        ];
+        let slides = build_presentation(elements).into_slides();
+        let contents = slides[0]
+            .iter_operations()
+            .find_map(|operation| match operation {
+                RenderOperation::AlignVertically { contents, .. } => Some(contents.clone()),
+                _ => None,
+            })
+            .expect("not aligned");
+        assert_eq!(extract_text_lines(&contents), &["first", "second"]);
+    }
+
+    #[test]
+    fn speaker_notes() {
+        let elements = vec![
+            MarkdownElement::Paragraph(vec![ParagraphElement::Text("hi".into())]),
+            MarkdownElement::Comment { comment: "speaker_note: say hi".into(), source_position: Default::default() },
+            MarkdownElement::Comment {
+                comment: "\nspeaker_note: |\n  first line\n  second line\n".into(),
+                source_position: Default::default(),
+            },
+            MarkdownElement::Comment {
+                comment: "speaker_note: Note: mention [this] # not a comment".into(),
+                source_position: Default::default(),
+            },
+            MarkdownElement::Comment { comment: "end_slide".into(), source_position: Default::default() },
+            MarkdownElement::Paragraph(vec![ParagraphElement::Text("bye".into())]),
+        ];
+        let slides = build_presentation(elements).into_slides();
+        let expected = ["say hi", "first line\nsecond line", "Note: mention [this] # not a comment"];
+        assert_eq!(slides[0].speaker_notes(), &expected);
+        assert!(slides[1].speaker_notes().is_empty());
+        let lines = extract_slide_text_lines(slides.into_iter().next().unwrap());
+        assert_eq!(lines, &["hi"]);
+    }
+
+    #[rstest]
+    #[case::pause("pause", CommentCommand::Pause)]
+    #[case::pause(" pause ", CommentCommand::Pause)]
+    #[case::end_slide("end_slide", CommentCommand::EndSlide)]
+    #[case::column_layout("column_layout: [1, 2]", CommentCommand::InitColumnLayout(vec![1, 2].into()))]
+    #[case::responsive_column_layout(
+        "column_layout: {0: [1, 1], 120: [2, 1]}",
+        CommentCommand::InitColumnLayout(ColumnLayout::Responsive([(0, vec![1, 1]), (120, vec![2, 1])].into()))
+    )]
+    #[case::column("column: 1", CommentCommand::Column(1))]
+    #[case::reset_layout("reset_layout", CommentCommand::ResetLayout)]
+    #[case::jump_to_middle("jump_to_middle", CommentCommand::JumpToMiddle)]
+    #[case::alignment("alignment: center", CommentCommand::Alignment(CommentCommandAlignment::Center))]
+    #[case::justify("alignment: justify", CommentCommand::Alignment(CommentCommandAlignment::Justify))]
+    #[case::skip_slide("skip_slide", CommentCommand::SkipSlide)]
+    #[case::include("include: intro.md", CommentCommand::Include("intro.md".into()))]
+    #[case::speaker_note("speaker_note: a: b", CommentCommand::SpeakerNote("a: b".into()))]
+    #[case::timer("timer: 5m", CommentCommand::Timer("5m".into()))]
+    #[case::big_text("big_text: Hello", CommentCommand::BigText("Hello".into()))]
+    #[case::qr("qr: https://example.com", CommentCommand::Qr("https://example.com".into()))]
+    #[case::typewriter("typewriter: 30", CommentCommand::Typewriter(30))]
+    #[case::vertical_alignment(
+        "vertical_alignment: bottom",
+        CommentCommand::VerticalAlignment(VerticalAlignment::Bottom)
+    )]
+    fn command_formatting(#[case] input: &str, #[case] expected: CommentCommand) {
+        let parsed: CommentCommand = input.parse().expect("deserialization failed");
+        assert_eq!(parsed, expected);
+    }
+
+    #[rstest]
+    #[case::presenting(true, &["first", "last"])]
+    #[case::developing(false, &["first", "draft", "last"])]
+    fn skip_slide(#[case] skip_slides: bool, #[case] expected: &[&str]) {
+        let elements = vec![
+            MarkdownElement::Paragraph(vec![ParagraphElement::Text("first".into())]),
+            build_end_slide(),
+            MarkdownElement::Comment { comment: "skip_slide".into(), source_position: Default::default() },
+            MarkdownElement::Paragraph(vec![ParagraphElement::Text("draft".into())]),
+            build_end_slide(),
+            MarkdownElement::Paragraph(vec![ParagraphElement::Text("last".into())]),
+        ];
+        let options = PresentationBuilderOptions { skip_slides, ..Default::default() };
+        let presentation = build_presentation_with(&Default::default(), options, elements);
+        let slides: Vec<_> = presentation.into_slides().into_iter().flat_map(extract_slide_text_lines).collect();
+        assert_eq!(slides, expected);
+    }
+
+    #[test]
+    fn skip_every_slide() {
+        let elements = vec![
+            MarkdownElement::Comment { comment: "skip_slide".into(), source_position: Default::default() },
+            MarkdownElement::Paragraph(vec![ParagraphElement::Text("draft".into())]),
+        ];
+        let options = PresentationBuilderOptions { skip_slides: true, ..Default::default() };
+        let presentation = build_presentation_with(&Default::default(), options, elements);
+        let slides = presentation.into_slides();
+        assert_eq!(slides.len(), 1);
+        assert!(extract_slide_text_lines(slides.into_iter().next().unwrap()).is_empty());
+    }
+
+    #[test]
+    fn alignment_override() {
+        let elements = vec![
+            MarkdownElement::Paragraph(vec![ParagraphElement::Text("before".into())]),
+            MarkdownElement::Comment { comment: "alignment: right".into(), source_position: Default::default() },
+            MarkdownElement::Paragraph(vec![ParagraphElement::Text("after".into())]),
+            build_end_slide(),
+            MarkdownElement::Paragraph(vec![ParagraphElement::Text("next".into())]),
+        ];
+        let presentation = build_presentation(elements);
+        let alignments: Vec<_> = presentation
+            .iter_slides()
+            .flat_map(|slide| slide.iter_operations())
+            .filter_map(|op| match op {
+                RenderOperation::RenderText { line, alignment } => Some((line.width(), alignment.clone())),
+                _ => None,
+            })
+            .filter(|(width, _)| *width > 0)
+            .map(|(_, alignment)| alignment)
+            .collect();
+        // The alignment only applies to the rest of the slide.
+        let expected = vec![Alignment::default(), Alignment::Right { margin: Margin::Fixed(0) }, Alignment::default()];
+        assert_eq!(alignments, expected);
+    }
+
+    #[test]
+    fn slide_theme() {
+        let slide_theme = "slide_theme:\n  default:\n    colors:\n      background: \"ff0000\"";
+        let elements = vec![
+            MarkdownElement::Comment { comment: slide_theme.into(), source_position: Default::default() },
+            MarkdownElement::Paragraph(vec![ParagraphElement::Text("hi".into())]),
+            build_end_slide(),
+            MarkdownElement::Paragraph(vec![ParagraphElement::Text("bye".into())]),
+        ];
+        let presentation = build_presentation(elements);
+        let backgrounds: Vec<_> = presentation
+            .iter_slides()
+            .map(|slide| {
+                slide.iter_operations().find_map(|op| match op {
+                    RenderOperation::SetColors(colors) => Some(colors.background),
+                    _ => None,
+                })
+            })
+            .collect();
+        // Only the first slide uses the overridden background.
+        let default_background = PresentationTheme::default().default_style.colors.background;
+        assert_eq!(backgrounds, vec![Some(Some(Color::new(255, 0, 0))), Some(default_background)]);
+    }
+
+    #[test]
+    fn slide_theme_after_first_slide() {
+        let slide_theme = "slide_theme:\n  default:\n    colors:\n      background: \"ff0000\"";
+        let elements = vec![
+            MarkdownElement::Paragraph(vec![ParagraphElement::Text("hi".into())]),
+            build_end_slide(),
+            MarkdownElement::Comment { comment: slide_theme.into(), source_position: Default::default() },
+            MarkdownElement::Paragraph(vec![ParagraphElement::Text("bye".into())]),
+        ];
+        let presentation = build_presentation(elements);
+        assert_eq!(presentation.iter_slides().count(), 2);
+    }
+
+    #[test]
+    fn slide_theme_not_at_start() {
+        let elements = vec![
+            MarkdownElement::Paragraph(vec![ParagraphElement::Text("hi".into())]),
+            MarkdownElement::Comment {
+                comment: "slide_theme:\n  footer:\n    style: empty".into(),
+                source_position: Default::default(),
+            },
+        ];
+        let result = try_build_presentation(elements);
+        assert!(matches!(result, Err(BuildError::SlideThemeNotAtStart)));
+    }
+
+    #[test]
+    fn invalid_slide_theme() {
+        let elements = vec![MarkdownElement::Comment {
+            comment: "slide_theme:\n  default:\n    colors: 42".into(),
+            source_position: Default::default(),
+        }];
+        let result = try_build_presentation(elements);
+        assert!(matches!(result, Err(BuildError::InvalidSlideTheme(_))));
+    }
+
+    fn build_with_includes<C: AsRef<[u8]>>(
+        files: &[(&str, C)],
+        elements: Vec<MarkdownElement>,
+    ) -> Result<Presentation, BuildError> {
+        let directory = tempfile::tempdir().expect("no temp dir");
+        for (name, contents) in files {
+            let path = directory.path().join(name);
+            fs::create_dir_all(path.parent().unwrap()).expect("failed to create directory");
+            fs::write(path, contents).expect("failed to write file");
+        }
+        let highlighter = CodeHighlighter::new("base16-ocean.dark").unwrap();
+        let theme = PresentationTheme::default();
+        let mut resources = Resources::new(directory.path());
+        let mut execution_cache = ExecutionCache::default();
+        let builder =
+            PresentationBuilder::new(highlighter, &theme, &mut resources, &mut execution_cache, Default::default());
+        builder.build(elements)
+    }
+
+    #[test]
+    fn include() {
+        let files = [("a.md", "from a\n\n<!-- include: b.md -->"), ("b.md", "from b :rocket:\n\n<!-- end_slide -->")];
+        let elements = vec![
+            MarkdownElement::Comment { comment: "include: a.md".into(), source_position: Default::default() },
+            MarkdownElement::Paragraph(vec![ParagraphElement::Text("after".into())]),
+        ];
+        let presentation = build_with_includes(&files, elements).expect("build failed");
+        let slides: Vec<_> = presentation.into_slides().into_iter().map(extract_slide_text_lines).collect();
+        let expected = vec![vec!["from a".to_string(), "from b 🚀".to_string()], vec!["after".to_string()]];
+        assert_eq!(slides, expected);
+    }
+
+    #[test]
+    fn nested_include_paths() {
+        let files = [
+            ("sections/a.md", b"<!-- include: nested/b.md -->".to_vec()),
+            ("sections/nested/b.md", b"![](doge.png)\n\n```text file:code.txt\n```".to_vec()),
+            ("sections/nested/code.txt", b"from the nested directory".to_vec()),
+            ("sections/nested/doge.png", fs::read("examples/doge.png").expect("no image")),
+        ];
+        let elements = vec![MarkdownElement::Comment {
+            comment: "include: sections/a.md".into(),
+            source_position: Default::default(),
+        }];
+        let presentation = build_with_includes(&files, elements).expect("build failed");
+        let operations: Vec<_> = presentation.iter_slides().flat_map(|slide| slide.iter_operations()).collect();
+        assert!(operations.iter().any(|op| matches!(op, RenderOperation::RenderImage(..))));
+        let lines: Vec<_> = operations
+            .iter()
+            .filter_map(|op| match op {
+                RenderOperation::RenderPreformattedLine(line) => Some(plain_text(line)),
+                _ => None,
+            })
+            .collect();
+        assert!(lines.iter().any(|line| line.contains("from the nested directory")), "{lines:?}");
+    }
+
+    #[test]
+    fn include_cycle() {
+        let files = [("a.md", "<!-- include: b.md -->"), ("b.md", "<!-- include: a.md -->")];
+        let elements =
+            vec![MarkdownElement::Comment { comment: "include: a.md".into(), source_position: Default::default() }];
+        let result = build_with_includes(&files, elements);
+        assert!(matches!(result, Err(BuildError::IncludeCycle(_))));
+    }
+
+    #[test]
+    fn include_missing_file() {
+        let elements =
+            vec![MarkdownElement::Comment { comment: "include: a.md".into(), source_position: Default::default() }];
+        let result = build_with_includes::<&str>(&[], elements);
+        assert!(matches!(result, Err(BuildError::LoadInclude(_))));
+    }
+
+    #[test]
+    fn jump_to_middle() {
+        let elements = vec![
+            MarkdownElement::Comment { comment: "jump_to_middle".into(), source_position: Default::default() },
+            MarkdownElement::Paragraph(vec![ParagraphElement::Text("hi".into())]),
+        ];
+        let presentation = build_presentation(elements);
+        let slides = presentation.into_slides();
+        let operations: Vec<_> = slides[0].iter_operations().collect();
+        let jump = operations.iter().position(|op| matches!(op, RenderOperation::JumpToVerticalCenter));
+        let text = operations.iter().position(|op| matches!(op, RenderOperation::RenderText { .. }));
+        assert!(jump.is_some());
+        assert!(jump < text);
+    }
+
+    #[test]
+    fn nested_layout() {
+        let elements = vec![
+            build_column_layout(1),
+            build_column(0),
+            MarkdownElement::Comment { comment: "column_layout: [1, 1]".into(), source_position: Default::default() },
+            build_column(1),
+            MarkdownElement::Comment { comment: "reset_layout".into(), source_position: Default::default() },
+            // We're back in the outer layout's column.
+            MarkdownElement::Paragraph(vec![ParagraphElement::Text("hi".into())]),
+            MarkdownElement::Comment { comment: "reset_layout".into(), source_position: Default::default() },
+        ];
+        let presentation = build_presentation(elements);
+        let slides = presentation.into_slides();
+        let exits: Vec<_> = slides[0]
+            .iter_operations()
+            .filter(|op| matches!(op, RenderOperation::ExitLayout | RenderOperation::ExitNestedLayout))
+            .map(|op| matches!(op, RenderOperation::ExitNestedLayout))
+            .collect();
+        // The nested exit, the outer exit, and the one in the footer.
+        assert_eq!(exits, &[true, false, false]);
+    }
+
+    #[test]
+    fn nested_layout_column_index() {
+        let elements = vec![
+            MarkdownElement::Comment {
+                comment: "column_layout: [1, 1, 1]".into(),
+                source_position: Default::default(),
+            },
+            build_column(0),
+            build_column_layout(1),
+            // The nested layout only has one column.
+            build_column(2),
+        ];
+        let result = try_build_presentation(elements);
+        assert!(matches!(result, Err(BuildError::ColumnIndexTooLarge)));
+    }
+
+    #[rstest]
+    #[case::different_counts("column_layout: {0: [1], 100: [1, 1]}")]
+    #[case::zero("column_layout: {0: [1, 0]}")]
+    #[case::empty("column_layout: {}")]
+    fn invalid_responsive_layouts(#[case] definition: &str) {
+        let elements =
+            vec![MarkdownElement::Comment { comment: definition.into(), source_position: Default::default() }];
+        let result = try_build_presentation(elements);
+        assert!(matches!(result, Err(BuildError::InvalidLayout(_))));
+    }
+
+    #[test]
+    fn end_slide_inside_layout() {
+        let elements = vec![build_column_layout(1), build_end_slide()];
+        let presentation = build_presentation(elements);
+        assert_eq!(presentation.iter_slides().count(), 2);
+    }
+
+    #[test]
+    fn end_slide_inside_column() {
+        let elements = vec![build_column_layout(1), build_column(0), build_end_slide()];
+        let presentation = build_presentation(elements);
+        assert_eq!(presentation.iter_slides().count(), 2);
+    }
+
+    #[test]
+    fn pause_inside_layout() {
+        let elements = vec![build_column_layout(1), build_pause(), build_column(0)];
+        let presentation = build_presentation(elements);
+        assert_eq!(presentation.iter_slides().count(), 1);
+    }
+
+    #[test]
+    fn iterate_list() {
+        let iter = ListIterator::new(
+            vec![
+                ListItem { depth: 0, contents: "0".into(), item_type: ListItemType::Unordered, checked: None },
+                ListItem { depth: 0, contents: "1".into(), item_type: ListItemType::Unordered, checked: None },
+                ListItem { depth: 1, contents: "00".into(), item_type: ListItemType::Unordered, checked: None },
+                ListItem { depth: 1, contents: "01".into(), item_type: ListItemType::Unordered, checked: None },
+                ListItem { depth: 1, contents: "02".into(), item_type: ListItemType::Unordered, checked: None },
+                ListItem { depth: 2, contents: "001".into(), item_type: ListItemType::Unordered, checked: None },
+                ListItem { depth: 0, contents: "2".into(), item_type: ListItemType::Unordered, checked: None },
+            ],
+            0,
+        );
+        let expected_indexes = [0, 1, 0, 1, 2, 0, 2];
+        let indexes: Vec<_> = iter.map(|item| item.index).collect();
+        assert_eq!(indexes, expected_indexes);
+    }
+
+    #[test]
+    fn iterate_list_starting_from_other() {
+        let list = ListIterator::new(
+            vec![
+                ListItem { depth: 0, contents: "0".into(), item_type: ListItemType::Unordered, checked: None },
+                ListItem { depth: 0, contents: "1".into(), item_type: ListItemType::Unordered, checked: None },
+            ],
+            3,
+        );
+        let expected_indexes = [3, 4];
+        let indexes: Vec<_> = list.into_iter().map(|item| item.index).collect();
+        assert_eq!(indexes, expected_indexes);
+    }
+
+    #[test]
+    fn ordered_list_with_pauses() {
+        let elements = vec![
+            MarkdownElement::List(vec![
+                ListItem { depth: 0, contents: "one".into(), item_type: ListItemType::OrderedPeriod, checked: None },
+                ListItem {
+                    depth: 1,
+                    contents: "one_one".into(),
+                    item_type: ListItemType::OrderedPeriod,
+                    checked: None,
+                },
+                ListItem {
+                    depth: 1,
+                    contents: "one_two".into(),
+                    item_type: ListItemType::OrderedPeriod,
+                    checked: None,
+                },
+            ]),
+            build_pause(),
+            MarkdownElement::List(vec![ListItem {
+                depth: 0,
+                contents: "two".into(),
+                item_type: ListItemType::OrderedPeriod,
+                checked: None,
+            }]),
+        ];
+        let slides = build_presentation(elements).into_slides();
+        let lines = extract_slide_text_lines(slides.into_iter().next().unwrap());
+        let expected_lines = &["   1. one", "      1. one_one", "      2. one_two", "   2. two"];
+        assert_eq!(lines, expected_lines);
+    }
+
+    #[test]
+    fn list_bullets_per_depth() {
+        let mut theme = PresentationTheme::default();
+        theme.list.bullets = Some(vec!["-".into(), "+".into()]);
+        let item = |depth, contents: &str| ListItem {
+            depth,
+            contents: contents.into(),
+            item_type: ListItemType::Unordered,
+            checked: None,
+        };
+        let elements = vec![MarkdownElement::List(vec![item(0, "a"), item(1, "b"), item(2, "c"), item(3, "d")])];
+        let slides = build_presentation_with(&theme, Default::default(), elements).into_slides();
+        let lines = extract_slide_text_lines(slides.into_iter().next().unwrap());
+        let expected_lines = &["   -a", "      +b", "         +c", "            +d"];
+        assert_eq!(lines, expected_lines);
+    }
+
+    #[rstest]
+    #[case::arabic(None, None, &["    9. i", "   10. j"])]
+    #[case::roman(Some(ListNumbering::UpperRoman), Some(")"), &["     IX) i", "      X) j"])]
+    fn ordered_list_numbering(
+        #[case] numbering: Option<ListNumbering>,
+        #[case] suffix: Option<&str>,
+        #[case] expected: &[&str],
+    ) {
+        let mut theme = PresentationTheme::default();
+        theme.list.numbering = numbering.map(|numbering| vec![numbering]);
+        theme.list.number_suffix = suffix.map(String::from);
+        let items = "abcdefghij"
+            .chars()
+            .map(|c| ListItem {
+                depth: 0,
+                contents: c.to_string().into(),
+                item_type: ListItemType::OrderedPeriod,
+                checked: None,
+            })
+            .collect();
+        let slides =
+            build_presentation_with(&theme, Default::default(), vec![MarkdownElement::List(items)]).into_slides();
+        let lines = extract_slide_text_lines(slides.into_iter().next().unwrap());
+        assert_eq!(&lines[8..], expected);
+    }
+
+    #[test]
+    fn task_list() {
+        let item =
+            |contents: &str, item_type, checked| ListItem { depth: 0, contents: contents.into(), item_type, checked };
+        let elements = vec![MarkdownElement::List(vec![
+            item("one", ListItemType::Unordered, Some(false)),
+            item("two", ListItemType::Unordered, Some(true)),
+            item("three", ListItemType::OrderedPeriod, Some(false)),
+        ])];
+        let slides = build_presentation(elements).into_slides();
+        let lines = extract_slide_text_lines(slides.into_iter().next().unwrap());
+        let expected_lines = &["   ☐one", "   ☑two", "   3. ☐three"];
+        assert_eq!(lines, expected_lines);
+    }
+
+    #[test]
+    fn pause_after_list() {
+        let elements = vec![
+            MarkdownElement::List(vec![ListItem {
+                depth: 0,
+                contents: "one".into(),
+                item_type: ListItemType::OrderedPeriod,
+                checked: None,
+            }]),
+            build_pause(),
+            MarkdownElement::Heading { level: 1, text: "hi".into() },
+            MarkdownElement::List(vec![ListItem {
+                depth: 0,
+                contents: "two".into(),
+                item_type: ListItemType::OrderedPeriod,
+                checked: None,
+            }]),
+        ];
+        let slides = build_presentation(elements).into_slides();
+        let first_chunk = &slides[0];
+        let operations = first_chunk.iter_operations().collect::<Vec<_>>();
+        // This is pretty easy to break, refactor soon
+        let last_operation = &operations[operations.len() - 4];
+        assert!(matches!(last_operation, RenderOperation::RenderLineBreak), "last operation is {last_operation:?}");
+    }
+
+    #[rstest]
+    #[case::config(true, None, 3)]
+    #[case::disabled(false, None, 1)]
+    #[case::front_matter(false, Some("options:\n  incremental_lists: true"), 3)]
+    #[case::front_matter_override(true, Some("options:\n  incremental_lists: false"), 1)]
+    fn reveal_lists_incrementally(
+        #[case] incremental_lists: bool,
+        #[case] front_matter: Option<&str>,
+        #[case] expected_chunks: usize,
+    ) {
+        let item = |depth, contents: &str| ListItem {
+            depth,
+            contents: contents.into(),
+            item_type: ListItemType::OrderedPeriod,
+            checked: None,
+        };
+        let mut elements =
+            vec![MarkdownElement::List(vec![item(0, "one"), item(1, "nested"), item(0, "two"), item(0, "three")])];
+        if let Some(front_matter) = front_matter {
+            elements.insert(0, MarkdownElement::FrontMatter(front_matter.into()));
+        }
+        let options = PresentationBuilderOptions { incremental_lists, ..Default::default() };
+        let slides = build_presentation_with(&Default::default(), options, elements).into_slides();
+        let slide = slides.into_iter().next().unwrap();
+        assert_eq!(slide.iter_chunks().count(), expected_chunks);
+        let lines = extract_slide_text_lines(slide);
+        assert_eq!(lines, &["   1. one", "      1. nested", "   2. two", "   3. three"]);
+    }
+
+    #[rstest]
+    #[case::multiline("hello\nworld")]
+    #[case::many_open_braces("{{{")]
+    #[case::many_close_braces("}}}")]
+    fn ignore_comments(#[case] comment: &str) {
+        assert!(PresentationBuilder::should_ignore_comment(comment));
+    }
+
+    #[test]
+    fn scroll_execution_output() {
+        let code =
+            Code { contents: String::new(), language: CodeLanguage::Shell("sh".into()), flags: Default::default() };
+        let operation = RunCodeOperation::new(
+            code,
+            Default::default(),
+            Default::default(),
+            &ExecutionOutputBlockStyle { max_height: Some(2), ..Default::default() },
+            Default::default(),
+        );
+        {
+            let mut inner = operation.inner.borrow_mut();
+            inner.output_lines = vec!["a".into(), "b".into(), "c".into()];
+            inner.state = RenderOnDemandState::Rendered;
+        }
+        let dimensions = WindowSize { rows: 10, columns: 10, height: 10, width: 10, has_pixels: false };
+        let visible_lines = |operation: &RunCodeOperation| {
+            operation
+                .as_render_operations(&dimensions)
+                .into_iter()
+                .filter_map(|operation| match operation {
+                    RenderOperation::RenderPreformattedLine(line) => Some(plain_text(&line)),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(visible_lines(&operation), &["a", "b"]);
+
+        assert!(operation.scroll(1));
+        assert_eq!(visible_lines(&operation), &["b", "c"]);
+
+        // We're at the bottom already.
+        assert!(!operation.scroll(1));
+        assert!(operation.scroll(-5));
+        assert_eq!(visible_lines(&operation), &["a", "b"]);
+    }
+
+    #[test]
+    fn follow_execution_output() {
+        let code =
+            Code { contents: String::new(), language: CodeLanguage::Shell("sh".into()), flags: Default::default() };
+        let operation = RunCodeOperation::new(
+            code,
+            Default::default(),
+            Default::default(),
+            &ExecutionOutputBlockStyle { max_height: Some(2), ..Default::default() },
+            Default::default(),
+        );
+        {
+            let mut inner = operation.inner.borrow_mut();
+            inner.output_lines = vec!["a".into(), "b".into(), "c".into()];
+            inner.follow_output = true;
+            inner.state = RenderOnDemandState::Rendering;
+        }
+        let dimensions = WindowSize { rows: 10, columns: 10, height: 10, width: 10, has_pixels: false };
+        let visible_lines = |operation: &RunCodeOperation| {
+            operation
+                .as_render_operations(&dimensions)
+                .into_iter()
+                .filter_map(|operation| match operation {
+                    RenderOperation::RenderPreformattedLine(line) => Some(plain_text(&line)),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(visible_lines(&operation), &["b", "c"]);
+
+        operation.inner.borrow_mut().output_lines.push("d".into());
+        assert_eq!(visible_lines(&operation), &["c", "d"]);
+
+        // Scrolling up stops following it.
+        assert!(operation.scroll(-1));
+        operation.inner.borrow_mut().output_lines.push("e".into());
+        assert_eq!(visible_lines(&operation), &["b", "c"]);
+
+        assert!(operation.toggle_follow());
+        assert_eq!(visible_lines(&operation), &["d", "e"]);
+    }
+
+    #[test]
+    fn concurrent_executions() {
+        let code = |contents: &str| {
+            let flags = CodeFlags { execute: true, ..Default::default() };
+            MarkdownElement::Code(Box::new(Code {
+                contents: contents.into(),
+                language: CodeLanguage::Shell("sh".into()),
+                flags,
+            }))
+        };
+        // Each of these waits for the other one so this only finishes if they run at the same time.
+        let directory = tempfile::tempdir().expect("no temp dir");
+        let path = directory.path().display();
+        let elements = vec![
+            code(&format!("touch {path}/a; while [ ! -f {path}/b ]; do sleep 0.05; done; echo a")),
+            code(&format!("touch {path}/b; while [ ! -f {path}/a ]; do sleep 0.05; done; echo b")),
+        ];
+        let mut presentation = build_presentation(elements);
+        assert!(presentation.render_slide_widgets());
+        while !presentation.widgets_rendered() {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert!(!presentation.widgets_rendering());
+    }
+
+    #[rstest]
+    #[case::success("echo hi", &["hi"])]
+    #[case::failure("echo oops >&2; exit 3", &["oops", "[exit: 3]"])]
+    fn execute_replace(#[case] contents: &str, #[case] expected: &[&str]) {
+        let flags = CodeFlags { execute: true, execute_replace: true, ..Default::default() };
+        let code = Code { contents: contents.into(), language: CodeLanguage::Shell("sh".into()), flags };
+        let mut presentation = build_presentation(vec![MarkdownElement::Code(code.into())]);
+        let operations: Vec<_> = presentation
+            .iter_slides()
+            .flat_map(|slide| slide.iter_operations())
+            .filter(|operation| {
+                matches!(operation, RenderOperation::RenderPreformattedLine(_) | RenderOperation::RenderOnDemand(_))
+            })
+            .cloned()
+            .collect();
+        let [RenderOperation::RenderOnDemand(operation)] = operations.as_slice() else {
+            panic!("unexpected operations: {operations:?}");
+        };
+
+        // The code only runs once the slide is displayed.
+        assert!(matches!(operation.poll_state(), RenderOnDemandState::NotStarted));
+        assert!(presentation.render_automatic_slide_widgets());
+        while !matches!(operation.poll_state(), RenderOnDemandState::Rendered) {
+            thread::sleep(Duration::from_millis(10));
+        }
+        let lines: Vec<_> = operation
+            .as_render_operations(&WindowSize { rows: 10, columns: 80, width: 0, height: 0, has_pixels: false })
+            .iter()
+            .filter_map(|operation| match operation {
+                RenderOperation::RenderPreformattedLine(line) => Some(plain_text(line)),
+                RenderOperation::RenderDynamic(_) => panic!("output rendered in a block"),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(lines, expected);
+    }
+
+    #[test]
+    fn running_execution_heading() {
+        let code =
+            Code { contents: String::new(), language: CodeLanguage::Shell("sh".into()), flags: Default::default() };
+        let operation = RunCodeOperation::new(
+            code,
+            Default::default(),
+            Default::default(),
+            &Default::default(),
+            Default::default(),
+        );
+        {
+            let mut inner = operation.inner.borrow_mut();
+            inner.started_at = Some(Instant::now() - Duration::from_millis(3250));
+            inner.state = RenderOnDemandState::Rendering;
+        }
+        let dimensions = WindowSize { rows: 10, columns: 10, height: 10, width: 10, has_pixels: false };
+        let operations = operation.as_render_operations(&dimensions);
+        let heading = operations
+            .iter()
+            .find_map(|operation| match operation {
+                RenderOperation::RenderDynamic(separator) => Some(format!("{separator:?}")),
+                _ => None,
+            })
+            .expect("no heading");
+        assert!(heading.contains("running ⠹ 3s"), "{heading}");
+    }
+
+    #[test]
+    fn automatic_executions() {
+        let code = |auto_execute| {
+            let flags = CodeFlags { execute: true, auto_execute, ..Default::default() };
+            MarkdownElement::Code(Box::new(Code {
+                contents: "true".into(),
+                language: CodeLanguage::Shell("sh".into()),
+                flags,
+            }))
+        };
+        let mut presentation = build_presentation(vec![code(false)]);
+        assert!(!presentation.render_automatic_slide_widgets());
+
+        let mut presentation = build_presentation(vec![code(true)]);
+        assert!(presentation.render_automatic_slide_widgets());
+        // It's already running so it's not started again.
+        assert!(!presentation.render_automatic_slide_widgets());
+    }
+
+    #[test]
+    fn step_executions() {
+        let code = |step| {
+            let flags = CodeFlags { execute: true, step, ..Default::default() };
+            MarkdownElement::Code(Box::new(Code {
+                contents: "true".into(),
+                language: CodeLanguage::Shell("sh".into()),
+                flags,
+            }))
+        };
+        let mut presentation = build_presentation(vec![code(Some(2)), code(Some(1)), code(None)]);
+        let started = |presentation: &Presentation| -> Vec<bool> {
+            presentation
+                .current_slide()
+                .iter_operations()
+                .filter_map(|op| match op {
+                    RenderOperation::RenderOnDemand(op) => {
+                        Some(!matches!(op.poll_state(), RenderOnDemandState::NotStarted))
+                    }
+                    _ => None,
+                })
+                .collect()
+        };
+        assert!(presentation.render_slide_widgets());
+        assert_eq!(started(&presentation), &[false, true, true]);
+
+        assert!(presentation.render_slide_widgets());
+        assert_eq!(started(&presentation), &[true, true, true]);
+
+        assert!(!presentation.render_slide_widgets());
+    }
+
+    #[test]
+    fn highlight_groups() {
+        let highlight_groups = vec![LineGroup::parse("1").unwrap(), LineGroup::parse("2").unwrap()];
+        let flags = CodeFlags { highlight_groups, ..Default::default() };
+        let code = Code { contents: "let a = 1;\nlet b = 2;".into(), language: CodeLanguage::Rust, flags };
+        let elements = vec![MarkdownElement::Code(code.into()), build_end_slide(), build_end_slide()];
+        let mut presentation = build_presentation(elements);
+        let dimensions = WindowSize { rows: 10, columns: 10, height: 10, width: 10, has_pixels: false };
+        let rendered = |presentation: &Presentation| -> String {
+            presentation
+                .current_slide()
+                .iter_operations()
+                .find_map(|operation| match operation {
+                    RenderOperation::RenderDynamic(operation) => {
+                        Some(format!("{:?}", operation.as_render_operations(&dimensions)))
+                    }
+                    _ => None,
+                })
+                .expect("no highlight groups")
+        };
+        let first = rendered(&presentation);
+
+        // The first step moves to the next group within the same slide.
+        assert!(presentation.jump_next_slide());
+        assert_eq!(presentation.current_slide_index(), 0);
+        let second = rendered(&presentation);
+        assert_ne!(first, second);
+
+        assert!(presentation.jump_next_slide());
+        assert_eq!(presentation.current_slide_index(), 1);
+
+        // Going back shows the last group.
+        assert!(presentation.jump_previous_slide());
+        assert_eq!(presentation.current_slide_index(), 0);
+        assert_eq!(rendered(&presentation), second);
+
+        assert!(presentation.jump_previous_slide());
+        assert_eq!(presentation.current_slide_index(), 0);
+        assert_eq!(rendered(&presentation), first);
+    }
+
+    #[test]
+    fn reset_executions() {
+        let flags = CodeFlags { execute: true, ..Default::default() };
+        let code = Code { contents: "echo hi".into(), language: CodeLanguage::Shell("sh".into()), flags };
+        let mut presentation = build_presentation(vec![MarkdownElement::Code(code.into())]);
+        assert!(!presentation.reset_slide_widgets());
+
+        assert!(presentation.render_slide_widgets());
+        assert!(presentation.reset_slide_widgets());
+        // It's back to where it started so it can be run again.
+        assert!(presentation.render_slide_widgets());
+    }
+
+    #[test]
+    fn chained_code() {
+        let code = |contents: &str, id: Option<&str>, continues: Option<&str>| {
+            let flags = CodeFlags {
+                execute: true,
+                id: id.map(Into::into),
+                continues: continues.map(Into::into),
+                ..Default::default()
+            };
+            let language = CodeLanguage::Shell("sh".into());
+            MarkdownElement::Code(Box::new(Code { contents: contents.into(), language, flags }))
+        };
+        let elements = vec![
+            code("a=1", Some("first"), None),
+            code("b=2", Some("second"), Some("first")),
+            code("echo $((a + b))", None, Some("second")),
+        ];
+        let presentation = build_presentation(elements);
+        let operations: Vec<_> = presentation
+            .iter_slides()
+            .flat_map(|slide| slide.iter_operations())
+            .filter_map(|operation| match operation {
+                RenderOperation::RenderOnDemand(operation) => Some(operation.clone()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(operations.len(), 3);
+
+        // The last snippet only knows about `a` and `b` if the ones it continues run along with it.
+        let operation = &operations[2];
+        assert!(operation.start_render());
+        while !matches!(operation.poll_state(), RenderOnDemandState::Rendered) {
+            thread::sleep(Duration::from_millis(10));
+        }
+        let output: Vec<_> = operation
+            .as_render_operations(&WindowSize { rows: 10, columns: 80, width: 0, height: 0, has_pixels: false })
+            .iter()
+            .filter_map(|operation| match operation {
+                RenderOperation::RenderPreformattedLine(line) => Some(plain_text(line)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(output.first().map(String::as_str), Some("3"));
+    }
+
+    #[test]
+    fn unknown_code_id() {
+        let flags = CodeFlags { continues: Some("potato".into()), ..Default::default() };
+        let code = Code { contents: String::new(), language: CodeLanguage::Python, flags };
+        let result = try_build_presentation(vec![MarkdownElement::Code(code.into())]);
+        assert!(matches!(result, Err(BuildError::UnknownCodeId(id)) if id == "potato"));
+    }
+
+    #[test]
+    fn missing_external_code_file() {
+        let flags = CodeFlags { external_file: Some("presenterm-missing/snippet.sh".into()), ..Default::default() };
+        let code = Code { contents: String::new(), language: CodeLanguage::Shell("sh".into()), flags };
+        let result = try_build_presentation(vec![MarkdownElement::Code(code.into())]);
+        assert!(matches!(result, Err(BuildError::LoadCode(_))));
+    }
+
+    #[rstest]
+    #[case::no_markers(false, &["b", "c", "e"])]
+    #[case::markers(true, &["…", "b", "c", "…", "e", "…"])]
+    fn code_line_ranges(#[case] elision_markers: bool, #[case] expected: &[&str]) {
+        let flags = CodeFlags { line_ranges: vec![2..=3, 5..=5], elision_markers, ..Default::default() };
+        let code = Code { contents: "a\nb\nc\nd\ne\nf\n".into(), language: CodeLanguage::Unknown("".into()), flags };
+        let presentation = build_presentation(vec![MarkdownElement::Code(code.into())]);
+        let lines: Vec<_> = presentation
+            .iter_slides()
+            .flat_map(|slide| slide.iter_operations())
+            .filter_map(|op| match op {
+                RenderOperation::RenderPreformattedLine(line) => Some(plain_text(line)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(lines, expected);
+    }
+
+    #[rstest]
+    #[case::enabled(None, true)]
+    #[case::disabled(Some(false), false)]
+    fn external_code_file_title(#[case] file_names: Option<bool>, #[case] has_title: bool) {
+        let mut file = tempfile::NamedTempFile::new().expect("no temp file");
+        file.write_all(b"echo hi").expect("write failed");
+        let flags = CodeFlags { external_file: Some(file.path().into()), ..Default::default() };
+        let code = Code { contents: String::new(), language: CodeLanguage::Shell("sh".into()), flags };
+
+        let mut theme = PresentationTheme::default();
+        theme.code.title.file_names = file_names;
+        let presentation =
+            build_presentation_with(&theme, Default::default(), vec![MarkdownElement::Code(code.into())]);
+        let path = file.path().display().to_string();
+        let title_found = presentation
+            .iter_slides()
+            .flat_map(|slide| slide.iter_operations())
+            .any(|op| matches!(op, RenderOperation::RenderPreformattedLine(line) if plain_text(line).contains(&path)));
+        assert_eq!(title_found, has_title);
+    }
+
+    #[test]
+    fn relative_working_directory() {
+        let highlighter = CodeHighlighter::new("base16-ocean.dark").unwrap();
+        let theme = PresentationTheme::default();
+        let mut resources = Resources::new("examples");
+        let mut cache = ExecutionCache::default();
+        let builder = PresentationBuilder::new(highlighter, &theme, &mut resources, &mut cache, Default::default());
+        let flags = CodeFlags { working_directory: Some("scripts".into()), ..Default::default() };
+        let mut code = Code { contents: String::new(), language: CodeLanguage::Shell("sh".into()), flags };
+        builder.apply_execution_defaults(&mut code);
+
+        let expected = env::current_dir().expect("no current directory").join("examples/scripts");
+        assert_eq!(code.flags.working_directory, Some(expected));
+    }
+
+    #[test]
+    fn execution_state_survives_rebuilds() {
+        let build = |cache: &mut ExecutionCache, contents: &str| {
+            let flags = CodeFlags { execute: true, ..Default::default() };
+            let code = Code { contents: contents.into(), language: CodeLanguage::Shell("sh".into()), flags };
+            let highlighter = CodeHighlighter::new("base16-ocean.dark").unwrap();
+            let theme = PresentationTheme::default();
+            let mut resources = Resources::new("/tmp");
+            let builder = PresentationBuilder::new(highlighter, &theme, &mut resources, cache, Default::default());
+            builder.build(vec![MarkdownElement::Code(code.into())]).expect("build failed")
+        };
+        let mut cache = ExecutionCache::default();
+        let mut presentation = build(&mut cache, "echo hi");
+        assert!(presentation.render_slide_widgets());
+
+        // Same code means the same state so it's already rendering.
+        let mut presentation = build(&mut cache, "echo hi");
+        assert!(!presentation.render_slide_widgets());
+
+        let mut presentation = build(&mut cache, "echo bye");
+        assert!(presentation.render_slide_widgets());
+    }
+
+    #[test]
+    fn inline_code_padding() {
+        let mut theme = PresentationTheme::default();
+        theme.inline_code.padding = Some(" ".into());
+        let elements = vec![MarkdownElement::Paragraph(vec![ParagraphElement::Text(Text {
+            chunks: vec![StyledText::from("run "), StyledText::new("ls", TextStyle::default().code())],
+        })])];
+        let presentation = build_presentation_with(&theme, Default::default(), elements);
+        let texts: Vec<_> = presentation
+            .iter_slides()
+            .flat_map(|slide| slide.iter_operations())
+            .filter_map(|op| match op {
+                RenderOperation::RenderText { line, .. } => Some(line),
+                _ => None,
+            })
+            .flat_map(|line| line.iter_texts())
+            .map(|text| text.text.text.clone())
+            .collect();
+        assert_eq!(texts, &["run ", " ls "]);
+    }
+
+    #[test]
+    fn strikethrough_colors() {
+        let mut theme = PresentationTheme::default();
+        theme.strikethrough.colors.foreground = Some(Color::new(1, 2, 3));
+        let elements = vec![MarkdownElement::Paragraph(vec![ParagraphElement::Text(Text {
+            chunks: vec![StyledText::from("keep "), StyledText::new("gone", TextStyle::default().strikethrough())],
+        })])];
+        let presentation = build_presentation_with(&theme, Default::default(), elements);
+        let foregrounds: Vec<_> = presentation
+            .iter_slides()
+            .flat_map(|slide| slide.iter_operations())
+            .filter_map(|op| match op {
+                RenderOperation::RenderText { line, .. } => Some(line),
+                _ => None,
+            })
+            .flat_map(|line| line.iter_texts())
+            .map(|text| text.text.style.colors.foreground)
+            .collect();
+        assert_eq!(foregrounds, &[None, Some(Color::new(1, 2, 3))]);
+    }
+
+    #[test]
+    fn internal_links() {
+        let link = |anchor: &str| {
+            MarkdownElement::Paragraph(vec![ParagraphElement::Text(Text {
+                chunks: vec![StyledText { link: Some(format!("#{anchor}")), ..StyledText::from("go") }],
+            })])
+        };
+        let elements = vec![
+            link("architecture-overview"),
+            build_end_slide(),
+            MarkdownElement::SetexHeading { text: Text::from("Intro"), level: 2 },
+            link("details"),
+            build_end_slide(),
+            MarkdownElement::SetexHeading { text: Text::from("Architecture Overview!"), level: 2 },
+            MarkdownElement::Heading { text: Text::from("Details"), level: 2 },
+        ];
+        let mut presentation = build_presentation(elements);
+        assert!(presentation.follow_link());
+        assert_eq!(presentation.current_slide_index(), 2);
+
+        presentation.jump_slide(1);
+        assert!(presentation.follow_link());
+        assert_eq!(presentation.current_slide_index(), 2);
+    }
+
+    #[rstest]
+    #[case::hyperlinks(true, &["see ", "the ", "docs"])]
+    #[case::plain(false, &["see ", "https://example.com"])]
+    fn external_links(#[case] hyperlinks: bool, #[case] expected: &[&str]) {
+        let options = PresentationBuilderOptions { hyperlinks: Some(hyperlinks), ..Default::default() };
+        let link = |text: &str, style: TextStyle| StyledText {
+            link: Some("https://example.com".into()),
+            ..StyledText::new(text, style.link())
+        };
+        let elements = vec![MarkdownElement::Paragraph(vec![ParagraphElement::Text(Text {
+            chunks: vec![
+                StyledText::from("see "),
+                link("the ", TextStyle::default()),
+                link("docs", TextStyle::default().bold()),
+            ],
+        })])];
+        let presentation = build_presentation_with(&Default::default(), options, elements);
+        let texts: Vec<_> = presentation
+            .iter_slides()
+            .flat_map(|slide| slide.iter_operations())
+            .filter_map(|op| match op {
+                RenderOperation::RenderText { line, .. } => Some(line),
+                _ => None,
+            })
+            .flat_map(|line| line.iter_texts())
+            .map(|text| text.text.text.clone())
+            .collect();
+        assert_eq!(texts, expected);
+    }
+
+    #[test]
+    fn block_quote_attribution() {
+        let elements = vec![MarkdownElement::BlockQuote {
+            lines: vec!["stay hungry, stay foolish".into()],
+            attribution: Some("Steve Jobs".into()),
+        }];
+        let presentation = build_presentation(elements);
+        let lines: Vec<_> = presentation
+            .iter_slides()
+            .flat_map(|slide| slide.iter_operations())
+            .filter_map(|op| match op {
+                RenderOperation::RenderPreformattedLine(line) => Some((plain_text(line), line.block_length)),
+                _ => None,
+            })
+            .collect();
+        let block_length = "stay hungry, stay foolish".len();
+        let expected = vec![
+            ("stay hungry, stay foolish".to_string(), block_length),
+            (format!("{}— Steve Jobs", " ".repeat(13)), block_length),
+        ];
+        assert_eq!(lines, expected);
     }
 }
@@ -0,0 +1,394 @@
+use crate::{
+    markdown::{elements::ThematicBreakMarker, parse::LineBreaks},
+    render::media::ImageProtocol,
+};
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+/// The user configuration.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// The code execution configuration.
+    #[serde(default)]
+    pub execution: ExecutionConfig,
+
+    /// The syntax highlighting configuration.
+    #[serde(default)]
+    pub highlighting: HighlightingConfig,
+
+    /// The configuration used when rendering typst code into images.
+    #[serde(default)]
+    pub typst: TypstConfig,
+
+    /// The configuration used when rendering LaTeX code into images.
+    #[serde(default)]
+    pub latex: LatexConfig,
+
+    /// The configuration used when rendering mermaid diagrams into images.
+    #[serde(default)]
+    pub mermaid: MermaidConfig,
+
+    /// The markdown parsing configuration.
+    #[serde(default)]
+    pub markdown: MarkdownConfig,
+
+    /// The configuration for revealing the contents of slides automatically.
+    #[serde(default)]
+    pub reveal: RevealConfig,
+
+    /// The image rendering configuration.
+    #[serde(default)]
+    pub images: ImagesConfig,
+}
+
+impl Config {
+    /// Load the configuration from a path.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ConfigLoadError> {
+        let contents = fs::read_to_string(path)?;
+        let config = serde_yaml::from_str(&contents)?;
+        Ok(config)
+    }
+
+    /// Get the path where the configuration is looked up by default.
+    ///
+    /// This is `$XDG_CONFIG_HOME/presenterm/config.yaml`, falling back to `~/.config` if that
+    /// variable is not set.
+    pub fn default_path() -> Option<PathBuf> {
+        let base = match env::var_os("XDG_CONFIG_HOME") {
+            Some(path) if !path.is_empty() => PathBuf::from(path),
+            _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+        };
+        Some(base.join("presenterm").join("config.yaml"))
+    }
+}
+
+/// The code execution configuration.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExecutionConfig {
+    /// The number of seconds executed code is allowed to run for before being killed.
+    ///
+    /// This can be overridden for a particular piece of code by using the `timeout` attribute.
+    #[serde(default)]
+    pub timeout: Option<u64>,
+
+    /// The directory executed code is run from.
+    ///
+    /// Relative paths are relative to the presentation's directory. This can be overridden for a particular piece
+    /// of code by using the `cwd` attribute.
+    #[serde(default)]
+    pub cwd: Option<PathBuf>,
+
+    /// Where code is executed.
+    #[serde(default)]
+    pub backend: ExecutionBackend,
+
+    /// The command used to execute code in each language, e.g. `go: ["go", "run", "$FILE"]`.
+    ///
+    /// `$FILE` is replaced with the path to the file the code is written to and `$OUT` with a path that can be used
+    /// to write a compiled binary to. These take precedence over the built in support for executing code.
+    #[serde(default)]
+    pub commands: BTreeMap<String, Vec<String>>,
+
+    /// The configuration used when executing code inside docker containers.
+    #[serde(default)]
+    pub docker: DockerConfig,
+}
+
+/// Where code is executed.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecutionBackend {
+    /// Execute code locally.
+    #[default]
+    Local,
+
+    /// Execute code inside docker containers.
+    Docker,
+}
+
+/// The configuration used when executing code inside docker containers.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DockerConfig {
+    /// The image to use for each language, e.g. `python: python:3.12-slim`.
+    #[serde(default)]
+    pub images: BTreeMap<String, String>,
+}
+
+/// The syntax highlighting configuration.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HighlightingConfig {
+    /// The path to a `bat` cache directory to load syntaxes and themes from, e.g. `~/.cache/bat`.
+    ///
+    /// This is the directory printed by `bat --cache-dir`.
+    #[serde(default)]
+    pub bat_cache: Option<PathBuf>,
+
+    /// The path to a directory containing `.sublime-syntax` files to load on top of the existing syntaxes.
+    ///
+    /// If not set, the `syntaxes` directory next to the configuration file is used if it exists.
+    #[serde(default)]
+    pub syntaxes_path: Option<PathBuf>,
+
+    /// What is used to highlight code.
+    #[serde(default)]
+    pub backend: HighlightingBackend,
+}
+
+/// What is used to highlight code.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HighlightingBackend {
+    /// Use syntect.
+    #[default]
+    Syntect,
+
+    /// Use tree-sitter for the languages it supports and syntect for the rest.
+    ///
+    /// This requires presenterm to be built with the `tree-sitter` feature.
+    TreeSitter,
+}
+
+/// The markdown parsing configuration.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MarkdownConfig {
+    /// Whether to replace `:shortcode:` style emojis with the emoji they represent.
+    ///
+    /// This defaults to true.
+    #[serde(default)]
+    pub emoji_shortcodes: Option<bool>,
+
+    /// Whether to turn `--` and `---` into en and em dashes, straight quotes into curly quotes, and `...` into an
+    /// ellipsis.
+    ///
+    /// This defaults to false.
+    #[serde(default)]
+    pub smart_punctuation: bool,
+
+    /// How single line breaks within a paragraph are handled.
+    ///
+    /// By default lines are joined like markdown usually does. Hard line breaks, like lines ending in a backslash,
+    /// always break the line.
+    #[serde(default)]
+    pub line_breaks: LineBreaks,
+
+    /// Whether to render links as hyperlinks that can be clicked on, using OSC 8 escape sequences.
+    ///
+    /// If this isn't set, hyperlinks are only used if the terminal looks like it supports them. When they're not
+    /// used, the URL a link points to is displayed instead of its text.
+    #[serde(default)]
+    pub hyperlinks: Option<bool>,
+
+    /// The thematic breaks that end the current slide instead of rendering a horizontal line, e.g. `["***"]`.
+    ///
+    /// The `end_slide` comment command always ends the slide.
+    #[serde(default)]
+    pub slide_separators: Vec<ThematicBreakMarker>,
+
+    /// The heading level at or above which headings start a new slide, e.g. 2 to make every H1 and H2 do so.
+    ///
+    /// Headings that start a slide are displayed as its title.
+    #[serde(default)]
+    pub slide_level: Option<u8>,
+
+    /// Whether to reveal the top level items in lists one at a time, as if there was a pause in between them.
+    ///
+    /// This can be overridden in a presentation's front matter.
+    #[serde(default)]
+    pub incremental_lists: bool,
+}
+
+/// The image rendering configuration.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ImagesConfig {
+    /// The protocol used to draw images in the terminal.
+    ///
+    /// If not set, this is detected based on the terminal being used.
+    #[serde(default)]
+    pub protocol: Option<ImageProtocol>,
+}
+
+/// The configuration for revealing the contents of slides automatically.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RevealConfig {
+    /// The number of milliseconds after which the contents following a pause are revealed automatically.
+    ///
+    /// If not set, contents are only revealed when moving forward in the presentation. This can be overridden for a
+    /// particular slide by using the `reveal_delay` comment command.
+    #[serde(default)]
+    pub delay: Option<u64>,
+
+    /// The number of milliseconds after which the next item in an incremental list is revealed automatically.
+    ///
+    /// If not set, `delay` is used.
+    #[serde(default)]
+    pub list_item_delay: Option<u64>,
+}
+
+/// The configuration used when rendering typst code into images.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TypstConfig {
+    /// The pixels per inch to render images with.
+    ///
+    /// Higher values make rendered code look bigger. This defaults to 300.
+    #[serde(default)]
+    pub ppi: Option<u32>,
+}
+
+/// The configuration used when rendering LaTeX code into images.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LatexConfig {
+    /// The command used to render LaTeX code into a PNG image, e.g. `["my-latex-render", "$FILE", "$OUT"]`.
+    ///
+    /// `$FILE` is replaced with the path to a file containing the code and `$OUT` with the path the image must be
+    /// written to. If not set, the code is converted into typst via `pandoc` and rendered using `typst`.
+    #[serde(default)]
+    pub command: Option<Vec<String>>,
+}
+
+/// The configuration used when rendering mermaid diagrams into images.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MermaidConfig {
+    /// The path to the `mmdc` binary used to render diagrams.
+    ///
+    /// If not set, `mmdc` is looked up in `$PATH`.
+    #[serde(default)]
+    pub command: Option<String>,
+
+    /// The scale factor to render diagrams with.
+    ///
+    /// Higher values make diagrams look bigger. This defaults to 2.
+    #[serde(default)]
+    pub scale: Option<u32>,
+}
+
+/// An error when loading the configuration.
+#[derive(thiserror::Error, Debug)]
+pub enum ConfigLoadError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+
+    #[error("invalid configuration: {0}")]
+    Invalid(#[from] serde_yaml::Error),
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_execution_timeout() {
+        let config: Config = serde_yaml::from_str("execution:\n  timeout: 10").expect("invalid config");
+        assert_eq!(config.execution.timeout, Some(10));
+    }
+
+    #[test]
+    fn parse_docker_backend() {
+        let input = r"
+execution:
+  backend: docker
+  docker:
+    images:
+      python: python:3.12-slim
+";
+        let config: Config = serde_yaml::from_str(input).expect("invalid config");
+        assert_eq!(config.execution.backend, ExecutionBackend::Docker);
+        assert_eq!(config.execution.docker.images.get("python").map(String::as_str), Some("python:3.12-slim"));
+    }
+
+    #[test]
+    fn parse_execution_working_directory() {
+        let config: Config = serde_yaml::from_str("execution:\n  cwd: scripts").expect("invalid config");
+        assert_eq!(config.execution.cwd, Some("scripts".into()));
+    }
+
+    #[test]
+    fn parse_commands() {
+        let input = r#"
+execution:
+  commands:
+    go: ["go", "run", "$FILE"]
+"#;
+        let config: Config = serde_yaml::from_str(input).expect("invalid config");
+        assert_eq!(config.execution.commands.get("go"), Some(&vec!["go".into(), "run".into(), "$FILE".into()]));
+    }
+
+    #[test]
+    fn parse_bat_cache() {
+        let config: Config = serde_yaml::from_str("highlighting:\n  bat_cache: /tmp/bat").expect("invalid config");
+        assert_eq!(config.highlighting.bat_cache, Some("/tmp/bat".into()));
+    }
+
+    #[test]
+    fn parse_highlighting_backend() {
+        let config: Config = serde_yaml::from_str("highlighting:\n  backend: tree_sitter").expect("invalid config");
+        assert_eq!(config.highlighting.backend, HighlightingBackend::TreeSitter);
+    }
+
+    #[test]
+    fn parse_rendering() {
+        let input = r#"
+typst:
+  ppi: 400
+latex:
+  command: ["render", "$FILE", "$OUT"]
+mermaid:
+  command: /opt/mmdc
+  scale: 3
+"#;
+        let config: Config = serde_yaml::from_str(input).expect("invalid config");
+        assert_eq!(config.typst.ppi, Some(400));
+        assert_eq!(config.latex.command, Some(vec!["render".into(), "$FILE".into(), "$OUT".into()]));
+        assert_eq!(config.mermaid.command.as_deref(), Some("/opt/mmdc"));
+        assert_eq!(config.mermaid.scale, Some(3));
+    }
+
+    #[test]
+    fn parse_markdown() {
+        let config: Config = serde_yaml::from_str("markdown:\n  emoji_shortcodes: false").expect("invalid config");
+        assert_eq!(config.markdown.emoji_shortcodes, Some(false));
+        assert_eq!(config.markdown.hyperlinks, None);
+
+        let config: Config = serde_yaml::from_str("markdown:\n  slide_separators: [\"***\"]").expect("invalid config");
+        assert_eq!(config.markdown.slide_separators, &[ThematicBreakMarker::Asterisks]);
+    }
+
+    #[test]
+    fn parse_reveal() {
+        let config: Config =
+            serde_yaml::from_str("reveal:\n  delay: 1500\n  list_item_delay: 500").expect("invalid config");
+        assert_eq!(config.reveal.delay, Some(1500));
+        assert_eq!(config.reveal.list_item_delay, Some(500));
+    }
+
+    #[test]
+    fn parse_image_protocol() {
+        let config: Config = serde_yaml::from_str("images:\n  protocol: sixel").expect("invalid config");
+        assert_eq!(config.images.protocol, Some(ImageProtocol::Sixel));
+    }
+
+    #[test]
+    fn parse_syntaxes_path() {
+        let config: Config = serde_yaml::from_str("highlighting:\n  syntaxes_path: syntaxes").expect("invalid config");
+        assert_eq!(config.highlighting.syntaxes_path, Some("syntaxes".into()));
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        let result = serde_yaml::from_str::<Config>("potato: 42");
+        assert!(result.is_err());
+    }
+}
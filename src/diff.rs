@@ -71,9 +71,16 @@ impl ContentDiff for RenderOperation {
             (RenderText { alignment: original, .. }, RenderText { alignment: updated, .. }) if original != updated => {
                 false
             }
-            (RenderImage(original), RenderImage(updated)) if original != updated => true,
+            (RenderImage(original, original_position), RenderImage(updated, updated_position))
+                if original != updated || original_position != updated_position =>
+            {
+                true
+            }
+            (RenderBackgroundImage(original), RenderBackgroundImage(updated)) if original != updated => true,
             (RenderPreformattedLine(original), RenderPreformattedLine(updated)) if original != updated => true,
-            (InitColumnLayout { columns: original }, InitColumnLayout { columns: updated }) if original != updated => {
+            (InitColumnLayout { columns: original, .. }, InitColumnLayout { columns: updated, .. })
+                if original != updated =>
+            {
                 true
             }
             (EnterColumn { column: original }, EnterColumn { column: updated }) if original != updated => true,
@@ -107,13 +114,14 @@ where
 mod test {
     use super::*;
     use crate::{
-        presentation::{AsRenderOperations, PreformattedLine, Slide},
-        render::properties::WindowSize,
+        markdown::elements::StyledText,
+        presentation::{AsRenderOperations, ImagePosition, PreformattedLine, Slide},
+        render::{media::Image, properties::WindowSize},
         style::{Color, Colors},
         theme::{Alignment, Margin},
     };
     use rstest::rstest;
-    use std::rc::Rc;
+    use std::{fs, rc::Rc};
 
     #[derive(Debug)]
     struct Dynamic;
@@ -133,14 +141,16 @@ mod test {
     #[case(RenderOperation::RenderText{line: String::from("asd").into(), alignment: Default::default()})]
     #[case(RenderOperation::RenderPreformattedLine(
         PreformattedLine{
-            text: "asd".into(),
+            spans: vec![StyledText::from("asd")],
             alignment: Default::default(),
             block_length: 42,
-            unformatted_length: 1337
+            unformatted_length: 1337,
+            overflow: None,
+            raw: false,
         }
     ))]
     #[case(RenderOperation::RenderDynamic(Rc::new(Dynamic)))]
-    #[case(RenderOperation::InitColumnLayout{ columns: vec![1, 2] })]
+    #[case(RenderOperation::InitColumnLayout{ columns: vec![1, 2].into(), style: Default::default() })]
     #[case(RenderOperation::EnterColumn{ column: 1 })]
     #[case(RenderOperation::ExitLayout)]
     fn same_not_modified(#[case] operation: RenderOperation) {
@@ -155,6 +165,15 @@ mod test {
         assert!(lhs.is_content_different(&rhs));
     }
 
+    #[test]
+    fn different_image_position() {
+        let contents = fs::read("examples/doge.png").expect("no image");
+        let image = Image::new(&contents).expect("invalid image");
+        let lhs = RenderOperation::RenderImage(image.clone(), ImagePosition::Left);
+        let rhs = RenderOperation::RenderImage(image, ImagePosition::Right);
+        assert!(lhs.is_content_different(&rhs));
+    }
+
     #[test]
     fn different_text_alignment() {
         let lhs = RenderOperation::RenderText {
@@ -177,8 +196,8 @@ mod test {
 
     #[test]
     fn different_column_layout() {
-        let lhs = RenderOperation::InitColumnLayout { columns: vec![1, 2] };
-        let rhs = RenderOperation::InitColumnLayout { columns: vec![1, 3] };
+        let lhs = RenderOperation::InitColumnLayout { columns: vec![1, 2].into(), style: Default::default() };
+        let rhs = RenderOperation::InitColumnLayout { columns: vec![1, 3].into(), style: Default::default() };
         assert!(lhs.is_content_different(&rhs));
     }
 
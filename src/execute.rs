@@ -1,55 +1,461 @@
 //! Code execution.
 
-use crate::markdown::elements::{Code, CodeLanguage};
+use crate::{
+    config::{ExecutionBackend, ExecutionConfig},
+    markdown::elements::{Code, CodeLanguage},
+    render::highlighting::CodeHighlighter,
+};
 use std::{
+    ffi::OsString,
+    fmt::{self, Display},
+    fs::{self, OpenOptions},
     io::{self, BufRead, BufReader, Write},
-    process::{self, ChildStdout, Stdio},
-    sync::{Arc, Mutex},
+    path::{Path, PathBuf},
+    process::{self, ExitStatus, Stdio},
+    sync::{mpsc, Arc, Mutex},
     thread::{self},
+    time::{Duration, Instant},
 };
-use tempfile::NamedTempFile;
+use tempfile::TempDir;
+
+/// The directory snippets are mounted at when running inside a container.
+const CONTAINER_SNIPPET_DIRECTORY: &str = "/snippet";
+
+/// The directory the working directory is mounted at when running inside a container.
+const CONTAINER_WORKING_DIRECTORY: &str = "/workdir";
+
+/// The script used to compile and run rust code that's read from stdin on a remote host.
+const REMOTE_RUST_SCRIPT: &str = r#"dir=$(mktemp -d) && trap 'rm -rf "$dir"' EXIT && cat > "$dir/snippet.rs" && rustc --edition 2021 -A warnings -o "$dir/snippet" "$dir/snippet.rs" && "$dir/snippet""#;
+
+/// The script used to compile and run rust code on Windows.
+const WINDOWS_RUST_SCRIPT: &str =
+    "@rustc --edition 2021 -A warnings -o \"%~dp0snippet.exe\" \"%~dp0snippet.rs\" && \"%~dp0snippet.exe\"\r\n";
+
+/// The family of operating systems code is run in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Platform {
+    Unix,
+    Windows,
+}
+
+impl Platform {
+    /// The platform we're running on.
+    const LOCAL: Self = if cfg!(windows) { Self::Windows } else { Self::Unix };
+}
+
+fn is_powershell(interpreter: &str) -> bool {
+    matches!(interpreter, "powershell" | "pwsh")
+}
 
 /// Allows executing code.
-pub(crate) struct CodeExecuter;
+#[derive(Clone, Debug, Default)]
+pub struct CodeExecuter {
+    config: ExecutionConfig,
+    ssh_destination: Option<String>,
+    disabled: bool,
+    transcript: Option<ExecutionTranscript>,
+}
 
 impl CodeExecuter {
+    /// Construct a new code executer.
+    pub fn new(config: ExecutionConfig) -> Self {
+        Self { config, ssh_destination: None, disabled: false, transcript: None }
+    }
+
+    /// Append every piece of code that's executed, along with its output, to a transcript file.
+    ///
+    /// The file is created if it doesn't exist.
+    pub fn with_transcript(mut self, path: PathBuf) -> io::Result<Self> {
+        // Make sure we can write to it now rather than failing once code is executed.
+        OpenOptions::new().create(true).append(true).open(&path)?;
+        self.transcript = Some(ExecutionTranscript { path, lock: Default::default() });
+        Ok(self)
+    }
+
+    /// Set whether code is allowed to be executed.
+    ///
+    /// When it isn't, any attempt to execute code fails with an error explaining why.
+    pub fn allow_execution(mut self, allowed: bool) -> Self {
+        self.disabled = !allowed;
+        self
+    }
+
+    /// Whether code is allowed to be executed.
+    pub(crate) fn is_enabled(&self) -> bool {
+        !self.disabled
+    }
+
+    /// Execute code in a remote host via ssh rather than using the configured backend.
+    pub(crate) fn with_ssh_destination(mut self, destination: String) -> Self {
+        self.ssh_destination = Some(destination);
+        self
+    }
+
+    /// Whether code is executed in a remote host.
+    pub(crate) fn is_remote(&self) -> bool {
+        self.ssh_destination.is_some()
+    }
+
     /// Execute a piece of code.
-    pub(crate) fn execute(code: &Code) -> Result<ExecutionHandle, CodeExecuteError> {
-        if !code.language.supports_execution() {
+    pub(crate) fn execute(&self, code: &Code) -> Result<ExecutionHandle, CodeExecuteError> {
+        let command = self.execution_command(code)?;
+        let transcript = self.transcript.clone().map(|transcript| (transcript, code.clone()));
+        Self::spawn(command, code.flags.timeout, code.flags.interactive, transcript)
+    }
+
+    /// Execute a piece of code that takes over the terminal and wait for it to finish.
+    ///
+    /// The caller is expected to have handed the terminal over before calling this.
+    /// Its output goes straight to the terminal so it isn't part of the returned state nor of the transcript.
+    pub(crate) fn execute_attached(&self, code: &Code) -> Result<ExecutionState, CodeExecuteError> {
+        let ExecutionCommand { mut command, input, container, directory } = self.execution_command(code)?;
+        if input.is_some() {
+            return Err(CodeExecuteError::InteractiveUnsupported);
+        }
+        let mut process_handle = command
+            .stdin(Stdio::inherit())
+            .stdout(Stdio::inherit())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .map_err(CodeExecuteError::SpawnProcess)?;
+        let started_at = Instant::now();
+        let mut timed_out = false;
+        let status = loop {
+            match process_handle.try_wait() {
+                Ok(Some(status)) => break Some(status),
+                Ok(None) => (),
+                Err(_) => break None,
+            };
+            if !timed_out && code.flags.timeout.is_some_and(|timeout| started_at.elapsed() >= timeout) {
+                // The process shares our process group so it can use the terminal, meaning only it can be killed.
+                let _ = process_handle.kill();
+                if let Some(container) = &container {
+                    kill_container(container);
+                }
+                timed_out = true;
+            }
+            thread::sleep(Duration::from_millis(20));
+        };
+        // The snippet needs to be around until it's done running.
+        drop(directory);
+        let exit = status.and_then(ProcessExit::from_status);
+        let status = match status {
+            _ if timed_out => ProcessStatus::TimedOut,
+            Some(status) if status.success() => ProcessStatus::Success,
+            _ => ProcessStatus::Failure,
+        };
+        let state = ExecutionState { status, exit, ..Default::default() };
+        if let Some(transcript) = &self.transcript {
+            // There's nobody to report this to so the best we can do is ignore it.
+            let _ = transcript.append(code, &state);
+        }
+        Ok(state)
+    }
+
+    fn execution_command(&self, code: &Code) -> Result<ExecutionCommand, CodeExecuteError> {
+        if !code.language.supports_execution() && self.command_template(&code.language).is_none() {
             return Err(CodeExecuteError::UnsupportedExecution);
         }
         if !code.flags.execute {
             return Err(CodeExecuteError::NotExecutableCode);
         }
+        if self.disabled {
+            return Err(CodeExecuteError::ExecutionDisabled);
+        }
+        match (&self.ssh_destination, &self.config.backend) {
+            // The snippet itself is sent over stdin when using ssh so there's no way to send user input.
+            (Some(_), _) if code.flags.interactive || code.flags.acquire_terminal => {
+                Err(CodeExecuteError::InteractiveUnsupported)
+            }
+            (Some(destination), _) => Self::ssh_command(code, destination),
+            (None, ExecutionBackend::Local) => self.local_command(code),
+            (None, ExecutionBackend::Docker) => self.docker_command(code),
+        }
+    }
+
+    fn snippet_contents(code: &Code) -> String {
+        let contents = code.executable_contents();
         match &code.language {
-            CodeLanguage::Shell(interpreter) => Self::execute_shell(interpreter, &code.contents),
-            _ => Err(CodeExecuteError::UnsupportedExecution),
+            // Allow snippets that only contain statements by wrapping them in a `main` function.
+            CodeLanguage::Rust if !contents.contains("fn main") => format!("fn main() {{\n{contents}\n}}\n"),
+            _ => contents,
+        }
+    }
+
+    /// Find the command template configured for a language, along with the name it was configured for.
+    fn command_template(&self, language: &CodeLanguage) -> Option<(&str, &[String])> {
+        self.config
+            .commands
+            .iter()
+            .find(|(name, _)| &CodeLanguage::from_name(name) == language)
+            .map(|(name, arguments)| (name.as_str(), arguments.as_slice()))
+    }
+
+    fn snippet_file_name(&self, language: &CodeLanguage, platform: Platform) -> String {
+        match (self.command_template(language), language) {
+            // Some tools require the file to have the right extension.
+            (Some(_), CodeLanguage::Unknown(name)) => format!("snippet.{name}"),
+            (Some(_), _) => format!("snippet.{}", CodeHighlighter::language_extension(language)),
+            (None, CodeLanguage::Rust) => "snippet.rs".into(),
+            (None, CodeLanguage::BatchFile) => "snippet.bat".into(),
+            (None, CodeLanguage::Shell(interpreter)) if platform == Platform::Windows && is_powershell(interpreter) => {
+                "snippet.ps1".into()
+            }
+            (None, _) => "snippet".into(),
         }
     }
 
-    fn execute_shell(interpreter: &str, code: &str) -> Result<ExecutionHandle, CodeExecuteError> {
-        let mut output_file = NamedTempFile::new().map_err(CodeExecuteError::TempFile)?;
-        output_file.write_all(code.as_bytes()).map_err(CodeExecuteError::TempFile)?;
-        output_file.flush().map_err(CodeExecuteError::TempFile)?;
-        let process_handle = process::Command::new("/usr/bin/env")
-            .arg(interpreter)
-            .arg(output_file.path())
-            .stdin(Stdio::null())
+    fn write_snippet(&self, code: &Code, platform: Platform) -> Result<TempDir, CodeExecuteError> {
+        let directory = tempfile::Builder::new().prefix("presenterm-").tempdir().map_err(CodeExecuteError::TempFile)?;
+        let file_name = self.snippet_file_name(&code.language, platform);
+        fs::write(directory.path().join(file_name), Self::snippet_contents(code))
+            .map_err(CodeExecuteError::TempFile)?;
+        if platform == Platform::Windows && code.language == CodeLanguage::Rust {
+            // There's no `sh` to compile and run it in one go so use a batch script instead.
+            fs::write(directory.path().join("run.bat"), WINDOWS_RUST_SCRIPT).map_err(CodeExecuteError::TempFile)?;
+        }
+        Ok(directory)
+    }
+
+    /// Get the arguments that run a snippet written in the given directory.
+    fn run_arguments(
+        &self,
+        language: &CodeLanguage,
+        directory: &Path,
+        platform: Platform,
+    ) -> Result<Vec<OsString>, CodeExecuteError> {
+        if let Some((_, template)) = self.command_template(language) {
+            let file = directory.join(self.snippet_file_name(language, platform));
+            let output = directory.join("snippet.out");
+            let arguments = template
+                .iter()
+                .map(|argument| {
+                    argument.replace("$FILE", &file.to_string_lossy()).replace("$OUT", &output.to_string_lossy()).into()
+                })
+                .collect();
+            return Ok(arguments);
+        }
+        let file = directory.join(self.snippet_file_name(language, platform));
+        let arguments = match (language, platform) {
+            (CodeLanguage::Shell(interpreter), Platform::Windows) if is_powershell(interpreter) => vec![
+                interpreter.into(),
+                "-NoProfile".into(),
+                "-ExecutionPolicy".into(),
+                "Bypass".into(),
+                "-File".into(),
+                file.into(),
+            ],
+            (CodeLanguage::Shell(interpreter), _) => vec![interpreter.into(), file.into()],
+            (CodeLanguage::BatchFile, Platform::Windows) => vec!["cmd".into(), "/C".into(), file.into()],
+            // Run unbuffered so output is streamed as it's generated.
+            (CodeLanguage::Python, Platform::Unix) => vec!["python3".into(), "-u".into(), file.into()],
+            (CodeLanguage::Python, Platform::Windows) => vec!["python".into(), "-u".into(), file.into()],
+            // Compile and run in a single process so compilation errors show up as part of the output.
+            (CodeLanguage::Rust, Platform::Unix) => vec![
+                "sh".into(),
+                "-c".into(),
+                r#"rustc --edition 2021 -A warnings -o "$1" "$2" && exec "$1""#.into(),
+                "sh".into(),
+                directory.join("snippet").into(),
+                file.into(),
+            ],
+            (CodeLanguage::Rust, Platform::Windows) => {
+                vec!["cmd".into(), "/C".into(), directory.join("run.bat").into()]
+            }
+            _ => return Err(CodeExecuteError::UnsupportedExecution),
+        };
+        Ok(arguments)
+    }
+
+    /// Get the arguments that run a snippet, including any extra arguments the interpreter should be given.
+    fn command_arguments(
+        &self,
+        code: &Code,
+        directory: &Path,
+        platform: Platform,
+    ) -> Result<Vec<OsString>, CodeExecuteError> {
+        let mut arguments = self.run_arguments(&code.language, directory, platform)?;
+        let extra_arguments = code.flags.arguments.iter().map(OsString::from);
+        if self.command_template(&code.language).is_some() {
+            // Custom commands say where these go by using `$ARGS`, otherwise they're placed at the end.
+            match arguments.iter().position(|argument| argument == "$ARGS") {
+                Some(index) => {
+                    arguments.splice(index..=index, extra_arguments);
+                }
+                None => arguments.extend(extra_arguments),
+            };
+            return Ok(arguments);
+        }
+        if code.flags.arguments.is_empty() {
+            return Ok(arguments);
+        }
+        // Rust code isn't run through an interpreter so there's nowhere to put these.
+        if code.language == CodeLanguage::Rust {
+            return Err(CodeExecuteError::ArgumentsUnsupported);
+        }
+        arguments.splice(1..1, extra_arguments);
+        Ok(arguments)
+    }
+
+    fn local_command(&self, code: &Code) -> Result<ExecutionCommand, CodeExecuteError> {
+        let platform = Platform::LOCAL;
+        let directory = self.write_snippet(code, platform)?;
+        let mut arguments = self.command_arguments(code, directory.path(), platform)?.into_iter();
+        let mut command = match platform {
+            Platform::Unix => process::Command::new("/usr/bin/env"),
+            Platform::Windows => process::Command::new(arguments.next().expect("no program")),
+        };
+        command.args(arguments);
+        command.envs(code.flags.environment.iter().map(|(name, value)| (name, value)));
+        if let Some(path) = &code.flags.working_directory {
+            command.current_dir(path);
+        }
+        Ok(ExecutionCommand { command, input: None, container: None, directory: Some(directory) })
+    }
+
+    fn docker_command(&self, code: &Code) -> Result<ExecutionCommand, CodeExecuteError> {
+        let language_name = match (self.command_template(&code.language), &code.language) {
+            (Some((name, _)), _) => name,
+            (None, CodeLanguage::Shell(interpreter)) => interpreter.as_str(),
+            (None, CodeLanguage::Python) => "python",
+            (None, CodeLanguage::Rust) => "rust",
+            _ => return Err(CodeExecuteError::UnsupportedExecution),
+        };
+        let image = self
+            .config
+            .docker
+            .images
+            .get(language_name)
+            .ok_or_else(|| CodeExecuteError::NoDockerImage(language_name.to_string()))?;
+        // Containers run linux regardless of the platform we're running on.
+        let directory = self.write_snippet(code, Platform::Unix)?;
+        // The directory name is unique so it's a good enough container name.
+        let container = directory.path().file_name().expect("no file name").to_string_lossy().to_string();
+        let mut volume = directory.path().as_os_str().to_os_string();
+        volume.push(":");
+        volume.push(CONTAINER_SNIPPET_DIRECTORY);
+
+        let mut command = process::Command::new("docker");
+        command.args(["run", "--rm"]);
+        if code.flags.interactive || code.flags.acquire_terminal {
+            command.arg("--interactive");
+        }
+        if code.flags.acquire_terminal {
+            command.arg("--tty");
+        }
+        for (name, value) in &code.flags.environment {
+            command.arg("--env").arg(format!("{name}={value}"));
+        }
+        command.args(["--name", &container, "--volume"]).arg(volume);
+        match &code.flags.working_directory {
+            Some(path) => {
+                let mut volume = path.as_os_str().to_os_string();
+                volume.push(":");
+                volume.push(CONTAINER_WORKING_DIRECTORY);
+                command.arg("--volume").arg(volume).args(["--workdir", CONTAINER_WORKING_DIRECTORY]);
+            }
+            None => {
+                command.args(["--workdir", CONTAINER_SNIPPET_DIRECTORY]);
+            }
+        };
+        command.arg(image).args(self.command_arguments(
+            code,
+            Path::new(CONTAINER_SNIPPET_DIRECTORY),
+            Platform::Unix,
+        )?);
+        Ok(ExecutionCommand { command, input: None, container: Some(container), directory: Some(directory) })
+    }
+
+    fn ssh_command(code: &Code, destination: &str) -> Result<ExecutionCommand, CodeExecuteError> {
+        // The snippet is piped into the remote process so these all need to read it from stdin.
+        let arguments: String =
+            code.flags.arguments.iter().map(|argument| format!(" {}", shell_quote(argument))).collect();
+        let remote_command = match &code.language {
+            CodeLanguage::Shell(interpreter) => format!("{interpreter}{arguments}"),
+            CodeLanguage::Python => format!("python3{arguments} -u -"),
+            CodeLanguage::Rust if arguments.is_empty() => format!("sh -c {}", shell_quote(REMOTE_RUST_SCRIPT)),
+            CodeLanguage::Rust => return Err(CodeExecuteError::ArgumentsUnsupported),
+            _ => return Err(CodeExecuteError::UnsupportedExecution),
+        };
+        // Environment variables can't be forwarded without configuring the server so set them remotely instead.
+        let remote_command = match code.flags.environment.is_empty() {
+            true => remote_command,
+            false => {
+                let variables =
+                    code.flags.environment.iter().map(|(name, value)| shell_quote(&format!("{name}={value}")));
+                format!("env {} {remote_command}", variables.collect::<Vec<_>>().join(" "))
+            }
+        };
+        let remote_command = match &code.flags.working_directory {
+            Some(path) => format!("cd {} && {remote_command}", shell_quote(&path.to_string_lossy())),
+            None => remote_command,
+        };
+        let mut command = process::Command::new("ssh");
+        // Never prompt for anything as there's no way to answer.
+        command.args(["-T", "-o", "BatchMode=yes", "--", destination, &remote_command]);
+        let input = Self::snippet_contents(code);
+        Ok(ExecutionCommand { command, input: Some(input), container: None, directory: None })
+    }
+
+    fn spawn(
+        command: ExecutionCommand,
+        timeout: Option<Duration>,
+        interactive: bool,
+        transcript: Option<(ExecutionTranscript, Code)>,
+    ) -> Result<ExecutionHandle, CodeExecuteError> {
+        let ExecutionCommand { mut command, input, container, directory } = command;
+        // Put the process in its own process group so killing it also kills anything it spawned.
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::CommandExt;
+            command.process_group(0);
+        }
+        let stdin = if input.is_some() || interactive { Stdio::piped() } else { Stdio::null() };
+        let mut process_handle = command
+            .stdin(stdin)
             .stdout(Stdio::piped())
-            .stderr(Stdio::null())
+            .stderr(Stdio::piped())
             .spawn()
             .map_err(CodeExecuteError::SpawnProcess)?;
+        let mut stdin = process_handle.stdin.take();
+        if let Some(input) = input {
+            if let Some(mut stdin) = stdin.take() {
+                // Write on a separate thread so a process that doesn't read its input can't block us.
+                thread::spawn(move || stdin.write_all(input.as_bytes()));
+            }
+        }
 
+        let killer = ProcessKiller { pid: process_handle.id(), container };
         let state: Arc<Mutex<ExecutionState>> = Default::default();
-        let reader_handle = ProcessReader::spawn(process_handle, state.clone(), output_file);
-        let handle = ExecutionHandle { state, reader_handle };
+        let watchdog = timeout.map(|timeout| ProcessWatchdog::spawn(killer.clone(), state.clone(), timeout));
+        let reader_handle = ProcessReader::spawn(process_handle, state.clone(), directory, watchdog, transcript);
+        let handle = ExecutionHandle { state, killer, stdin, reader_handle };
         Ok(handle)
     }
 }
 
+/// Quote a string so it's interpreted literally by a POSIX shell.
+fn shell_quote(input: &str) -> String {
+    format!("'{}'", input.replace('\'', r"'\''"))
+}
+
+/// A command that executes a piece of code.
+struct ExecutionCommand {
+    command: process::Command,
+
+    /// The input to be written into the process' stdin.
+    input: Option<String>,
+
+    /// The name of the container the code runs in, if any.
+    container: Option<String>,
+
+    /// The directory where the code was written to, if any.
+    directory: Option<TempDir>,
+}
+
 /// An error during the execution of some code.
 #[derive(thiserror::Error, Debug)]
-pub(crate) enum CodeExecuteError {
+pub enum CodeExecuteError {
     #[error("code language doesn't support execution")]
     UnsupportedExecution,
 
@@ -61,12 +467,26 @@ pub(crate) enum CodeExecuteError {
 
     #[error("error spawning process: {0}")]
     SpawnProcess(io::Error),
+
+    #[error("no docker image configured for language '{0}'")]
+    NoDockerImage(String),
+
+    #[error("interactive code and code that acquires the terminal can't be executed over ssh")]
+    InteractiveUnsupported,
+
+    #[error("rust code doesn't support interpreter arguments")]
+    ArgumentsUnsupported,
+
+    #[error("code execution is disabled, run presenterm with --allow-exec to enable it")]
+    ExecutionDisabled,
 }
 
 /// A handle for the execution of a piece of code.
 #[derive(Debug)]
 pub(crate) struct ExecutionHandle {
     state: Arc<Mutex<ExecutionState>>,
+    killer: ProcessKiller,
+    stdin: Option<process::ChildStdin>,
     #[allow(dead_code)]
     reader_handle: thread::JoinHandle<()>,
 }
@@ -76,6 +496,124 @@ impl ExecutionHandle {
     pub(crate) fn state(&self) -> ExecutionState {
         self.state.lock().unwrap().clone()
     }
+
+    /// Check whether the process accepts input.
+    pub(crate) fn is_interactive(&self) -> bool {
+        self.stdin.is_some()
+    }
+
+    /// Send a line of input to the process.
+    ///
+    /// The line is also added to the process' output, the same way a terminal would echo it.
+    pub(crate) fn send_line(&mut self, line: &str) -> io::Result<()> {
+        let Some(stdin) = &mut self.stdin else {
+            return Err(io::Error::new(io::ErrorKind::BrokenPipe, "process doesn't accept input"));
+        };
+        self.state.lock().unwrap().output.push(line.to_string());
+        stdin.write_all(line.as_bytes())?;
+        stdin.write_all(b"\n")?;
+        stdin.flush()
+    }
+
+    /// Close the process' input so it sees an end of file.
+    pub(crate) fn close_input(&mut self) {
+        self.stdin.take();
+    }
+
+    /// Kill the process, along with any processes it spawned.
+    ///
+    /// The state is marked as aborted, even if the process hasn't been reaped yet.
+    pub(crate) fn kill(&self) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        // Holding the lock guarantees the process hasn't been reaped so its pid can't be reused.
+        if state.status.is_finished() {
+            return Ok(());
+        }
+        self.killer.kill()?;
+        state.status = ProcessStatus::Aborted;
+        Ok(())
+    }
+}
+
+/// Kill a process that's the leader of its own process group, along with the rest of the group.
+#[cfg(unix)]
+fn kill_process_group(pid: u32) -> io::Result<()> {
+    // SAFETY: this is just a syscall; the pid is the process group id as the process was spawned as
+    // a process group leader.
+    let result = unsafe { libc::killpg(pid as libc::pid_t, libc::SIGKILL) };
+    if result == 0 { Ok(()) } else { Err(io::Error::last_os_error()) }
+}
+
+/// Kill a process along with any processes it spawned.
+#[cfg(windows)]
+fn kill_process_group(pid: u32) -> io::Result<()> {
+    let status = process::Command::new("taskkill")
+        .args(["/F", "/T", "/PID", &pid.to_string()])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()?;
+    if status.success() { Ok(()) } else { Err(io::Error::new(io::ErrorKind::Other, "failed to kill process")) }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn kill_process_group(_pid: u32) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "killing processes is not supported in this platform"))
+}
+
+/// Kill a docker container.
+///
+/// Killing the docker client doesn't stop the container so that needs to be done explicitly. This happens in the
+/// background as it can take a while.
+fn kill_container(container: &str) {
+    let mut command = process::Command::new("docker");
+    command.args(["kill", container]).stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::null());
+    thread::spawn(move || command.status());
+}
+
+/// Kills a spawned process.
+#[derive(Clone, Debug)]
+struct ProcessKiller {
+    pid: u32,
+    container: Option<String>,
+}
+
+impl ProcessKiller {
+    fn kill(&self) -> io::Result<()> {
+        kill_process_group(self.pid)?;
+        if let Some(container) = &self.container {
+            kill_container(container);
+        }
+        Ok(())
+    }
+}
+
+/// Kills a process if it doesn't finish before a timeout.
+struct ProcessWatchdog {
+    killer: ProcessKiller,
+    state: Arc<Mutex<ExecutionState>>,
+    timeout: Duration,
+    finished: mpsc::Receiver<()>,
+}
+
+impl ProcessWatchdog {
+    fn spawn(killer: ProcessKiller, state: Arc<Mutex<ExecutionState>>, timeout: Duration) -> mpsc::Sender<()> {
+        let (sender, finished) = mpsc::channel();
+        let watchdog = Self { killer, state, timeout, finished };
+        thread::spawn(|| watchdog.run());
+        sender
+    }
+
+    fn run(self) {
+        // The sender is dropped when the process is done so this only times out if it's still running.
+        if !matches!(self.finished.recv_timeout(self.timeout), Err(mpsc::RecvTimeoutError::Timeout)) {
+            return;
+        }
+        let mut state = self.state.lock().unwrap();
+        if !state.status.is_finished() && self.killer.kill().is_ok() {
+            state.status = ProcessStatus::TimedOut;
+        }
+    }
 }
 
 /// Consumes the output of a process and stores it in a shared state.
@@ -83,49 +621,130 @@ struct ProcessReader {
     handle: process::Child,
     state: Arc<Mutex<ExecutionState>>,
     #[allow(dead_code)]
-    file_handle: NamedTempFile,
+    directory: Option<TempDir>,
+    #[allow(dead_code)]
+    watchdog: Option<mpsc::Sender<()>>,
+    transcript: Option<(ExecutionTranscript, Code)>,
 }
 
 impl ProcessReader {
     fn spawn(
         handle: process::Child,
         state: Arc<Mutex<ExecutionState>>,
-        file_handle: NamedTempFile,
+        directory: Option<TempDir>,
+        watchdog: Option<mpsc::Sender<()>>,
+        transcript: Option<(ExecutionTranscript, Code)>,
     ) -> thread::JoinHandle<()> {
-        let reader = Self { handle, state, file_handle };
+        let reader = Self { handle, state, directory, watchdog, transcript };
         thread::spawn(|| reader.run())
     }
 
     fn run(mut self) {
         let stdout = self.handle.stdout.take().expect("no stdout");
-        let stdout = BufReader::new(stdout);
-        let _ = Self::process_output(self.state.clone(), stdout);
-        let success = match self.handle.try_wait() {
-            Ok(Some(code)) => code.success(),
-            _ => false,
-        };
-        let status = match success {
-            true => ProcessStatus::Success,
-            false => ProcessStatus::Failure,
-        };
-        self.state.lock().unwrap().status = status;
+        let stderr = self.handle.stderr.take().expect("no stderr");
+
+        // Consume stderr on a separate thread so neither stream blocks on the other.
+        let stderr_state = self.state.clone();
+        let stderr_reader = thread::spawn(move || {
+            Self::process_output(BufReader::new(stderr), |line| stderr_state.lock().unwrap().stderr.push(line))
+        });
+        // TODO: consider not locking per line...
+        let _ = Self::process_output(BufReader::new(stdout), |line| self.state.lock().unwrap().output.push(line));
+        let _ = stderr_reader.join();
+        // Closing stdout doesn't mean the process is done so wait for it to finish. Reaping the
+        // process must happen while holding the lock so a concurrent kill doesn't target a reused
+        // pid, hence why this polls rather than blocking.
+        loop {
+            let mut state = self.state.lock().unwrap();
+            let (status, exit) = match self.handle.try_wait() {
+                Ok(Some(code)) if code.success() => (ProcessStatus::Success, ProcessExit::from_status(code)),
+                Ok(Some(code)) => (ProcessStatus::Failure, ProcessExit::from_status(code)),
+                Ok(None) => {
+                    drop(state);
+                    thread::sleep(Duration::from_millis(20));
+                    continue;
+                }
+                Err(_) => (ProcessStatus::Failure, None),
+            };
+            // Don't lose the fact that this was killed on purpose.
+            if !matches!(state.status, ProcessStatus::Aborted | ProcessStatus::TimedOut) {
+                state.status = status;
+            }
+            state.exit = exit;
+            break;
+        }
+        if let Some((transcript, code)) = &self.transcript {
+            let state = self.state.lock().unwrap().clone();
+            // There's nobody to report this to so the best we can do is ignore it.
+            let _ = transcript.append(code, &state);
+        }
     }
 
-    fn process_output(state: Arc<Mutex<ExecutionState>>, stdout: BufReader<ChildStdout>) -> io::Result<()> {
-        for line in stdout.lines() {
-            let line = line?;
-            // TODO: consider not locking per line...
-            state.lock().unwrap().output.push(line);
+    fn process_output<R, F>(reader: R, mut consume: F) -> io::Result<()>
+    where
+        R: BufRead,
+        F: FnMut(String),
+    {
+        for line in reader.lines() {
+            consume(line?);
         }
         Ok(())
     }
 }
 
+/// A file that executed code and its output are appended to.
+#[derive(Clone, Debug)]
+struct ExecutionTranscript {
+    path: PathBuf,
+    // Snippets can finish at the same time so make sure their entries don't get interleaved.
+    lock: Arc<Mutex<()>>,
+}
+
+impl ExecutionTranscript {
+    fn append(&self, code: &Code, state: &ExecutionState) -> io::Result<()> {
+        let entry = Self::format_entry(code, state);
+        let _guard = self.lock.lock().unwrap();
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        file.write_all(entry.as_bytes())
+    }
+
+    fn format_entry(code: &Code, state: &ExecutionState) -> String {
+        let language = match &code.language {
+            CodeLanguage::Shell(name) | CodeLanguage::Unknown(name) => name.as_str(),
+            language => CodeHighlighter::language_extension(language),
+        };
+        let mut entry = format!("```{language}\n{}\n```\n\n", code.executable_contents().trim_end_matches('\n'));
+        for (name, lines) in [("stdout", &state.output), ("stderr", &state.stderr)] {
+            if lines.is_empty() {
+                continue;
+            }
+            entry.push_str(&format!("{name}:\n\n```\n"));
+            for line in lines {
+                entry.push_str(line);
+                entry.push('\n');
+            }
+            entry.push_str("```\n\n");
+        }
+        let status = match (&state.status, &state.exit) {
+            (ProcessStatus::Aborted, _) => "aborted".to_string(),
+            (ProcessStatus::TimedOut, _) => "timed out".to_string(),
+            (_, Some(exit)) => exit.to_string(),
+            (_, None) => "unknown exit status".to_string(),
+        };
+        entry.push_str(&format!("[{status}]\n\n"));
+        entry
+    }
+}
+
 /// The state of the execution of a process.
 #[derive(Clone, Default, Debug)]
 pub(crate) struct ExecutionState {
     pub(crate) output: Vec<String>,
+    pub(crate) stderr: Vec<String>,
     pub(crate) status: ProcessStatus,
+
+    /// The way the process exited, only available once it's finished.
+    pub(crate) exit: Option<ProcessExit>,
 }
 
 /// The status of a process.
@@ -135,12 +754,57 @@ pub(crate) enum ProcessStatus {
     Running,
     Success,
     Failure,
+    Aborted,
+    TimedOut,
 }
 
 impl ProcessStatus {
     /// Check whether the underlying process is finished.
     pub(crate) fn is_finished(&self) -> bool {
-        matches!(self, ProcessStatus::Success | ProcessStatus::Failure)
+        matches!(
+            self,
+            ProcessStatus::Success | ProcessStatus::Failure | ProcessStatus::Aborted | ProcessStatus::TimedOut
+        )
+    }
+}
+
+/// The way in which a process exited.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ProcessExit {
+    /// The process exited with the given exit code.
+    Code(i32),
+
+    /// The process was terminated by the given signal.
+    Signal(i32),
+}
+
+impl ProcessExit {
+    fn from_status(status: ExitStatus) -> Option<Self> {
+        if let Some(code) = status.code() {
+            return Some(Self::Code(code));
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::ExitStatusExt;
+            if let Some(signal) = status.signal() {
+                return Some(Self::Signal(signal));
+            }
+        }
+        None
+    }
+
+    /// Check whether this represents a successful exit.
+    pub(crate) fn is_success(&self) -> bool {
+        matches!(self, Self::Code(0))
+    }
+}
+
+impl Display for ProcessExit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Code(code) => write!(f, "exit: {code}"),
+            Self::Signal(signal) => write!(f, "signal: {signal}"),
+        }
     }
 }
 
@@ -148,6 +812,7 @@ impl ProcessStatus {
 mod test {
     use super::*;
     use crate::markdown::elements::CodeFlags;
+    use rstest::rstest;
 
     #[test]
     fn shell_code_execution() {
@@ -155,8 +820,12 @@ mod test {
 echo 'hello world'
 echo 'bye'"
             .into();
-        let code = Code { contents, language: CodeLanguage::Shell("sh".into()), flags: CodeFlags { execute: true } };
-        let handle = CodeExecuter::execute(&code).expect("execution failed");
+        let code = Code {
+            contents,
+            language: CodeLanguage::Shell("sh".into()),
+            flags: CodeFlags { execute: true, ..Default::default() },
+        };
+        let handle = CodeExecuter::default().execute(&code).expect("execution failed");
         let state = loop {
             let state = handle.state();
             if state.status.is_finished() {
@@ -168,11 +837,497 @@ echo 'bye'"
         assert_eq!(state.output, expected_lines);
     }
 
+    #[test]
+    fn shell_code_execution_stderr() {
+        let contents = r"
+echo 'hello world'
+echo 'oops' >&2
+echo 'bye'"
+            .into();
+        let code = Code {
+            contents,
+            language: CodeLanguage::Shell("sh".into()),
+            flags: CodeFlags { execute: true, ..Default::default() },
+        };
+        let handle = CodeExecuter::default().execute(&code).expect("execution failed");
+        let state = loop {
+            let state = handle.state();
+            if state.status.is_finished() {
+                break state;
+            }
+        };
+
+        assert_eq!(state.output, &["hello world", "bye"]);
+        assert_eq!(state.stderr, &["oops"]);
+    }
+
+    #[test]
+    fn shell_code_exit_code() {
+        let contents = "exit 3".into();
+        let code = Code {
+            contents,
+            language: CodeLanguage::Shell("sh".into()),
+            flags: CodeFlags { execute: true, ..Default::default() },
+        };
+        let handle = CodeExecuter::default().execute(&code).expect("execution failed");
+        let state = loop {
+            let state = handle.state();
+            if state.status.is_finished() {
+                break state;
+            }
+        };
+
+        assert!(matches!(state.status, ProcessStatus::Failure));
+        assert_eq!(state.exit, Some(ProcessExit::Code(3)));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn shell_code_killed_by_signal() {
+        let contents = "kill -9 $$".into();
+        let code = Code {
+            contents,
+            language: CodeLanguage::Shell("sh".into()),
+            flags: CodeFlags { execute: true, ..Default::default() },
+        };
+        let handle = CodeExecuter::default().execute(&code).expect("execution failed");
+        let state = loop {
+            let state = handle.state();
+            if state.status.is_finished() {
+                break state;
+            }
+        };
+
+        assert_eq!(state.exit, Some(ProcessExit::Signal(9)));
+        assert_eq!(state.exit.unwrap().to_string(), "signal: 9");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn kill_running_process() {
+        // The child `sleep` keeps stdout open so this only finishes if the whole group is killed.
+        let contents = "echo hi\nsleep 30\necho bye".into();
+        let code = Code {
+            contents,
+            language: CodeLanguage::Shell("sh".into()),
+            flags: CodeFlags { execute: true, ..Default::default() },
+        };
+        let handle = CodeExecuter::default().execute(&code).expect("execution failed");
+        while handle.state().output.is_empty() {}
+        handle.kill().expect("kill failed");
+
+        let state = loop {
+            let state = handle.state();
+            if state.exit.is_some() {
+                break state;
+            }
+        };
+        assert!(matches!(state.status, ProcessStatus::Aborted));
+        assert_eq!(state.output, &["hi"]);
+        assert_eq!(state.exit, Some(ProcessExit::Signal(9)));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn execution_timeout() {
+        let contents = "echo hi\nsleep 30".into();
+        let flags = CodeFlags { execute: true, timeout: Some(Duration::from_millis(200)), ..Default::default() };
+        let code = Code { contents, language: CodeLanguage::Shell("sh".into()), flags };
+        let handle = CodeExecuter::default().execute(&code).expect("execution failed");
+        let state = loop {
+            let state = handle.state();
+            if state.exit.is_some() {
+                break state;
+            }
+        };
+        assert!(matches!(state.status, ProcessStatus::TimedOut));
+        assert_eq!(state.output, &["hi"]);
+    }
+
+    #[test]
+    fn docker_command() {
+        let mut config = ExecutionConfig { backend: ExecutionBackend::Docker, ..Default::default() };
+        config.docker.images.insert("python".into(), "python:3.12".into());
+        let executer = CodeExecuter::new(config);
+        let code = Code { contents: "print(42)".into(), language: CodeLanguage::Python, flags: Default::default() };
+        let ExecutionCommand { command, container, directory, .. } =
+            executer.docker_command(&code).expect("no command");
+        let container = container.expect("no container");
+        let directory = directory.expect("no directory");
+
+        let volume = format!("{}:/snippet", directory.path().display());
+        let expected = [
+            "run",
+            "--rm",
+            "--name",
+            &container,
+            "--volume",
+            &volume,
+            "--workdir",
+            "/snippet",
+            "python:3.12",
+            "python3",
+            "-u",
+            "/snippet/snippet",
+        ];
+        assert_eq!(command.get_program(), "docker");
+        assert_eq!(command.get_args().collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn docker_command_working_directory() {
+        let mut config = ExecutionConfig { backend: ExecutionBackend::Docker, ..Default::default() };
+        config.docker.images.insert("python".into(), "python:3.12".into());
+        let executer = CodeExecuter::new(config);
+        let flags = CodeFlags { working_directory: Some("/tmp/scripts".into()), ..Default::default() };
+        let code = Code { contents: "print(42)".into(), language: CodeLanguage::Python, flags };
+        let ExecutionCommand { command, .. } = executer.docker_command(&code).expect("no command");
+        let args: Vec<_> = command.get_args().map(|arg| arg.to_string_lossy().to_string()).collect();
+        let position =
+            args.iter().position(|arg| arg == "/tmp/scripts:/workdir").expect("working directory not mounted");
+        assert_eq!(args[position + 1..position + 3], ["--workdir", "/workdir"]);
+    }
+
+    #[test]
+    fn command_template() {
+        let mut config = ExecutionConfig::default();
+        let template = ["sh", "-c", "cp $FILE $OUT && cat $OUT"];
+        config.commands.insert("potato".into(), template.map(String::from).to_vec());
+        let executer = CodeExecuter::new(config);
+        let flags = CodeFlags { execute: true, ..Default::default() };
+        let code = Code { contents: "hello".into(), language: CodeLanguage::Unknown("potato".into()), flags };
+        let handle = executer.execute(&code).expect("execution failed");
+        let state = loop {
+            let state = handle.state();
+            if state.status.is_finished() {
+                break state;
+            }
+        };
+        assert_eq!(state.output, &["hello"]);
+    }
+
+    #[test]
+    fn command_template_file_name() {
+        let mut config = ExecutionConfig::default();
+        config.commands.insert("go".into(), vec!["go".into(), "run".into(), "$FILE".into()]);
+        let executer = CodeExecuter::new(config);
+        let arguments =
+            executer.run_arguments(&CodeLanguage::Go, Path::new("/tmp/foo"), Platform::Unix).expect("no arguments");
+        assert_eq!(arguments, &["go", "run", "/tmp/foo/snippet.go"]);
+    }
+
+    #[rstest]
+    #[case::powershell(CodeLanguage::Shell("pwsh".into()), &["pwsh", "-NoProfile", "-ExecutionPolicy", "Bypass", "-File", "/tmp/foo/snippet.ps1"])]
+    #[case::batch(CodeLanguage::BatchFile, &["cmd", "/C", "/tmp/foo/snippet.bat"])]
+    #[case::python(CodeLanguage::Python, &["python", "-u", "/tmp/foo/snippet"])]
+    #[case::rust(CodeLanguage::Rust, &["cmd", "/C", "/tmp/foo/run.bat"])]
+    fn windows_arguments(#[case] language: CodeLanguage, #[case] expected: &[&str]) {
+        let executer = CodeExecuter::default();
+        let arguments =
+            executer.run_arguments(&language, Path::new("/tmp/foo"), Platform::Windows).expect("no arguments");
+        assert_eq!(arguments, expected);
+    }
+
+    #[test]
+    fn batch_files_require_windows() {
+        let executer = CodeExecuter::default();
+        let result = executer.run_arguments(&CodeLanguage::BatchFile, Path::new("/tmp/foo"), Platform::Unix);
+        assert!(matches!(result, Err(CodeExecuteError::UnsupportedExecution)));
+    }
+
+    #[test]
+    fn execution_disabled() {
+        let flags = CodeFlags { execute: true, ..Default::default() };
+        let code = Code { contents: "echo hi".into(), language: CodeLanguage::Shell("sh".into()), flags };
+        let result = CodeExecuter::default().allow_execution(false).execute(&code);
+        assert!(matches!(result, Err(CodeExecuteError::ExecutionDisabled)));
+    }
+
+    #[test]
+    fn docker_missing_image() {
+        let config = ExecutionConfig { backend: ExecutionBackend::Docker, ..Default::default() };
+        let executer = CodeExecuter::new(config);
+        let code = Code { contents: "print(42)".into(), language: CodeLanguage::Python, flags: Default::default() };
+        let result = executer.docker_command(&code);
+        assert!(matches!(result, Err(CodeExecuteError::NoDockerImage(language)) if language == "python"));
+    }
+
+    #[test]
+    fn ssh_command() {
+        let code = Code { contents: "print(42)".into(), language: CodeLanguage::Python, flags: Default::default() };
+        let ExecutionCommand { command, input, .. } =
+            CodeExecuter::ssh_command(&code, "potato@example.com").expect("no command");
+
+        let expected = ["-T", "-o", "BatchMode=yes", "--", "potato@example.com", "python3 -u -"];
+        assert_eq!(command.get_program(), "ssh");
+        assert_eq!(command.get_args().collect::<Vec<_>>(), expected);
+        assert_eq!(input.as_deref(), Some("print(42)"));
+    }
+
+    #[test]
+    fn ssh_command_environment() {
+        let flags = CodeFlags { environment: vec![("NAME".into(), "it's me".into())], ..Default::default() };
+        let code = Code { contents: "echo $NAME".into(), language: CodeLanguage::Shell("bash".into()), flags };
+        let ExecutionCommand { command, .. } = CodeExecuter::ssh_command(&code, "host").expect("no command");
+        let remote_command = command.get_args().last().expect("no args");
+        assert_eq!(remote_command, r"env 'NAME=it'\''s me' bash");
+    }
+
+    #[test]
+    fn ssh_command_working_directory() {
+        let flags = CodeFlags { working_directory: Some("my dir".into()), ..Default::default() };
+        let code = Code { contents: "ls".into(), language: CodeLanguage::Shell("bash".into()), flags };
+        let ExecutionCommand { command, .. } = CodeExecuter::ssh_command(&code, "host").expect("no command");
+        let remote_command = command.get_args().last().expect("no args");
+        assert_eq!(remote_command, "cd 'my dir' && bash");
+    }
+
+    #[test]
+    fn shell_code_working_directory() {
+        let directory = tempfile::tempdir().expect("no temp dir");
+        fs::write(directory.path().join("data.txt"), "hello").expect("write failed");
+        let flags = CodeFlags { execute: true, working_directory: Some(directory.path().into()), ..Default::default() };
+        let code = Code { contents: "cat data.txt".into(), language: CodeLanguage::Shell("sh".into()), flags };
+        let handle = CodeExecuter::default().execute(&code).expect("execution failed");
+        let state = loop {
+            let state = handle.state();
+            if state.status.is_finished() {
+                break state;
+            }
+        };
+        assert_eq!(state.output, &["hello"]);
+    }
+
+    #[test]
+    fn shell_code_environment() {
+        let contents = "echo \"$GREETING $NAME\"".into();
+        let environment = vec![("GREETING".into(), "hi".into()), ("NAME".into(), "mom".into())];
+        let flags = CodeFlags { execute: true, environment, ..Default::default() };
+        let code = Code { contents, language: CodeLanguage::Shell("sh".into()), flags };
+        let handle = CodeExecuter::default().execute(&code).expect("execution failed");
+        let state = loop {
+            let state = handle.state();
+            if state.status.is_finished() {
+                break state;
+            }
+        };
+        assert_eq!(state.output, &["hi mom"]);
+    }
+
+    #[test]
+    fn shell_code_arguments() {
+        let flags = CodeFlags { execute: true, arguments: vec!["-e".into()], ..Default::default() };
+        let code = Code { contents: "false\necho hi".into(), language: CodeLanguage::Shell("sh".into()), flags };
+        let handle = CodeExecuter::default().execute(&code).expect("execution failed");
+        let state = loop {
+            let state = handle.state();
+            if state.status.is_finished() {
+                break state;
+            }
+        };
+        assert!(state.output.is_empty());
+    }
+
+    #[test]
+    fn ssh_command_arguments() {
+        let flags = CodeFlags { arguments: vec!["-x".into()], ..Default::default() };
+        let code = Code { contents: "print(42)".into(), language: CodeLanguage::Python, flags };
+        let ExecutionCommand { command, .. } = CodeExecuter::ssh_command(&code, "host").expect("no command");
+        let remote_command = command.get_args().last().expect("no args");
+        assert_eq!(remote_command, "python3 '-x' -u -");
+    }
+
+    #[rstest]
+    #[case::placeholder(&["run", "$FILE", "$ARGS", "--end"], &["run", "/tmp/foo/snippet.go", "-x", "-y", "--end"])]
+    #[case::appended(&["sh", "-c", "go run $FILE"], &["sh", "-c", "go run /tmp/foo/snippet.go", "-x", "-y"])]
+    fn command_template_arguments(#[case] template: &[&str], #[case] expected: &[&str]) {
+        let mut config = ExecutionConfig::default();
+        config.commands.insert("go".into(), template.iter().map(|argument| argument.to_string()).collect());
+        let executer = CodeExecuter::new(config);
+        let flags = CodeFlags { arguments: vec!["-x".into(), "-y".into()], ..Default::default() };
+        let code = Code { contents: String::new(), language: CodeLanguage::Go, flags };
+        let arguments = executer.command_arguments(&code, Path::new("/tmp/foo"), Platform::Unix).expect("no arguments");
+        assert_eq!(arguments, expected);
+    }
+
+    #[test]
+    fn rust_code_arguments() {
+        let flags = CodeFlags { execute: true, arguments: vec!["-x".into()], ..Default::default() };
+        let code = Code { contents: "fn main() {}".into(), language: CodeLanguage::Rust, flags };
+        let result = CodeExecuter::default().execute(&code);
+        assert!(matches!(result, Err(CodeExecuteError::ArgumentsUnsupported)));
+    }
+
+    #[test]
+    fn transcript() {
+        let directory = tempfile::tempdir().expect("no temp dir");
+        let path = directory.path().join("transcript.md");
+        let executer = CodeExecuter::default().with_transcript(path.clone()).expect("no transcript");
+        let flags = CodeFlags { execute: true, ..Default::default() };
+        let contents = "echo hi\necho bye >&2\nexit 3".into();
+        let code = Code { contents, language: CodeLanguage::Shell("sh".into()), flags };
+        let handle = executer.execute(&code).expect("execution failed");
+        handle.reader_handle.join().expect("reader failed");
+
+        let transcript = fs::read_to_string(path).expect("no transcript");
+        let expected = "```sh\necho hi\necho bye >&2\nexit 3\n```\n\nstdout:\n\n```\nhi\n```\n\nstderr:\n\n```\nbye\n```\n\n[exit: 3]\n\n";
+        assert_eq!(transcript, expected);
+    }
+
+    #[test]
+    fn attached_execution() {
+        let flags = CodeFlags { execute: true, acquire_terminal: true, ..Default::default() };
+        let code = Code { contents: "exit 3".into(), language: CodeLanguage::Shell("sh".into()), flags };
+        let state = CodeExecuter::default().execute_attached(&code).expect("execution failed");
+        assert!(matches!(state.status, ProcessStatus::Failure), "unexpected status: {:?}", state.status);
+        assert_eq!(state.exit, Some(ProcessExit::Code(3)));
+    }
+
+    #[test]
+    fn attached_execution_timeout() {
+        let flags = CodeFlags {
+            execute: true,
+            acquire_terminal: true,
+            timeout: Some(Duration::from_millis(100)),
+            ..Default::default()
+        };
+        let code = Code { contents: "sleep 10".into(), language: CodeLanguage::Shell("sh".into()), flags };
+        let started_at = Instant::now();
+        let state = CodeExecuter::default().execute_attached(&code).expect("execution failed");
+        assert!(matches!(state.status, ProcessStatus::TimedOut), "unexpected status: {:?}", state.status);
+        assert!(started_at.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn attached_execution_transcript() {
+        let directory = tempfile::tempdir().expect("no temp dir");
+        let path = directory.path().join("transcript.md");
+        let executer = CodeExecuter::default().with_transcript(path.clone()).expect("no transcript");
+        let flags = CodeFlags { execute: true, acquire_terminal: true, ..Default::default() };
+        let code = Code { contents: "exit 3".into(), language: CodeLanguage::Shell("sh".into()), flags };
+        executer.execute_attached(&code).expect("execution failed");
+
+        let transcript = fs::read_to_string(path).expect("no transcript");
+        assert_eq!(transcript, "```sh\nexit 3\n```\n\n[exit: 3]\n\n");
+    }
+
+    #[test]
+    fn hidden_lines_are_executed() {
+        let contents = "# let greeting = \"hi\";\n#\nprintln!(\"{greeting}\");".into();
+        let flags = CodeFlags { execute: true, ..Default::default() };
+        let code = Code { contents, language: CodeLanguage::Rust, flags };
+        let expected = "fn main() {\nlet greeting = \"hi\";\n\nprintln!(\"{greeting}\");\n\n}\n";
+        assert_eq!(CodeExecuter::snippet_contents(&code), expected);
+    }
+
+    #[test]
+    fn rust_code_over_stdin() {
+        // Make sure the command used to run remote rust code works once the remote shell parses it.
+        let contents = r#"println!("hi from rust");"#.into();
+        let code = Code { contents, language: CodeLanguage::Rust, flags: Default::default() };
+        let ExecutionCommand { command, input, .. } = CodeExecuter::ssh_command(&code, "host").expect("no command");
+        let remote_command = command.get_args().last().expect("no args").to_owned();
+        let mut command = process::Command::new("sh");
+        command.arg("-c").arg(remote_command);
+        let command = ExecutionCommand { command, input, container: None, directory: None };
+        let handle = CodeExecuter::spawn(command, None, false, None).expect("execution failed");
+        let state = loop {
+            let state = handle.state();
+            if state.status.is_finished() {
+                break state;
+            }
+        };
+        assert_eq!(state.output, &["hi from rust"]);
+    }
+
+    #[test]
+    fn interactive_code_execution() {
+        let contents = "read name\necho \"hi $name\"\ncat".into();
+        let flags = CodeFlags { execute: true, interactive: true, ..Default::default() };
+        let code = Code { contents, language: CodeLanguage::Shell("sh".into()), flags };
+        let mut handle = CodeExecuter::default().execute(&code).expect("execution failed");
+        assert!(handle.is_interactive());
+
+        handle.send_line("mom").expect("sending input failed");
+        while handle.state().output.len() < 2 {}
+        handle.send_line("bye").expect("sending input failed");
+        handle.close_input();
+        let state = loop {
+            let state = handle.state();
+            if state.status.is_finished() {
+                break state;
+            }
+        };
+        assert_eq!(state.output, &["mom", "hi mom", "bye", "bye"]);
+    }
+
+    #[test]
+    fn python_code_execution() {
+        let contents = r"
+print('hello world')
+print('bye')"
+            .into();
+        let code =
+            Code { contents, language: CodeLanguage::Python, flags: CodeFlags { execute: true, ..Default::default() } };
+        let handle = CodeExecuter::default().execute(&code).expect("execution failed");
+        let state = loop {
+            let state = handle.state();
+            if state.status.is_finished() {
+                break state;
+            }
+        };
+
+        let expected_lines = vec!["hello world", "bye"];
+        assert_eq!(state.output, expected_lines);
+    }
+
+    #[test]
+    fn rust_code_execution() {
+        let contents = r#"
+let name = "world";
+println!("hello {name}");"#
+            .into();
+        let code =
+            Code { contents, language: CodeLanguage::Rust, flags: CodeFlags { execute: true, ..Default::default() } };
+        let handle = CodeExecuter::default().execute(&code).expect("execution failed");
+        let state = loop {
+            let state = handle.state();
+            if state.status.is_finished() {
+                break state;
+            }
+        };
+
+        let expected_lines = vec!["hello world"];
+        assert_eq!(state.output, expected_lines);
+        assert!(matches!(state.status, ProcessStatus::Success));
+    }
+
+    #[test]
+    fn rust_compilation_errors_are_displayed() {
+        let contents = "fn main() { let x: u32 = \"potato\"; }".into();
+        let code =
+            Code { contents, language: CodeLanguage::Rust, flags: CodeFlags { execute: true, ..Default::default() } };
+        let handle = CodeExecuter::default().execute(&code).expect("execution failed");
+        let state = loop {
+            let state = handle.state();
+            if state.status.is_finished() {
+                break state;
+            }
+        };
+
+        assert!(state.stderr.iter().any(|line| line.contains("mismatched types")), "{:?}", state.stderr);
+        assert!(matches!(state.status, ProcessStatus::Failure));
+    }
+
     #[test]
     fn non_executable_code_cant_be_executed() {
         let contents = String::new();
-        let code = Code { contents, language: CodeLanguage::Shell("sh".into()), flags: CodeFlags { execute: false } };
-        let result = CodeExecuter::execute(&code);
+        let code = Code {
+            contents,
+            language: CodeLanguage::Shell("sh".into()),
+            flags: CodeFlags { execute: false, ..Default::default() },
+        };
+        let result = CodeExecuter::default().execute(&code);
         assert!(result.is_err());
     }
 }
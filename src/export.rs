@@ -1,5 +1,5 @@
 use crate::{
-    builder::{BuildError, PresentationBuilder},
+    builder::{BuildError, ExecutionCache, PresentationBuilder, PresentationBuilderOptions},
     markdown::{elements::MarkdownElement, parse::ParseError},
     presentation::Presentation,
     CodeHighlighter, MarkdownParser, PresentationTheme, Resources,
@@ -20,6 +20,8 @@ pub struct Exporter<'a> {
     default_theme: &'a PresentationTheme,
     default_highlighter: CodeHighlighter,
     resources: Resources,
+    execution_cache: ExecutionCache,
+    options: PresentationBuilderOptions,
 }
 
 impl<'a> Exporter<'a> {
@@ -29,8 +31,9 @@ impl<'a> Exporter<'a> {
         default_theme: &'a PresentationTheme,
         default_highlighter: CodeHighlighter,
         resources: Resources,
+        options: PresentationBuilderOptions,
     ) -> Self {
-        Self { parser, default_theme, default_highlighter, resources }
+        Self { parser, default_theme, default_highlighter, resources, execution_cache: Default::default(), options }
     }
 
     /// Export the given presentation into PDF.
@@ -54,12 +57,18 @@ impl<'a> Exporter<'a> {
         let elements = self.parser.parse(content)?;
         let base_path = path.parent().expect("no parent").canonicalize().expect("canonicalize");
         let images = Self::build_image_metadata(&elements, &base_path);
-        let presentation =
-            PresentationBuilder::new(self.default_highlighter.clone(), self.default_theme, &mut self.resources)
-                .build(elements)?;
+        let presentation = PresentationBuilder::new(
+            self.default_highlighter.clone(),
+            self.default_theme,
+            &mut self.resources,
+            &mut self.execution_cache,
+            self.options.clone(),
+        )
+        .build(elements)?;
+        let speaker_notes = presentation.iter_slides().map(|slide| slide.speaker_notes().to_vec()).collect();
         let commands = Self::build_capture_commands(presentation);
         let presentation_path = path.canonicalize().map_err(ExportError::ReadPresentation)?;
-        let metadata = ExportMetadata { commands, presentation_path, images };
+        let metadata = ExportMetadata { commands, presentation_path, images, speaker_notes };
         Ok(metadata)
     }
 
@@ -102,7 +111,7 @@ impl<'a> Exporter<'a> {
     fn build_image_metadata(elements: &[MarkdownElement], base_path: &Path) -> Vec<ImageMetadata> {
         let mut positions = Vec::new();
         for element in elements {
-            if let MarkdownElement::Image { path, source_position } = element {
+            if let MarkdownElement::Image { path, source_position, .. } = element {
                 let full_path = base_path.join(path);
                 let meta = ImageMetadata {
                     content_path: path.into(),
@@ -138,6 +147,9 @@ pub struct ExportMetadata {
     presentation_path: PathBuf,
     images: Vec<ImageMetadata>,
     commands: Vec<CaptureCommand>,
+
+    /// The speaker notes for every slide, in the same order they're captured.
+    speaker_notes: Vec<Vec<String>>,
 }
 
 /// Metadata about an image.
@@ -169,7 +181,7 @@ mod test {
         let theme = Default::default();
         let highlighter = CodeHighlighter::new("base16-ocean.dark").unwrap();
         let resources = Resources::new("examples");
-        let mut exporter = Exporter::new(parser, &theme, highlighter, resources);
+        let mut exporter = Exporter::new(parser, &theme, highlighter, resources, Default::default());
         exporter.extract_metadata(content, Path::new(path)).expect("metadata extraction failed")
     }
 
@@ -178,6 +190,7 @@ mod test {
         let presentation = r"
 First
 
+<!-- speaker_note: say hi -->
 <!-- end_slide -->
 
 hi
@@ -221,5 +234,7 @@ mom
             WaitForChange,
         ];
         assert_eq!(meta.commands, expected_commands);
+        let expected_notes = vec![vec!["say hi".to_string()], vec![], vec![], vec![]];
+        assert_eq!(meta.speaker_notes, expected_notes);
     }
 }
@@ -1,4 +1,9 @@
-use std::{fs, io, path::PathBuf, time::SystemTime};
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
 
 /// Watchers the presentation's file.
 ///
@@ -8,25 +13,46 @@ use std::{fs, io, path::PathBuf, time::SystemTime};
 pub(crate) struct PresentationFileWatcher {
     path: PathBuf,
     last_modification: SystemTime,
+    external_files: HashMap<PathBuf, SystemTime>,
 }
 
 impl PresentationFileWatcher {
     /// Create a watcher over the given file path.
     pub(crate) fn new<P: Into<PathBuf>>(path: P) -> Self {
         let path = path.into();
-        let last_modification = fs::metadata(&path).and_then(|m| m.modified()).unwrap_or(SystemTime::UNIX_EPOCH);
-        Self { path, last_modification }
+        let last_modification = Self::modification_time(&path).unwrap_or(SystemTime::UNIX_EPOCH);
+        Self { path, last_modification, external_files: Default::default() }
+    }
+
+    /// Also watch a file the presentation depends on, like one code is loaded from.
+    pub(crate) fn watch_external_file(&mut self, path: &Path) {
+        if !self.external_files.contains_key(path) {
+            let last_modification = Self::modification_time(path).unwrap_or(SystemTime::UNIX_EPOCH);
+            self.external_files.insert(path.into(), last_modification);
+        }
     }
 
     /// Checker whether this file has modifications.
     pub(crate) fn has_modifications(&mut self) -> io::Result<bool> {
-        let metadata = fs::metadata(&self.path)?;
-        let modified_time = metadata.modified()?;
+        let modified_time = Self::modification_time(&self.path)?;
+        let mut modified = false;
         if modified_time > self.last_modification {
             self.last_modification = modified_time;
-            Ok(true)
-        } else {
-            Ok(false)
+            modified = true;
         }
+        for (path, last_modification) in &mut self.external_files {
+            // These may not exist yet or be in the middle of being written so ignore errors.
+            if let Ok(modified_time) = Self::modification_time(path) {
+                if modified_time > *last_modification {
+                    *last_modification = modified_time;
+                    modified = true;
+                }
+            }
+        }
+        Ok(modified)
+    }
+
+    fn modification_time(path: &Path) -> io::Result<SystemTime> {
+        fs::metadata(path)?.modified()
     }
 }
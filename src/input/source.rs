@@ -1,5 +1,15 @@
 use super::{fs::PresentationFileWatcher, user::UserInput};
-use std::{io, path::PathBuf, time::Duration};
+use std::{
+    io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+/// How long to wait for user input before checking for anything else to do.
+const POLL_TIMEOUT: Duration = Duration::from_millis(250);
+
+/// How long to wait for user input while widgets are being rendered.
+const ANIMATION_POLL_TIMEOUT: Duration = Duration::from_millis(50);
 
 /// The source of commands.
 ///
@@ -8,20 +18,36 @@ use std::{io, path::PathBuf, time::Duration};
 pub struct CommandSource {
     watcher: PresentationFileWatcher,
     user_input: UserInput,
+    poll_timeout: Duration,
 }
 
 impl CommandSource {
     /// Create a new command source over the given presentation path.
     pub fn new<P: Into<PathBuf>>(presentation_path: P) -> Self {
         let watcher = PresentationFileWatcher::new(presentation_path);
-        Self { watcher, user_input: UserInput::default() }
+        Self { watcher, user_input: UserInput::default(), poll_timeout: POLL_TIMEOUT }
+    }
+
+    /// Set whether user input should be captured by widgets rather than used to control the presentation.
+    pub(crate) fn capture_input(&mut self, capture: bool) {
+        self.user_input.capture_input(capture);
+    }
+
+    /// Set whether to wait for less time for user input, so animations can be updated often.
+    pub(crate) fn poll_often(&mut self, often: bool) {
+        self.poll_timeout = if often { ANIMATION_POLL_TIMEOUT } else { POLL_TIMEOUT };
+    }
+
+    /// Watch a file the presentation depends on and reload it if it changes.
+    pub(crate) fn watch_external_file(&mut self, path: &Path) {
+        self.watcher.watch_external_file(path);
     }
 
     /// Try to get the next command.
     ///
     /// This attempts to get a command and returns `Ok(None)` on timeout.
     pub(crate) fn try_next_command(&mut self) -> io::Result<Option<Command>> {
-        if let Some(command) = self.user_input.poll_next_command(Duration::from_millis(250))? {
+        if let Some(command) = self.user_input.poll_next_command(self.poll_timeout)? {
             return Ok(Some(command));
         };
         if self.watcher.has_modifications()? { Ok(Some(Command::Reload)) } else { Ok(None) }
@@ -51,9 +77,36 @@ pub(crate) enum Command {
     /// Jump to one particular slide.
     JumpSlide(u32),
 
+    /// Focus the next link in the currently visible slide.
+    FocusNextLink,
+
+    /// Jump to the slide the link in focus in the currently visible slide points to.
+    FollowLink,
+
     /// Render any widgets in the currently visible slide.
     RenderWidgets,
 
+    /// Render any widgets in the currently visible slide again, even if they were already rendered.
+    RerenderWidgets,
+
+    /// Discard anything rendered by the widgets in the currently visible slide.
+    ResetWidgets,
+
+    /// Scroll the widgets in the currently visible slide up.
+    ScrollWidgetsUp,
+
+    /// Scroll the widgets in the currently visible slide down.
+    ScrollWidgetsDown,
+
+    /// Toggle whether the widgets in the currently visible slide follow their contents.
+    ToggleWidgetsFollow,
+
+    /// Copy the contents of the code block in focus in the currently visible slide into the clipboard.
+    CopyCode,
+
+    /// Send input to the widget capturing it in the currently visible slide.
+    WidgetInput(WidgetInput),
+
     /// Exit the presentation.
     Exit,
 
@@ -65,3 +118,19 @@ pub(crate) enum Command {
     /// Like [Command::Reload] but also reloads any external resources like images and themes.
     HardReload,
 }
+
+/// Input sent to a widget.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum WidgetInput {
+    /// A character was typed.
+    Char(char),
+
+    /// The last typed character should be deleted.
+    Backspace,
+
+    /// The current line is complete.
+    Enter,
+
+    /// There's no more input.
+    EndOfInput,
+}
@@ -1,4 +1,4 @@
-use super::source::Command;
+use super::source::{Command, WidgetInput};
 use crossterm::event::{poll, read, Event, KeyCode, KeyEvent, KeyModifiers};
 use std::{io, mem, time::Duration};
 
@@ -6,6 +6,7 @@ use std::{io, mem, time::Duration};
 #[derive(Default)]
 pub(crate) struct UserInput {
     state: InputState,
+    capture: bool,
 }
 
 impl UserInput {
@@ -14,8 +15,24 @@ impl UserInput {
         if poll(timeout)? { self.next_command() } else { Ok(None) }
     }
 
+    /// Set whether key presses should be turned into input for widgets.
+    pub(crate) fn capture_input(&mut self, capture: bool) {
+        if capture != self.capture {
+            self.capture = capture;
+            self.state = InputState::Empty;
+        }
+    }
+
     /// Blocks waiting for the next command.
     pub(crate) fn next_command(&mut self) -> io::Result<Option<Command>> {
+        if self.capture {
+            let command = match read()? {
+                Event::Key(event) => Self::apply_captured_key_event(event),
+                Event::Resize(..) => Some(Command::Redraw),
+                _ => None,
+            };
+            return Ok(command);
+        }
         let current_state = mem::take(&mut self.state);
         let (command, next_state) = match read()? {
             Event::Key(event) => Self::apply_key_event(event, current_state),
@@ -41,6 +58,14 @@ impl UserInput {
             KeyCode::Char('e') if event.modifiers == KeyModifiers::CONTROL => {
                 (Some(Command::RenderWidgets), InputState::Empty)
             }
+            KeyCode::Char('E') => (Some(Command::RerenderWidgets), InputState::Empty),
+            KeyCode::Char('C') => (Some(Command::ResetWidgets), InputState::Empty),
+            KeyCode::Char('K') => (Some(Command::ScrollWidgetsUp), InputState::Empty),
+            KeyCode::Char('J') => (Some(Command::ScrollWidgetsDown), InputState::Empty),
+            KeyCode::Char('F') => (Some(Command::ToggleWidgetsFollow), InputState::Empty),
+            KeyCode::Char('y') => (Some(Command::CopyCode), InputState::Empty),
+            KeyCode::Tab => (Some(Command::FocusNextLink), InputState::Empty),
+            KeyCode::Enter => (Some(Command::FollowLink), InputState::Empty),
             KeyCode::Char('G') => Self::apply_uppercase_g(state),
             KeyCode::Char('g') => Self::apply_lowercase_g(state),
             KeyCode::Char(number) if number.is_ascii_digit() => {
@@ -54,6 +79,18 @@ impl UserInput {
         }
     }
 
+    fn apply_captured_key_event(event: KeyEvent) -> Option<Command> {
+        let input = match event.code {
+            KeyCode::Char('c') if event.modifiers == KeyModifiers::CONTROL => return Some(Command::Exit),
+            KeyCode::Char('d') if event.modifiers == KeyModifiers::CONTROL => WidgetInput::EndOfInput,
+            KeyCode::Char(c) if !event.modifiers.contains(KeyModifiers::CONTROL) => WidgetInput::Char(c),
+            KeyCode::Backspace => WidgetInput::Backspace,
+            KeyCode::Enter => WidgetInput::Enter,
+            _ => return None,
+        };
+        Some(Command::WidgetInput(input))
+    }
+
     fn apply_lowercase_g(state: InputState) -> (Option<Command>, InputState) {
         match state {
             InputState::PendingG => (Some(Command::JumpFirstSlide), InputState::Empty),
@@ -134,4 +171,40 @@ mod test {
         assert_eq!(command, Some(Command::JumpSlide(12)));
         assert_eq!(state, InputState::Empty);
     }
+
+    #[test]
+    fn captured_input() {
+        let command = UserInput::apply_captured_key_event(KeyCode::Char('G').into());
+        assert_eq!(command, Some(Command::WidgetInput(WidgetInput::Char('G'))));
+
+        let command = UserInput::apply_captured_key_event(KeyCode::Enter.into());
+        assert_eq!(command, Some(Command::WidgetInput(WidgetInput::Enter)));
+
+        let command = UserInput::apply_captured_key_event(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL));
+        assert_eq!(command, Some(Command::Exit));
+    }
+
+    #[test]
+    fn rerender_widgets() {
+        let (command, state) = UserInput::apply_key_event(KeyCode::Char('E').into(), InputState::PendingG);
+        assert_eq!(command, Some(Command::RerenderWidgets));
+        assert_eq!(state, InputState::Empty);
+    }
+
+    #[test]
+    fn copy_code() {
+        let (command, state) = UserInput::apply_key_event(KeyCode::Char('y').into(), InputState::PendingG);
+        assert_eq!(command, Some(Command::CopyCode));
+        assert_eq!(state, InputState::Empty);
+    }
+
+    #[test]
+    fn links() {
+        let (command, _) = UserInput::apply_key_event(KeyCode::Tab.into(), InputState::Empty);
+        assert_eq!(command, Some(Command::FocusNextLink));
+
+        let (command, state) = UserInput::apply_key_event(KeyCode::Enter.into(), InputState::PendingNumber(3));
+        assert_eq!(command, Some(Command::FollowLink));
+        assert_eq!(state, InputState::Empty);
+    }
 }
@@ -3,6 +3,7 @@
 //! This is not meant to be used as a crate!
 
 pub(crate) mod builder;
+pub(crate) mod config;
 pub(crate) mod diff;
 pub(crate) mod execute;
 pub(crate) mod export;
@@ -14,8 +15,16 @@ pub(crate) mod render;
 pub(crate) mod resource;
 pub(crate) mod style;
 pub(crate) mod theme;
+pub(crate) mod third_party;
+pub(crate) mod timer;
+pub(crate) mod typewriter;
+pub(crate) mod validate;
+pub(crate) mod variables;
 
 pub use crate::{
+    builder::PresentationBuilderOptions,
+    config::{Config, HighlightingBackend},
+    execute::CodeExecuter,
     export::{ExportError, Exporter},
     input::source::CommandSource,
     markdown::parse::MarkdownParser,
@@ -23,4 +32,6 @@ pub use crate::{
     render::highlighting::CodeHighlighter,
     resource::Resources,
     theme::PresentationTheme,
+    third_party::ThirdPartyRender,
+    validate::SnippetValidator,
 };
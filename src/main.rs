@@ -2,9 +2,14 @@ use clap::{error::ErrorKind, CommandFactory, Parser};
 use colored::Colorize;
 use comrak::Arena;
 use presenterm::{
-    CodeHighlighter, CommandSource, Exporter, MarkdownParser, PresentMode, PresentationTheme, Presenter, Resources,
+    CodeExecuter, CodeHighlighter, CommandSource, Config, Exporter, HighlightingBackend, MarkdownParser, PresentMode,
+    PresentationBuilderOptions, PresentationTheme, Presenter, Resources, SnippetValidator, ThirdPartyRender,
+};
+use std::{
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::Duration,
 };
-use std::path::{Path, PathBuf};
 
 /// Run slideshows from your terminal.
 #[derive(Parser)]
@@ -22,6 +27,21 @@ struct Cli {
     #[clap(long, hide = true)]
     generate_pdf_metadata: bool,
 
+    /// Execute all code marked for execution and report any failures rather than displaying the
+    /// presentation.
+    #[clap(long)]
+    validate_snippets: bool,
+
+    /// Allow executing code marked for execution.
+    ///
+    /// Without this, code can still be marked for execution but attempting to run it fails.
+    #[clap(long)]
+    allow_exec: bool,
+
+    /// Append every piece of code that's executed, along with its output, to this file.
+    #[clap(long)]
+    transcript: Option<PathBuf>,
+
     /// Whether to use presentation mode.
     #[clap(short, long, default_value_t = false)]
     present: bool,
@@ -29,6 +49,12 @@ struct Cli {
     /// The theme to use.
     #[clap(short, long, default_value = "dark")]
     theme: String,
+
+    /// The path to the configuration file.
+    ///
+    /// If not set, `$XDG_CONFIG_HOME/presenterm/config.yaml` will be used if it exists.
+    #[clap(short, long)]
+    config_file: Option<PathBuf>,
 }
 
 fn show_splashes() -> String {
@@ -49,7 +75,29 @@ fn show_splashes() -> String {
     format!("{logo}")
 }
 
+fn load_config(cli: &Cli) -> Result<Config, Box<dyn std::error::Error>> {
+    let config = match &cli.config_file {
+        Some(path) => Config::load(path)?,
+        None => match Config::default_path() {
+            Some(path) if path.exists() => Config::load(path)?,
+            _ => Config::default(),
+        },
+    };
+    Ok(config)
+}
+
+fn syntaxes_path(cli: &Cli, config: &Config) -> Option<PathBuf> {
+    if let Some(path) = &config.highlighting.syntaxes_path {
+        return Some(path.clone());
+    }
+    // By default look for them next to the configuration file.
+    let config_path = cli.config_file.clone().or_else(Config::default_path)?;
+    let path = config_path.parent()?.join("syntaxes");
+    path.is_dir().then_some(path)
+}
+
 fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
+    let config = load_config(&cli)?;
     let Some(default_theme) = PresentationTheme::from_name(&cli.theme) else {
         let mut cmd = Cli::command();
         let valid_themes = PresentationTheme::theme_names().collect::<Vec<_>>().join(", ");
@@ -62,12 +110,63 @@ fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
         false => PresentMode::Development,
     };
     let arena = Arena::new();
-    let parser = MarkdownParser::new(&arena);
+    let emoji_shortcodes = config.markdown.emoji_shortcodes.unwrap_or(true);
+    let line_breaks = config.markdown.line_breaks;
+    let smart_punctuation = config.markdown.smart_punctuation;
+    let parser = MarkdownParser::new(&arena)
+        .with_emoji_shortcodes(emoji_shortcodes)
+        .with_smart_punctuation(smart_punctuation)
+        .with_line_breaks(line_breaks);
+    if let Some(path) = &config.highlighting.bat_cache {
+        CodeHighlighter::load_bat_assets(path)?;
+    }
+    if let Some(path) = syntaxes_path(&cli, &config) {
+        CodeHighlighter::load_syntaxes(&path)?;
+    }
+    if config.highlighting.backend == HighlightingBackend::TreeSitter {
+        CodeHighlighter::enable_tree_sitter()?;
+    }
     let default_highlighter = CodeHighlighter::new("base16-ocean.dark")?;
     let resources_path = cli.path.parent().unwrap_or(Path::new("/"));
     let resources = Resources::new(resources_path);
-    if cli.export_pdf || cli.generate_pdf_metadata {
-        let mut exporter = Exporter::new(parser, &default_theme, default_highlighter, resources);
+    // Validating snippets means running them so there's no need to ask for permission twice.
+    let allow_execution = cli.allow_exec || cli.validate_snippets;
+    let execution_timeout = config.execution.timeout.map(Duration::from_secs);
+    let execution_working_directory = config.execution.cwd.clone();
+    let mut code_executer = CodeExecuter::new(config.execution).allow_execution(allow_execution);
+    if let Some(path) = cli.transcript {
+        code_executer = code_executer
+            .with_transcript(path.clone())
+            .map_err(|e| format!("failed to open transcript {}: {e}", path.display()))?;
+    }
+    let options = PresentationBuilderOptions {
+        execution_timeout,
+        execution_working_directory,
+        code_executer: Rc::new(code_executer),
+        third_party: Rc::new(ThirdPartyRender::new(config.typst, config.latex, config.mermaid)),
+        emoji_shortcodes,
+        smart_punctuation,
+        line_breaks,
+        hyperlinks: config.markdown.hyperlinks,
+        slide_separators: config.markdown.slide_separators,
+        slide_level: config.markdown.slide_level,
+        incremental_lists: config.markdown.incremental_lists,
+        reveal_delay: config.reveal.delay.map(Duration::from_millis),
+        list_reveal_delay: config.reveal.list_item_delay.map(Duration::from_millis),
+        // Skipped slides are still displayed while working on a presentation.
+        skip_slides: cli.present || cli.export_pdf || cli.generate_pdf_metadata,
+    };
+    if cli.validate_snippets {
+        let mut validator = SnippetValidator::new(parser, &default_theme, default_highlighter, resources, options);
+        let failures = validator.validate(&cli.path)?;
+        for failure in &failures {
+            eprintln!("{failure}");
+        }
+        if !failures.is_empty() {
+            return Err(format!("{} snippet(s) failed", failures.len()).into());
+        }
+    } else if cli.export_pdf || cli.generate_pdf_metadata {
+        let mut exporter = Exporter::new(parser, &default_theme, default_highlighter, resources, options);
         if cli.export_pdf {
             exporter.export_pdf(&cli.path)?;
         } else {
@@ -76,7 +175,8 @@ fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
         }
     } else {
         let commands = CommandSource::new(&cli.path);
-        let presenter = Presenter::new(&default_theme, default_highlighter, commands, parser, resources, mode);
+        let presenter = Presenter::new(&default_theme, default_highlighter, commands, parser, resources, mode, options)
+            .with_image_protocol(config.images.protocol);
         presenter.present(&cli.path)?;
     }
     Ok(())
@@ -86,5 +186,6 @@ fn main() {
     let cli = Cli::parse();
     if let Err(e) = run(cli) {
         eprintln!("Failed to run presentation: {e}");
+        std::process::exit(1);
     }
 }
@@ -1,7 +1,7 @@
-use crate::style::TextStyle;
-use std::{iter, path::PathBuf};
+use crate::{markdown::text::TextWidth, style::TextStyle, theme::OutputOverflow};
+use serde::Deserialize;
+use std::{collections::BTreeSet, iter, ops::RangeInclusive, path::PathBuf, time::Duration};
 use strum::EnumIter;
-use unicode_width::UnicodeWidthStr;
 
 /// A markdown element.
 ///
@@ -13,7 +13,7 @@ pub(crate) enum MarkdownElement {
     FrontMatter(String),
 
     /// A setex heading.
-    SetexHeading { text: Text },
+    SetexHeading { text: Text, level: u8 },
 
     /// A normal heading.
     Heading { level: u8, text: Text },
@@ -21,8 +21,8 @@ pub(crate) enum MarkdownElement {
     /// A paragraph, composed of text and line breaks.
     Paragraph(Vec<ParagraphElement>),
 
-    /// An image.
-    Image { path: PathBuf, source_position: SourcePosition },
+    /// An image along with its alt text.
+    Image { path: PathBuf, alt_text: String, source_position: SourcePosition },
 
     /// A list.
     ///
@@ -30,19 +30,42 @@ pub(crate) enum MarkdownElement {
     List(Vec<ListItem>),
 
     /// A block of code.
-    Code(Code),
+    Code(Box<Code>),
 
     /// A table.
     Table(Table),
 
     /// A thematic break.
-    ThematicBreak,
+    ThematicBreak(ThematicBreakMarker),
 
     /// An HTML comment.
     Comment { comment: String, source_position: SourcePosition },
 
     /// A quote.
-    BlockQuote(Vec<String>),
+    BlockQuote {
+        /// The lines in the quote.
+        lines: Vec<String>,
+
+        /// Who the quote is attributed to, taken from a last line like `— Author`.
+        attribution: Option<String>,
+    },
+}
+
+/// The characters a thematic break is written with.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+pub enum ThematicBreakMarker {
+    /// `---`
+    #[default]
+    #[serde(rename = "---")]
+    Dashes,
+
+    /// `***`
+    #[serde(rename = "***")]
+    Asterisks,
+
+    /// `___`
+    #[serde(rename = "___")]
+    Underscores,
 }
 
 #[derive(Clone, Debug, Default)]
@@ -126,24 +149,32 @@ impl<T: Into<StyledText>> From<T> for Text {
 pub(crate) struct StyledText {
     pub(crate) text: String,
     pub(crate) style: TextStyle,
+
+    /// The URL this text links to, if any.
+    pub(crate) link: Option<String>,
 }
 
 impl StyledText {
     /// Construct a new styled text.
     pub(crate) fn new<S: Into<String>>(text: S, style: TextStyle) -> Self {
-        Self { text: text.into(), style }
+        Self { text: text.into(), style, link: None }
+    }
+
+    /// Get the anchor this text links to, if it's a link to another part of the presentation.
+    pub(crate) fn anchor(&self) -> Option<&str> {
+        self.link.as_deref().and_then(|link| link.strip_prefix('#'))
     }
 }
 
 impl From<String> for StyledText {
     fn from(text: String) -> Self {
-        Self { text, style: TextStyle::default() }
+        Self { text, style: TextStyle::default(), link: None }
     }
 }
 
 impl From<&str> for StyledText {
     fn from(text: &str) -> Self {
-        Self { text: text.into(), style: TextStyle::default() }
+        Self { text: text.into(), style: TextStyle::default(), link: None }
     }
 }
 
@@ -160,6 +191,9 @@ pub(crate) struct ListItem {
 
     /// The type of list item.
     pub(crate) item_type: ListItemType,
+
+    /// Whether this is an item in a task list and if so, whether it's checked.
+    pub(crate) checked: Option<bool>,
 }
 
 /// The type of a list item.
@@ -176,7 +210,7 @@ pub(crate) enum ListItemType {
 }
 
 /// A piece of code.
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub(crate) struct Code {
     /// The code itself.
     pub(crate) contents: String,
@@ -188,44 +222,136 @@ pub(crate) struct Code {
     pub(crate) flags: CodeFlags,
 }
 
+impl Code {
+    /// Get the lines in this code that should be displayed.
+    ///
+    /// Executable code can contain hidden lines that are executed but never displayed.
+    pub(crate) fn visible_lines(&self) -> impl Iterator<Item = &str> {
+        self.contents.lines().filter(|line| !self.is_hidden(line))
+    }
+
+    /// Check whether a line in this code is hidden, meaning it's executed but never displayed.
+    pub(crate) fn is_hidden(&self, line: &str) -> bool {
+        self.hidden_line_prefix().and_then(|prefix| Self::strip_hidden(line, prefix)).is_some()
+    }
+
+    /// Only keep the lines in this code that are within its line ranges, if it has any.
+    ///
+    /// This returns the positions, within the lines that were kept, where any lines were cut out. A position equal to
+    /// the number of lines kept means lines were cut out at the end.
+    pub(crate) fn select_lines(&mut self) -> Vec<usize> {
+        if self.flags.line_ranges.is_empty() {
+            return Vec::new();
+        }
+        let lines: Vec<_> = self.contents.lines().collect();
+        let mut selected = BTreeSet::new();
+        for range in &self.flags.line_ranges {
+            // Line numbers start at 1.
+            let start = range.start().saturating_sub(1);
+            let end = (*range.end()).min(lines.len());
+            selected.extend(start..end);
+        }
+        let mut elisions = Vec::new();
+        let mut contents = String::new();
+        let mut next = 0;
+        for (position, index) in selected.iter().enumerate() {
+            if *index != next {
+                elisions.push(position);
+            }
+            contents.push_str(lines[*index]);
+            contents.push('\n');
+            next = index + 1;
+        }
+        if next != lines.len() {
+            elisions.push(selected.len());
+        }
+        self.contents = contents;
+        elisions
+    }
+
+    /// Get the contents of this code that should be executed.
+    ///
+    /// This includes hidden lines, with their prefix removed.
+    pub(crate) fn executable_contents(&self) -> String {
+        let Some(prefix) = self.hidden_line_prefix() else {
+            return self.contents.clone();
+        };
+        self.contents.lines().map(|line| Self::strip_hidden(line, prefix).unwrap_or(line)).fold(
+            String::new(),
+            |mut output, line| {
+                output.push_str(line);
+                output.push('\n');
+                output
+            },
+        )
+    }
+
+    fn hidden_line_prefix(&self) -> Option<&'static str> {
+        if !self.flags.execute {
+            return None;
+        }
+        match self.language {
+            // Follow rustdoc's convention.
+            CodeLanguage::Rust => Some("# "),
+            CodeLanguage::Shell(_) | CodeLanguage::Python => Some("/// "),
+            _ => None,
+        }
+    }
+
+    fn strip_hidden<'b>(line: &'b str, prefix: &str) -> Option<&'b str> {
+        // Allow empty hidden lines without the trailing space.
+        if line == prefix.trim_end() { Some("") } else { line.strip_prefix(prefix) }
+    }
+}
+
 /// The language of a piece of code.
-#[derive(Clone, Debug, PartialEq, Eq, EnumIter)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, EnumIter)]
 pub(crate) enum CodeLanguage {
     Ada,
+    Ansi,
     Asp,
     Awk,
     Bash,
+    BigText,
     BatchFile,
     C,
+    Chart,
     CMake,
     Crontab,
     CSharp,
     Clojure,
     Cpp,
     Css,
+    D2,
     DLang,
     Docker,
+    Dot,
     Dotenv,
     Elixir,
     Elm,
     Erlang,
     Go,
+    GraphQL,
     Haskell,
     Html,
     Java,
     JavaScript,
     Json,
+    Julia,
     Kotlin,
     Latex,
     Lua,
     Makefile,
     Markdown,
+    Mermaid,
+    Nix,
     OCaml,
     Perl,
     Php,
     Protobuf,
     Puppet,
     Python,
+    Qr,
     R,
     Rust,
     Scala,
@@ -234,8 +360,10 @@ pub(crate) enum CodeLanguage {
     Swift,
     Svelte,
     Terraform,
+    Toml,
+    Typst,
     TypeScript,
-    Unknown,
+    Unknown(String),
     Xml,
     Yaml,
     Vue,
@@ -243,16 +371,179 @@ pub(crate) enum CodeLanguage {
 }
 
 impl CodeLanguage {
+    /// Get the language for the name used in a code block's info string.
+    pub(crate) fn from_name(name: &str) -> Self {
+        use CodeLanguage::*;
+        match name {
+            "ada" => Ada,
+            "ansi" => Ansi,
+            "asp" => Asp,
+            "awk" => Awk,
+            "big-text" => BigText,
+            "c" => C,
+            "chart" => Chart,
+            "cmake" => CMake,
+            "crontab" => Crontab,
+            "csharp" => CSharp,
+            "clojure" => Clojure,
+            "cpp" | "c++" => Cpp,
+            "css" => Css,
+            "d" => DLang,
+            "docker" => Docker,
+            "dot" | "graphviz" => Dot,
+            "dotenv" => Dotenv,
+            "elixir" => Elixir,
+            "elm" => Elm,
+            "erlang" => Erlang,
+            "go" => Go,
+            "graphql" => GraphQL,
+            "haskell" => Haskell,
+            "html" => Html,
+            "java" => Java,
+            "javascript" | "js" => JavaScript,
+            "json" => Json,
+            "julia" => Julia,
+            "kotlin" => Kotlin,
+            "latex" => Latex,
+            "lua" => Lua,
+            "make" => Makefile,
+            "markdown" => Markdown,
+            "mermaid" => Mermaid,
+            "nix" => Nix,
+            "ocaml" => OCaml,
+            "perl" => Perl,
+            "php" => Php,
+            "protobuf" => Protobuf,
+            "puppet" => Puppet,
+            "python" => Python,
+            "qr" => Qr,
+            "r" => R,
+            "rust" => Rust,
+            "scala" => Scala,
+            "shell" => Shell("sh".into()),
+            interpreter @ ("bash" | "sh" | "zsh" | "fish" | "powershell" | "pwsh") => Shell(interpreter.into()),
+            "batch" | "bat" | "cmd" => BatchFile,
+            "sql" => Sql,
+            "svelte" => Svelte,
+            "swift" => Swift,
+            "terraform" => Terraform,
+            "toml" => Toml,
+            "typst" => Typst,
+            "typescript" | "ts" => TypeScript,
+            "xml" => Xml,
+            "yaml" => Yaml,
+            "vue" => Vue,
+            "zig" => Zig,
+            _ => Unknown(name.into()),
+        }
+    }
+
     pub(crate) fn supports_execution(&self) -> bool {
-        matches!(self, Self::Shell(_))
+        matches!(self, Self::Shell(_) | Self::Python | Self::Rust | Self::BatchFile)
     }
 }
 
 /// Flags for code blocks.
-#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
 pub(crate) struct CodeFlags {
     /// Whether a code block is marked as executable.
     pub(crate) execute: bool,
+
+    /// Whether this code should be executed when the presentation is built and replaced with its output.
+    pub(crate) execute_replace: bool,
+
+    /// Whether this code should be executed as soon as it becomes visible.
+    pub(crate) auto_execute: bool,
+
+    /// Whether this code takes over the terminal while it runs rather than having its output captured.
+    pub(crate) acquire_terminal: bool,
+
+    /// The step this code is executed in, when executing code in a slide one step at a time.
+    pub(crate) step: Option<u32>,
+
+    /// The groups of lines to highlight.
+    ///
+    /// If there's more than one, each of them is highlighted in turn as the presentation moves forward.
+    pub(crate) highlight_groups: Vec<LineGroup>,
+
+    /// Whether this code is a diff, where lines that start with `+` or `-` were added or removed.
+    pub(crate) diff: bool,
+
+    /// What to do with lines that are too long to fit, overriding the theme.
+    pub(crate) overflow: Option<OutputOverflow>,
+
+    /// The maximum amount of time this code is allowed to run for when executed.
+    pub(crate) timeout: Option<Duration>,
+
+    /// Whether the user can send input to this code when it's executed.
+    pub(crate) interactive: bool,
+
+    /// The extra arguments passed to the interpreter when executing this code.
+    pub(crate) arguments: Vec<String>,
+
+    /// The environment variables to set when executing this code.
+    pub(crate) environment: Vec<(String, String)>,
+
+    /// The directory to run this code from when executed.
+    pub(crate) working_directory: Option<PathBuf>,
+
+    /// The file this code's contents are loaded from, if any.
+    pub(crate) external_file: Option<PathBuf>,
+
+    /// The identifier other code can use to continue this one.
+    pub(crate) id: Option<String>,
+
+    /// The identifier of the code this one continues, which is prepended to it when executed.
+    pub(crate) continues: Option<String>,
+
+    /// The title displayed in a bar above the code, e.g. the name of the file it comes from.
+    pub(crate) title: Option<String>,
+
+    /// The ranges of lines to keep, e.g. to only show an excerpt of a file. Line numbers start at 1.
+    ///
+    /// All lines are kept if this is empty.
+    pub(crate) line_ranges: Vec<RangeInclusive<usize>>,
+
+    /// Whether a marker is displayed wherever lines were left out because of `line_ranges`.
+    pub(crate) elision_markers: bool,
+
+    /// Whether this code should be rendered into an image rather than displayed as code, e.g. for LaTeX formulas.
+    pub(crate) render: bool,
+
+    /// Whether this code is displayed exactly as it is written, without highlighting, padding, or wrapping it.
+    pub(crate) raw: bool,
+}
+
+/// A group of lines in a piece of code, e.g. `1,4-6`.
+///
+/// Line numbers start at 1.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub(crate) struct LineGroup(Vec<RangeInclusive<usize>>);
+
+impl LineGroup {
+    /// Parse a comma separated list of line numbers and ranges of them.
+    pub(crate) fn parse(input: &str) -> Option<Self> {
+        let mut ranges = Vec::new();
+        for part in input.split(',') {
+            let range = match part.split_once('-') {
+                Some((start, end)) => start.trim().parse().ok()?..=end.trim().parse().ok()?,
+                None => {
+                    let line = part.trim().parse().ok()?;
+                    line..=line
+                }
+            };
+            if range.is_empty() || *range.start() == 0 {
+                return None;
+            }
+            ranges.push(range);
+        }
+        Some(Self(ranges))
+    }
+
+    /// Check whether a line, starting at 1, is part of this group.
+    pub(crate) fn contains(&self, line: usize) -> bool {
+        self.0.iter().any(|range| range.contains(&line))
+    }
 }
 
 /// A table.
@@ -263,6 +554,9 @@ pub(crate) struct Table {
 
     /// All of the rows in this table, excluding the header.
     pub(crate) rows: Vec<TableRow>,
+
+    /// The alignment of the text in each column.
+    pub(crate) alignments: Vec<ColumnAlignment>,
 }
 
 impl Table {
@@ -281,6 +575,15 @@ impl Table {
     }
 }
 
+/// The alignment of the text in a table's column.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) enum ColumnAlignment {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
 /// A table row.
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub(crate) struct TableRow(pub(crate) Vec<Text>);
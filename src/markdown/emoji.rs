@@ -0,0 +1,75 @@
+use once_cell::sync::Lazy;
+use shortcodes::SHORTCODES;
+use std::collections::HashMap;
+
+mod shortcodes;
+
+static EMOJIS: Lazy<HashMap<&str, &str>> = Lazy::new(|| SHORTCODES.iter().copied().collect());
+
+/// Replace every known `:shortcode:` in a piece of text with the emoji it represents.
+///
+/// Unknown shortcodes are left as they are.
+pub(crate) fn replace_shortcodes(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find(':') {
+        output.push_str(&rest[..start]);
+        let candidate = &rest[start + 1..];
+        let name_length = candidate.find(|c: char| !is_shortcode_char(c)).unwrap_or(candidate.len());
+        let emoji = match candidate[name_length..].starts_with(':') {
+            true => EMOJIS.get(&candidate[..name_length]),
+            false => None,
+        };
+        match emoji {
+            Some(emoji) => {
+                output.push_str(emoji);
+                rest = &candidate[name_length + 1..];
+            }
+            None => {
+                // The closing colon could be the start of another shortcode so keep going from it.
+                output.push(':');
+                rest = candidate;
+            }
+        }
+    }
+    output.push_str(rest);
+    output
+}
+
+fn is_shortcode_char(c: char) -> bool {
+    c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '_' | '+' | '-')
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::single(":rocket:", "🚀")]
+    #[case::surrounded("let's go :rocket: now", "let's go 🚀 now")]
+    #[case::multiple(":tada::tada:", "🎉🎉")]
+    #[case::symbols(":+1: and :-1:", "👍 and 👎")]
+    #[case::github_alias(":star_struck: :hugs:", "🤩 🤗")]
+    #[case::cldr_name(":grinning_face:", "😀")]
+    #[case::keycap(":hash:", "#️⃣")]
+    #[case::emoji_presentation(":relaxed:", "☺️")]
+    #[case::number("ratio 3:1:2", "ratio 3:1:2")]
+    #[case::unknown(":presenterm:", ":presenterm:")]
+    #[case::unknown_then_known(":presenterm:rocket:", ":presenterm🚀")]
+    #[case::time("it's 10:30:00", "it's 10:30:00")]
+    #[case::unclosed("hello :rocket", "hello :rocket")]
+    #[case::uppercase(":ROCKET:", ":ROCKET:")]
+    fn replace(#[case] input: &str, #[case] expected: &str) {
+        assert_eq!(replace_shortcodes(input), expected);
+    }
+
+    #[test]
+    fn sorted_shortcodes() {
+        let names: Vec<_> = SHORTCODES.iter().map(|(name, _)| *name).collect();
+        let mut sorted = names.clone();
+        sorted.sort();
+        sorted.dedup();
+        assert_eq!(names, sorted);
+    }
+}
@@ -0,0 +1,158 @@
+use crate::style::{Color, Colors, TextStyle};
+
+/// A piece of inline HTML we know how to handle.
+#[derive(Debug, PartialEq)]
+pub(crate) enum HtmlInline {
+    /// A `<br>` tag.
+    LineBreak,
+
+    /// A tag that styles the text inside it.
+    OpenTag { name: String, style: TextStyle },
+
+    /// The end of a tag that was previously opened.
+    CloseTag { name: String },
+}
+
+/// Parse a piece of inline HTML, like `<b>` or `<span style="color: #ff0000">`.
+///
+/// Only `br`, `b`, `strong`, `i`, `em`, `u`, `s`, `del`, and `span` tags are supported. Style attributes in `span`
+/// tags can set the `color` and `background-color`, using either hex colors or a handful of basic color names; any
+/// other property in them, as well as colors we can't parse, is ignored.
+///
+/// Anything else, like other tags or comments, isn't something we can display so `None` is returned for it.
+pub(crate) fn parse_html_inline(html: &str) -> Option<HtmlInline> {
+    let inner = html.trim().strip_prefix('<').and_then(|html| html.strip_suffix('>'))?;
+    if let Some(name) = inner.strip_prefix('/') {
+        let name = name.trim().to_lowercase();
+        return STYLE_TAGS.contains(&name.as_str()).then_some(HtmlInline::CloseTag { name });
+    }
+    let inner = inner.trim_end_matches('/');
+    let (name, attributes) = inner.split_once(char::is_whitespace).unwrap_or((inner, ""));
+    let name = name.to_lowercase();
+    let style = match name.as_str() {
+        "br" => return Some(HtmlInline::LineBreak),
+        "b" | "strong" => TextStyle::default().bold(),
+        "i" | "em" => TextStyle::default().italics(),
+        "u" => TextStyle::default().underlined(),
+        "s" | "del" => TextStyle::default().strikethrough(),
+        "span" => match find_attribute(attributes, "style") {
+            Some(style) => parse_style(style),
+            None => TextStyle::default(),
+        },
+        _ => return None,
+    };
+    Some(HtmlInline::OpenTag { name, style })
+}
+
+const STYLE_TAGS: &[&str] = &["b", "strong", "i", "em", "u", "s", "del", "span"];
+
+fn find_attribute<'a>(attributes: &'a str, name: &str) -> Option<&'a str> {
+    let mut rest = attributes.trim_start();
+    while !rest.is_empty() {
+        let (attribute, after) = rest.split_once('=')?;
+        let after = after.trim_start();
+        let quote = after.chars().next().filter(|c| matches!(c, '"' | '\''))?;
+        let (value, after) = after[1..].split_once(quote)?;
+        if attribute.trim().eq_ignore_ascii_case(name) {
+            return Some(value);
+        }
+        rest = after.trim_start();
+    }
+    None
+}
+
+fn parse_style(style: &str) -> TextStyle {
+    let mut colors = Colors::default();
+    for declaration in style.split(';') {
+        let Some((property, value)) = declaration.split_once(':') else {
+            continue;
+        };
+        // Declarations we can't make sense of are dropped, the same way unsupported tags are.
+        let color = parse_color(value.trim());
+        match property.trim().to_lowercase().as_str() {
+            "color" if color.is_some() => colors.foreground = color,
+            "background-color" if color.is_some() => colors.background = color,
+            _ => (),
+        };
+    }
+    TextStyle::default().colors(colors)
+}
+
+fn parse_color(value: &str) -> Option<Color> {
+    let value = value.to_lowercase();
+    let hex = match value.as_str() {
+        "black" => "000000",
+        "white" => "ffffff",
+        "red" => "ff0000",
+        "green" => "008000",
+        "blue" => "0000ff",
+        "yellow" => "ffff00",
+        "cyan" => "00ffff",
+        "magenta" => "ff00ff",
+        "gray" | "grey" => "808080",
+        "orange" => "ffa500",
+        "purple" => "800080",
+        other => other.strip_prefix('#').unwrap_or(other),
+    };
+    hex.parse().ok()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    fn open(name: &str, style: TextStyle) -> HtmlInline {
+        HtmlInline::OpenTag { name: name.into(), style }
+    }
+
+    #[rstest]
+    #[case::br("<br>", HtmlInline::LineBreak)]
+    #[case::br_self_closing("<br />", HtmlInline::LineBreak)]
+    #[case::bold("<b>", open("b", TextStyle::default().bold()))]
+    #[case::strong("<STRONG>", open("strong", TextStyle::default().bold()))]
+    #[case::italics("<i>", open("i", TextStyle::default().italics()))]
+    #[case::underline("<u>", open("u", TextStyle::default().underlined()))]
+    #[case::close("</b>", HtmlInline::CloseTag { name: "b".into() })]
+    #[case::span("<span>", open("span", TextStyle::default()))]
+    #[case::span_color(
+        "<span style=\"color: #ff0000\">",
+        open("span", TextStyle::default().colors(Colors { foreground: Some(Color::new(255, 0, 0)), background: None }))
+    )]
+    #[case::span_colors(
+        "<span class='x' style='font-size: 2em; background-color:00ff00; color: 0000ff;'>",
+        open(
+            "span",
+            TextStyle::default().colors(Colors {
+                foreground: Some(Color::new(0, 0, 255)),
+                background: Some(Color::new(0, 255, 0))
+            })
+        )
+    )]
+    #[case::named_color(
+        "<span style=\"color:Red\">",
+        open("span", TextStyle::default().colors(Colors { foreground: Some(Color::new(255, 0, 0)), background: None }))
+    )]
+    fn parse(#[case] input: &str, #[case] expected: HtmlInline) {
+        assert_eq!(parse_html_inline(input), Some(expected));
+    }
+
+    #[rstest]
+    #[case::unknown_tag("<div>")]
+    #[case::unknown_close_tag("</div>")]
+    #[case::comment("<!-- hi -->")]
+    fn parse_unsupported(#[case] input: &str) {
+        assert_eq!(parse_html_inline(input), None);
+    }
+
+    #[rstest]
+    #[case::unknown_name("potato")]
+    #[case::rgb("rgb(255, 0, 0)")]
+    #[case::short_hex("#f00")]
+    #[case::hsl("hsl(0, 100%, 50%)")]
+    #[case::important("red !important")]
+    fn parse_invalid_color(#[case] color: &str) {
+        let result = parse_html_inline(&format!("<span style=\"color: {color}\">"));
+        assert_eq!(result, Some(open("span", TextStyle::default())));
+    }
+}
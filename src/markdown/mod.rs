@@ -1,3 +1,5 @@
 pub(crate) mod elements;
+pub(crate) mod emoji;
+pub(crate) mod html;
 pub(crate) mod parse;
 pub(crate) mod text;
@@ -1,21 +1,30 @@
 use crate::{
-    markdown::elements::{
-        Code, CodeFlags, CodeLanguage, ListItem, ListItemType, MarkdownElement, ParagraphElement, StyledText, Table,
-        TableRow, Text,
+    markdown::{
+        elements::{
+            Code, CodeFlags, CodeLanguage, ColumnAlignment, LineGroup, ListItem, ListItemType, MarkdownElement,
+            ParagraphElement, StyledText, Table, TableRow, Text, ThematicBreakMarker,
+        },
+        emoji,
+        html::{parse_html_inline, HtmlInline},
     },
     style::TextStyle,
+    theme::OutputOverflow,
 };
 use comrak::{
     format_commonmark,
     nodes::{
         AstNode, ListDelimType, ListType, NodeCodeBlock, NodeHeading, NodeHtmlBlock, NodeList, NodeValue, Sourcepos,
+        TableAlignment,
     },
     parse_document, Arena, ComrakOptions, ListStyleType,
 };
+use serde::Deserialize;
 use std::{
     fmt::{self, Debug, Display},
     io::BufWriter,
     mem,
+    ops::RangeInclusive,
+    time::Duration,
 };
 
 use super::elements::SourcePosition;
@@ -31,6 +40,7 @@ impl Default for ParserOptions {
         options.extension.front_matter_delimiter = Some("---".into());
         options.extension.table = true;
         options.extension.strikethrough = true;
+        options.extension.tasklist = true;
         Self(options)
     }
 }
@@ -41,22 +51,62 @@ impl Default for ParserOptions {
 pub struct MarkdownParser<'a> {
     arena: &'a Arena<AstNode<'a>>,
     options: ComrakOptions,
+    emoji_shortcodes: bool,
+    line_breaks: LineBreaks,
 }
 
 impl<'a> MarkdownParser<'a> {
     /// Construct a new markdown parser.
     pub fn new(arena: &'a Arena<AstNode<'a>>) -> Self {
-        Self { arena, options: ParserOptions::default().0 }
+        Self { arena, options: ParserOptions::default().0, emoji_shortcodes: true, line_breaks: LineBreaks::default() }
+    }
+
+    /// Set whether `:shortcode:` style emojis are replaced with the emoji they represent.
+    pub fn with_emoji_shortcodes(mut self, enabled: bool) -> Self {
+        self.emoji_shortcodes = enabled;
+        self
+    }
+
+    /// Set whether to use typographic punctuation, turning `--`/`---` into en/em dashes, straight quotes into curly
+    /// quotes, and `...` into an ellipsis.
+    pub fn with_smart_punctuation(mut self, enabled: bool) -> Self {
+        self.options.parse.smart = enabled;
+        self
+    }
+
+    /// Set how single line breaks within a paragraph are handled.
+    pub fn with_line_breaks(mut self, line_breaks: LineBreaks) -> Self {
+        self.line_breaks = line_breaks;
+        self
     }
 
     /// Parse the contents of a markdown file.
     pub(crate) fn parse(&self, contents: &str) -> ParseResult<Vec<MarkdownElement>> {
         let node = parse_document(self.arena, contents, &self.options);
+        if self.emoji_shortcodes {
+            Self::replace_emoji_shortcodes(node);
+        }
+        if self.line_breaks == LineBreaks::Preserve {
+            Self::preserve_line_breaks(node);
+        }
         let mut elements = Vec::new();
         let mut lines_offset = 0;
         for node in node.children() {
-            let mut parsed_elements =
-                Self::parse_node(node).map_err(|e| ParseError::new(e.kind, e.sourcepos.offset_lines(lines_offset)))?;
+            let data = node.data.borrow();
+            // Formulas are looked up in the source as markdown escapes would otherwise mangle them.
+            let formula = match &data.value {
+                NodeValue::Paragraph => Self::parse_formula(contents, data.sourcepos, lines_offset),
+                _ => None,
+            };
+            let mut parsed_elements = match (&data.value, formula) {
+                (_, Some(formula)) => vec![formula],
+                // The node doesn't tell us which characters were used so look them up in the source.
+                (NodeValue::ThematicBreak, _) => {
+                    vec![Self::parse_thematic_break(contents, data.sourcepos, lines_offset)]
+                }
+                _ => Self::parse_node(node)
+                    .map_err(|e| ParseError::new(e.kind, e.sourcepos.offset_lines(lines_offset)))?,
+            };
             if let Some(MarkdownElement::FrontMatter(contents)) = parsed_elements.first() {
                 lines_offset += contents.lines().count() + 2;
             }
@@ -67,6 +117,26 @@ impl<'a> MarkdownParser<'a> {
         Ok(elements)
     }
 
+    fn replace_emoji_shortcodes(root: &'a AstNode<'a>) {
+        // Only plain text is touched so shortcodes in code are kept as they are.
+        for node in root.descendants() {
+            if let NodeValue::Text(text) = &mut node.data.borrow_mut().value {
+                if text.contains(':') {
+                    *text = emoji::replace_shortcodes(text);
+                }
+            }
+        }
+    }
+
+    fn preserve_line_breaks(root: &'a AstNode<'a>) {
+        for node in root.descendants() {
+            let value = &mut node.data.borrow_mut().value;
+            if matches!(value, NodeValue::SoftBreak) {
+                *value = NodeValue::LineBreak;
+            }
+        }
+    }
+
     fn adjust_source_positions<'b>(elements: impl Iterator<Item = &'b mut MarkdownElement>, lines_offset: usize) {
         for element in elements {
             let position = match element {
@@ -78,8 +148,8 @@ impl<'a> MarkdownParser<'a> {
                 | MarkdownElement::List(_)
                 | MarkdownElement::Code(_)
                 | MarkdownElement::Table(_)
-                | MarkdownElement::ThematicBreak
-                | MarkdownElement::BlockQuote(_) => continue,
+                | MarkdownElement::ThematicBreak(_)
+                | MarkdownElement::BlockQuote { .. } => continue,
                 MarkdownElement::Comment { source_position, .. } => source_position,
             };
             *position = position.offset_lines(lines_offset);
@@ -97,9 +167,8 @@ impl<'a> MarkdownParser<'a> {
                 let items = Self::parse_list(node, list.marker_offset as u8 / 2)?;
                 MarkdownElement::List(items)
             }
-            NodeValue::Table(_) => Self::parse_table(node)?,
+            NodeValue::Table(alignments) => Self::parse_table(node, alignments)?,
             NodeValue::CodeBlock(block) => Self::parse_code_block(block, data.sourcepos)?,
-            NodeValue::ThematicBreak => MarkdownElement::ThematicBreak,
             NodeValue::HtmlBlock(block) => Self::parse_html_block(block, data.sourcepos)?,
             NodeValue::BlockQuote => Self::parse_block_quote(node)?,
             other => return Err(ParseErrorKind::UnsupportedElement(other.identifier()).with_sourcepos(data.sourcepos)),
@@ -107,6 +176,36 @@ impl<'a> MarkdownParser<'a> {
         Ok(vec![element])
     }
 
+    fn parse_thematic_break(contents: &str, sourcepos: Sourcepos, lines_offset: usize) -> MarkdownElement {
+        let line = contents.lines().nth(sourcepos.start.line.saturating_sub(1) + lines_offset).unwrap_or_default();
+        let marker = match line.trim_start().chars().next() {
+            Some('*') => ThematicBreakMarker::Asterisks,
+            Some('_') => ThematicBreakMarker::Underscores,
+            _ => ThematicBreakMarker::Dashes,
+        };
+        MarkdownElement::ThematicBreak(marker)
+    }
+
+    /// Parse a paragraph that only contains a `$...$` or `$$...$$` formula as a LaTeX code block to be rendered.
+    fn parse_formula(contents: &str, sourcepos: Sourcepos, lines_offset: usize) -> Option<MarkdownElement> {
+        let start = sourcepos.start.line.saturating_sub(1) + lines_offset;
+        let line_count = sourcepos.end.line.saturating_sub(sourcepos.start.line) + 1;
+        let source = contents.lines().skip(start).take(line_count).collect::<Vec<_>>().join("\n");
+        let source = source.trim();
+        let formula = match source.strip_prefix("$$").and_then(|source| source.strip_suffix("$$")) {
+            Some(formula) => formula,
+            None => source.strip_prefix('$').and_then(|source| source.strip_suffix('$'))?,
+        };
+        // e.g. `$x$ and $y$` is text with two formulas in it rather than a formula.
+        let formula = formula.trim();
+        if formula.is_empty() || formula.contains('$') {
+            return None;
+        }
+        let flags = CodeFlags { render: true, ..Default::default() };
+        let code = Code { contents: format!("{formula}\n"), language: CodeLanguage::Latex, flags };
+        Some(MarkdownElement::Code(Box::new(code)))
+    }
+
     fn parse_front_matter(contents: &str) -> ParseResult<MarkdownElement> {
         // Remote leading and trailing delimiters before parsing. This is quite poopy but hey, it
         // works.
@@ -144,75 +243,167 @@ impl<'a> MarkdownParser<'a> {
             };
             lines.push(line.to_string());
         }
-        Ok(MarkdownElement::BlockQuote(lines))
+        let attribution = Self::take_attribution(&mut lines);
+        Ok(MarkdownElement::BlockQuote { lines, attribution })
+    }
+
+    /// Take the attribution in a quote out of it, if its last line looks like `— Author` or `-- Author`.
+    fn take_attribution(lines: &mut Vec<String>) -> Option<String> {
+        let last = lines.last()?;
+        // Formatting the quote back into markdown escapes hyphens at the start of a line.
+        let author = ["—", "―", "–", "--", "\\--"].iter().find_map(|marker| last.strip_prefix(marker))?.trim();
+        if author.is_empty() {
+            return None;
+        }
+        let author = author.to_string();
+        lines.pop();
+        while lines.last().is_some_and(|line| line.is_empty()) {
+            lines.pop();
+        }
+        Some(author)
     }
 
     fn parse_code_block(block: &NodeCodeBlock, sourcepos: Sourcepos) -> ParseResult<MarkdownElement> {
         if !block.fenced {
             return Err(ParseErrorKind::UnfencedCodeBlock.with_sourcepos(sourcepos));
         }
-        use CodeLanguage::*;
-        let info = block.info.as_str();
-        let mut tokens = info.split(' ');
-        let language = match tokens.next().unwrap_or("") {
-            "ada" => Ada,
-            "asp" => Asp,
-            "awk" => Awk,
-            "c" => C,
-            "cmake" => CMake,
-            "crontab" => Crontab,
-            "csharp" => CSharp,
-            "clojure" => Clojure,
-            "cpp" | "c++" => Cpp,
-            "css" => Css,
-            "d" => DLang,
-            "docker" => Docker,
-            "dotenv" => Dotenv,
-            "elixir" => Elixir,
-            "elm" => Elm,
-            "erlang" => Erlang,
-            "go" => Go,
-            "haskell" => Haskell,
-            "html" => Html,
-            "java" => Java,
-            "javascript" | "js" => JavaScript,
-            "json" => Json,
-            "kotlin" => Kotlin,
-            "latex" => Latex,
-            "lua" => Lua,
-            "make" => Makefile,
-            "markdown" => Markdown,
-            "ocaml" => OCaml,
-            "perl" => Perl,
-            "php" => Php,
-            "protobuf" => Protobuf,
-            "puppet" => Puppet,
-            "python" => Python,
-            "r" => R,
-            "rust" => Rust,
-            "scala" => Scala,
-            "shell" => Shell("sh".into()),
-            interpreter @ ("bash" | "sh" | "zsh" | "fish") => Shell(interpreter.into()),
-            "sql" => Sql,
-            "svelte" => Svelte,
-            "swift" => Swift,
-            "terraform" => Terraform,
-            "typescript" | "ts" => TypeScript,
-            "xml" => Xml,
-            "yaml" => Yaml,
-            "vue" => Vue,
-            "zig" => Zig,
-            _ => Unknown,
+        let tokens = Self::split_code_info(&block.info).map_err(|e| e.with_sourcepos(sourcepos))?;
+        let mut tokens = tokens.iter().map(String::as_str);
+        let name = tokens.next().unwrap_or("");
+        // e.g. `diff+rust` is a diff of rust code.
+        let (name, diff) = match name.strip_prefix("diff+") {
+            Some(name) => (name, true),
+            None => (name, false),
         };
-        let flags = CodeFlags { execute: tokens.any(|token| token == "+exec") };
+        let language = CodeLanguage::from_name(name);
+        let mut flags = Self::parse_code_flags(tokens).map_err(|e| e.with_sourcepos(sourcepos))?;
+        flags.diff = diff;
         let code = Code { contents: block.literal.clone(), language, flags };
-        Ok(MarkdownElement::Code(code))
+        Ok(MarkdownElement::Code(Box::new(code)))
+    }
+
+    /// Split a code block's info string into whitespace separated tokens.
+    ///
+    /// Double quotes can be used to include whitespace in a token, e.g. `env:GREETING="hello world"`.
+    fn split_code_info(info: &str) -> Result<Vec<String>, ParseErrorKind> {
+        let mut tokens = Vec::new();
+        let mut current: Option<String> = None;
+        let mut quoted = false;
+        for c in info.chars() {
+            match c {
+                '"' => {
+                    quoted = !quoted;
+                    current.get_or_insert_with(String::new);
+                }
+                c if c.is_whitespace() && !quoted => tokens.extend(current.take()),
+                c => current.get_or_insert_with(String::new).push(c),
+            }
+        }
+        if quoted {
+            return Err(ParseErrorKind::InvalidCodeAttribute("unterminated quote".into()));
+        }
+        tokens.extend(current);
+        Ok(tokens)
+    }
+
+    fn parse_code_flags<'b, I>(tokens: I) -> Result<CodeFlags, ParseErrorKind>
+    where
+        I: Iterator<Item = &'b str>,
+    {
+        let mut flags = CodeFlags::default();
+        for token in tokens {
+            if token == "+exec" {
+                flags.execute = true;
+            } else if token == "+exec_auto" {
+                flags.execute = true;
+                flags.auto_execute = true;
+            } else if token == "+exec_replace" {
+                flags.execute = true;
+                flags.execute_replace = true;
+            } else if token == "+acquire_terminal" {
+                flags.execute = true;
+                flags.acquire_terminal = true;
+            } else if let Some(lines) = token.strip_prefix('{').and_then(|token| token.strip_suffix('}')) {
+                for group in lines.split('|') {
+                    let group = LineGroup::parse(group).ok_or_else(|| {
+                        ParseErrorKind::InvalidCodeAttribute(format!("invalid highlighted lines: {token}"))
+                    })?;
+                    flags.highlight_groups.push(group);
+                }
+            } else if token == "+render" {
+                flags.render = true;
+            } else if token == "+raw" {
+                flags.raw = true;
+            } else if token == "+ellipsis" {
+                flags.elision_markers = true;
+            } else if token == "interactive" {
+                flags.interactive = true;
+            } else if let Some(value) = token.strip_prefix("timeout=") {
+                let seconds = value
+                    .parse()
+                    .map_err(|_| ParseErrorKind::InvalidCodeAttribute(format!("invalid timeout: {value}")))?;
+                flags.timeout = Some(Duration::from_secs(seconds));
+            } else if let Some(value) = token.strip_prefix("step=") {
+                let step = value
+                    .parse()
+                    .map_err(|_| ParseErrorKind::InvalidCodeAttribute(format!("invalid step: {value}")))?;
+                flags.step = Some(step);
+            } else if let Some(value) = token.strip_prefix("overflow=") {
+                let overflow = match value {
+                    "wrap" => OutputOverflow::Wrap,
+                    "truncate" => OutputOverflow::Truncate,
+                    _ => return Err(ParseErrorKind::InvalidCodeAttribute(format!("invalid overflow: {value}"))),
+                };
+                flags.overflow = Some(overflow);
+            } else if let Some(arguments) = token.strip_prefix("args=") {
+                flags.arguments.extend(arguments.split_whitespace().map(String::from));
+            } else if let Some(variable) = token.strip_prefix("env:") {
+                let (name, value) = variable.split_once('=').ok_or_else(|| {
+                    ParseErrorKind::InvalidCodeAttribute(format!("environment variable has no value: {variable}"))
+                })?;
+                flags.environment.push((name.to_string(), value.to_string()));
+            } else if let Some(path) = token.strip_prefix("cwd=") {
+                flags.working_directory = Some(path.into());
+            } else if let Some(path) = token.strip_prefix("file:") {
+                flags.external_file = Some(path.into());
+            } else if let Some(id) = token.strip_prefix("id=") {
+                flags.id = Some(id.into());
+            } else if let Some(id) = token.strip_prefix("continue=") {
+                flags.continues = Some(id.into());
+            } else if let Some(title) = token.strip_prefix("title=") {
+                flags.title = Some(title.into());
+            } else if let Some(value) = token.strip_prefix("lines=") {
+                let ranges = Self::parse_line_ranges(value)
+                    .ok_or_else(|| ParseErrorKind::InvalidCodeAttribute(format!("invalid line ranges: {value}")))?;
+                flags.line_ranges.extend(ranges);
+            }
+        }
+        Ok(flags)
+    }
+
+    /// Parse a comma separated list of line numbers and ranges of them, e.g. `3,10..35`.
+    fn parse_line_ranges(value: &str) -> Option<Vec<RangeInclusive<usize>>> {
+        let mut ranges = Vec::new();
+        for part in value.split(',') {
+            let range = match part.split_once("..") {
+                Some((start, end)) => start.parse().ok()?..=end.parse().ok()?,
+                None => {
+                    let line = part.parse().ok()?;
+                    line..=line
+                }
+            };
+            if range.is_empty() || *range.start() == 0 {
+                return None;
+            }
+            ranges.push(range);
+        }
+        Some(ranges)
     }
 
     fn parse_heading(heading: &NodeHeading, node: &'a AstNode<'a>) -> ParseResult<MarkdownElement> {
         let text = Self::parse_text(node)?;
         if heading.setext {
-            Ok(MarkdownElement::SetexHeading { text })
+            Ok(MarkdownElement::SetexHeading { text, level: heading.level })
         } else {
             Ok(MarkdownElement::Heading { text, level: heading.level })
         }
@@ -226,12 +417,13 @@ impl<'a> MarkdownParser<'a> {
             match inline {
                 Inline::Text(text) => paragraph_elements.push(ParagraphElement::Text(text)),
                 Inline::LineBreak => paragraph_elements.push(ParagraphElement::LineBreak),
-                Inline::Image(path) => {
+                Inline::Image { path, alt_text } => {
                     if !paragraph_elements.is_empty() {
                         elements.push(MarkdownElement::Paragraph(mem::take(&mut paragraph_elements)));
                     }
                     elements.push(MarkdownElement::Image {
                         path: path.into(),
+                        alt_text,
                         source_position: node.data.borrow().sourcepos.into(),
                     });
                 }
@@ -249,6 +441,8 @@ impl<'a> MarkdownParser<'a> {
         for inline in inlines {
             match inline {
                 Inline::Text(text) => chunks.extend(text.chunks),
+                // There's no way to break these so join the lines instead.
+                Inline::LineBreak => chunks.push(StyledText::from(" ")),
                 other => {
                     return Err(ParseErrorKind::UnsupportedStructure { container: "text", element: other.kind() }
                         .with_sourcepos(node.data.borrow().sourcepos));
@@ -259,12 +453,20 @@ impl<'a> MarkdownParser<'a> {
     }
 
     fn parse_list(root: &'a AstNode<'a>, depth: u8) -> ParseResult<Vec<ListItem>> {
+        // Task list items don't carry the list's type so take it from the list itself.
+        let list = match &root.data.borrow().value {
+            NodeValue::List(list) => *list,
+            _ => NodeList::default(),
+        };
         let mut elements = Vec::new();
         for node in root.children() {
             let data = node.data.borrow();
             match &data.value {
                 NodeValue::Item(item) => {
-                    elements.extend(Self::parse_list_item(item, node, depth)?);
+                    elements.extend(Self::parse_list_item(item, node, depth, None)?);
+                }
+                NodeValue::TaskItem(symbol) => {
+                    elements.extend(Self::parse_list_item(&list, node, depth, Some(symbol.is_some()))?);
                 }
                 other => {
                     return Err(ParseErrorKind::UnsupportedStructure {
@@ -278,7 +480,12 @@ impl<'a> MarkdownParser<'a> {
         Ok(elements)
     }
 
-    fn parse_list_item(item: &NodeList, root: &'a AstNode<'a>, depth: u8) -> ParseResult<Vec<ListItem>> {
+    fn parse_list_item(
+        item: &NodeList,
+        root: &'a AstNode<'a>,
+        depth: u8,
+        mut checked: Option<bool>,
+    ) -> ParseResult<Vec<ListItem>> {
         let item_type = match (item.list_type, item.delimiter) {
             (ListType::Bullet, _) => ListItemType::Unordered,
             (ListType::Ordered, ListDelimType::Paren) => ListItemType::OrderedParens,
@@ -290,7 +497,8 @@ impl<'a> MarkdownParser<'a> {
             match &data.value {
                 NodeValue::Paragraph => {
                     let contents = Self::parse_text(node)?;
-                    elements.push(ListItem { contents, depth, item_type: item_type.clone() });
+                    // Only the first paragraph in a task list item has a checkbox.
+                    elements.push(ListItem { contents, depth, item_type: item_type.clone(), checked: checked.take() });
                 }
                 NodeValue::List(_) => {
                     elements.extend(Self::parse_list(node, depth + 1)?);
@@ -307,7 +515,7 @@ impl<'a> MarkdownParser<'a> {
         Ok(elements)
     }
 
-    fn parse_table(node: &'a AstNode<'a>) -> ParseResult<MarkdownElement> {
+    fn parse_table(node: &'a AstNode<'a>, alignments: &[TableAlignment]) -> ParseResult<MarkdownElement> {
         let mut header = TableRow(Vec::new());
         let mut rows = Vec::new();
         for node in node.children() {
@@ -326,7 +534,15 @@ impl<'a> MarkdownParser<'a> {
                 rows.push(row)
             }
         }
-        Ok(MarkdownElement::Table(Table { header, rows }))
+        let alignments = alignments
+            .iter()
+            .map(|alignment| match alignment {
+                TableAlignment::None | TableAlignment::Left => ColumnAlignment::Left,
+                TableAlignment::Center => ColumnAlignment::Center,
+                TableAlignment::Right => ColumnAlignment::Right,
+            })
+            .collect();
+        Ok(MarkdownElement::Table(Table { header, rows, alignments }))
     }
 
     fn parse_table_row(node: &'a AstNode<'a>) -> ParseResult<TableRow> {
@@ -351,6 +567,7 @@ impl<'a> MarkdownParser<'a> {
 struct InlinesParser {
     inlines: Vec<Inline>,
     pending_text: Vec<StyledText>,
+    html_tags: Vec<(String, TextStyle)>,
 }
 
 impl InlinesParser {
@@ -371,7 +588,31 @@ impl InlinesParser {
         let data = node.data.borrow();
         match &data.value {
             NodeValue::Text(text) => {
-                self.pending_text.push(StyledText::new(text.clone(), style.clone()));
+                let mut style = style.clone();
+                // Innermost tags take precedence when merging.
+                for (_, html_style) in self.html_tags.iter().rev() {
+                    style.merge(html_style);
+                }
+                self.pending_text.push(StyledText::new(text.clone(), style));
+            }
+            NodeValue::HtmlInline(html) => {
+                // Tags we can't display are dropped, leaving only the text inside them.
+                let Some(html) = parse_html_inline(html) else {
+                    return Ok(());
+                };
+                match html {
+                    HtmlInline::LineBreak => {
+                        self.store_pending_text();
+                        self.inlines.push(Inline::LineBreak);
+                    }
+                    HtmlInline::OpenTag { name, style } => self.html_tags.push((name, style)),
+                    HtmlInline::CloseTag { name } => {
+                        // Closing a tag that was never opened is harmless so it's simply ignored.
+                        if let Some(index) = self.html_tags.iter().rposition(|(tag, _)| *tag == name) {
+                            self.html_tags.truncate(index);
+                        }
+                    }
+                }
             }
             NodeValue::Code(code) => {
                 self.pending_text.push(StyledText::new(code.literal.clone(), TextStyle::default().code()));
@@ -381,7 +622,11 @@ impl InlinesParser {
             NodeValue::Strikethrough => self.process_children(node, style.clone().strikethrough())?,
             NodeValue::SoftBreak => self.pending_text.push(StyledText::from(" ")),
             NodeValue::Link(link) => {
-                self.pending_text.push(StyledText::new(link.url.clone(), TextStyle::default().link()))
+                let start = self.pending_text.len();
+                self.process_children(node, style.clone().link())?;
+                for text in &mut self.pending_text[start..] {
+                    text.link = Some(link.url.clone());
+                }
             }
             NodeValue::LineBreak => {
                 self.store_pending_text();
@@ -389,7 +634,18 @@ impl InlinesParser {
             }
             NodeValue::Image(link) => {
                 self.store_pending_text();
-                self.inlines.push(Inline::Image(link.url.clone()));
+                // The alt text is whatever text is inside the image, ignoring its styling.
+                let alt_text = InlinesParser::default()
+                    .parse(node)?
+                    .into_iter()
+                    .filter_map(|inline| match inline {
+                        Inline::Text(text) => Some(text.chunks),
+                        _ => None,
+                    })
+                    .flatten()
+                    .map(|chunk| chunk.text)
+                    .collect();
+                self.inlines.push(Inline::Image { path: link.url.clone(), alt_text });
             }
             other => {
                 return Err(ParseErrorKind::UnsupportedStructure { container: "text", element: other.identifier() }
@@ -407,9 +663,21 @@ impl InlinesParser {
     }
 }
 
+/// The way single line breaks within a paragraph are handled.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LineBreaks {
+    /// Join the lines, like markdown usually does.
+    #[default]
+    Join,
+
+    /// Keep every line in its own line.
+    Preserve,
+}
+
 enum Inline {
     Text(Text),
-    Image(String),
+    Image { path: String, alt_text: String },
     LineBreak,
 }
 
@@ -417,7 +685,7 @@ impl Inline {
     fn kind(&self) -> &'static str {
         match self {
             Self::Text(_) => "text",
-            Self::Image(_) => "image",
+            Self::Image { .. } => "image",
             Self::LineBreak => "line break",
         }
     }
@@ -457,6 +725,9 @@ pub(crate) enum ParseErrorKind {
     /// We don't support unfenced code blocks.
     UnfencedCodeBlock,
 
+    /// A code block contains an invalid attribute.
+    InvalidCodeAttribute(String),
+
     /// An internal parsing error.
     Internal(String),
 }
@@ -469,6 +740,7 @@ impl Display for ParseErrorKind {
                 write!(f, "unsupported structure in {container}: {element}")
             }
             Self::UnfencedCodeBlock => write!(f, "only fenced code blocks are supported"),
+            Self::InvalidCodeAttribute(message) => write!(f, "invalid code attribute: {message}"),
             Self::Internal(message) => write!(f, "internal error: {message}"),
         }
     }
@@ -525,6 +797,8 @@ impl Identifier for NodeValue {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::style::{Color, Colors};
+    use rstest::rstest;
     use std::path::Path;
 
     fn parse_single(input: &str) -> MarkdownElement {
@@ -575,22 +849,174 @@ boop
         assert_eq!(elements, expected_elements);
     }
 
+    #[test]
+    fn double_tilde_strikethrough() {
+        let parsed = parse_single("~~gone~~");
+        let MarkdownElement::Paragraph(elements) = parsed else { panic!("not a paragraph: {parsed:?}") };
+        let expected_chunks = vec![StyledText::new("gone", TextStyle::default().strikethrough())];
+
+        let expected_elements = &[ParagraphElement::Text(Text { chunks: expected_chunks })];
+        assert_eq!(elements, expected_elements);
+    }
+
+    #[test]
+    fn emoji_shortcodes() {
+        let parsed = parse_single("launch :rocket: but not `:tada:`");
+        let MarkdownElement::Paragraph(elements) = parsed else { panic!("not a paragraph: {parsed:?}") };
+        let expected_chunks =
+            vec![StyledText::from("launch 🚀 but not "), StyledText::new(":tada:", TextStyle::default().code())];
+
+        let expected_elements = &[ParagraphElement::Text(Text { chunks: expected_chunks })];
+        assert_eq!(elements, expected_elements);
+    }
+
+    #[test]
+    fn emoji_shortcodes_disabled() {
+        let arena = Arena::new();
+        let parser = MarkdownParser::new(&arena).with_emoji_shortcodes(false);
+        let parsed = parser.parse("launch :rocket:").expect("parsing failed");
+        let MarkdownElement::Paragraph(elements) = &parsed[0] else { panic!("not a paragraph: {parsed:?}") };
+        let expected_elements = &[ParagraphElement::Text(Text { chunks: vec![StyledText::from("launch :rocket:")] })];
+        assert_eq!(elements, expected_elements);
+    }
+
     #[test]
     fn link() {
         let parsed = parse_single("my [website](https://example.com)");
         let MarkdownElement::Paragraph(elements) = parsed else { panic!("not a paragraph: {parsed:?}") };
-        let expected_chunks =
-            vec![StyledText::from("my "), StyledText::new("https://example.com", TextStyle::default().link())];
+        let expected_chunks = vec![
+            StyledText::from("my "),
+            StyledText {
+                link: Some("https://example.com".into()),
+                ..StyledText::new("website", TextStyle::default().link())
+            },
+        ];
 
         let expected_elements = &[ParagraphElement::Text(Text { chunks: expected_chunks })];
         assert_eq!(elements, expected_elements);
     }
 
+    #[test]
+    fn internal_link() {
+        let parsed = parse_single("see [the **architecture**](#architecture-overview)");
+        let MarkdownElement::Paragraph(elements) = parsed else { panic!("not a paragraph: {parsed:?}") };
+        let link = |text: &str, style: TextStyle| StyledText {
+            link: Some("#architecture-overview".into()),
+            ..StyledText::new(text, style)
+        };
+        let expected_chunks = vec![
+            StyledText::from("see "),
+            link("the ", TextStyle::default().link()),
+            link("architecture", TextStyle::default().link().bold()),
+        ];
+
+        let expected_elements = &[ParagraphElement::Text(Text { chunks: expected_chunks })];
+        assert_eq!(elements, expected_elements);
+        let ParagraphElement::Text(text) = &elements[0] else { panic!("not text") };
+        assert_eq!(text.chunks[1].anchor(), Some("architecture-overview"));
+    }
+
+    #[test]
+    fn smart_punctuation() {
+        let arena = Arena::new();
+        let parser = MarkdownParser::new(&arena).with_smart_punctuation(true);
+        let parsed = parser.parse(r#"it's "quoted" -- or --- not... `"code" -- ...`"#).expect("parsing failed");
+        let [MarkdownElement::Paragraph(elements)] = parsed.as_slice() else { panic!("not a paragraph: {parsed:?}") };
+        let [ParagraphElement::Text(text)] = elements.as_slice() else { panic!("not text: {elements:?}") };
+        let text: String = text.chunks.iter().map(|chunk| chunk.text.as_str()).collect();
+        assert_eq!(text, "it’s “quoted” – or — not… \"code\" -- ...");
+    }
+
+    #[test]
+    fn smart_punctuation_attribution() {
+        let arena = Arena::new();
+        let parser = MarkdownParser::new(&arena).with_smart_punctuation(true);
+        let parsed = parser.parse("> stay hungry\n>\n> -- Steve Jobs").expect("parsing failed");
+        let [MarkdownElement::BlockQuote { attribution, .. }] = parsed.as_slice() else {
+            panic!("not a block quote: {parsed:?}")
+        };
+        assert_eq!(attribution.as_deref(), Some("Steve Jobs"));
+    }
+
+    #[test]
+    fn inline_html() {
+        let parsed = parse_single(r#"hi <b>bold <i>both</i></b><br>then <span style="color: #ff0000">red</span>"#);
+        let MarkdownElement::Paragraph(elements) = parsed else { panic!("not a paragraph: {parsed:?}") };
+        let red = Colors { foreground: Some(Color::new(255, 0, 0)), background: None };
+        let expected_elements = &[
+            ParagraphElement::Text(Text {
+                chunks: vec![
+                    StyledText::from("hi "),
+                    StyledText::new("bold ", TextStyle::default().bold()),
+                    StyledText::new("both", TextStyle::default().italics().bold()),
+                ],
+            }),
+            ParagraphElement::LineBreak,
+            ParagraphElement::Text(Text {
+                chunks: vec![StyledText::from("then "), StyledText::new("red", TextStyle::default().colors(red))],
+            }),
+        ];
+        assert_eq!(elements, expected_elements);
+    }
+
+    #[test]
+    fn unsupported_inline_html() {
+        let parsed = parse_single("hi <div>there</div> <!-- note -->friend");
+        let MarkdownElement::Paragraph(elements) = parsed else { panic!("not a paragraph: {parsed:?}") };
+        let [ParagraphElement::Text(text)] = elements.as_slice() else { panic!("unexpected elements: {elements:?}") };
+        let text: String = text.chunks.iter().map(|chunk| chunk.text.as_str()).collect();
+        assert_eq!(text, "hi there friend");
+    }
+
+    #[test]
+    fn invalid_inline_html() {
+        let parsed = parse_single("hi <span style='color: potato'>there</span>");
+        let MarkdownElement::Paragraph(elements) = parsed else { panic!("not a paragraph: {parsed:?}") };
+        let expected = vec![ParagraphElement::Text(Text {
+            chunks: vec![StyledText::from("hi "), StyledText::new("there", TextStyle::default())],
+        })];
+        assert_eq!(elements, expected);
+    }
+
+    #[rstest]
+    #[case::join(LineBreaks::Join, &["roses are red", "violets are blue sugar is sweet"])]
+    #[case::preserve(LineBreaks::Preserve, &["roses are red", "violets are blue", "sugar is sweet"])]
+    fn line_breaks_mode(#[case] line_breaks: LineBreaks, #[case] expected: &[&str]) {
+        let arena = Arena::new();
+        let parser = MarkdownParser::new(&arena).with_line_breaks(line_breaks);
+        let parsed = parser.parse("roses are red\\\nviolets are blue\nsugar is sweet").expect("parsing failed");
+        let [MarkdownElement::Paragraph(elements)] = parsed.as_slice() else { panic!("not a paragraph: {parsed:?}") };
+        let lines: Vec<String> = elements
+            .iter()
+            .filter_map(|element| match element {
+                ParagraphElement::Text(text) => Some(text.chunks.iter().map(|chunk| chunk.text.as_str()).collect()),
+                ParagraphElement::LineBreak => None,
+            })
+            .collect();
+        assert_eq!(lines, expected);
+    }
+
+    #[test]
+    fn hard_break_in_list_item() {
+        let parsed = parse_single("* roses are red\\\n  violets are blue");
+        let MarkdownElement::List(items) = parsed else { panic!("not a list: {parsed:?}") };
+        let text: String = items[0].contents.chunks.iter().map(|chunk| chunk.text.as_str()).collect();
+        assert_eq!(text, "roses are red violets are blue");
+    }
+
     #[test]
     fn image() {
         let parsed = parse_single("![](potato.png)");
-        let MarkdownElement::Image { path, .. } = parsed else { panic!("not an image: {parsed:?}") };
+        let MarkdownElement::Image { path, alt_text, .. } = parsed else { panic!("not an image: {parsed:?}") };
         assert_eq!(path, Path::new("potato.png"));
+        assert_eq!(alt_text, "");
+    }
+
+    #[test]
+    fn image_alt_text() {
+        let parsed = parse_single("![a *big* potato](potato.png)");
+        let MarkdownElement::Image { alt_text, .. } = parsed else { panic!("not an image: {parsed:?}") };
+        assert_eq!(alt_text, "a big potato");
     }
 
     #[test]
@@ -611,7 +1037,8 @@ Title
 ===
 ",
         );
-        let MarkdownElement::SetexHeading { text } = parsed else { panic!("not a slide title: {parsed:?}") };
+        let MarkdownElement::SetexHeading { text, level } = parsed else { panic!("not a slide title: {parsed:?}") };
+        assert_eq!(level, 1);
         let expected_chunks = [StyledText::from("Title")];
         assert_eq!(text.chunks, expected_chunks);
     }
@@ -647,6 +1074,21 @@ Title
         assert_eq!(next().depth, 0);
     }
 
+    #[test]
+    fn task_list() {
+        let parsed = parse_single(
+            r"
+ - [ ] One
+ - [x] Two
+ - Three",
+        );
+        let MarkdownElement::List(items) = parsed else { panic!("not a list: {parsed:?}") };
+        let checked: Vec<_> = items.iter().map(|item| item.checked).collect();
+        assert_eq!(checked, &[Some(false), Some(true), None]);
+        assert_eq!(items[0].item_type, ListItemType::Unordered);
+        assert_eq!(items[0].contents, Text::from("One"));
+    }
+
     #[test]
     fn line_breaks() {
         let parsed = parse_all(
@@ -700,6 +1142,294 @@ echo hi mom
         assert!(code.flags.execute);
     }
 
+    #[test]
+    fn code_block_timeout() {
+        let parsed = parse_single(
+            r"
+```bash +exec timeout=10
+sleep 100
+````
+",
+        );
+        let MarkdownElement::Code(code) = parsed else { panic!("not a code block: {parsed:?}") };
+        assert!(code.flags.execute);
+        assert_eq!(code.flags.timeout, Some(Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn interactive_code_block() {
+        let parsed = parse_single(
+            r"
+```bash +exec interactive
+read name
+````
+",
+        );
+        let MarkdownElement::Code(code) = parsed else { panic!("not a code block: {parsed:?}") };
+        assert!(code.flags.execute);
+        assert!(code.flags.interactive);
+    }
+
+    #[test]
+    fn code_block_environment() {
+        let parsed = parse_single(
+            r#"
+```bash +exec env:FOO=bar env:GREETING="hello world" env:EMPTY=
+echo $FOO
+````
+"#,
+        );
+        let MarkdownElement::Code(code) = parsed else { panic!("not a code block: {parsed:?}") };
+        let expected = [("FOO", "bar"), ("GREETING", "hello world"), ("EMPTY", "")]
+            .map(|(name, value)| (name.to_string(), value.to_string()));
+        assert_eq!(code.flags.environment, expected);
+    }
+
+    #[test]
+    fn code_block_acquire_terminal() {
+        let parsed = parse_single(
+            r"
+```bash +acquire_terminal
+vim
+````
+",
+        );
+        let MarkdownElement::Code(code) = parsed else { panic!("not a code block: {parsed:?}") };
+        assert!(code.flags.execute);
+        assert!(code.flags.acquire_terminal);
+    }
+
+    #[rstest]
+    #[case::valid("step=2", Some(2))]
+    #[case::none("", None)]
+    fn code_block_step(#[case] attribute: &str, #[case] expected: Option<u32>) {
+        let parsed = parse_single(&format!("```bash +exec {attribute}\necho hi\n```"));
+        let MarkdownElement::Code(code) = parsed else { panic!("not a code block: {parsed:?}") };
+        assert_eq!(code.flags.step, expected);
+    }
+
+    #[rstest]
+    #[case::single("{3}", vec![vec![3]])]
+    #[case::ranges("{1,4-6}", vec![vec![1, 4, 5, 6]])]
+    #[case::groups("{1-2|5|7-8,10}", vec![vec![1, 2], vec![5], vec![7, 8, 10]])]
+    fn code_block_highlighted_lines(#[case] attribute: &str, #[case] expected: Vec<Vec<usize>>) {
+        let parsed = parse_single(&format!("```rust {attribute}\nlet a = 1;\n```"));
+        let MarkdownElement::Code(code) = parsed else { panic!("not a code block: {parsed:?}") };
+        let groups: Vec<Vec<_>> = code
+            .flags
+            .highlight_groups
+            .iter()
+            .map(|group| (1..=10).filter(|line| group.contains(*line)).collect())
+            .collect();
+        assert_eq!(groups, expected);
+    }
+
+    #[rstest]
+    #[case::empty("{}")]
+    #[case::zero("{0}")]
+    #[case::backwards("{5-2}")]
+    #[case::garbage("{potato}")]
+    #[case::empty_group("{1||2}")]
+    fn code_block_invalid_highlighted_lines(#[case] attribute: &str) {
+        let arena = Arena::new();
+        let result = MarkdownParser::new(&arena).parse(&format!("```rust {attribute}\nlet a = 1;\n```"));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn code_block_arguments() {
+        let parsed = parse_single(
+            r#"
+```bash +exec args="-x -e"
+echo hi
+````
+"#,
+        );
+        let MarkdownElement::Code(code) = parsed else { panic!("not a code block: {parsed:?}") };
+        assert_eq!(code.flags.arguments, &["-x", "-e"]);
+    }
+
+    #[rstest]
+    #[case::wrap("wrap", OutputOverflow::Wrap)]
+    #[case::truncate("truncate", OutputOverflow::Truncate)]
+    fn code_block_overflow(#[case] value: &str, #[case] expected: OutputOverflow) {
+        let parsed = parse_single(&format!("```rust overflow={value}\nlet a = 1;\n```"));
+        let MarkdownElement::Code(code) = parsed else { panic!("not a code block: {parsed:?}") };
+        assert_eq!(code.flags.overflow, Some(expected));
+    }
+
+    #[test]
+    fn code_block_invalid_overflow() {
+        let arena = Arena::new();
+        let result = MarkdownParser::new(&arena).parse("```rust overflow=potato\nlet a = 1;\n```");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn code_block_title() {
+        let parsed = parse_single("```rust title=\"src/main.rs\"\nfn main() {}\n```");
+        let MarkdownElement::Code(code) = parsed else { panic!("not a code block: {parsed:?}") };
+        assert_eq!(code.flags.title.as_deref(), Some("src/main.rs"));
+    }
+
+    #[rstest]
+    #[case::single("7", vec![7..=7])]
+    #[case::range("10..35", vec![10..=35])]
+    #[case::multiple("1..3,7,10..12", vec![1..=3, 7..=7, 10..=12])]
+    fn code_block_line_ranges(#[case] value: &str, #[case] expected: Vec<RangeInclusive<usize>>) {
+        let parsed = parse_single(&format!("```rust file:main.rs lines={value}\n```"));
+        let MarkdownElement::Code(code) = parsed else { panic!("not a code block: {parsed:?}") };
+        assert_eq!(code.flags.line_ranges, expected);
+    }
+
+    #[rstest]
+    #[case::empty("")]
+    #[case::zero("0..3")]
+    #[case::reversed("5..3")]
+    #[case::open_ended("5..")]
+    fn code_block_invalid_line_ranges(#[case] value: &str) {
+        let arena = Arena::new();
+        let result = MarkdownParser::new(&arena).parse(&format!("```rust lines={value}\nlet a = 1;\n```"));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn code_block_render() {
+        let parsed = parse_single("```latex +render\n\\frac{a}{b}\n```");
+        let MarkdownElement::Code(code) = parsed else { panic!("not a code block: {parsed:?}") };
+        assert_eq!(code.language, CodeLanguage::Latex);
+        assert!(code.flags.render);
+    }
+
+    #[rstest]
+    #[case::inline("$\\frac{a_1}{b}$")]
+    #[case::display("$$\\frac{a_1}{b}$$")]
+    #[case::multiline("$$\n\\frac{a_1}{b}\n$$")]
+    fn formula(#[case] input: &str) {
+        let parsed = parse_single(input);
+        let MarkdownElement::Code(code) = parsed else { panic!("not a code block: {parsed:?}") };
+        assert_eq!(code.language, CodeLanguage::Latex);
+        assert_eq!(code.contents, "\\frac{a_1}{b}\n");
+        assert!(code.flags.render);
+    }
+
+    #[rstest]
+    #[case::text_around("the formula $x^2$")]
+    #[case::many_formulas("$x$ and $y$")]
+    #[case::empty("$$ $$")]
+    fn not_formula(#[case] input: &str) {
+        let parsed = parse_single(input);
+        assert!(matches!(parsed, MarkdownElement::Paragraph(_)), "not a paragraph: {parsed:?}");
+    }
+
+    #[test]
+    fn code_block_raw() {
+        let parsed = parse_single("```text +raw\n\n  +--+  \n  |  |\n```");
+        let MarkdownElement::Code(code) = parsed else { panic!("not a code block: {parsed:?}") };
+        assert!(code.flags.raw);
+        assert_eq!(code.contents, "\n  +--+  \n  |  |\n");
+    }
+
+    #[test]
+    fn code_block_diff() {
+        let parsed = parse_single("```diff+rust\n+let a = 1;\n```");
+        let MarkdownElement::Code(code) = parsed else { panic!("not a code block: {parsed:?}") };
+        assert_eq!(code.language, CodeLanguage::Rust);
+        assert!(code.flags.diff);
+    }
+
+    #[test]
+    fn code_block_working_directory() {
+        let parsed = parse_single(
+            r#"
+```bash +exec cwd="my scripts"
+ls
+````
+"#,
+        );
+        let MarkdownElement::Code(code) = parsed else { panic!("not a code block: {parsed:?}") };
+        assert_eq!(code.flags.working_directory, Some("my scripts".into()));
+    }
+
+    #[test]
+    fn code_block_external_file() {
+        let parsed = parse_single(
+            r#"
+```bash +exec file:scripts/hello.sh
+````
+"#,
+        );
+        let MarkdownElement::Code(code) = parsed else { panic!("not a code block: {parsed:?}") };
+        assert_eq!(code.flags.external_file, Some("scripts/hello.sh".into()));
+        assert!(code.flags.execute);
+    }
+
+    #[test]
+    fn code_block_execute_replace() {
+        let parsed = parse_single(
+            r#"
+```bash +exec_replace
+date
+````
+"#,
+        );
+        let MarkdownElement::Code(code) = parsed else { panic!("not a code block: {parsed:?}") };
+        assert!(code.flags.execute);
+        assert!(code.flags.execute_replace);
+    }
+
+    #[test]
+    fn code_block_auto_execute() {
+        let parsed = parse_single(
+            r#"
+```bash +exec_auto
+date
+````
+"#,
+        );
+        let MarkdownElement::Code(code) = parsed else { panic!("not a code block: {parsed:?}") };
+        assert!(code.flags.execute);
+        assert!(code.flags.auto_execute);
+    }
+
+    #[test]
+    fn code_block_chaining() {
+        let parsed = parse_single(
+            r#"
+```python +exec id=second continue=first
+print(x)
+````
+"#,
+        );
+        let MarkdownElement::Code(code) = parsed else { panic!("not a code block: {parsed:?}") };
+        assert_eq!(code.flags.id.as_deref(), Some("second"));
+        assert_eq!(code.flags.continues.as_deref(), Some("first"));
+    }
+
+    #[rstest]
+    #[case::plain("rust +exec", &["rust", "+exec"])]
+    #[case::extra_whitespace("  rust   +exec ", &["rust", "+exec"])]
+    #[case::quoted(r#"bash env:A="b c" d"#, &["bash", "env:A=b c", "d"])]
+    #[case::empty_quotes(r#"bash """#, &["bash", ""])]
+    fn split_code_info(#[case] input: &str, #[case] expected: &[&str]) {
+        let tokens = MarkdownParser::split_code_info(input).expect("split failed");
+        assert_eq!(tokens, expected);
+    }
+
+    #[test]
+    fn code_block_unterminated_quote() {
+        let arena = Arena::new();
+        let result = MarkdownParser::new(&arena).parse("```bash +exec env:A=\"b\necho hi\n```");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn code_block_invalid_timeout() {
+        let arena = Arena::new();
+        let result = MarkdownParser::new(&arena).parse("```bash +exec timeout=potato\nsleep 100\n```");
+        assert!(result.is_err());
+    }
+
     #[test]
     fn inline_code() {
         let parsed = parse_single("some `inline code`");
@@ -715,17 +1445,20 @@ echo hi mom
     fn table() {
         let parsed = parse_single(
             r"
-| Name | Taste |
-| ------ | ------ |
-| Potato | Great |
-| Carrot | Yuck |
+| Name | Taste | Price |
+| ------ | :----: | -----: |
+| Potato | Great | 1 |
+| Carrot | Yuck | 2 |
 ",
         );
-        let MarkdownElement::Table(Table { header, rows }) = parsed else { panic!("not a table: {parsed:?}") };
-        assert_eq!(header.0.len(), 2);
+        let MarkdownElement::Table(Table { header, rows, alignments }) = parsed else {
+            panic!("not a table: {parsed:?}")
+        };
+        assert_eq!(header.0.len(), 3);
         assert_eq!(rows.len(), 2);
-        assert_eq!(rows[0].0.len(), 2);
-        assert_eq!(rows[1].0.len(), 2);
+        assert_eq!(rows[0].0.len(), 3);
+        assert_eq!(rows[1].0.len(), 3);
+        assert_eq!(alignments, &[ColumnAlignment::Left, ColumnAlignment::Center, ColumnAlignment::Right]);
     }
 
     #[test]
@@ -764,7 +1497,10 @@ echo hi mom
 > * b
 ",
         );
-        let MarkdownElement::BlockQuote(lines) = parsed else { panic!("not a block quote: {parsed:?}") };
+        let MarkdownElement::BlockQuote { lines, attribution } = parsed else {
+            panic!("not a block quote: {parsed:?}")
+        };
+        assert_eq!(attribution, None);
         assert_eq!(lines.len(), 5);
         assert_eq!(lines[0], "bar");
         assert_eq!(lines[1], "foo");
@@ -773,6 +1509,19 @@ echo hi mom
         assert_eq!(lines[4], "* b");
     }
 
+    #[rstest]
+    #[case::em_dash("> stay hungry\n> — Steve Jobs")]
+    #[case::double_hyphen("> stay hungry\n> -- Steve Jobs")]
+    #[case::separate_paragraph("> stay hungry\n>\n> — Steve Jobs")]
+    fn block_quote_attribution(#[case] input: &str) {
+        let parsed = parse_single(input);
+        let MarkdownElement::BlockQuote { lines, attribution } = parsed else {
+            panic!("not a block quote: {parsed:?}")
+        };
+        assert_eq!(lines, &["stay hungry"]);
+        assert_eq!(attribution.as_deref(), Some("Steve Jobs"));
+    }
+
     #[test]
     fn thematic_break() {
         let parsed = parse_all(
@@ -785,7 +1534,17 @@ bye
 ",
         );
         assert_eq!(parsed.len(), 3);
-        assert!(matches!(parsed[1], MarkdownElement::ThematicBreak));
+        assert!(matches!(parsed[1], MarkdownElement::ThematicBreak(ThematicBreakMarker::Dashes)));
+    }
+
+    #[rstest]
+    #[case::dashes("- - -", ThematicBreakMarker::Dashes)]
+    #[case::asterisks("***", ThematicBreakMarker::Asterisks)]
+    #[case::underscores("  ____", ThematicBreakMarker::Underscores)]
+    fn thematic_break_markers(#[case] input: &str, #[case] expected: ThematicBreakMarker) {
+        let parsed = parse_all(&format!("hi\n\n{input}\n\nbye"));
+        let MarkdownElement::ThematicBreak(marker) = &parsed[1] else { panic!("not a thematic break: {parsed:?}") };
+        assert_eq!(marker, &expected);
     }
 
     #[test]
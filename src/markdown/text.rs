@@ -2,6 +2,48 @@ use super::elements::StyledText;
 use crate::style::TextStyle;
 use unicode_width::UnicodeWidthChar;
 
+/// The number of columns a piece of text takes up when displayed in a terminal.
+///
+/// This is the same as the unicode width of the text except for emoji sequences. Unlike their unicode width,
+/// terminals display emojis that use the emoji presentation selector (e.g. `⚠️`) using 2 columns, and emojis joined
+/// via zero width joiners (e.g. `👩‍💻`) as a single one.
+pub(crate) trait TextWidth {
+    /// Get the width of this text.
+    fn width(&self) -> usize;
+}
+
+impl TextWidth for str {
+    fn width(&self) -> usize {
+        let mut previous = None;
+        let mut width = 0;
+        for c in self.chars() {
+            width += char_width(c, previous);
+            previous = Some(c);
+        }
+        width
+    }
+}
+
+/// Get the width of a character given the one that comes before it.
+pub(crate) fn char_width(c: char, previous: Option<char>) -> usize {
+    match (previous, c) {
+        // The emoji presentation selector makes the character before it be displayed as a wide emoji.
+        (Some(previous), EMOJI_PRESENTATION) => 2_usize.saturating_sub(previous.width().unwrap_or(0)),
+        (Some(ZERO_WIDTH_JOINER), _) => 0,
+        _ => c.width().unwrap_or(0),
+    }
+}
+
+/// Whether text can be broken right before or after this character, even if there's no space in between.
+///
+/// This is the case for wide characters such as CJK ideographs, as those languages don't use spaces between words.
+fn breaks_anywhere(c: char) -> bool {
+    c.width() == Some(2)
+}
+
+const EMOJI_PRESENTATION: char = '\u{fe0f}';
+const ZERO_WIDTH_JOINER: char = '\u{200d}';
+
 /// A weighted line of text.
 ///
 /// The weight of a character is its given by its width in unicode.
@@ -19,6 +61,45 @@ impl WeightedLine {
         self.0.iter().map(|text| text.width()).sum()
     }
 
+    /// Focus the texts in this line that link to the given anchor and unfocus every other one.
+    pub(crate) fn focus_anchor(&mut self, anchor: Option<&str>) {
+        for text in &mut self.0 {
+            if let Some(link_anchor) = text.text.anchor() {
+                let focused = Some(link_anchor) == anchor;
+                text.text.style.set_focused(focused);
+            }
+        }
+    }
+
+    /// The number of characters in this line.
+    pub(crate) fn characters(&self) -> usize {
+        self.0.iter().map(|text| text.text.text.chars().count()).sum()
+    }
+
+    /// Get a copy of this line where only its first `characters` characters are visible.
+    ///
+    /// The rest are replaced with blanks so the visible ones are placed where they would be in the full line.
+    pub(crate) fn typed(&self, characters: usize) -> Self {
+        let mut remaining = characters;
+        let mut texts = Vec::new();
+        for text in &self.0 {
+            let contents = &text.text.text;
+            let split = contents.char_indices().nth(remaining).map(|(index, _)| index).unwrap_or(contents.len());
+            remaining = remaining.saturating_sub(contents.chars().count());
+            let (visible, hidden) = contents.split_at(split);
+            if !visible.is_empty() {
+                let mut visible_text = text.text.clone();
+                visible_text.text = visible.into();
+                texts.push(WeightedText::from(visible_text));
+            }
+            if !hidden.is_empty() {
+                let blank = StyledText::new(" ".repeat(hidden.width()), TextStyle::default());
+                texts.push(WeightedText::from(blank));
+            }
+        }
+        Self(texts)
+    }
+
     /// Get an iterator to the underlying text chunks.
     #[cfg(test)]
     pub(crate) fn iter_texts(&self) -> impl Iterator<Item = &WeightedText> {
@@ -54,7 +135,12 @@ pub(crate) struct WeightedText {
 
 impl WeightedText {
     fn to_ref(&self) -> WeightedTextRef {
-        WeightedTextRef { text: &self.text.text, accumulators: &self.accumulators, style: self.text.style.clone() }
+        WeightedTextRef {
+            text: &self.text.text,
+            accumulators: &self.accumulators,
+            style: self.text.style.clone(),
+            link: self.text.link.as_deref(),
+        }
     }
 
     fn width(&self) -> usize {
@@ -67,10 +153,12 @@ impl From<StyledText> for WeightedText {
         let mut accumulators = Vec::new();
         let mut width = 0;
         let mut bytes = 0;
+        let mut previous = None;
         for c in text.text.chars() {
             accumulators.push(CharAccumulator { width, bytes });
-            width += c.width().unwrap_or(0);
+            width += char_width(c, previous);
             bytes += c.len_utf8();
+            previous = Some(c);
         }
         accumulators.push(CharAccumulator { width, bytes });
         Self { text, accumulators }
@@ -102,7 +190,7 @@ impl<'a> Iterator for SplitTextIter<'a> {
             let (head, rest) = current.word_split_at_length(remaining as usize);
             // Prevent splitting a word partially. We do allow this on the first chunk as otherwise
             // a word longer than `max_length` would never be split.
-            if !rest.text.is_empty() && !rest.text.starts_with(' ') && !elements.is_empty() {
+            if !rest.text.is_empty() && !rest.text.starts_with(' ') && !head.ends_in_break() && !elements.is_empty() {
                 self.current = Some(current);
                 break;
             }
@@ -130,6 +218,7 @@ pub(crate) struct WeightedTextRef<'a> {
     text: &'a str,
     accumulators: &'a [CharAccumulator],
     style: TextStyle,
+    link: Option<&'a str>,
 }
 
 impl<'a> WeightedTextRef<'a> {
@@ -138,6 +227,11 @@ impl<'a> WeightedTextRef<'a> {
         (self.text, self.style)
     }
 
+    /// Get the URL this text links to, if any.
+    pub(crate) fn link(&self) -> Option<&'a str> {
+        self.link
+    }
+
     // Attempts to split this at a word boundary.
     //
     // This will try to consume as many words as possible up to the given maximum length, and
@@ -148,11 +242,20 @@ impl<'a> WeightedTextRef<'a> {
         }
 
         let target_chunk = self.substr(max_length + 1);
-        let output_chunk = match target_chunk.rsplit_once(' ') {
-            Some((before, _)) => before,
-            None => self.substr(max_length),
-        };
-        (self.make_ref(0, output_chunk.len()), self.make_ref(output_chunk.len(), self.text.len()))
+        let space_split = target_chunk.rfind(' ');
+        // Text in languages that don't use spaces between words can be split in between any of its characters.
+        let mut fitting_chunk = self.substr(max_length);
+        if fitting_chunk.is_empty() {
+            // Take a character wider than the max length anyway, otherwise it would never fit anywhere.
+            fitting_chunk = self.text.chars().next().map(|c| &self.text[..c.len_utf8()]).unwrap_or_default();
+        }
+        let wide_split = fitting_chunk.char_indices().rev().map(|(index, c)| index + c.len_utf8()).find(|index| {
+            let before = self.text[..*index].chars().next_back();
+            let after = self.text[*index..].chars().next();
+            before.is_some_and(breaks_anywhere) || after.is_some_and(breaks_anywhere)
+        });
+        let split = space_split.max(wide_split).unwrap_or(fitting_chunk.len());
+        (self.make_ref(0, split), self.make_ref(split, self.text.len()))
     }
 
     fn substr(&self, max_length: usize) -> &'a str {
@@ -165,14 +268,14 @@ impl<'a> WeightedTextRef<'a> {
         let leading_char_count = self.text[0..from].chars().count();
         let output_char_count = text.chars().count();
         let character_lengths = &self.accumulators[leading_char_count..leading_char_count + output_char_count + 1];
-        WeightedTextRef { text, accumulators: character_lengths, style: self.style.clone() }
+        WeightedTextRef { text, accumulators: character_lengths, style: self.style.clone(), link: self.link }
     }
 
     fn trim_start(self) -> Self {
         let text = self.text.trim_start();
         let trimmed = self.text.chars().count() - text.chars().count();
         let accumulators = &self.accumulators[trimmed..];
-        Self { text, accumulators, style: self.style }
+        Self { text, accumulators, style: self.style, link: self.link }
     }
 
     fn width(&self) -> usize {
@@ -181,22 +284,48 @@ impl<'a> WeightedTextRef<'a> {
         last_width - first_width
     }
 
-    fn bytes_until(&self, index: usize) -> usize {
-        let last_bytes =
-            self.accumulators.get(index).or_else(|| self.accumulators.last()).map(|a| a.bytes).unwrap_or(0);
-        let first_bytes = self.accumulators.first().map(|a| a.bytes).unwrap_or(0);
-        last_bytes - first_bytes
+    fn ends_in_break(&self) -> bool {
+        self.text.chars().last().is_some_and(breaks_anywhere)
+    }
+
+    // Get the number of bytes in the longest prefix of this text that's at most `width` columns wide.
+    fn bytes_until(&self, width: usize) -> usize {
+        let Some(first) = self.accumulators.first() else {
+            return 0;
+        };
+        let last = self.accumulators.iter().take_while(|a| a.width - first.width <= width).last().unwrap_or(first);
+        last.bytes - first.bytes
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use rstest::rstest;
 
     fn join_lines<'a>(lines: impl Iterator<Item = Vec<WeightedTextRef<'a>>>) -> Vec<String> {
         lines.map(|l| l.iter().map(|weighted| weighted.text).collect::<Vec<_>>().join(" ")).collect()
     }
 
+    #[rstest]
+    #[case::nothing(0, &["    ", "    "])]
+    #[case::partial_first(2, &["he", "  ", "    "])]
+    #[case::partial_second(5, &["hey ", "y", "   "])]
+    #[case::wide(6, &["hey ", "yo", "  "])]
+    #[case::everything(9, &["hey ", "yo💻"])]
+    fn typed_line(#[case] characters: usize, #[case] expected: &[&str]) {
+        let line = WeightedLine::from(vec![
+            WeightedText::from(StyledText::new("hey ", TextStyle::default().bold())),
+            WeightedText::from(StyledText::from("yo💻")),
+        ]);
+        assert_eq!(line.characters(), 7);
+        let typed = line.typed(characters);
+        let texts: Vec<_> = typed.iter_texts().map(|text| text.text.text.as_str()).collect();
+        assert_eq!(texts, expected);
+        assert_eq!(typed.width(), line.width());
+        assert_eq!(typed.iter_texts().next().unwrap().text.style.is_bold(), characters > 0);
+    }
+
     #[test]
     fn text_creation() {
         let text = WeightedText::from(StyledText::from("hello world"));
@@ -269,6 +398,47 @@ mod test {
         assert_eq!(rest.text, " string");
     }
 
+    #[rstest]
+    #[case::ascii("hello", 5)]
+    #[case::cjk("你好", 4)]
+    #[case::emoji("🚀", 2)]
+    #[case::emoji_presentation("⚠️", 2)]
+    #[case::text_presentation("⚠", 1)]
+    #[case::zero_width_joiner("👩\u{200d}💻", 2)]
+    #[case::combining("e\u{301}", 1)]
+    fn text_width(#[case] text: &str, #[case] expected: usize) {
+        assert_eq!(text.width(), expected);
+        let weighted = WeightedText::from(StyledText::from(text));
+        assert_eq!(weighted.width(), expected);
+    }
+
+    #[test]
+    fn split_wide_characters() {
+        let text = WeightedText::from(StyledText::from("你好世界"));
+        let (head, rest) = text.to_ref().word_split_at_length(5);
+        assert_eq!(head.text, "你好");
+        assert_eq!(head.width(), 4);
+        assert_eq!(rest.text, "世界");
+    }
+
+    #[test]
+    fn split_wide_character_too_long() {
+        let text = WeightedText::from(StyledText::from("你好"));
+        let (head, rest) = text.to_ref().word_split_at_length(1);
+        assert_eq!(head.text, "你");
+        assert_eq!(rest.text, "好");
+    }
+
+    #[rstest]
+    #[case::cjk(&["你好世界"], 6, &["你好世", "界"])]
+    #[case::cjk_after_text(&["hi ", "你好世界"], 6, &["hi  你", "好世界"])]
+    #[case::mixed(&["hello 世界 world"], 8, &["hello 世", "界 world"])]
+    fn split_lines_with_wide_characters(#[case] texts: &[&str], #[case] max_length: usize, #[case] expected: &[&str]) {
+        let text = WeightedLine(texts.iter().map(|text| WeightedText::from(StyledText::from(*text))).collect());
+        let lines = join_lines(text.split(max_length));
+        assert_eq!(lines, expected);
+    }
+
     #[test]
     fn split_at_full_length() {
         let text = WeightedLine(vec![WeightedText::from(StyledText::from("hello world"))]);
@@ -1,22 +1,52 @@
 use crate::{
-    markdown::text::WeightedLine,
+    input::source::WidgetInput,
+    markdown::{
+        elements::{Code, StyledText},
+        text::WeightedLine,
+    },
     render::{media::Image, properties::WindowSize},
     style::Colors,
-    theme::{Alignment, Margin, PresentationTheme},
+    theme::{Alignment, ColumnLayoutStyle, Margin, OutputOverflow, PresentationTheme, VerticalAlignment},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+    path::PathBuf,
+    rc::Rc,
+    time::Duration,
 };
-use serde::Deserialize;
-use std::rc::Rc;
 
 /// A presentation.
 pub(crate) struct Presentation {
     slides: Vec<Slide>,
+    anchors: HashMap<String, usize>,
     current_slide_index: usize,
+    shows_clock: bool,
 }
 
 impl Presentation {
     /// Construct a new presentation.
     pub(crate) fn new(slides: Vec<Slide>) -> Self {
-        Self { slides, current_slide_index: 0 }
+        Self { slides, anchors: HashMap::new(), current_slide_index: 0, shows_clock: false }
+    }
+
+    /// Mark this presentation as showing a clock, which means it needs to be redrawn every minute.
+    pub(crate) fn with_clock(mut self, shows_clock: bool) -> Self {
+        self.shows_clock = shows_clock;
+        self
+    }
+
+    /// Whether this presentation shows a clock anywhere.
+    pub(crate) fn shows_clock(&self) -> bool {
+        self.shows_clock
+    }
+
+    /// Set the anchors that links in this presentation can point to, along with the index of the slide each of
+    /// them is in.
+    pub(crate) fn with_anchors(mut self, anchors: HashMap<String, usize>) -> Self {
+        self.anchors = anchors;
+        self
     }
 
     /// Iterate the slides in this presentation.
@@ -105,30 +135,192 @@ impl Presentation {
         self.current_slide().current_chunk()
     }
 
+    /// Get the time after which the next chunk in the current slide should be revealed automatically, if any.
+    pub(crate) fn reveal_delay(&self) -> Option<Duration> {
+        self.current_slide().reveal_delay()
+    }
+
+    /// Reveal the next chunk in the current slide, without moving on to the next slide.
+    pub(crate) fn reveal_next_chunk(&mut self) -> bool {
+        self.current_slide_mut().increase_visible_chunks()
+    }
+
+    /// Focus the next link visible in the current slide.
+    ///
+    /// This cycles back to the first link after the last one.
+    pub(crate) fn focus_next_link(&mut self) -> bool {
+        self.current_slide_mut().focus_next_link()
+    }
+
+    /// Jump to the slide the link in focus in the current slide points to.
+    ///
+    /// If no link is in focus but there's only one visible in the slide, that one is followed.
+    pub(crate) fn follow_link(&mut self) -> bool {
+        let Some(anchor) = self.current_slide().selected_link() else {
+            return false;
+        };
+        match self.anchors.get(anchor) {
+            Some(&slide_index) => self.jump_slide(slide_index),
+            None => false,
+        }
+    }
+
     /// Render all widgets in this slide.
+    ///
+    /// Widgets that are part of a sequence of steps are instead rendered one step at a time, so only the ones in the
+    /// first step that hasn't been rendered yet are started.
     pub(crate) fn render_slide_widgets(&mut self) -> bool {
+        let slide = self.current_slide_mut();
+        let next_step = slide
+            .iter_operations()
+            .filter_map(|operation| match operation {
+                RenderOperation::RenderOnDemand(operation)
+                    if matches!(operation.poll_state(), RenderOnDemandState::NotStarted) =>
+                {
+                    operation.render_step()
+                }
+                _ => None,
+            })
+            .min();
+        let mut any_rendered = false;
+        for operation in slide.iter_operations_mut() {
+            if let RenderOperation::RenderOnDemand(operation) = operation {
+                let step = operation.render_step();
+                if step.is_none() || step == next_step {
+                    // Start all of them so they run concurrently.
+                    any_rendered = operation.start_render() || any_rendered;
+                }
+            }
+        }
+        any_rendered
+    }
+
+    /// Render all widgets in this slide that render automatically and haven't been rendered yet.
+    pub(crate) fn render_automatic_slide_widgets(&mut self) -> bool {
         let slide = self.current_slide_mut();
         let mut any_rendered = false;
         for operation in slide.iter_operations_mut() {
             if let RenderOperation::RenderOnDemand(operation) = operation {
-                any_rendered = any_rendered || operation.start_render();
+                if operation.renders_automatically() {
+                    any_rendered = operation.start_render() || any_rendered;
+                }
             }
         }
         any_rendered
     }
 
+    /// Discard anything rendered by the widgets in this slide.
+    pub(crate) fn reset_slide_widgets(&mut self) -> bool {
+        let slide = self.current_slide_mut();
+        let mut any_reset = false;
+        for operation in slide.iter_operations_mut() {
+            if let RenderOperation::RenderOnDemand(operation) = operation {
+                any_reset = operation.reset_render() || any_reset;
+            }
+        }
+        any_reset
+    }
+
+    /// Render all widgets in this slide again, discarding whatever they previously rendered.
+    ///
+    /// Widgets that are part of a sequence of steps start over from the first one.
+    pub(crate) fn rerender_slide_widgets(&mut self) -> bool {
+        self.reset_slide_widgets();
+        self.render_slide_widgets()
+    }
+
+    /// Check whether any widget in this slide is still rendering.
+    pub(crate) fn widgets_rendering(&mut self) -> bool {
+        let slide = self.current_slide_mut();
+        let mut any_rendering = false;
+        for operation in slide.iter_operations_mut() {
+            if let RenderOperation::RenderOnDemand(operation) = operation {
+                any_rendering = matches!(operation.poll_state(), RenderOnDemandState::Rendering) || any_rendering;
+            }
+        }
+        any_rendering
+    }
+
+    /// Scroll all widgets in this slide by the given number of lines.
+    pub(crate) fn scroll_slide_widgets(&mut self, lines: isize) -> bool {
+        let slide = self.current_slide_mut();
+        let mut any_scrolled = false;
+        for operation in slide.iter_operations_mut() {
+            if let RenderOperation::RenderOnDemand(operation) = operation {
+                any_scrolled = operation.scroll(lines) || any_scrolled;
+            }
+        }
+        any_scrolled
+    }
+
+    /// Toggle whether all widgets in this slide follow their contents as they're rendered.
+    pub(crate) fn toggle_slide_widgets_follow(&mut self) -> bool {
+        let slide = self.current_slide_mut();
+        let mut any_toggled = false;
+        for operation in slide.iter_operations_mut() {
+            if let RenderOperation::RenderOnDemand(operation) = operation {
+                any_toggled = operation.toggle_follow() || any_toggled;
+            }
+        }
+        any_toggled
+    }
+
+    /// Check whether any widget in this slide wants to capture user input.
+    pub(crate) fn widgets_capture_input(&self) -> bool {
+        self.current_slide().iter_operations().any(|operation| match operation {
+            RenderOperation::RenderOnDemand(operation) => operation.captures_input(),
+            _ => false,
+        })
+    }
+
+    /// Send user input to the widget in this slide that's capturing it.
+    pub(crate) fn send_widget_input(&mut self, input: &WidgetInput) -> bool {
+        let slide = self.current_slide_mut();
+        for operation in slide.iter_operations_mut() {
+            if let RenderOperation::RenderOnDemand(operation) = operation {
+                if operation.captures_input() {
+                    return operation.send_input(input);
+                }
+            }
+        }
+        false
+    }
+
+    /// Abort any widgets in this slide that are still rendering.
+    pub(crate) fn abort_slide_widgets(&mut self) -> bool {
+        let slide = self.current_slide_mut();
+        let mut any_aborted = false;
+        for operation in slide.iter_operations_mut() {
+            if let RenderOperation::RenderOnDemand(operation) = operation {
+                any_aborted = operation.abort_render() || any_aborted;
+            }
+        }
+        any_aborted
+    }
+
     /// Poll every widget in the current slide and check whether they're rendered.
     pub(crate) fn widgets_rendered(&mut self) -> bool {
         let slide = self.current_slide_mut();
         let mut all_rendered = true;
         for operation in slide.iter_operations_mut() {
             if let RenderOperation::RenderOnDemand(operation) = operation {
-                all_rendered = all_rendered && matches!(operation.poll_state(), RenderOnDemandState::Rendered);
+                // Poll all of them so they're all updated, even if some of them are still rendering.
+                all_rendered = matches!(operation.poll_state(), RenderOnDemandState::Rendered) && all_rendered;
             }
         }
         all_rendered
     }
 
+    /// Get the code visible in the current slide that needs to take over the terminal when executed.
+    pub(crate) fn current_terminal_snippets(&self) -> Vec<TerminalSnippet> {
+        self.current_slide().visible_terminal_snippets()
+    }
+
+    /// Get the contents of the last code block that's visible in the current slide.
+    pub(crate) fn last_visible_code_block(&self) -> Option<&str> {
+        self.current_slide().last_visible_code_block()
+    }
+
     fn current_slide_mut(&mut self) -> &mut Slide {
         &mut self.slides[self.current_slide_index]
     }
@@ -143,22 +335,34 @@ pub(crate) struct Slide {
     chunks: Vec<SlideChunk>,
     footer: Vec<RenderOperation>,
     visible_chunks: usize,
+    focused_link: Option<usize>,
+    speaker_notes: Vec<String>,
 }
 
 impl Slide {
     pub(crate) fn new(chunks: Vec<SlideChunk>, footer: Vec<RenderOperation>) -> Self {
-        Self { chunks, footer, visible_chunks: 1 }
+        Self { chunks, footer, visible_chunks: 1, focused_link: None, speaker_notes: Vec::new() }
+    }
+
+    pub(crate) fn with_speaker_notes(mut self, speaker_notes: Vec<String>) -> Self {
+        self.speaker_notes = speaker_notes;
+        self
+    }
+
+    /// Get the speaker notes for this slide, in the order they were defined.
+    pub(crate) fn speaker_notes(&self) -> &[String] {
+        &self.speaker_notes
     }
 
     pub(crate) fn iter_operations(&self) -> impl Iterator<Item = &RenderOperation> + Clone {
-        self.chunks.iter().take(self.visible_chunks).flat_map(|chunk| chunk.0.iter()).chain(self.footer.iter())
+        self.chunks.iter().take(self.visible_chunks).flat_map(|chunk| chunk.operations.iter()).chain(self.footer.iter())
     }
 
     pub(crate) fn iter_operations_mut(&mut self) -> impl Iterator<Item = &mut RenderOperation> {
         self.chunks
             .iter_mut()
             .take(self.visible_chunks)
-            .flat_map(|chunk| chunk.0.iter_mut())
+            .flat_map(|chunk| chunk.operations.iter_mut())
             .chain(self.footer.iter_mut())
     }
 
@@ -166,28 +370,111 @@ impl Slide {
         self.chunks.iter()
     }
 
+    /// Get the last code block that's visible in this slide.
+    pub(crate) fn last_visible_code_block(&self) -> Option<&str> {
+        self.chunks
+            .iter()
+            .take(self.visible_chunks)
+            .flat_map(|chunk| chunk.code_blocks.iter())
+            .last()
+            .map(String::as_str)
+    }
+
+    /// Get the code visible in this slide that needs to take over the terminal when executed.
+    pub(crate) fn visible_terminal_snippets(&self) -> Vec<TerminalSnippet> {
+        self.chunks.iter().take(self.visible_chunks).flat_map(|chunk| chunk.terminal_snippets.iter()).cloned().collect()
+    }
+
+    /// Get the anchors pointed to by the links visible in this slide, in the order they're displayed.
+    pub(crate) fn visible_links(&self) -> Vec<&str> {
+        let mut links: Vec<&str> = Vec::new();
+        for link in self.chunks.iter().take(self.visible_chunks).flat_map(|chunk| chunk.links.iter()) {
+            if !links.contains(&link.as_str()) {
+                links.push(link);
+            }
+        }
+        links
+    }
+
+    /// Get the anchor pointed to by the link that would be followed in this slide.
+    fn selected_link(&self) -> Option<&str> {
+        let links = self.visible_links();
+        match self.focused_link {
+            Some(index) => links.get(index).copied(),
+            None if links.len() == 1 => Some(links[0]),
+            None => None,
+        }
+    }
+
+    fn focus_next_link(&mut self) -> bool {
+        let links = self.visible_links();
+        if links.is_empty() {
+            return false;
+        }
+        let next = match self.focused_link {
+            Some(index) => (index + 1) % links.len(),
+            None => 0,
+        };
+        self.focus_link(Some(next));
+        true
+    }
+
+    fn focus_link(&mut self, index: Option<usize>) {
+        let anchor = index.and_then(|index| self.visible_links().get(index).map(|anchor| anchor.to_string()));
+        self.focused_link = index;
+        // Unfocus links in hidden chunks too so they don't show up focused once they're visible.
+        for operation in self.chunks.iter_mut().flat_map(|chunk| chunk.operations.iter_mut()) {
+            if let RenderOperation::RenderText { line, .. } = operation {
+                line.focus_anchor(anchor.as_deref());
+            }
+        }
+    }
+
     #[cfg(test)]
     pub(crate) fn into_operations(self) -> Vec<RenderOperation> {
-        self.chunks.into_iter().flat_map(|chunk| chunk.0.into_iter()).chain(self.footer.into_iter()).collect()
+        self.chunks.into_iter().flat_map(|chunk| chunk.operations.into_iter()).chain(self.footer.into_iter()).collect()
     }
 
     fn jump_chunk(&mut self, chunk_index: usize) {
         self.visible_chunks = (chunk_index + 1).min(self.chunks.len());
+        for (index, chunk) in self.chunks.iter().enumerate() {
+            if index < chunk_index {
+                chunk.apply_all_mutations();
+            } else {
+                chunk.reset_mutations();
+            }
+        }
     }
 
     fn current_chunk(&self) -> usize {
         self.visible_chunks.saturating_sub(1)
     }
 
+    fn reveal_delay(&self) -> Option<Duration> {
+        if self.visible_chunks < self.chunks.len() { self.last_visible_chunk().reveal_delay } else { None }
+    }
+
     fn show_first_chunk(&mut self) {
         self.visible_chunks = 1;
+        if self.focused_link.is_some() {
+            self.focus_link(None);
+        }
+        for chunk in &self.chunks {
+            chunk.reset_mutations();
+        }
     }
 
     fn show_all_chunks(&mut self) {
         self.visible_chunks = self.chunks.len();
+        for chunk in &self.chunks {
+            chunk.apply_all_mutations();
+        }
     }
 
     fn decrease_visible_chunks(&mut self) -> bool {
+        if self.last_visible_chunk().mutate_previous() {
+            return true;
+        }
         if self.visible_chunks == 1 {
             false
         } else {
@@ -197,6 +484,9 @@ impl Slide {
     }
 
     fn increase_visible_chunks(&mut self) -> bool {
+        if self.last_visible_chunk().mutate_next() {
+            return true;
+        }
         if self.visible_chunks == self.chunks.len() {
             false
         } else {
@@ -204,6 +494,10 @@ impl Slide {
             true
         }
     }
+
+    fn last_visible_chunk(&self) -> &SlideChunk {
+        &self.chunks[self.visible_chunks - 1]
+    }
 }
 
 impl From<Vec<RenderOperation>> for Slide {
@@ -212,25 +506,103 @@ impl From<Vec<RenderOperation>> for Slide {
     }
 }
 
+/// A piece of code that needs to take over the terminal when executed.
+#[derive(Clone, Debug)]
+pub(crate) struct TerminalSnippet {
+    pub(crate) code: Code,
+
+    /// The error that happened the last time this was executed, if any, which is displayed in the slide.
+    pub(crate) error: Rc<RefCell<Option<String>>>,
+}
+
 #[derive(Clone, Debug, Default)]
-pub(crate) struct SlideChunk(Vec<RenderOperation>);
+pub(crate) struct SlideChunk {
+    operations: Vec<RenderOperation>,
+    code_blocks: Vec<String>,
+    terminal_snippets: Vec<TerminalSnippet>,
+    mutators: Vec<Rc<dyn ChunkMutator>>,
+    links: Vec<String>,
+    reveal_delay: Option<Duration>,
+}
 
 impl SlideChunk {
     pub(crate) fn new(operations: Vec<RenderOperation>) -> Self {
-        Self(operations)
+        Self {
+            operations,
+            code_blocks: Vec::new(),
+            terminal_snippets: Vec::new(),
+            mutators: Vec::new(),
+            links: Vec::new(),
+            reveal_delay: None,
+        }
+    }
+
+    /// Set the contents of the code blocks displayed in this chunk, in the order they're displayed.
+    pub(crate) fn with_code_blocks(mut self, code_blocks: Vec<String>) -> Self {
+        self.code_blocks = code_blocks;
+        self
+    }
+
+    /// Set the code in this chunk that needs to take over the terminal when executed.
+    pub(crate) fn with_terminal_snippets(mut self, snippets: Vec<TerminalSnippet>) -> Self {
+        self.terminal_snippets = snippets;
+        self
+    }
+
+    /// Set the operations in this chunk that change as the presentation moves forward, before moving on to the
+    /// next chunk.
+    pub(crate) fn with_mutators(mut self, mutators: Vec<Rc<dyn ChunkMutator>>) -> Self {
+        self.mutators = mutators;
+        self
+    }
+
+    /// Set the anchors pointed to by the links in this chunk, in the order they're displayed.
+    pub(crate) fn with_links(mut self, links: Vec<String>) -> Self {
+        self.links = links;
+        self
+    }
+
+    /// Set the time after which the chunk that follows this one is revealed automatically.
+    pub(crate) fn with_reveal_delay(mut self, reveal_delay: Option<Duration>) -> Self {
+        self.reveal_delay = reveal_delay;
+        self
     }
 
     pub(crate) fn iter_operations(&self) -> impl Iterator<Item = &RenderOperation> + Clone {
-        self.0.iter()
+        self.operations.iter()
+    }
+
+    fn mutate_next(&self) -> bool {
+        self.mutators.iter().any(|mutator| mutator.mutate_next())
+    }
+
+    fn mutate_previous(&self) -> bool {
+        self.mutators.iter().rev().any(|mutator| mutator.mutate_previous())
+    }
+
+    fn reset_mutations(&self) {
+        for mutator in &self.mutators {
+            mutator.reset_mutations();
+        }
+    }
+
+    fn apply_all_mutations(&self) {
+        for mutator in &self.mutators {
+            mutator.apply_all_mutations();
+        }
     }
 
     pub(crate) fn pop_last(&mut self) -> Option<RenderOperation> {
-        self.0.pop()
+        self.operations.pop()
+    }
+
+    pub(crate) fn operations_mut(&mut self) -> &mut Vec<RenderOperation> {
+        &mut self.operations
     }
 }
 
 /// The metadata for a presentation.
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Default, Deserialize)]
 pub(crate) struct PresentationMetadata {
     /// The presentation title.
     pub(crate) title: Option<String>,
@@ -243,9 +615,33 @@ pub(crate) struct PresentationMetadata {
     #[serde(default)]
     pub(crate) author: Option<String>,
 
+    /// The date the presentation is given on.
+    #[serde(default)]
+    pub(crate) date: Option<String>,
+
     /// The presentation's theme metadata.
     #[serde(default)]
     pub(crate) theme: PresentationThemeMetadata,
+
+    /// The presentation's code execution metadata.
+    #[serde(default)]
+    pub(crate) execution: PresentationExecutionMetadata,
+
+    /// The path to an image to display behind the introduction slide.
+    #[serde(default)]
+    pub(crate) background_image: Option<PathBuf>,
+
+    /// Options that override the ones in the configuration for this presentation.
+    #[serde(default)]
+    pub(crate) options: PresentationOptionsMetadata,
+
+    /// The closing slide to add at the end of the presentation.
+    #[serde(default)]
+    pub(crate) closing_slide: Option<ClosingSlideMetadata>,
+
+    /// The values that can be used as variables in the presentation.
+    #[serde(default)]
+    pub(crate) variables: BTreeMap<String, serde_yaml::Value>,
 }
 
 /// A presentation's theme metadata.
@@ -264,13 +660,132 @@ pub(crate) struct PresentationThemeMetadata {
     pub(crate) overrides: Option<PresentationTheme>,
 }
 
+/// A presentation's code execution metadata.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub(crate) struct PresentationExecutionMetadata {
+    /// The ssh destination, e.g. `user@host`, to run code in.
+    #[serde(default)]
+    pub(crate) ssh: Option<String>,
+}
+
+/// A presentation's closing slide metadata.
+///
+/// The presentation's author is displayed in it as well.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct ClosingSlideMetadata {
+    /// The message to display, which defaults to "Thanks!".
+    #[serde(default)]
+    pub(crate) message: Option<String>,
+
+    /// The author's handle, e.g. `@bob@example.social`.
+    #[serde(default)]
+    pub(crate) handle: Option<String>,
+
+    /// A URL to point people at, e.g. where the slides can be found.
+    #[serde(default)]
+    pub(crate) url: Option<String>,
+}
+
+/// A presentation's options.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct PresentationOptionsMetadata {
+    /// Whether to reveal the top level items in lists one at a time.
+    #[serde(default)]
+    pub(crate) incremental_lists: Option<bool>,
+
+    /// How slides are numbered.
+    #[serde(default)]
+    pub(crate) slide_numbers: SlideNumbering,
+}
+
+/// How slides are numbered.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct SlideNumbering {
+    /// How the `{slide_number}` footer placeholder is formatted.
+    #[serde(default)]
+    pub(crate) format: SlideNumberFormat,
+
+    /// Whether the introduction slide is counted.
+    ///
+    /// This defaults to true. If it's not counted, the slide after it is the first one.
+    #[serde(default)]
+    pub(crate) count_intro: Option<bool>,
+
+    /// The number of the first slide, which defaults to 1.
+    #[serde(default)]
+    pub(crate) start: Option<usize>,
+}
+
+impl SlideNumbering {
+    /// Get the number for the slide at the given index, if it's counted.
+    pub(crate) fn number(&self, index: usize, has_intro: bool) -> Option<usize> {
+        let index = match has_intro && !self.count_intro.unwrap_or(true) {
+            true => index.checked_sub(1)?,
+            false => index,
+        };
+        Some(index + self.start.unwrap_or(1))
+    }
+
+    /// Get the number of the last slide in a presentation with the given number of slides.
+    pub(crate) fn last(&self, total_slides: usize, has_intro: bool) -> usize {
+        total_slides.checked_sub(1).and_then(|index| self.number(index, has_intro)).unwrap_or(0)
+    }
+
+    /// Format a slide's number given the number of the last slide.
+    pub(crate) fn format(&self, number: usize, last: usize) -> String {
+        match self.format {
+            SlideNumberFormat::Fraction => format!("{number}/{last}"),
+            SlideNumberFormat::Number => number.to_string(),
+            SlideNumberFormat::Padded => {
+                let width = last.to_string().len().max(2);
+                format!("{number:0width$}")
+            }
+            SlideNumberFormat::Percent => {
+                // This is based on the slide's position so the number slides start at doesn't change it.
+                let start = self.start.unwrap_or(1);
+                let position = (number + 1).saturating_sub(start);
+                let count = (last + 1).saturating_sub(start).max(1);
+                format!("{}%", position * 100 / count)
+            }
+        }
+    }
+}
+
+/// How a slide's number is formatted.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum SlideNumberFormat {
+    /// The slide number and the last one, e.g. `3/42`.
+    #[default]
+    Fraction,
+
+    /// Only the slide number, e.g. `3`.
+    Number,
+
+    /// The slide number padded with zeroes to as many digits as the last one, with at least 2, e.g. `03`.
+    Padded,
+
+    /// How far into the presentation the slide is, e.g. `7%`.
+    Percent,
+}
+
 /// A line of preformatted text to be rendered.
 #[derive(Clone, Debug, PartialEq)]
 pub(crate) struct PreformattedLine {
-    pub(crate) text: String,
+    /// The styled pieces of text this line is made of.
+    pub(crate) spans: Vec<StyledText>,
     pub(crate) unformatted_length: usize,
     pub(crate) block_length: usize,
     pub(crate) alignment: Alignment,
+
+    /// What to do if this line doesn't fit, if anything.
+    pub(crate) overflow: Option<OutputOverflow>,
+
+    /// Whether this line is part of a raw block, which goes past the margins rather than being cut off if too wide.
+    pub(crate) raw: bool,
 }
 
 /// A render operation.
@@ -288,6 +803,12 @@ pub(crate) enum RenderOperation {
     /// Jump the draw cursor into the vertical center, that is, at `screen_height / 2`.
     JumpToVerticalCenter,
 
+    /// Move the cursor so that the rest of the slide's contents are aligned vertically.
+    ///
+    /// This carries all of those contents, including the ones that aren't visible yet, so they're placed in the same
+    /// spot no matter how many of them are visible.
+    AlignVertically { alignment: VerticalAlignment, contents: Rc<Vec<RenderOperation>> },
+
     /// Jumps to the N-th to last row in the slide.
     ///
     /// The index is zero based where 0 represents the bottom row.
@@ -300,7 +821,10 @@ pub(crate) enum RenderOperation {
     RenderLineBreak,
 
     /// Render an image.
-    RenderImage(Image),
+    RenderImage(Image, ImagePosition),
+
+    /// Render an image that covers the entire window, behind everything else.
+    RenderBackgroundImage(Image),
 
     /// Render a preformatted line.
     ///
@@ -322,7 +846,9 @@ pub(crate) enum RenderOperation {
     ///
     /// The value for each column is the width of the column in column-unit units, where the entire
     /// screen contains `columns.sum()` column-units.
-    InitColumnLayout { columns: Vec<u8> },
+    ///
+    /// If this is done while inside a column, the new layout is nested within that column.
+    InitColumnLayout { columns: ColumnLayout, style: ColumnLayoutStyle },
 
     /// Enter a column in a column layout.
     ///
@@ -330,8 +856,13 @@ pub(crate) enum RenderOperation {
     EnterColumn { column: usize },
 
     /// Exit the current layout and go back to the default one.
+    ///
+    /// This exits any nested layouts as well.
     ExitLayout,
 
+    /// Exit the innermost nested layout and go back to the column it's nested in.
+    ExitNestedLayout,
+
     /// Apply a margin to every following operation.
     ApplyMargin(MarginProperties),
 
@@ -339,16 +870,91 @@ pub(crate) enum RenderOperation {
     PopMargin,
 }
 
+/// Where an image is placed horizontally.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ImagePosition {
+    /// Place it against the left edge.
+    Left,
+
+    /// Place it in the middle.
+    #[default]
+    Center,
+
+    /// Place it against the right edge.
+    Right,
+}
+
+/// The widths of the columns in a column layout.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub(crate) enum ColumnLayout {
+    /// Use the same widths regardless of the terminal size.
+    Fixed(Vec<u8>),
+
+    /// Use different widths depending on the terminal size.
+    ///
+    /// Each key is the minimum number of columns the terminal needs to have for its widths to be used.
+    Responsive(BTreeMap<u16, Vec<u8>>),
+}
+
+impl ColumnLayout {
+    /// Get the column widths to use for a terminal that has the given number of columns.
+    pub(crate) fn widths(&self, terminal_columns: u16) -> &[u8] {
+        match self {
+            Self::Fixed(widths) => widths,
+            Self::Responsive(widths) => widths
+                .range(..=terminal_columns)
+                .next_back()
+                .or_else(|| widths.iter().next())
+                .map(|(_, widths)| widths.as_slice())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Iterate every set of column widths in this layout.
+    pub(crate) fn iter_widths(&self) -> Box<dyn Iterator<Item = &[u8]> + '_> {
+        match self {
+            Self::Fixed(widths) => Box::new(std::iter::once(widths.as_slice())),
+            Self::Responsive(widths) => Box::new(widths.values().map(Vec::as_slice)),
+        }
+    }
+}
+
+impl From<Vec<u8>> for ColumnLayout {
+    fn from(widths: Vec<u8>) -> Self {
+        Self::Fixed(widths)
+    }
+}
+
 /// Slide properties, set on initialization.
 #[derive(Clone, Debug, Default)]
 pub(crate) struct MarginProperties {
     /// The horizontal margin.
     pub(crate) horizontal_margin: Margin,
 
+    /// The maximum width the contents can take after applying the horizontal margin.
+    pub(crate) max_width: Option<Margin>,
+
     /// The margin at the bottom of the slide.
     pub(crate) bottom_slide_margin: u16,
 }
 
+/// A type that changes as the presentation moves forwards and backwards within the slide chunk it's part of.
+pub(crate) trait ChunkMutator: std::fmt::Debug {
+    /// Move on to the next state, returning whether there was one.
+    fn mutate_next(&self) -> bool;
+
+    /// Move back to the previous state, returning whether there was one.
+    fn mutate_previous(&self) -> bool;
+
+    /// Go back to the initial state.
+    fn reset_mutations(&self);
+
+    /// Move on to the final state.
+    fn apply_all_mutations(&self);
+}
+
 /// A type that can generate render operations.
 pub(crate) trait AsRenderOperations: std::fmt::Debug {
     /// Generate render operations.
@@ -360,8 +966,42 @@ pub(crate) trait RenderOnDemand: AsRenderOperations {
     /// Start the on demand render for this operation.
     fn start_render(&self) -> bool;
 
+    /// Check whether this should start rendering as soon as it's visible.
+    fn renders_automatically(&self) -> bool;
+
+    /// Check whether rendering this runs a piece of code, which is what snippet validation looks at.
+    fn runs_code(&self) -> bool;
+
+    /// Get the step this is rendered in, if it's part of a sequence of them.
+    ///
+    /// Operations in a slide that have a step are rendered one step at a time, in ascending order.
+    fn render_step(&self) -> Option<u32>;
+
     /// Poll and update the internal on demand state and return the latest.
     fn poll_state(&self) -> RenderOnDemandState;
+
+    /// Abort the on demand render if it's still in progress.
+    fn abort_render(&self) -> bool;
+
+    /// Discard anything rendered so far, aborting it if it's in progress, and go back to the initial state.
+    ///
+    /// Returns whether there was anything to discard.
+    fn reset_render(&self) -> bool;
+
+    /// Get a description of the error that happened while rendering, if any.
+    fn render_error(&self) -> Option<String>;
+
+    /// Scroll the rendered contents by the given number of lines, returning whether anything changed.
+    fn scroll(&self, lines: isize) -> bool;
+
+    /// Toggle whether the view keeps up with new contents as they're rendered, returning whether anything changed.
+    fn toggle_follow(&self) -> bool;
+
+    /// Check whether this wants to capture user input.
+    fn captures_input(&self) -> bool;
+
+    /// Send user input to this operation.
+    fn send_input(&self, input: &WidgetInput) -> bool;
 }
 
 /// The state of a [RenderOnDemand].
@@ -422,4 +1062,66 @@ mod test {
         assert_eq!(presentation.current_slide_index(), expected_slide);
         assert_eq!(presentation.current_slide().visible_chunks - 1, expected_chunk);
     }
+
+    #[test]
+    fn follow_links() {
+        let linking_slide = |links: &[&str]| {
+            let chunk = SlideChunk::default().with_links(links.iter().map(|link| link.to_string()).collect());
+            Slide::new(vec![chunk], vec![])
+        };
+        let anchors = HashMap::from([("first".to_string(), 0), ("last".to_string(), 2)]);
+        let mut presentation =
+            Presentation::new(vec![linking_slide(&["last"]), linking_slide(&["first", "last"]), linking_slide(&[])])
+                .with_anchors(anchors);
+
+        // The only link in the slide is followed without focusing it first.
+        assert!(presentation.follow_link());
+        assert_eq!(presentation.current_slide_index(), 2);
+        assert!(!presentation.focus_next_link());
+
+        presentation.jump_slide(1);
+        assert!(!presentation.follow_link());
+        presentation.focus_next_link();
+        presentation.focus_next_link();
+        assert!(presentation.follow_link());
+        assert_eq!(presentation.current_slide_index(), 2);
+
+        presentation.jump_slide(1);
+        presentation.focus_next_link();
+        assert!(presentation.follow_link());
+        assert_eq!(presentation.current_slide_index(), 0);
+    }
+
+    #[rstest]
+    #[case::narrower_than_all(50, &[1])]
+    #[case::exact(80, &[1, 1])]
+    #[case::between(100, &[1, 1])]
+    #[case::widest(200, &[2, 1])]
+    fn responsive_column_widths(#[case] terminal_columns: u16, #[case] expected: &[u8]) {
+        let layout = ColumnLayout::Responsive([(60, vec![1]), (80, vec![1, 1]), (120, vec![2, 1])].into());
+        assert_eq!(layout.widths(terminal_columns), expected);
+    }
+
+    #[rstest]
+    #[case::fraction("{}", 2, "3/42")]
+    #[case::number("format: number", 2, "3")]
+    #[case::padded("format: padded", 2, "03")]
+    #[case::percent("format: percent", 20, "50%")]
+    #[case::percent_start("{format: percent, start: 11}", 20, "50%")]
+    #[case::percent_last("{format: percent, start: 0}", 41, "100%")]
+    #[case::start("start: 11", 2, "13/52")]
+    #[case::uncounted_intro("count_intro: false", 2, "2/41")]
+    fn slide_numbering(#[case] input: &str, #[case] index: usize, #[case] expected: &str) {
+        let numbering: SlideNumbering = serde_yaml::from_str(input).expect("invalid numbering");
+        let number = numbering.number(index, true).expect("no number");
+        let last = numbering.last(42, true);
+        assert_eq!(numbering.format(number, last), expected);
+    }
+
+    #[test]
+    fn uncounted_intro() {
+        let numbering = SlideNumbering { count_intro: Some(false), ..Default::default() };
+        assert_eq!(numbering.number(0, true), None);
+        assert_eq!(numbering.number(0, false), Some(1));
+    }
 }
@@ -1,15 +1,18 @@
 use crate::{
-    builder::{BuildError, PresentationBuilder},
+    builder::{BuildError, ExecutionCache, PresentationBuilder, PresentationBuilderOptions},
     diff::PresentationDiffer,
+    execute::{ExecutionState, ProcessStatus},
     input::source::{Command, CommandSource},
     markdown::parse::{MarkdownParser, ParseError},
-    presentation::Presentation,
+    presentation::{Presentation, TerminalSnippet},
     render::{
         draw::{RenderError, RenderResult, TerminalDrawer},
         highlighting::CodeHighlighter,
+        media::ImageProtocol,
     },
     resource::Resources,
     theme::PresentationTheme,
+    variables::current_minute,
 };
 use std::{
     collections::HashSet,
@@ -17,6 +20,7 @@ use std::{
     io::{self, Stdout},
     mem,
     path::Path,
+    time::Instant,
 };
 
 /// A slideshow presenter.
@@ -28,9 +32,12 @@ pub struct Presenter<'a> {
     commands: CommandSource,
     parser: MarkdownParser<'a>,
     resources: Resources,
+    execution_cache: ExecutionCache,
     mode: PresentMode,
+    options: PresentationBuilderOptions,
     state: PresenterState,
     slides_with_pending_widgets: HashSet<usize>,
+    image_protocol: Option<ImageProtocol>,
 }
 
 impl<'a> Presenter<'a> {
@@ -42,6 +49,7 @@ impl<'a> Presenter<'a> {
         parser: MarkdownParser<'a>,
         resources: Resources,
         mode: PresentMode,
+        options: PresentationBuilderOptions,
     ) -> Self {
         Self {
             default_theme,
@@ -49,23 +57,44 @@ impl<'a> Presenter<'a> {
             commands,
             parser,
             resources,
+            execution_cache: Default::default(),
             mode,
+            options,
             state: PresenterState::Empty,
             slides_with_pending_widgets: HashSet::new(),
+            image_protocol: None,
         }
     }
 
+    /// Set the protocol used to draw images rather than detecting it based on the terminal being used.
+    pub fn with_image_protocol(mut self, image_protocol: Option<ImageProtocol>) -> Self {
+        self.image_protocol = image_protocol;
+        self
+    }
+
     /// Run a presentation.
     pub fn present(mut self, path: &Path) -> Result<(), PresentationError> {
         self.state = PresenterState::Presenting(self.load_presentation(path)?);
 
-        let mut drawer = TerminalDrawer::new(io::stdout())?;
+        let mut drawer = TerminalDrawer::new(io::stdout(), self.image_protocol)?;
         loop {
+            self.render_automatic_widgets();
             self.render(&mut drawer)?;
             self.update_widgets(&mut drawer)?;
+            let shown_at = Instant::now();
+            let rendered_minute = current_minute();
 
             loop {
                 self.update_widgets(&mut drawer)?;
+                if self.reveal_pending_chunk(shown_at) || self.clock_outdated(rendered_minute) {
+                    break;
+                }
+                let capture_input = match &self.state {
+                    PresenterState::Presenting(presentation) => presentation.widgets_capture_input(),
+                    _ => false,
+                };
+                self.commands.capture_input(capture_input);
+                self.commands.poll_often(!self.slides_with_pending_widgets.is_empty());
                 let Some(command) = self.commands.try_next_command()? else {
                     continue;
                 };
@@ -78,6 +107,15 @@ impl<'a> Presenter<'a> {
                     CommandSideEffect::Redraw => {
                         break;
                     }
+                    CommandSideEffect::AcquireTerminal(snippets) => {
+                        self.run_attached_snippets(&mut drawer, &snippets)?;
+                        // Anything else in this slide may have been started along with these.
+                        self.slides_with_pending_widgets.insert(self.state.presentation().current_slide_index());
+                        break;
+                    }
+                    CommandSideEffect::CopyToClipboard(text) => {
+                        drawer.copy_to_clipboard(&text)?;
+                    }
                     CommandSideEffect::PollWidgets => {
                         self.slides_with_pending_widgets.insert(self.state.presentation().current_slide_index());
                     }
@@ -87,6 +125,54 @@ impl<'a> Presenter<'a> {
         }
     }
 
+    fn render_automatic_widgets(&mut self) {
+        if let PresenterState::Presenting(presentation) = &mut self.state {
+            if presentation.render_automatic_slide_widgets() {
+                self.slides_with_pending_widgets.insert(presentation.current_slide_index());
+            }
+        }
+    }
+
+    /// Reveal the next chunk in the current slide if it's set to be revealed automatically and it's time to do so.
+    fn reveal_pending_chunk(&mut self, shown_at: Instant) -> bool {
+        let PresenterState::Presenting(presentation) = &mut self.state else {
+            return false;
+        };
+        match presentation.reveal_delay() {
+            Some(delay) if shown_at.elapsed() >= delay => presentation.reveal_next_chunk(),
+            _ => false,
+        }
+    }
+
+    /// Whether there's a clock being shown that hasn't been redrawn since the minute changed.
+    fn clock_outdated(&self, rendered_minute: u64) -> bool {
+        match &self.state {
+            PresenterState::Presenting(presentation) => {
+                presentation.shows_clock() && current_minute() != rendered_minute
+            }
+            _ => false,
+        }
+    }
+
+    fn run_attached_snippets(&self, drawer: &mut TerminalDrawer<Stdout>, snippets: &[TerminalSnippet]) -> RenderResult {
+        drawer.suspend()?;
+        for snippet in snippets {
+            // Errors are displayed in the slide once we go back to it.
+            let error = match self.options.code_executer.execute_attached(&snippet.code) {
+                Ok(ExecutionState { status: ProcessStatus::TimedOut, .. }) => Some("[timed out]".to_string()),
+                Ok(ExecutionState { status: ProcessStatus::Failure, exit: Some(exit), .. }) => {
+                    Some(format!("[{exit}]"))
+                }
+                Ok(ExecutionState { status: ProcessStatus::Failure, exit: None, .. }) => Some("[failed]".to_string()),
+                Ok(_) => None,
+                Err(e) => Some(format!("failed to execute code: {e}")),
+            };
+            *snippet.error.borrow_mut() = error;
+        }
+        drawer.resume()?;
+        Ok(())
+    }
+
     fn update_widgets(&mut self, drawer: &mut TerminalDrawer<Stdout>) -> RenderResult {
         let current_index = self.state.presentation().current_slide_index();
         if self.slides_with_pending_widgets.contains(&current_index) {
@@ -123,7 +209,15 @@ impl<'a> Presenter<'a> {
                 }
                 return CommandSideEffect::Reload;
             }
-            Command::Exit => return CommandSideEffect::Exit,
+            Command::Exit => {
+                // If anything is running in the current slide, stop that rather than exiting.
+                if let PresenterState::Presenting(presentation) = &mut self.state {
+                    if presentation.abort_slide_widgets() {
+                        return CommandSideEffect::PollWidgets;
+                    }
+                }
+                return CommandSideEffect::Exit;
+            }
             _ => (),
         };
 
@@ -138,14 +232,42 @@ impl<'a> Presenter<'a> {
             Command::JumpFirstSlide => presentation.jump_first_slide(),
             Command::JumpLastSlide => presentation.jump_last_slide(),
             Command::JumpSlide(number) => presentation.jump_slide(number.saturating_sub(1) as usize),
-            Command::RenderWidgets => {
-                if presentation.render_slide_widgets() {
+            Command::FocusNextLink => presentation.focus_next_link(),
+            Command::FollowLink => presentation.follow_link(),
+            Command::RenderWidgets | Command::RerenderWidgets => {
+                let any_rendered = match command {
+                    Command::RenderWidgets => presentation.render_slide_widgets(),
+                    _ => presentation.rerender_slide_widgets(),
+                };
+                let snippets = presentation.current_terminal_snippets();
+                if !snippets.is_empty() {
+                    return CommandSideEffect::AcquireTerminal(snippets);
+                }
+                if any_rendered {
                     self.slides_with_pending_widgets.insert(self.state.presentation().current_slide_index());
                     return CommandSideEffect::PollWidgets;
                 } else {
                     return CommandSideEffect::None;
                 }
             }
+            Command::ResetWidgets => {
+                // There's nothing left to poll.
+                self.slides_with_pending_widgets.remove(&presentation.current_slide_index());
+                presentation.reset_slide_widgets()
+            }
+            Command::ScrollWidgetsUp => presentation.scroll_slide_widgets(-1),
+            Command::ScrollWidgetsDown => presentation.scroll_slide_widgets(1),
+            Command::ToggleWidgetsFollow => presentation.toggle_slide_widgets_follow(),
+            Command::CopyCode => {
+                return match presentation.last_visible_code_block() {
+                    Some(code) => CommandSideEffect::CopyToClipboard(code.to_string()),
+                    None => CommandSideEffect::None,
+                };
+            }
+            Command::WidgetInput(input) => {
+                presentation.send_widget_input(&input);
+                return CommandSideEffect::None;
+            }
             // These are handled above as they don't require the presentation
             Command::Reload | Command::HardReload | Command::Exit => panic!("unreachable commands"),
         };
@@ -167,6 +289,10 @@ impl<'a> Presenter<'a> {
                     presentation.jump_slide(current.current_slide_index());
                     presentation.jump_chunk(current.current_chunk());
                 }
+                // Executed code survives reloads so keep polling it if it's still running.
+                if presentation.widgets_rendering() {
+                    self.slides_with_pending_widgets.insert(presentation.current_slide_index());
+                }
                 self.state = PresenterState::Presenting(presentation)
             }
             Err(e) => {
@@ -179,10 +305,19 @@ impl<'a> Presenter<'a> {
     fn load_presentation(&mut self, path: &Path) -> Result<Presentation, LoadPresentationError> {
         let content = fs::read_to_string(path).map_err(LoadPresentationError::Reading)?;
         let elements = self.parser.parse(&content)?;
-        let presentation =
-            PresentationBuilder::new(self.default_highlighter.clone(), self.default_theme, &mut self.resources)
-                .build(elements)?;
-        Ok(presentation)
+        let presentation = PresentationBuilder::new(
+            self.default_highlighter.clone(),
+            self.default_theme,
+            &mut self.resources,
+            &mut self.execution_cache,
+            self.options.clone(),
+        )
+        .build(elements);
+        // Do this even if building failed so fixing a broken file triggers a reload.
+        for path in self.resources.external_files() {
+            self.commands.watch_external_file(path);
+        }
+        Ok(presentation?)
     }
 }
 
@@ -190,6 +325,8 @@ enum CommandSideEffect {
     Exit,
     Redraw,
     PollWidgets,
+    CopyToClipboard(String),
+    AcquireTerminal(Vec<TerminalSnippet>),
     Reload,
     None,
 }
@@ -0,0 +1,423 @@
+use crate::{
+    markdown::elements::StyledText,
+    style::{Color, Colors, TextStyle},
+};
+use once_cell::sync::Lazy;
+use std::{env, fmt::Write, iter::Peekable, str::Chars};
+
+static DETECTED_COLOR_DEPTH: Lazy<ColorDepth> = Lazy::new(ColorDepth::detect);
+
+/// The colors used for the 16 standard ANSI colors, as used by xterm.
+const STANDARD_COLORS: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+/// The levels of the 6x6x6 color cube in the 256 color palette.
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// The colors a terminal can display.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ColorDepth {
+    /// Any RGB color.
+    TrueColor,
+
+    /// The 256 color palette.
+    Palette256,
+
+    /// The 16 standard colors.
+    Palette16,
+}
+
+impl ColorDepth {
+    /// Figure out the colors the terminal we're running in can display based on the `COLORTERM` and `TERM`
+    /// environment variables.
+    pub(crate) fn detect() -> Self {
+        let colorterm = env::var("COLORTERM").ok();
+        let term = env::var("TERM").ok();
+        Self::from_variables(colorterm.as_deref(), term.as_deref())
+    }
+
+    /// Get the colors the terminal we're running in can display, which is only detected once.
+    pub(crate) fn detected() -> Self {
+        *DETECTED_COLOR_DEPTH
+    }
+
+    fn from_variables(colorterm: Option<&str>, term: Option<&str>) -> Self {
+        if matches!(colorterm, Some("truecolor" | "24bit")) {
+            return Self::TrueColor;
+        }
+        match term {
+            Some(term) if term.ends_with("-direct") => Self::TrueColor,
+            Some(term) if term.contains("256color") => Self::Palette256,
+            Some("xterm" | "xterm-color" | "screen" | "tmux" | "linux" | "vt100" | "vt220" | "ansi" | "rxvt") => {
+                Self::Palette16
+            }
+            // Anything else is most likely a modern terminal that didn't bother setting COLORTERM.
+            _ => Self::TrueColor,
+        }
+    }
+
+    /// Get the SGR parameters that set the given color as the foreground or background color.
+    pub(crate) fn sgr_parameters(&self, (r, g, b): (u8, u8, u8), background: bool) -> String {
+        match self {
+            Self::TrueColor => {
+                let code = if background { 48 } else { 38 };
+                format!("{code};2;{r};{g};{b}")
+            }
+            Self::Palette256 => {
+                let code = if background { 48 } else { 38 };
+                format!("{code};5;{}", nearest_palette_color(r, g, b))
+            }
+            Self::Palette16 => {
+                let index = nearest_standard_color(r, g, b);
+                let code = match (index, background) {
+                    (0..=7, false) => 30 + index,
+                    (_, false) => 90 + index - 8,
+                    (0..=7, true) => 40 + index,
+                    (_, true) => 100 + index - 8,
+                };
+                code.to_string()
+            }
+        }
+    }
+
+    /// Turn styled pieces of text into a string that uses escape codes to style them.
+    ///
+    /// Colors are downsampled if the terminal can't display arbitrary RGB colors.
+    pub(crate) fn escape(&self, spans: &[StyledText]) -> String {
+        let mut output = String::new();
+        for StyledText { text, style, .. } in spans {
+            output.push_str(&self.escape_colors(&style.colors));
+            let attributes = [
+                (style.is_bold(), 1),
+                (style.is_italics(), 3),
+                (style.is_underlined(), 4),
+                (style.is_strikethrough(), 9),
+            ];
+            for (_, code) in attributes.iter().filter(|(enabled, _)| *enabled) {
+                let _ = write!(output, "\x1b[{code}m");
+            }
+            output.push_str(text);
+            if attributes.iter().any(|(enabled, _)| *enabled) {
+                output.push_str("\x1b[22;23;24;29m");
+            }
+        }
+        output
+    }
+
+    /// Get the escape codes that set the given colors, downsampling them if needed.
+    pub(crate) fn escape_colors(&self, colors: &Colors) -> String {
+        let mut output = String::new();
+        for (color, background) in [(colors.background, true), (colors.foreground, false)] {
+            if let Some(rgb) = color.and_then(|color| color.as_rgb()) {
+                let _ = write!(output, "\x1b[{}m", self.sgr_parameters(rgb, background));
+            }
+        }
+        output
+    }
+}
+
+/// Find the closest color to the given one in the 256 color palette.
+///
+/// Only the color cube and the grayscale ramp are considered as the first 16 colors vary between terminals.
+pub(crate) fn nearest_palette_color(r: u8, g: u8, b: u8) -> u8 {
+    let cube_index = |value: u8| {
+        CUBE_LEVELS
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, level)| level.abs_diff(value))
+            .map(|(index, _)| index)
+            .unwrap_or(0)
+    };
+    let (red, green, blue) = (cube_index(r), cube_index(g), cube_index(b));
+    let cube_color = (CUBE_LEVELS[red], CUBE_LEVELS[green], CUBE_LEVELS[blue]);
+
+    let average = (r as u16 + g as u16 + b as u16) / 3;
+    let gray_index = (average.saturating_sub(3) / 10).min(23) as u8;
+    let gray = 8 + gray_index * 10;
+
+    if distance((r, g, b), (gray, gray, gray)) < distance((r, g, b), cube_color) {
+        232 + gray_index
+    } else {
+        16 + (36 * red + 6 * green + blue) as u8
+    }
+}
+
+/// Find the closest color to the given one among the 16 standard ones.
+pub(crate) fn nearest_standard_color(r: u8, g: u8, b: u8) -> u8 {
+    STANDARD_COLORS
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, color)| distance((r, g, b), **color))
+        .map(|(index, _)| index as u8)
+        .unwrap_or(0)
+}
+
+fn distance(first: (u8, u8, u8), second: (u8, u8, u8)) -> u32 {
+    let component = |a: u8, b: u8| (a as i32 - b as i32).pow(2) as u32;
+    component(first.0, second.0) + component(first.1, second.1) + component(first.2, second.2)
+}
+
+/// Splits text that contains ANSI escape codes into styled chunks.
+///
+/// Only SGR sequences (the ones that change colors and text attributes) are interpreted. Any other
+/// escape sequence is dropped. The style is kept across lines as some tools only reset it once
+/// they're done writing.
+pub(crate) struct AnsiSplitter {
+    base_colors: Colors,
+    colors: Colors,
+    bold: bool,
+    italics: bool,
+    strikethrough: bool,
+}
+
+impl AnsiSplitter {
+    /// Construct a new splitter that uses the given colors when none are set.
+    pub(crate) fn new(base_colors: Colors) -> Self {
+        Self { colors: base_colors.clone(), base_colors, bold: false, italics: false, strikethrough: false }
+    }
+
+    /// Split a line into styled chunks.
+    pub(crate) fn split_line(&mut self, line: &str) -> Vec<StyledText> {
+        let mut chunks = Vec::new();
+        let mut text = String::new();
+        let mut chars = line.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c != '\x1b' {
+                text.push(c);
+                continue;
+            }
+            if !text.is_empty() {
+                chunks.push(StyledText::new(std::mem::take(&mut text), self.style()));
+            }
+            match chars.next() {
+                Some('[') => {
+                    let (parameters, terminator) = Self::read_csi(&mut chars);
+                    if terminator == Some('m') {
+                        self.apply_sgr(&parameters);
+                    }
+                }
+                Some(']') => Self::skip_osc(&mut chars),
+                _ => (),
+            }
+        }
+        if !text.is_empty() {
+            chunks.push(StyledText::new(text, self.style()));
+        }
+        chunks
+    }
+
+    fn read_csi(chars: &mut Peekable<Chars>) -> (String, Option<char>) {
+        let mut parameters = String::new();
+        for c in chars.by_ref() {
+            if ('\x40'..='\x7e').contains(&c) {
+                return (parameters, Some(c));
+            }
+            parameters.push(c);
+        }
+        (parameters, None)
+    }
+
+    fn skip_osc(chars: &mut Peekable<Chars>) {
+        // These are terminated either by BEL or by ST (ESC \).
+        while let Some(c) = chars.next() {
+            match c {
+                '\x07' => return,
+                '\x1b' if chars.peek() == Some(&'\\') => {
+                    chars.next();
+                    return;
+                }
+                _ => (),
+            }
+        }
+    }
+
+    fn apply_sgr(&mut self, parameters: &str) {
+        let mut codes = parameters.split(';').map(|code| code.parse::<u8>().unwrap_or(0));
+        while let Some(code) = codes.next() {
+            match code {
+                0 => self.reset(),
+                1 => self.bold = true,
+                3 => self.italics = true,
+                9 => self.strikethrough = true,
+                22 => self.bold = false,
+                23 => self.italics = false,
+                29 => self.strikethrough = false,
+                30..=37 => self.colors.foreground = Some(Self::standard_color(code - 30)),
+                38 => self.colors.foreground = Self::extended_color(&mut codes).or(self.colors.foreground),
+                39 => self.colors.foreground = self.base_colors.foreground,
+                40..=47 => self.colors.background = Some(Self::standard_color(code - 40)),
+                48 => self.colors.background = Self::extended_color(&mut codes).or(self.colors.background),
+                49 => self.colors.background = self.base_colors.background,
+                90..=97 => self.colors.foreground = Some(Self::standard_color(code - 90 + 8)),
+                100..=107 => self.colors.background = Some(Self::standard_color(code - 100 + 8)),
+                _ => (),
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.colors = self.base_colors.clone();
+        self.bold = false;
+        self.italics = false;
+        self.strikethrough = false;
+    }
+
+    fn extended_color<I: Iterator<Item = u8>>(codes: &mut I) -> Option<Color> {
+        match codes.next()? {
+            5 => Some(Self::palette_color(codes.next()?)),
+            2 => Some(Color::new(codes.next()?, codes.next()?, codes.next()?)),
+            _ => None,
+        }
+    }
+
+    fn standard_color(index: u8) -> Color {
+        let (r, g, b) = STANDARD_COLORS[index as usize];
+        Color::new(r, g, b)
+    }
+
+    fn palette_color(index: u8) -> Color {
+        match index {
+            0..=15 => Self::standard_color(index),
+            16..=231 => {
+                let index = index - 16;
+                let level = |value: u8| if value == 0 { 0 } else { 55 + value * 40 };
+                Color::new(level(index / 36), level(index / 6 % 6), level(index % 6))
+            }
+            _ => {
+                let value = 8 + (index - 232) * 10;
+                Color::new(value, value, value)
+            }
+        }
+    }
+
+    fn style(&self) -> TextStyle {
+        let mut style = TextStyle::default().colors(self.colors.clone());
+        if self.bold {
+            style = style.bold();
+        }
+        if self.italics {
+            style = style.italics();
+        }
+        if self.strikethrough {
+            style = style.strikethrough();
+        }
+        style
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    fn foreground(r: u8, g: u8, b: u8) -> TextStyle {
+        TextStyle::default().colors(Colors { foreground: Some(Color::new(r, g, b)), background: None })
+    }
+
+    #[test]
+    fn plain_text() {
+        let chunks = AnsiSplitter::new(Colors::default()).split_line("hello");
+        assert_eq!(chunks, &[StyledText::new("hello", TextStyle::default())]);
+    }
+
+    #[test]
+    fn colors_and_reset() {
+        let chunks = AnsiSplitter::new(Colors::default()).split_line("\x1b[31mred\x1b[0m plain \x1b[1;92mbold");
+        let expected = [
+            StyledText::new("red", foreground(205, 0, 0)),
+            StyledText::new(" plain ", TextStyle::default()),
+            StyledText::new("bold", foreground(0, 255, 0).bold()),
+        ];
+        assert_eq!(chunks, expected);
+    }
+
+    #[test]
+    fn style_spans_lines() {
+        let mut splitter = AnsiSplitter::new(Colors::default());
+        splitter.split_line("\x1b[3mfoo");
+        let chunks = splitter.split_line("bar");
+        assert_eq!(chunks, &[StyledText::new("bar", TextStyle::default().italics())]);
+    }
+
+    #[test]
+    fn non_sgr_sequences_dropped() {
+        let chunks = AnsiSplitter::new(Colors::default()).split_line("\x1b[2Kfoo\x1b]0;title\x07bar");
+        let text: String = chunks.into_iter().map(|chunk| chunk.text).collect();
+        assert_eq!(text, "foobar");
+    }
+
+    #[rstest]
+    #[case::palette_standard("38;5;1", foreground(205, 0, 0))]
+    #[case::palette_cube("38;5;196", foreground(255, 0, 0))]
+    #[case::palette_gray("38;5;232", foreground(8, 8, 8))]
+    #[case::rgb("38;2;1;2;3", foreground(1, 2, 3))]
+    fn extended_colors(#[case] parameters: &str, #[case] expected: TextStyle) {
+        let chunks = AnsiSplitter::new(Colors::default()).split_line(&format!("\x1b[{parameters}mx"));
+        assert_eq!(chunks, &[StyledText::new("x", expected)]);
+    }
+
+    #[rstest]
+    #[case::truecolor(ColorDepth::TrueColor, "\x1b[48;2;1;2;3m\x1b[38;2;200;10;10m\x1b[1mhi\x1b[22;23;24;29m")]
+    #[case::palette256(ColorDepth::Palette256, "\x1b[48;5;16m\x1b[38;5;160m\x1b[1mhi\x1b[22;23;24;29m")]
+    #[case::palette16(ColorDepth::Palette16, "\x1b[40m\x1b[31m\x1b[1mhi\x1b[22;23;24;29m")]
+    fn escape(#[case] depth: ColorDepth, #[case] expected: &str) {
+        let colors = Colors { background: Some(Color::new(1, 2, 3)), foreground: Some(Color::new(200, 10, 10)) };
+        let style = TextStyle::default().colors(colors).bold();
+        assert_eq!(depth.escape(&[StyledText::new("hi", style)]), expected);
+    }
+
+    #[rstest]
+    #[case::truecolor(Some("truecolor"), Some("xterm"), ColorDepth::TrueColor)]
+    #[case::palette256(None, Some("xterm-256color"), ColorDepth::Palette256)]
+    #[case::direct(None, Some("xterm-direct"), ColorDepth::TrueColor)]
+    #[case::palette16(None, Some("linux"), ColorDepth::Palette16)]
+    #[case::unknown(None, Some("xterm-kitty"), ColorDepth::TrueColor)]
+    #[case::nothing(None, None, ColorDepth::TrueColor)]
+    fn detect_color_depth(#[case] colorterm: Option<&str>, #[case] term: Option<&str>, #[case] expected: ColorDepth) {
+        assert_eq!(ColorDepth::from_variables(colorterm, term), expected);
+    }
+
+    #[rstest]
+    #[case::black((0, 0, 0), 16)]
+    #[case::red((255, 0, 0), 196)]
+    #[case::almost_red((250, 10, 5), 196)]
+    #[case::gray((128, 128, 128), 244)]
+    #[case::white((255, 255, 255), 231)]
+    fn palette_colors(#[case] color: (u8, u8, u8), #[case] expected: u8) {
+        assert_eq!(nearest_palette_color(color.0, color.1, color.2), expected);
+    }
+
+    #[rstest]
+    #[case::black((10, 10, 10), 0)]
+    #[case::red((200, 10, 10), 1)]
+    #[case::bright_blue((90, 90, 250), 12)]
+    #[case::white((250, 250, 250), 15)]
+    fn standard_colors(#[case] color: (u8, u8, u8), #[case] expected: u8) {
+        assert_eq!(nearest_standard_color(color.0, color.1, color.2), expected);
+    }
+
+    #[rstest]
+    #[case::truecolor(ColorDepth::TrueColor, false, "38;2;200;10;10")]
+    #[case::palette256(ColorDepth::Palette256, true, "48;5;160")]
+    #[case::palette16(ColorDepth::Palette16, false, "31")]
+    #[case::palette16_background(ColorDepth::Palette16, true, "41")]
+    fn sgr_parameters(#[case] depth: ColorDepth, #[case] background: bool, #[case] expected: &str) {
+        assert_eq!(depth.sgr_parameters((200, 10, 10), background), expected);
+    }
+}
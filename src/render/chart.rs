@@ -0,0 +1,352 @@
+use crate::markdown::text::TextWidth;
+use serde::Deserialize;
+
+/// The characters used to draw the last cell in a bar, by the number of eighths of it that are filled.
+const PARTIAL_BLOCKS: [char; 8] = [' ', '▏', '▎', '▍', '▌', '▋', '▊', '▉'];
+
+/// A list of labels and the value for each of them.
+type DataPoints = Vec<(String, f64)>;
+
+/// A chart defined by a small piece of data.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Chart {
+    kind: ChartKind,
+    title: Option<String>,
+    points: DataPoints,
+}
+
+impl Chart {
+    /// Parse a chart.
+    ///
+    /// This can either be a YAML mapping with the chart's `type`, `title`, and `data`, or a list of `label,value`
+    /// lines, optionally starting with a header, which is drawn as a bar chart. The `data` in the YAML form can be
+    /// either a mapping from labels to values or a string containing `label,value` lines.
+    pub(crate) fn parse(input: &str) -> Result<Self, ParseChartError> {
+        let definition = match serde_yaml::from_str(input) {
+            Ok(serde_yaml::Value::Mapping(_)) => serde_yaml::from_str(input)?,
+            _ => ChartDefinition { kind: ChartKind::Bar, title: None, data: ChartData::Csv(input.into()) },
+        };
+        let (header, points) = match definition.data {
+            ChartData::Csv(input) => parse_csv(&input)?,
+            ChartData::Values(values) => (None, parse_values(values)?),
+        };
+        if points.is_empty() {
+            return Err(ParseChartError::NoData);
+        }
+        Ok(Self { kind: definition.kind, title: definition.title.or(header), points })
+    }
+
+    /// Get this chart's title.
+    pub(crate) fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    /// Draw this chart so it fits in the given number of columns.
+    ///
+    /// Line charts are drawn using the given number of rows for the plot itself, plus the ones used by the x axis.
+    /// Every line has the same width.
+    pub(crate) fn render(&self, columns: usize, rows: usize) -> Vec<ChartLine> {
+        match self.kind {
+            ChartKind::Bar => self.render_bars(columns),
+            ChartKind::Line => self.render_line(columns, rows.max(2)),
+        }
+    }
+
+    fn render_bars(&self, columns: usize) -> Vec<ChartLine> {
+        let values: Vec<_> = self.points.iter().map(|(_, value)| format_value(*value)).collect();
+        let label_width = self.points.iter().map(|(label, _)| label.width()).max().unwrap_or(0);
+        let value_width = values.iter().map(|value| value.width()).max().unwrap_or(0);
+        let bar_width = columns.saturating_sub(label_width + value_width + 2).max(1);
+        let max = self.points.iter().map(|(_, value)| *value).fold(0.0, f64::max);
+        let mut lines = Vec::new();
+        for ((label, value), formatted) in self.points.iter().zip(values) {
+            let eighths = match max > 0.0 {
+                true => (value.max(0.0) / max * (bar_width * 8) as f64).round() as usize,
+                false => 0,
+            };
+            let mut bar = "█".repeat(eighths / 8);
+            if eighths % 8 != 0 {
+                bar.push(PARTIAL_BLOCKS[eighths % 8]);
+            }
+            let padding = bar_width - bar.width();
+            lines.push(ChartLine(vec![
+                (pad_right(label, label_width + 1), ChartPart::Axis),
+                (bar + &" ".repeat(padding), ChartPart::Plot),
+                (format!(" {formatted:>value_width$}"), ChartPart::Axis),
+            ]));
+        }
+        lines
+    }
+
+    fn render_line(&self, columns: usize, rows: usize) -> Vec<ChartLine> {
+        let min = self.points.iter().map(|(_, value)| *value).fold(f64::INFINITY, f64::min);
+        let max = self.points.iter().map(|(_, value)| *value).fold(f64::NEG_INFINITY, f64::max);
+        let (min_label, max_label) = (format_value(min), format_value(max));
+        let label_width = min_label.width().max(max_label.width());
+        let plot_width = columns.saturating_sub(label_width + 1).max(1);
+
+        // Every braille character is a grid of 2 columns and 4 rows of dots.
+        let (dot_columns, dot_rows) = (plot_width * 2, rows * 4);
+        let dots: Vec<_> = self
+            .points
+            .iter()
+            .enumerate()
+            .map(|(index, (_, value))| {
+                let x = match self.points.len() {
+                    1 => 0,
+                    count => (index * (dot_columns - 1) + (count - 1) / 2) / (count - 1),
+                };
+                let ratio = if max > min { (value - min) / (max - min) } else { 0.5 };
+                let y = dot_rows - 1 - (ratio * (dot_rows - 1) as f64).round() as usize;
+                (x, y)
+            })
+            .collect();
+        let mut grid = vec![vec![0_u8; plot_width]; rows];
+        let mut plot = |x: usize, y: usize| grid[y / 4][x / 2] |= braille_bit(x % 2, y % 4);
+        if let [(x, y)] = dots.as_slice() {
+            plot(*x, *y);
+        }
+        for segment in dots.windows(2) {
+            for (x, y) in line_points(segment[0], segment[1]) {
+                plot(x, y);
+            }
+        }
+
+        let mut lines = Vec::new();
+        for (row, cells) in grid.into_iter().enumerate() {
+            let (label, axis) = match row {
+                0 => (max_label.as_str(), '┤'),
+                _ if row == rows - 1 => (min_label.as_str(), '┤'),
+                _ => ("", '│'),
+            };
+            let cells: String = cells
+                .into_iter()
+                .map(|bits| if bits == 0 { ' ' } else { char::from_u32(0x2800 + bits as u32).unwrap_or(' ') })
+                .collect();
+            lines.push(ChartLine(vec![
+                (format!("{label:>label_width$}{axis}"), ChartPart::Axis),
+                (cells, ChartPart::Plot),
+            ]));
+        }
+        lines.push(ChartLine(vec![(
+            format!("{}└{}", " ".repeat(label_width), "─".repeat(plot_width)),
+            ChartPart::Axis,
+        )]));
+
+        // Label the first and last points below the x axis.
+        let first = &self.points[0].0;
+        let last = &self.points[self.points.len() - 1].0;
+        let mut x_labels = first.clone();
+        if self.points.len() > 1 && first.width() + last.width() < plot_width {
+            x_labels.push_str(&" ".repeat(plot_width - first.width() - last.width()));
+            x_labels.push_str(last);
+        }
+        let x_labels = format!("{}{}", " ".repeat(label_width + 1), x_labels);
+        lines.push(ChartLine(vec![(pad_right(&x_labels, label_width + 1 + plot_width), ChartPart::Axis)]));
+        lines
+    }
+}
+
+/// A line in a chart, made up of pieces that are styled differently.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct ChartLine(pub(crate) Vec<(String, ChartPart)>);
+
+/// A part of a chart.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum ChartPart {
+    /// Labels and axes.
+    Axis,
+
+    /// The bars or lines that represent the data.
+    Plot,
+}
+
+/// The type of a chart.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ChartKind {
+    /// Horizontal bars, one for each value.
+    #[default]
+    Bar,
+
+    /// A line that goes through every value.
+    Line,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ChartDefinition {
+    #[serde(default, rename = "type")]
+    kind: ChartKind,
+
+    #[serde(default)]
+    title: Option<String>,
+
+    data: ChartData,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ChartData {
+    Csv(String),
+    Values(serde_yaml::Mapping),
+}
+
+fn parse_values(values: serde_yaml::Mapping) -> Result<DataPoints, ParseChartError> {
+    let mut points = Vec::new();
+    for (label, value) in values {
+        // Labels can be numbers, like years.
+        let label = match label {
+            serde_yaml::Value::String(label) => label,
+            serde_yaml::Value::Number(label) => label.to_string(),
+            other => return Err(ParseChartError::InvalidValue(format!("{other:?}"))),
+        };
+        let value = value.as_f64().filter(|value| value.is_finite());
+        let value = value.ok_or_else(|| ParseChartError::InvalidValue(label.clone()))?;
+        points.push((label, value));
+    }
+    Ok(points)
+}
+
+fn parse_csv(input: &str) -> Result<(Option<String>, DataPoints), ParseChartError> {
+    let mut header = None;
+    let mut points = Vec::new();
+    for (index, line) in input.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let (label, value) = line.split_once(',').ok_or(ParseChartError::InvalidLine(index + 1))?;
+        let (label, value) = (label.trim(), value.trim());
+        match value.parse::<f64>() {
+            Ok(value) if value.is_finite() => points.push((label.to_string(), value)),
+            // The first line can be a header, in which case the name of the value column is the title.
+            _ if points.is_empty() && header.is_none() => header = Some(value.to_string()),
+            _ => return Err(ParseChartError::InvalidLine(index + 1)),
+        };
+    }
+    Ok((header, points))
+}
+
+fn format_value(value: f64) -> String {
+    let rounded = (value * 100.0).round() / 100.0;
+    rounded.to_string()
+}
+
+fn pad_right(text: &str, width: usize) -> String {
+    let padding = width.saturating_sub(text.width());
+    format!("{text}{}", " ".repeat(padding))
+}
+
+fn braille_bit(column: usize, row: usize) -> u8 {
+    match (column, row) {
+        (0, 3) => 0x40,
+        (1, 3) => 0x80,
+        (0, row) => 1 << row,
+        (_, row) => 1 << (row + 3),
+    }
+}
+
+/// Get the points in the line between 2 points, including both of them.
+fn line_points(from: (usize, usize), to: (usize, usize)) -> Vec<(usize, usize)> {
+    let (mut x, mut y) = (from.0 as isize, from.1 as isize);
+    let (to_x, to_y) = (to.0 as isize, to.1 as isize);
+    let (delta_x, delta_y) = ((to_x - x).abs(), -(to_y - y).abs());
+    let (step_x, step_y) = ((to_x - x).signum(), (to_y - y).signum());
+    let mut error = delta_x + delta_y;
+    let mut points = vec![(x as usize, y as usize)];
+    while (x, y) != (to_x, to_y) {
+        let doubled = error * 2;
+        if doubled >= delta_y {
+            error += delta_y;
+            x += step_x;
+        }
+        if doubled <= delta_x {
+            error += delta_x;
+            y += step_y;
+        }
+        points.push((x as usize, y as usize));
+    }
+    points
+}
+
+/// An error when parsing a chart.
+#[derive(thiserror::Error, Debug)]
+pub enum ParseChartError {
+    #[error("invalid chart definition: {0}")]
+    InvalidDefinition(#[from] serde_yaml::Error),
+
+    #[error("line {0} is not in the form 'label,value'")]
+    InvalidLine(usize),
+
+    #[error("invalid value for '{0}'")]
+    InvalidValue(String),
+
+    #[error("chart has no data")]
+    NoData,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    fn points(points: &[(&str, f64)]) -> DataPoints {
+        points.iter().map(|(label, value)| (label.to_string(), *value)).collect()
+    }
+
+    fn texts(lines: &[ChartLine]) -> Vec<String> {
+        lines.iter().map(|line| line.0.iter().map(|(text, _)| text.as_str()).collect()).collect()
+    }
+
+    #[rstest]
+    #[case::csv("a,1\nb, 2.5\n", None, ChartKind::Bar)]
+    #[case::csv_header("name,visits\na,1\n\nb,2.5", Some("visits"), ChartKind::Bar)]
+    #[case::yaml_values("type: line\ntitle: hi\ndata:\n  a: 1\n  b: 2.5", Some("hi"), ChartKind::Line)]
+    #[case::yaml_csv("type: bar\ndata: |\n  a,1\n  b,2.5", None, ChartKind::Bar)]
+    fn parse(#[case] input: &str, #[case] title: Option<&str>, #[case] kind: ChartKind) {
+        let chart = Chart::parse(input).expect("parse failed");
+        let expected = Chart { kind, title: title.map(String::from), points: points(&[("a", 1.0), ("b", 2.5)]) };
+        assert_eq!(chart, expected);
+    }
+
+    #[test]
+    fn numeric_labels() {
+        let chart = Chart::parse("data:\n  2023: 1\n  2024: 2").expect("parse failed");
+        assert_eq!(chart.points, points(&[("2023", 1.0), ("2024", 2.0)]));
+    }
+
+    #[rstest]
+    #[case::empty("")]
+    #[case::no_value("a\nb,1")]
+    #[case::invalid_value("a,1\nb,potato")]
+    #[case::unknown_type("type: pie\ndata: 'a,1'")]
+    #[case::unknown_field("potato: 1\ndata: 'a,1'")]
+    #[case::non_numeric_value("data:\n  a: potato")]
+    fn parse_invalid(#[case] input: &str) {
+        assert!(Chart::parse(input).is_err());
+    }
+
+    #[test]
+    fn bars() {
+        let chart = Chart::parse("a,4\nbb,2\nc,0.25").expect("parse failed");
+        let lines = chart.render(15, 0);
+        assert_eq!(texts(&lines), &["a  ███████    4", "bb ███▌       2", "c  ▌       0.25"]);
+        assert!(lines.iter().all(|line| line.0[1].1 == ChartPart::Plot));
+    }
+
+    #[test]
+    fn line() {
+        let chart = Chart::parse("type: line\ndata: |\n  a,0\n  b,10\n  c,0").expect("parse failed");
+        let lines = texts(&chart.render(8, 2));
+        assert_eq!(lines, &["10┤ ⢀⠎⢆ ", " 0┤⡰⠁ ⠈⢆", "  └─────", "   a   c"]);
+    }
+
+    #[rstest]
+    #[case::horizontal((0, 0), (3, 0), &[(0, 0), (1, 0), (2, 0), (3, 0)])]
+    #[case::diagonal((0, 2), (2, 0), &[(0, 2), (1, 1), (2, 0)])]
+    #[case::single((1, 1), (1, 1), &[(1, 1)])]
+    fn line_between(#[case] from: (usize, usize), #[case] to: (usize, usize), #[case] expected: &[(usize, usize)]) {
+        assert_eq!(line_points(from, to), expected);
+    }
+}
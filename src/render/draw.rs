@@ -1,4 +1,4 @@
-use super::{engine::RenderEngine, terminal::Terminal};
+use super::{engine::RenderEngine, media::ImageProtocol, terminal::Terminal};
 use crate::{
     markdown::{
         elements::StyledText,
@@ -17,6 +17,7 @@ pub(crate) type RenderResult = Result<(), RenderError>;
 /// Allows drawing elements in the terminal.
 pub(crate) struct TerminalDrawer<W: io::Write> {
     terminal: Terminal<W>,
+    image_protocol: ImageProtocol,
 }
 
 impl<W> TerminalDrawer<W>
@@ -24,21 +25,44 @@ where
     W: io::Write,
 {
     /// Construct a drawer over a [std::io::Write].
-    pub(crate) fn new(handle: W) -> io::Result<Self> {
+    ///
+    /// If no image protocol is given, the one to use is detected based on the terminal being used.
+    pub(crate) fn new(handle: W, image_protocol: Option<ImageProtocol>) -> io::Result<Self> {
         let terminal = Terminal::new(handle)?;
-        Ok(Self { terminal })
+        // This needs to happen after the terminal is set up as detecting it may require querying it.
+        let image_protocol = image_protocol.unwrap_or_else(ImageProtocol::detect);
+        Ok(Self { terminal, image_protocol })
     }
 
     /// Render a slide.
     pub(crate) fn render_slide(&mut self, presentation: &Presentation) -> RenderResult {
         let window_dimensions = WindowSize::current()?;
         let slide = presentation.current_slide();
-        let engine = RenderEngine::new(&mut self.terminal, window_dimensions);
+        let engine = RenderEngine::new(&mut self.terminal, window_dimensions, self.image_protocol);
         engine.render(slide.iter_operations())?;
         self.terminal.flush()?;
         Ok(())
     }
 
+    /// Give up control of the terminal so another process can use it.
+    pub(crate) fn suspend(&mut self) -> RenderResult {
+        self.terminal.suspend()?;
+        Ok(())
+    }
+
+    /// Take back control of the terminal after a call to [TerminalDrawer::suspend].
+    pub(crate) fn resume(&mut self) -> RenderResult {
+        self.terminal.resume()?;
+        Ok(())
+    }
+
+    /// Copy some text into the system clipboard.
+    pub(crate) fn copy_to_clipboard(&mut self, text: &str) -> RenderResult {
+        self.terminal.copy_to_clipboard(text)?;
+        self.terminal.flush()?;
+        Ok(())
+    }
+
     /// Render an error.
     pub(crate) fn render_error(&mut self, message: &str) -> RenderResult {
         let dimensions = WindowSize::current()?;
@@ -60,7 +84,7 @@ where
             RenderOperation::RenderLineBreak,
             RenderOperation::RenderText { line: WeightedLine::from(error), alignment: alignment.clone() },
         ];
-        let engine = RenderEngine::new(&mut self.terminal, dimensions);
+        let engine = RenderEngine::new(&mut self.terminal, dimensions, self.image_protocol);
         engine.render(operations.iter())?;
         self.terminal.flush()?;
         Ok(())
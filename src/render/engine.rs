@@ -1,19 +1,24 @@
 use super::{
+    ansi::ColorDepth,
     draw::{RenderError, RenderResult},
     layout::Layout,
-    media::{Image, MediaRender},
+    media::{Image, ImageProtocol, MediaRender},
     properties::CursorPosition,
     terminal::Terminal,
-    text::TextDrawer,
+    text::{split_preformatted_line, TextDrawer},
 };
 use crate::{
-    markdown::text::WeightedLine,
-    presentation::{AsRenderOperations, MarginProperties, PreformattedLine, RenderOnDemand, RenderOperation},
+    markdown::{elements::StyledText, text::WeightedLine},
+    presentation::{
+        AsRenderOperations, ColumnLayout, ImagePosition, MarginProperties, PreformattedLine, RenderOnDemand,
+        RenderOperation,
+    },
     render::{layout::Positioning, properties::WindowSize},
-    style::Colors,
-    theme::Alignment,
+    style::{Color, Colors, TextStyle},
+    theme::{Alignment, ColumnLayoutStyle, VerticalAlignment},
 };
-use std::{io, mem};
+use crossterm::style::Stylize;
+use std::{io, ops::Range};
 
 pub(crate) struct RenderEngine<'a, W>
 where
@@ -22,19 +27,39 @@ where
     terminal: &'a mut Terminal<W>,
     window_rects: Vec<WindowRect>,
     colors: Colors,
+    screen_background: Option<Color>,
+    column_background: Option<Color>,
     max_modified_row: u16,
-    layout: LayoutState,
+    layouts: Vec<LayoutState>,
+    media_render: MediaRender,
+    color_depth: ColorDepth,
+    measuring: bool,
 }
 
 impl<'a, W> RenderEngine<'a, W>
 where
     W: io::Write,
 {
-    pub(crate) fn new(terminal: &'a mut Terminal<W>, window_dimensions: WindowSize) -> Self {
+    pub(crate) fn new(
+        terminal: &'a mut Terminal<W>,
+        window_dimensions: WindowSize,
+        image_protocol: ImageProtocol,
+    ) -> Self {
         let max_modified_row = terminal.cursor_row;
         let current_rect = WindowRect { dimensions: window_dimensions, start_column: 0 };
         let window_rects = vec![current_rect.clone()];
-        Self { terminal, window_rects, colors: Default::default(), max_modified_row, layout: Default::default() }
+        Self {
+            terminal,
+            window_rects,
+            colors: Default::default(),
+            screen_background: None,
+            column_background: None,
+            max_modified_row,
+            layouts: Vec::new(),
+            media_render: MediaRender::new(image_protocol),
+            color_depth: ColorDepth::detected(),
+            measuring: false,
+        }
     }
 
     pub(crate) fn render<'b>(mut self, operations: impl Iterator<Item = &'b RenderOperation>) -> RenderResult {
@@ -52,17 +77,24 @@ where
             RenderOperation::SetColors(colors) => self.set_colors(colors),
             RenderOperation::JumpToVerticalCenter => self.jump_to_vertical_center(),
             RenderOperation::JumpToBottomRow { index } => self.jump_to_bottom(*index),
+            RenderOperation::AlignVertically { alignment, contents } => self.align_vertically(*alignment, contents),
             RenderOperation::RenderText { line: texts, alignment } => self.render_text(texts, alignment),
             RenderOperation::RenderLineBreak => self.render_line_break(),
-            RenderOperation::RenderImage(image) => self.render_image(image),
+            RenderOperation::RenderImage(image, position) => self.render_image(image, *position),
+            RenderOperation::RenderBackgroundImage(image) => self.render_background_image(image),
             RenderOperation::RenderPreformattedLine(operation) => self.render_preformatted_line(operation),
             RenderOperation::RenderDynamic(generator) => self.render_dynamic(generator.as_ref()),
             RenderOperation::RenderOnDemand(generator) => self.render_on_demand(generator.as_ref()),
-            RenderOperation::InitColumnLayout { columns } => self.init_column_layout(columns),
+            RenderOperation::InitColumnLayout { columns, style } => self.init_column_layout(columns, style),
             RenderOperation::EnterColumn { column } => self.enter_column(*column),
-            RenderOperation::ExitLayout => self.exit_layout(),
+            RenderOperation::ExitLayout => self.exit_layouts(),
+            RenderOperation::ExitNestedLayout => self.exit_layout(),
         }?;
-        self.max_modified_row = self.max_modified_row.max(self.terminal.cursor_row);
+        let row = self.terminal.cursor_row;
+        self.max_modified_row = self.max_modified_row.max(row);
+        if let Some(LayoutState::EnteredColumn { max_row, .. }) = self.layouts.last_mut() {
+            *max_row = (*max_row).max(row);
+        }
         Ok(())
     }
 
@@ -76,16 +108,25 @@ where
     }
 
     fn clear_screen(&mut self) -> RenderResult {
+        self.media_render.clear_images(self.terminal)?;
         self.terminal.clear_screen()?;
         self.terminal.move_to(0, 0)?;
         self.max_modified_row = 0;
+        self.screen_background = self.colors.background;
+        self.column_background = None;
         Ok(())
     }
 
     fn apply_margin(&mut self, properties: &MarginProperties) -> RenderResult {
-        let MarginProperties { horizontal_margin, bottom_slide_margin } = properties;
+        let MarginProperties { horizontal_margin, max_width, bottom_slide_margin } = properties;
         let current = self.current_rect();
-        let margin = horizontal_margin.as_characters(current.dimensions.columns);
+        let columns = current.dimensions.columns;
+        let mut margin = horizontal_margin.as_characters(columns);
+        if let Some(max_width) = max_width {
+            // Split whatever doesn't fit evenly on both sides so the contents stay centered.
+            let width = columns.saturating_sub(margin.saturating_mul(2));
+            margin += width.saturating_sub(max_width.as_characters(columns)) / 2;
+        }
         let new_rect = current.apply_margin(margin).shrink_rows(*bottom_slide_margin);
         self.window_rects.push(new_rect);
         Ok(())
@@ -100,10 +141,25 @@ where
     }
 
     fn set_colors(&mut self, colors: &Colors) -> RenderResult {
-        self.colors = colors.clone();
+        let mut colors = colors.clone();
+        // Columns with a background of their own use it wherever the screen's one would be used.
+        if self.column_background.is_some() && colors.background == self.screen_background {
+            colors.background = self.column_background;
+        }
+        self.colors = colors;
         self.apply_colors()
     }
 
+    fn set_column_background(&mut self, background: Option<Color>) -> RenderResult {
+        let current = self.column_background.or(self.screen_background);
+        self.column_background = background;
+        if self.colors.background == current {
+            self.colors.background = background.or(self.screen_background);
+            self.apply_colors()?;
+        }
+        Ok(())
+    }
+
     fn apply_colors(&mut self) -> RenderResult {
         self.terminal.set_colors(self.colors.clone())?;
         Ok(())
@@ -121,6 +177,44 @@ where
         Ok(())
     }
 
+    fn align_vertically(&mut self, alignment: VerticalAlignment, operations: &[RenderOperation]) -> RenderResult {
+        let start_row = self.terminal.cursor_row;
+        // If we can't tell how tall the contents are, leave them where they are.
+        let Some(height) = self.measure(operations) else {
+            return Ok(());
+        };
+        let free_rows = self.current_dimensions().rows.saturating_sub(start_row).saturating_sub(height);
+        let offset = match alignment {
+            VerticalAlignment::Top => 0,
+            VerticalAlignment::Center => free_rows / 2,
+            VerticalAlignment::Bottom => free_rows,
+        };
+        self.terminal.move_to_row(start_row + offset)?;
+        Ok(())
+    }
+
+    /// Measure the number of rows the given operations take.
+    fn measure(&self, operations: &[RenderOperation]) -> Option<u16> {
+        let start_row = self.terminal.cursor_row;
+        let mut terminal = Terminal::sink(start_row);
+        let mut engine = RenderEngine {
+            terminal: &mut terminal,
+            window_rects: self.window_rects.clone(),
+            colors: self.colors.clone(),
+            screen_background: self.screen_background,
+            column_background: self.column_background,
+            max_modified_row: start_row,
+            layouts: self.layouts.clone(),
+            media_render: self.media_render.clone(),
+            color_depth: self.color_depth,
+            measuring: true,
+        };
+        for operation in operations {
+            engine.render_one(operation).ok()?;
+        }
+        Some(engine.max_modified_row.saturating_sub(start_row))
+    }
+
     fn render_text(&mut self, text: &WeightedLine, alignment: &Alignment) -> RenderResult {
         let layout = self.build_layout(alignment.clone());
         let text_drawer = TextDrawer::new(&layout, text, self.current_dimensions(), &self.colors)?;
@@ -132,35 +226,88 @@ where
         Ok(())
     }
 
-    fn render_image(&mut self, image: &Image) -> RenderResult {
+    fn render_image(&mut self, image: &Image, image_position: ImagePosition) -> RenderResult {
         let position = CursorPosition { row: self.terminal.cursor_row, column: self.current_rect().start_column };
-        MediaRender
-            .draw_image(image, position, self.current_dimensions())
+        let dimensions = self.current_rect().dimensions.clone();
+        if self.measuring {
+            let rows =
+                MediaRender::image_rows(image, position, &dimensions).map_err(|e| RenderError::Other(Box::new(e)))?;
+            self.terminal.move_down(rows)?;
+            return Ok(());
+        }
+        self.media_render
+            .draw_image(image, position, image_position, &dimensions, self.terminal)
             .map_err(|e| RenderError::Other(Box::new(e)))?;
         // TODO try to avoid
         self.terminal.sync_cursor_row()?;
         Ok(())
     }
 
+    fn render_background_image(&mut self, image: &Image) -> RenderResult {
+        if self.measuring {
+            return Ok(());
+        }
+        let row = self.terminal.cursor_row;
+        // Backgrounds cover the entire window regardless of any margins.
+        let dimensions = self.window_rects[0].dimensions.clone();
+        self.media_render
+            .draw_background(image, &dimensions, self.terminal)
+            .map_err(|e| RenderError::Other(Box::new(e)))?;
+        self.terminal.move_to(0, row)?;
+        Ok(())
+    }
+
     fn render_preformatted_line(&mut self, operation: &PreformattedLine) -> RenderResult {
-        let PreformattedLine { text, unformatted_length, block_length, alignment } = operation;
+        let PreformattedLine { spans, unformatted_length, block_length, alignment, overflow, raw } = operation;
         let layout = self.build_layout(alignment.clone());
 
-        let Positioning { max_line_length, start_column } =
+        let Positioning { max_line_length, mut start_column } =
             layout.compute(self.current_dimensions(), *block_length as u16);
-        self.terminal.move_to_column(start_column)?;
-
-        let until_right_edge = usize::from(max_line_length).saturating_sub(*unformatted_length);
+        let max_line_length = usize::from(max_line_length);
+        if *raw && *block_length > max_line_length {
+            // Raw blocks use the margins around them rather than going past the screen's edge.
+            let terminal_columns = self.window_rects[0].dimensions.columns;
+            start_column = self.current_rect().overflowing_block_start(terminal_columns, *block_length as u16);
+        }
+        let lines = match overflow {
+            Some(overflow) if *unformatted_length > max_line_length => {
+                split_preformatted_line(spans, max_line_length, overflow)
+            }
+            _ => vec![(spans.clone(), *unformatted_length)],
+        };
+        for (index, (spans, length)) in lines.iter().enumerate() {
+            if index > 0 {
+                self.terminal.move_to_next_line(1)?;
+            }
+            self.terminal.move_to_column(start_column)?;
 
-        // Pad this code block with spaces so we get a nice little rectangle.
-        self.terminal.print_line(text)?;
-        self.terminal.print_line(&" ".repeat(until_right_edge))?;
+            // Pad this code block with spaces so we get a nice little rectangle.
+            let until_right_edge = max_line_length.saturating_sub(*length);
+            self.terminal.print_line(&self.escape(spans))?;
+            self.terminal.print_line(&" ".repeat(until_right_edge))?;
+        }
 
         // Restore colors
         self.apply_colors()?;
         Ok(())
     }
 
+    /// Turn styled pieces of text into escape codes the terminal can display.
+    ///
+    /// Pieces of text that don't set any colors use the ones currently in use.
+    fn escape(&self, spans: &[StyledText]) -> String {
+        let base_style = TextStyle::default().colors(self.colors.clone());
+        let spans: Vec<_> = spans
+            .iter()
+            .map(|span| {
+                let mut span = span.clone();
+                span.style.merge(&base_style);
+                span
+            })
+            .collect();
+        self.color_depth.escape(&spans)
+    }
+
     fn render_dynamic(&mut self, generator: &dyn AsRenderOperations) -> RenderResult {
         let operations = generator.as_render_operations(self.current_dimensions());
         for operation in operations {
@@ -177,36 +324,58 @@ where
         Ok(())
     }
 
-    fn init_column_layout(&mut self, columns: &[u8]) -> RenderResult {
-        if !matches!(self.layout, LayoutState::Default) {
-            self.exit_layout()?;
+    fn init_column_layout(&mut self, layout: &ColumnLayout, style: &ColumnLayoutStyle) -> RenderResult {
+        // A layout defined while in a column is nested in it, otherwise it replaces the one that wasn't entered.
+        if matches!(self.layouts.last(), Some(LayoutState::InitializedColumn { .. })) {
+            self.layouts.pop();
         }
-        let columns = columns.iter().copied().map(u16::from).collect();
-        let current_position = CursorPosition::current()?;
-        self.layout = LayoutState::InitializedColumn { columns, start_row: current_position.row };
+        let terminal_columns = self.window_rects[0].dimensions.columns;
+        let columns = layout.widths(terminal_columns).iter().copied().map(u16::from).collect();
+        let start_row = self.terminal.cursor_row;
+        let layout =
+            ColumnLayoutState { columns, start_row, style: style.clone(), parent_background: self.column_background };
+        self.layouts.push(LayoutState::InitializedColumn(layout));
         Ok(())
     }
 
     fn enter_column(&mut self, column_index: usize) -> RenderResult {
-        let (columns, start_row) = match mem::take(&mut self.layout) {
-            LayoutState::Default => return Err(RenderError::InvalidLayoutEnter),
-            LayoutState::InitializedColumn { columns, .. } | LayoutState::EnteredColumn { columns, .. }
-                if column_index >= columns.len() =>
+        let (layout, max_row) = match self.layouts.pop() {
+            None => return Err(RenderError::InvalidLayoutEnter),
+            Some(LayoutState::InitializedColumn(layout) | LayoutState::EnteredColumn { layout, .. })
+                if column_index >= layout.columns.len() =>
             {
                 return Err(RenderError::InvalidLayoutEnter);
             }
-            LayoutState::InitializedColumn { columns, start_row } => (columns, start_row),
-            LayoutState::EnteredColumn { columns, start_row, .. } => {
+            Some(LayoutState::InitializedColumn(layout)) => {
+                self.fill_column_backgrounds(&layout)?;
+                let start_row = layout.start_row;
+                (layout, start_row)
+            }
+            Some(LayoutState::EnteredColumn { layout, max_row }) => {
                 // Pop this one and start clean
                 self.pop_margin()?;
-                (columns, start_row)
+                (layout, max_row)
             }
         };
+        let style = layout.style.column(column_index);
+        let mut dimensions = self.column_rect(&layout.columns, column_index);
+        if let Some(padding) = style.padding {
+            dimensions = dimensions.apply_margin(padding.into());
+        }
+
+        self.window_rects.push(dimensions);
+        self.set_column_background(style.background.or(layout.parent_background))?;
+        self.terminal.move_to_row(layout.start_row)?;
+        self.layouts.push(LayoutState::EnteredColumn { layout, max_row });
+        Ok(())
+    }
+
+    /// Get the rect a column in a layout takes within the current rect.
+    fn column_rect(&self, columns: &[u16], column_index: usize) -> WindowRect {
         let total_column_units: u16 = columns.iter().sum();
-        let column_units_before: u16 = columns.iter().take(column_index).sum();
         let current_rect = self.current_rect();
         let unit_width = current_rect.dimensions.columns as f64 / total_column_units as f64;
-        let start_column = current_rect.start_column + (unit_width * column_units_before as f64) as u16;
+        let start_column = self.column_start(columns, column_index);
         let new_column_count = (total_column_units - columns[column_index]) * unit_width as u16;
         let new_size = current_rect.dimensions.shrink_columns(new_column_count);
         let mut dimensions = WindowRect { dimensions: new_size, start_column };
@@ -220,42 +389,111 @@ where
                 dimensions = dimensions.shrink_left(4);
             }
         }
+        dimensions
+    }
 
-        self.window_rects.push(dimensions);
-        self.layout = LayoutState::EnteredColumn { columns, start_row };
-        self.terminal.move_to_row(start_row)?;
+    /// Get the terminal column where a column in a layout starts, ignoring the gap between it and the previous one.
+    fn column_start(&self, columns: &[u16], column_index: usize) -> u16 {
+        let total_column_units: u16 = columns.iter().sum();
+        let column_units_before: u16 = columns.iter().take(column_index).sum();
+        let current_rect = self.current_rect();
+        let unit_width = current_rect.dimensions.columns as f64 / total_column_units as f64;
+        current_rect.start_column + (unit_width * column_units_before as f64) as u16
+    }
+
+    /// Paint the background of every column in a layout that has one, down to the bottom of the current rect.
+    fn fill_column_backgrounds(&mut self, layout: &ColumnLayoutState) -> RenderResult {
+        let rows = layout.start_row..self.current_dimensions().rows;
+        for column_index in 0..layout.columns.len() {
+            if let Some(background) = layout.style.column(column_index).background {
+                let rect = self.column_rect(&layout.columns, column_index);
+                self.fill(&rect, rows.clone(), Some(background))?;
+            }
+        }
         Ok(())
     }
 
+    /// Clear the column backgrounds below a layout's last row and draw the separators between its columns.
+    fn finish_column_layout(&mut self, layout: &ColumnLayoutState, end_row: u16) -> RenderResult {
+        self.set_column_background(layout.parent_background)?;
+        let background = layout.parent_background.or(self.screen_background);
+        let rows = end_row..self.current_dimensions().rows;
+        for column_index in 0..layout.columns.len() {
+            if layout.style.column(column_index).background.is_some() {
+                let rect = self.column_rect(&layout.columns, column_index);
+                self.fill(&rect, rows.clone(), background)?;
+            }
+        }
+        let Some(separator) = &layout.style.separator else {
+            return Ok(());
+        };
+        let mut colors = layout.style.separator_colors.clone();
+        colors.background = colors.background.or(background);
+        for column_index in 1..layout.columns.len() {
+            let column = self.column_start(&layout.columns, column_index);
+            for row in layout.start_row..end_row {
+                self.terminal.move_to(column, row)?;
+                self.terminal.print_styled_line(TextStyle::default().colors(colors.clone()).apply(separator))?;
+            }
+        }
+        self.apply_colors()
+    }
+
+    /// Fill the given rows of a rect, clipped to the current one, with a background color.
+    fn fill(&mut self, rect: &WindowRect, rows: Range<u16>, background: Option<Color>) -> RenderResult {
+        let current_rect = self.current_rect();
+        let end_column = (rect.start_column + rect.dimensions.columns)
+            .min(current_rect.start_column + current_rect.dimensions.columns);
+        let line = " ".repeat(end_column.saturating_sub(rect.start_column) as usize);
+        let background = background.map(Color::into).unwrap_or(crossterm::style::Color::Reset);
+        for row in rows {
+            self.terminal.move_to(rect.start_column, row)?;
+            self.terminal.print_styled_line(line.clone().on(background))?;
+        }
+        self.apply_colors()
+    }
+
     fn exit_layout(&mut self) -> RenderResult {
-        match &self.layout {
-            LayoutState::Default | LayoutState::InitializedColumn { .. } => Ok(()),
-            LayoutState::EnteredColumn { .. } => {
-                self.terminal.move_to(0, self.max_modified_row)?;
-                self.layout = LayoutState::Default;
+        match self.layouts.pop() {
+            None | Some(LayoutState::InitializedColumn { .. }) => Ok(()),
+            Some(LayoutState::EnteredColumn { layout, max_row }) => {
+                // Continue below the longest column in this layout, and make sure the parent one knows about it.
+                let row = max_row.max(self.terminal.cursor_row);
+                if let Some(LayoutState::EnteredColumn { max_row, .. }) = self.layouts.last_mut() {
+                    *max_row = (*max_row).max(row);
+                }
                 self.pop_margin()?;
+                self.finish_column_layout(&layout, row)?;
+                self.terminal.move_to(0, row)?;
                 Ok(())
             }
         }
     }
 
+    fn exit_layouts(&mut self) -> RenderResult {
+        while !self.layouts.is_empty() {
+            self.exit_layout()?;
+        }
+        Ok(())
+    }
+
     fn build_layout(&self, alignment: Alignment) -> Layout {
         Layout::new(alignment).with_start_column(self.current_rect().start_column)
     }
 }
 
-#[derive(Default)]
+#[derive(Clone)]
 enum LayoutState {
-    #[default]
-    Default,
-    InitializedColumn {
-        columns: Vec<u16>,
-        start_row: u16,
-    },
-    EnteredColumn {
-        columns: Vec<u16>,
-        start_row: u16,
-    },
+    InitializedColumn(ColumnLayoutState),
+    EnteredColumn { layout: ColumnLayoutState, max_row: u16 },
+}
+
+#[derive(Clone)]
+struct ColumnLayoutState {
+    columns: Vec<u16>,
+    start_row: u16,
+    style: ColumnLayoutStyle,
+    parent_background: Option<Color>,
 }
 
 #[derive(Clone, Debug)]
@@ -271,6 +509,12 @@ impl WindowRect {
         Self { dimensions, start_column }
     }
 
+    /// Get the column a block wider than this rect starts at so it's centered on it as much as the screen allows.
+    fn overflowing_block_start(&self, terminal_columns: u16, block_length: u16) -> u16 {
+        let center = self.start_column + self.dimensions.columns / 2;
+        center.saturating_sub(block_length / 2).min(terminal_columns.saturating_sub(block_length))
+    }
+
     fn shrink_left(&self, size: u16) -> Self {
         let dimensions = self.dimensions.clone();
         let start_column = self.start_column.saturating_add(size);
@@ -287,3 +531,170 @@ impl WindowRect {
         Self { dimensions, start_column: self.start_column }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::theme::{ColumnStyle, Margin};
+    use rstest::rstest;
+    use std::rc::Rc;
+
+    fn content() -> Vec<RenderOperation> {
+        vec![
+            RenderOperation::RenderText { line: String::from("hi").into(), alignment: Default::default() },
+            RenderOperation::RenderLineBreak,
+            RenderOperation::RenderText { line: String::from("bye").into(), alignment: Default::default() },
+            RenderOperation::RenderLineBreak,
+        ]
+    }
+
+    fn render(operations: &[RenderOperation]) -> u16 {
+        let mut terminal = Terminal::sink(0);
+        let dimensions = WindowSize { rows: 10, columns: 20, width: 0, height: 0, has_pixels: false };
+        let engine = RenderEngine::new(&mut terminal, dimensions, ImageProtocol::AsciiBlocks);
+        let margin = RenderOperation::ApplyMargin(MarginProperties {
+            horizontal_margin: Margin::Fixed(0),
+            max_width: None,
+            bottom_slide_margin: 0,
+        });
+        engine.render([margin].iter().chain(operations)).expect("render failed");
+        terminal.cursor_row
+    }
+
+    #[rstest]
+    #[case::margin(Margin::Fixed(2), None, 2, 16)]
+    #[case::wide_enough(Margin::Fixed(2), Some(Margin::Fixed(16)), 2, 16)]
+    #[case::max_width(Margin::Fixed(2), Some(Margin::Fixed(10)), 5, 10)]
+    #[case::max_width_percent(Margin::Fixed(0), Some(Margin::Percent(50)), 5, 10)]
+    #[case::max_width_odd(Margin::Fixed(0), Some(Margin::Fixed(15)), 2, 16)]
+    fn content_width(
+        #[case] horizontal_margin: Margin,
+        #[case] max_width: Option<Margin>,
+        #[case] start_column: u16,
+        #[case] columns: u16,
+    ) {
+        let mut terminal = Terminal::sink(0);
+        let dimensions = WindowSize { rows: 10, columns: 20, width: 0, height: 0, has_pixels: false };
+        let mut engine = RenderEngine::new(&mut terminal, dimensions, ImageProtocol::AsciiBlocks);
+        let properties = MarginProperties { horizontal_margin, max_width, bottom_slide_margin: 2 };
+        engine.apply_margin(&properties).expect("apply failed");
+
+        let rect = engine.current_rect();
+        assert_eq!(rect.start_column, start_column);
+        assert_eq!(rect.dimensions.columns, columns);
+        assert_eq!(rect.dimensions.rows, 8);
+    }
+
+    #[rstest]
+    #[case::centered(6, 10, 4)]
+    #[case::right_edge(12, 16, 6)]
+    #[case::wider_than_screen(6, 30, 0)]
+    fn overflowing_block(#[case] start_column: u16, #[case] block_length: u16, #[case] expected: u16) {
+        let dimensions = WindowSize { rows: 10, columns: 6, width: 0, height: 0, has_pixels: false };
+        let rect = WindowRect { dimensions, start_column };
+        assert_eq!(rect.overflowing_block_start(22, block_length), expected);
+    }
+
+    fn column_layout_style() -> ColumnLayoutStyle {
+        let columns = vec![
+            ColumnStyle { background: Some(Color::new(255, 0, 0)), padding: None },
+            ColumnStyle { background: None, padding: Some(2) },
+        ];
+        ColumnLayoutStyle { separator: Some("|".into()), separator_colors: Default::default(), columns: Some(columns) }
+    }
+
+    #[test]
+    fn preformatted_line_colors() {
+        let mut terminal = Terminal::sink(0);
+        let dimensions = WindowSize { rows: 10, columns: 20, width: 0, height: 0, has_pixels: false };
+        let mut engine = RenderEngine::new(&mut terminal, dimensions, ImageProtocol::AsciiBlocks);
+        engine.color_depth = ColorDepth::Palette16;
+        let colors = Colors { background: Some(Color::new(0, 0, 0)), foreground: Some(Color::new(255, 255, 255)) };
+        engine.set_colors(&colors).expect("set colors failed");
+
+        let red = TextStyle::default().colors(Colors { background: None, foreground: Some(Color::new(200, 10, 10)) });
+        let spans = [StyledText::new("a", red), StyledText::from("b")];
+        assert_eq!(engine.escape(&spans), "\x1b[40m\x1b[31ma\x1b[40m\x1b[97mb");
+    }
+
+    #[test]
+    fn column_padding() {
+        let mut terminal = Terminal::sink(0);
+        let dimensions = WindowSize { rows: 10, columns: 20, width: 0, height: 0, has_pixels: false };
+        let mut engine = RenderEngine::new(&mut terminal, dimensions, ImageProtocol::AsciiBlocks);
+        engine.init_column_layout(&vec![1, 1].into(), &column_layout_style()).expect("init failed");
+
+        engine.enter_column(0).expect("enter failed");
+        let rect = engine.current_rect();
+        assert_eq!((rect.start_column, rect.dimensions.columns), (0, 6));
+
+        engine.enter_column(1).expect("enter failed");
+        let rect = engine.current_rect();
+        assert_eq!((rect.start_column, rect.dimensions.columns), (16, 6));
+    }
+
+    #[test]
+    fn column_background() {
+        let screen = Colors { background: Some(Color::new(0, 0, 0)), foreground: Some(Color::new(1, 1, 1)) };
+        let red = Some(Color::new(255, 0, 0));
+        let mut terminal = Terminal::sink(0);
+        let dimensions = WindowSize { rows: 10, columns: 20, width: 0, height: 0, has_pixels: false };
+        let mut engine = RenderEngine::new(&mut terminal, dimensions, ImageProtocol::AsciiBlocks);
+        engine.set_colors(&screen).expect("set colors failed");
+        engine.clear_screen().expect("clear failed");
+        engine.init_column_layout(&vec![1, 1].into(), &column_layout_style()).expect("init failed");
+
+        engine.enter_column(0).expect("enter failed");
+        assert_eq!(engine.colors.background, red);
+        // Going back to the screen's colors keeps the column's background.
+        engine.set_colors(&screen).expect("set colors failed");
+        assert_eq!(engine.colors.background, red);
+        assert_eq!(engine.colors.foreground, screen.foreground);
+        // Other backgrounds are left alone.
+        let other = Colors { background: Some(Color::new(0, 0, 255)), foreground: None };
+        engine.set_colors(&other).expect("set colors failed");
+        assert_eq!(engine.colors, other);
+        engine.set_colors(&screen).expect("set colors failed");
+
+        engine.enter_column(1).expect("enter failed");
+        assert_eq!(engine.colors, screen);
+
+        engine.enter_column(0).expect("enter failed");
+        engine.exit_layouts().expect("exit failed");
+        assert_eq!(engine.colors, screen);
+    }
+
+    #[rstest]
+    #[case::top(VerticalAlignment::Top, 2)]
+    #[case::center(VerticalAlignment::Center, 6)]
+    #[case::bottom(VerticalAlignment::Bottom, 10)]
+    fn align_vertically(#[case] alignment: VerticalAlignment, #[case] expected_row: u16) {
+        let align = RenderOperation::AlignVertically { alignment, contents: Rc::new(content()) };
+        let operations: Vec<_> = [align].into_iter().chain(content()).collect();
+        assert_eq!(render(&operations), expected_row);
+    }
+
+    #[test]
+    fn align_vertically_ignores_footer() {
+        let align =
+            RenderOperation::AlignVertically { alignment: VerticalAlignment::Center, contents: Rc::new(content()) };
+        let operations: Vec<_> = [align]
+            .into_iter()
+            .chain(content())
+            .chain([RenderOperation::PopMargin])
+            .chain(vec![RenderOperation::RenderLineBreak; 3])
+            .collect();
+        // The contents take 2 rows so they start at row 4, the footer adds another 3 rows after them.
+        assert_eq!(render(&operations), 9);
+    }
+
+    #[test]
+    fn align_vertically_hidden_contents() {
+        // Only half of the contents are visible but they're placed as if all of them were.
+        let contents = content().into_iter().chain(content()).collect();
+        let align =
+            RenderOperation::AlignVertically { alignment: VerticalAlignment::Center, contents: Rc::new(contents) };
+        let operations: Vec<_> = [align].into_iter().chain(content()).collect();
+        assert_eq!(render(&operations), 5);
+    }
+}
@@ -0,0 +1,122 @@
+use crate::markdown::text::TextWidth;
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+
+/// The font used to render big text, which is bundled into the binary.
+static BLOCK_FONT: Lazy<FigFont> =
+    Lazy::new(|| FigFont::parse(include_str!("../../fonts/block.flf")).expect("corrupted embedded font"));
+
+/// Render a piece of text using large glyphs from the bundled font.
+///
+/// Every returned row has the same width. If the font doesn't have a glyph for any of the characters in the text,
+/// nothing is returned so the text can be displayed as is rather than with missing characters.
+pub(crate) fn big_text(text: &str) -> Option<Vec<String>> {
+    BLOCK_FONT.render(text)
+}
+
+/// A font in the FIGfont format, as used by `figlet`.
+///
+/// Only the parts of the format needed to draw glyphs next to each other are supported: glyphs are never smushed
+/// together and code-tagged characters are ignored.
+#[derive(Debug)]
+pub(crate) struct FigFont {
+    height: usize,
+    glyphs: HashMap<char, Vec<String>>,
+}
+
+impl FigFont {
+    /// Parse a font from the contents of a `.flf` file.
+    pub(crate) fn parse(contents: &str) -> Result<Self, ParseFigFontError> {
+        let mut lines = contents.lines();
+        let header = lines.next().ok_or(ParseFigFontError::MissingHeader)?;
+        // The header contains the hardblank, height, baseline, max length, old layout, and number of comment lines.
+        let parameters: Vec<_> =
+            header.strip_prefix("flf2a").ok_or(ParseFigFontError::MissingHeader)?.split_whitespace().collect();
+        let hardblank =
+            parameters.first().and_then(|value| value.chars().next()).ok_or(ParseFigFontError::MissingHeader)?;
+        let number = |index: usize| -> Result<usize, ParseFigFontError> {
+            parameters.get(index).and_then(|value| value.parse().ok()).ok_or(ParseFigFontError::MissingHeader)
+        };
+        let height = number(1)?;
+        let comment_lines = number(5)?;
+        let mut lines = lines.skip(comment_lines);
+
+        let mut glyphs = HashMap::new();
+        for code in 32..127_u8 {
+            let mut glyph = Vec::with_capacity(height);
+            for _ in 0..height {
+                let line = lines.next().ok_or(ParseFigFontError::MissingGlyph(code as char))?;
+                // Every line ends with one or more end marks, which is whatever its last character is.
+                let end_mark = line.chars().last().ok_or(ParseFigFontError::MissingGlyph(code as char))?;
+                let line = line.trim_end_matches(end_mark).replace(hardblank, " ");
+                glyph.push(line);
+            }
+            let width = glyph.iter().map(|line| line.width()).max().unwrap_or(0);
+            if glyph.iter().any(|line| line.width() != width) {
+                return Err(ParseFigFontError::UnevenGlyph(code as char));
+            }
+            glyphs.insert(code as char, glyph);
+        }
+        Ok(Self { height, glyphs })
+    }
+
+    /// Render a piece of text using this font.
+    ///
+    /// Returns `None` if the font doesn't have a glyph for any of the characters in the text. The trailing blank
+    /// columns the glyphs use as spacing are removed from the last one.
+    pub(crate) fn render(&self, text: &str) -> Option<Vec<String>> {
+        let glyphs = text.chars().map(|c| self.glyphs.get(&c)).collect::<Option<Vec<_>>>()?;
+        let mut rows: Vec<String> = (0..self.height)
+            .map(|row| glyphs.iter().map(|glyph| glyph[row].as_str()).collect::<Vec<_>>().concat())
+            .collect();
+        let trailing = rows.iter().map(|row| row.len() - row.trim_end_matches(' ').len()).min().unwrap_or(0);
+        for row in &mut rows {
+            row.truncate(row.len() - trailing);
+        }
+        Some(rows)
+    }
+}
+
+/// An error when parsing a FIGfont.
+#[derive(thiserror::Error, Debug)]
+pub(crate) enum ParseFigFontError {
+    #[error("missing or invalid header")]
+    MissingHeader,
+
+    #[error("missing glyph for '{0}'")]
+    MissingGlyph(char),
+
+    #[error("lines in glyph for '{0}' have different widths")]
+    UnevenGlyph(char),
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn embedded_font() {
+        let rows = big_text("Hi!").expect("no rows");
+        assert_eq!(rows, &["█  █ ███ █", "█  █  █  █", "████  █  █", "█  █  █   ", "█  █ ███ █"]);
+    }
+
+    #[test]
+    fn same_width_rows() {
+        for text in ["hello world", "1, 2... 3?", "~"] {
+            let rows = big_text(text).expect("no rows");
+            assert_eq!(rows.len(), 5);
+            assert!(rows.iter().all(|row| row.width() == rows[0].width()), "uneven rows for {text}: {rows:?}");
+        }
+    }
+
+    #[test]
+    fn unknown_characters() {
+        assert_eq!(big_text("Café"), None);
+    }
+
+    #[test]
+    fn invalid_font() {
+        assert!(matches!(FigFont::parse("potato"), Err(ParseFigFontError::MissingHeader)));
+        assert!(matches!(FigFont::parse("flf2a$ 1 1 1 -1 0\n$@\n"), Err(ParseFigFontError::MissingGlyph('!'))));
+    }
+}
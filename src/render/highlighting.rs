@@ -1,10 +1,26 @@
-use crate::markdown::elements::CodeLanguage;
-use once_cell::sync::Lazy;
+#[cfg(feature = "tree-sitter")]
+use super::tree_sitter::TreeSitterHighlighter;
+use crate::{
+    markdown::elements::{CodeLanguage, StyledText},
+    style::{Color, Colors, TextStyle},
+};
+use once_cell::sync::{Lazy, OnceCell};
+use serde::Deserialize;
+use std::{
+    collections::{hash_map::DefaultHasher, BTreeMap, HashMap},
+    fs,
+    hash::{Hash, Hasher},
+    io,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
 use syntect::{
+    dumps::{from_reader, from_uncompressed_data},
     easy::HighlightLines,
-    highlighting::{Style, Theme, ThemeSet},
-    parsing::SyntaxSet,
-    util::{as_24_bit_terminal_escaped, LinesWithEndings},
+    highlighting::{Color as SyntectColor, FontStyle, Style, Theme, ThemeSet},
+    parsing::{SyntaxReference, SyntaxSet},
+    util::LinesWithEndings,
+    LoadingError,
 };
 
 static SYNTAX_SET: Lazy<SyntaxSet> = Lazy::new(|| {
@@ -12,86 +28,367 @@ static SYNTAX_SET: Lazy<SyntaxSet> = Lazy::new(|| {
     bincode::deserialize(contents).expect("syntaxes are broken")
 });
 static THEMES: Lazy<ThemeSet> = Lazy::new(ThemeSet::load_defaults);
+static BAT_ASSETS: OnceCell<BatAssets> = OnceCell::new();
+static CUSTOM_SYNTAXES: OnceCell<SyntaxSet> = OnceCell::new();
+#[cfg(feature = "tree-sitter")]
+static TREE_SITTER_ENABLED: OnceCell<()> = OnceCell::new();
+static HIGHLIGHT_CACHE: Lazy<Mutex<HashMap<HighlightKey, Arc<HighlightedLines>>>> = Lazy::new(Default::default);
+
+/// The maximum number of pieces of code kept in the highlight cache before it's emptied.
+const MAX_CACHED_HIGHLIGHTS: usize = 1024;
+
+/// The styled pieces of text in each line of a piece of code.
+type HighlightedLines = Vec<Vec<(Style, String)>>;
+
+/// Splits code into pieces of text, each with the style to use for it.
+pub(crate) trait Highlighter {
+    /// Highlight a piece of code, returning the styled pieces of text in each of its lines.
+    ///
+    /// Lines include their line endings. `None` is returned if the given language isn't supported.
+    fn highlight_lines<'a>(&self, code: &'a str, language: &CodeLanguage) -> Option<Vec<Vec<(Style, &'a str)>>>;
+}
 
 /// A code highlighter.
 #[derive(Clone)]
 pub struct CodeHighlighter {
+    theme_name: String,
     theme: &'static Theme,
+    background: Option<SyntectColor>,
+    syntect: SyntectHighlighter,
+    #[cfg(feature = "tree-sitter")]
+    tree_sitter: Option<TreeSitterHighlighter>,
 }
 
 impl CodeHighlighter {
     /// Construct a new highlighted using the given [syntect] theme name.
-    pub fn new(theme: &str) -> Result<Self, ThemeNotFound> {
-        let theme = THEMES.themes.get(theme).ok_or(ThemeNotFound)?;
-        Ok(Self { theme })
+    ///
+    /// If [CodeHighlighter::load_bat_assets] was called before, themes in bat's cache take precedence over the
+    /// bundled ones.
+    pub fn new(theme_name: &str) -> Result<Self, ThemeNotFound> {
+        let bat_assets = BAT_ASSETS.get();
+        let theme = bat_assets
+            .and_then(|assets| assets.themes.themes.get(theme_name))
+            .or_else(|| THEMES.themes.get(theme_name))
+            .ok_or(ThemeNotFound)?;
+        let syntaxes =
+            CUSTOM_SYNTAXES.get().or_else(|| bat_assets.map(|assets| &assets.syntaxes)).unwrap_or(&SYNTAX_SET);
+        let syntect = SyntectHighlighter { theme, syntaxes };
+        #[cfg(feature = "tree-sitter")]
+        let tree_sitter = TREE_SITTER_ENABLED.get().map(|_| TreeSitterHighlighter::new(theme));
+        Ok(Self {
+            theme_name: theme_name.into(),
+            theme,
+            background: None,
+            syntect,
+            #[cfg(feature = "tree-sitter")]
+            tree_sitter,
+        })
+    }
+
+    /// Use the given background color rather than the theme's.
+    pub(crate) fn with_background(mut self, color: Color) -> Self {
+        self.background = Self::to_syntect_color(color);
+        self
+    }
+
+    fn background(&self) -> Option<SyntectColor> {
+        self.background.or(self.theme.settings.background)
+    }
+
+    /// Use tree-sitter to highlight code in the languages it supports, falling back to syntect for the rest.
+    ///
+    /// This affects any highlighter constructed afterwards and fails if presenterm was built without the
+    /// `tree-sitter` feature.
+    pub fn enable_tree_sitter() -> Result<(), TreeSitterUnavailable> {
+        #[cfg(feature = "tree-sitter")]
+        {
+            let _ = TREE_SITTER_ENABLED.set(());
+            Ok(())
+        }
+        #[cfg(not(feature = "tree-sitter"))]
+        Err(TreeSitterUnavailable)
+    }
+
+    /// Use the syntaxes and themes in a `bat` cache directory rather than the bundled ones.
+    ///
+    /// This affects any highlighter constructed afterwards and can only be done once.
+    pub fn load_bat_assets(path: &Path) -> Result<(), LoadAssetsError> {
+        let assets = BatAssets::load(path)?;
+        // If these were already loaded there's nothing to do.
+        let _ = BAT_ASSETS.set(assets);
+        Ok(())
+    }
+
+    /// Load every `.sublime-syntax` file in a directory and use them on top of the existing syntaxes.
+    ///
+    /// This affects any highlighter constructed afterwards and can only be done once. If bat's assets are going to
+    /// be used, [CodeHighlighter::load_bat_assets] must be called before this.
+    pub fn load_syntaxes(path: &Path) -> Result<(), LoadAssetsError> {
+        let base = BAT_ASSETS.get().map(|assets| &assets.syntaxes).unwrap_or(&SYNTAX_SET);
+        let syntaxes = Self::merge_syntaxes(base, path)?;
+        let _ = CUSTOM_SYNTAXES.set(syntaxes);
+        Ok(())
+    }
+
+    fn merge_syntaxes(base: &SyntaxSet, path: &Path) -> Result<SyntaxSet, LoadAssetsError> {
+        let mut builder = base.clone().into_builder();
+        builder.add_from_folder(path, true).map_err(|e| LoadAssetsError::Syntaxes(path.into(), e))?;
+        Ok(builder.build())
     }
 
     /// Highlight a piece of code.
     ///
-    /// This splits the given piece of code into lines, highlights them individually, and returns them.
-    pub(crate) fn highlight<'a>(&self, code: &'a str, language: &CodeLanguage) -> Vec<CodeLine<'a>> {
-        let extension = Self::language_extension(language);
-        let syntax = SYNTAX_SET.find_syntax_by_extension(extension).unwrap();
-        let mut highlight_lines = HighlightLines::new(syntax, self.theme);
+    /// This splits the given piece of code into lines, highlights them individually, and returns them. The style
+    /// for each line is determined by calling `line_style` with its index, starting at 0.
+    pub(crate) fn highlight<'a, F>(&self, code: &'a str, language: &CodeLanguage, line_style: F) -> Vec<CodeLine<'a>>
+    where
+        F: Fn(usize) -> LineStyle,
+    {
+        let line_styles: Vec<_> =
+            LinesWithEndings::from(code).enumerate().map(|(index, _)| line_style(index)).collect();
+        // Diff markers would throw off the parser so highlight the code without them and put them back afterwards.
+        let mut unmarked = String::new();
+        let mut markers = Vec::new();
+        for (line, line_style) in LinesWithEndings::from(code).zip(&line_styles) {
+            let marker = match line_style {
+                LineStyle::Changed(_) => line.char_indices().find(|(_, c)| !c.is_whitespace()),
+                _ => None,
+            };
+            match marker {
+                Some((position, '+' | '-')) => {
+                    unmarked.push_str(&line[..position]);
+                    unmarked.push(' ');
+                    unmarked.push_str(&line[position + 1..]);
+                    markers.push(marker);
+                }
+                _ => {
+                    unmarked.push_str(line);
+                    markers.push(None);
+                }
+            }
+        }
         let mut lines = Vec::new();
-        for line in LinesWithEndings::from(code) {
-            let ranges: Vec<(Style, &str)> = highlight_lines.highlight_line(line, &SYNTAX_SET).unwrap();
-            let escaped = as_24_bit_terminal_escaped(&ranges, true);
-            let code_line = CodeLine { original: line, formatted: escaped };
-            lines.push(code_line);
+        let highlighted = self.cached_highlight_lines(&unmarked, language);
+        for ((original, ranges), (line_style, marker)) in
+            LinesWithEndings::from(code).zip(highlighted.iter()).zip(line_styles.into_iter().zip(markers))
+        {
+            let ranges = ranges.iter().map(|(style, text)| (*style, text.as_str())).collect();
+            let mut ranges = match marker {
+                Some((position, marker)) => Self::restore_marker(ranges, position, marker),
+                None => ranges,
+            };
+            if let Some(background) = self.background {
+                for (style, _) in &mut ranges {
+                    style.background = background;
+                }
+            }
+            self.apply_line_style(&mut ranges, line_style);
+            let spans =
+                ranges.into_iter().map(|(style, text)| StyledText::new(text, Self::to_text_style(style))).collect();
+            lines.push(CodeLine { original, spans });
         }
         lines
     }
 
-    fn language_extension(language: &CodeLanguage) -> &'static str {
+    /// Highlight a piece of code, reusing the result from the last time it was highlighted if there was one.
+    ///
+    /// Highlighting is by far the most expensive part of rendering code so this keeps reloading presentations fast.
+    fn cached_highlight_lines(&self, code: &str, language: &CodeLanguage) -> Arc<HighlightedLines> {
+        let mut hasher = DefaultHasher::new();
+        code.hash(&mut hasher);
+        let key = HighlightKey {
+            code_hash: hasher.finish(),
+            language: language.clone(),
+            theme_name: self.theme_name.clone(),
+            tree_sitter: self.uses_tree_sitter(),
+        };
+        if let Some(lines) = HIGHLIGHT_CACHE.lock().unwrap().get(&key) {
+            return lines.clone();
+        }
+        let lines: HighlightedLines = self
+            .highlight_lines(code, language)
+            .into_iter()
+            .map(|line| line.into_iter().map(|(style, text)| (style, text.to_string())).collect())
+            .collect();
+        let lines = Arc::new(lines);
+        let mut cache = HIGHLIGHT_CACHE.lock().unwrap();
+        if cache.len() >= MAX_CACHED_HIGHLIGHTS {
+            cache.clear();
+        }
+        cache.insert(key, lines.clone());
+        lines
+    }
+
+    fn uses_tree_sitter(&self) -> bool {
+        #[cfg(feature = "tree-sitter")]
+        return self.tree_sitter.is_some();
+        #[cfg(not(feature = "tree-sitter"))]
+        false
+    }
+
+    fn highlight_lines<'a>(&self, code: &'a str, language: &CodeLanguage) -> Vec<Vec<(Style, &'a str)>> {
+        #[cfg(feature = "tree-sitter")]
+        if let Some(lines) =
+            self.tree_sitter.as_ref().and_then(|highlighter| highlighter.highlight_lines(code, language))
+        {
+            return lines;
+        }
+        self.syntect.highlight_lines(code, language).unwrap_or_default()
+    }
+
+    fn to_text_style(style: Style) -> TextStyle {
+        let convert = |color: SyntectColor| Color::new(color.r, color.g, color.b);
+        let colors =
+            Colors { background: Some(convert(style.background)), foreground: Some(convert(style.foreground)) };
+        let mut text_style = TextStyle::default().colors(colors);
+        if style.font_style.contains(FontStyle::BOLD) {
+            text_style = text_style.bold();
+        }
+        if style.font_style.contains(FontStyle::ITALIC) {
+            text_style = text_style.italics();
+        }
+        if style.font_style.contains(FontStyle::UNDERLINE) {
+            text_style = text_style.underlined();
+        }
+        text_style
+    }
+
+    fn restore_marker(ranges: Vec<(Style, &str)>, position: usize, marker: char) -> Vec<(Style, &str)> {
+        let marker = if marker == '+' { "+" } else { "-" };
+        let mut output = Vec::new();
+        let mut offset = 0;
+        for (style, text) in ranges {
+            let end = offset + text.len();
+            if (offset..end).contains(&position) {
+                let split = position - offset;
+                output.extend([(style, &text[..split]), (style, marker), (style, &text[split + 1..])]);
+            } else {
+                output.push((style, text));
+            }
+            offset = end;
+        }
+        output.retain(|(_, text)| !text.is_empty());
+        output
+    }
+
+    /// Get the colors used by this highlighter's theme for text that has no particular style.
+    pub(crate) fn default_colors(&self) -> Colors {
+        let convert = |color: SyntectColor| Color::new(color.r, color.g, color.b);
+        let settings = &self.theme.settings;
+        Colors { background: self.background().map(convert), foreground: settings.foreground.map(convert) }
+    }
+
+    fn apply_line_style(&self, ranges: &mut [(Style, &str)], line_style: LineStyle) {
+        match line_style {
+            LineStyle::Normal => (),
+            LineStyle::Emphasized => {
+                for (style, _) in ranges {
+                    // Use the theme's color for highlighted lines if it has one.
+                    match self.theme.settings.line_highlight {
+                        Some(color) => style.background = color,
+                        None => style.font_style |= FontStyle::BOLD,
+                    }
+                }
+            }
+            LineStyle::Dimmed => {
+                // Fade the text into the background so it's still readable but doesn't draw attention.
+                let background = self.background().unwrap_or(SyntectColor::BLACK);
+                for (style, _) in ranges {
+                    style.foreground = Self::blend(style.foreground, background);
+                }
+            }
+            LineStyle::Changed(color) => {
+                if let Some(color) = Self::to_syntect_color(color) {
+                    for (style, _) in ranges {
+                        style.background = color;
+                    }
+                }
+            }
+        }
+    }
+
+    fn to_syntect_color(color: Color) -> Option<SyntectColor> {
+        let (r, g, b) = color.as_rgb()?;
+        Some(SyntectColor { r, g, b, a: 255 })
+    }
+
+    fn blend(foreground: SyntectColor, background: SyntectColor) -> SyntectColor {
+        let mix = |a: u8, b: u8| ((a as u16 + b as u16) / 2) as u8;
+        SyntectColor {
+            r: mix(foreground.r, background.r),
+            g: mix(foreground.g, background.g),
+            b: mix(foreground.b, background.b),
+            a: foreground.a,
+        }
+    }
+
+    pub(crate) fn language_extension(language: &CodeLanguage) -> &'static str {
         use CodeLanguage::*;
         match language {
             Ada => "adb",
+            // This one is never highlighted but give it the same look as plain text.
+            Ansi => "txt",
             Asp => "asa",
             Awk => "awk",
             Bash => "bash",
+            // This one is drawn using large glyphs rather than highlighted.
+            BigText => "txt",
             BatchFile => "bat",
             C => "c",
+            // This one is drawn as a chart rather than highlighted.
+            Chart => "txt",
             CMake => "cmake",
             CSharp => "cs",
             Clojure => "clj",
             Cpp => "cpp",
             Crontab => "crontab",
             Css => "css",
+            D2 => "txt",
             DLang => "d",
             Docker => "Dockerfile",
+            Dot => "dot",
             Dotenv => "env",
             Elixir => "ex",
             Elm => "elm",
             Erlang => "erl",
             Go => "go",
+            GraphQL => "graphql",
             Haskell => "hs",
             Html => "html",
             Java => "java",
             JavaScript => "js",
             Json => "json",
+            Julia => "jl",
             Kotlin => "kt",
             Latex => "tex",
             Lua => "lua",
             Makefile => "make",
             Markdown => "md",
+            Mermaid => "txt",
+            Nix => "nix",
             OCaml => "ml",
             Perl => "pl",
             Php => "php",
             Protobuf => "proto",
             Puppet => "pp",
             Python => "py",
+            // This one is drawn as a QR code rather than highlighted.
+            Qr => "txt",
             R => "r",
             Rust => "rs",
             Scala => "scala",
+            Shell(interpreter) if interpreter == "fish" => "fish",
             Shell(_) => "sh",
             Sql => "sql",
             Swift => "swift",
             Svelte => "svelte",
             Terraform => "tf",
+            Toml => "toml",
+            // There's no syntax for this one so give it the same look as plain text.
+            Typst => "txt",
             TypeScript => "ts",
             // default to plain text so we get the same look&feel
-            Unknown => "txt",
+            Unknown(_) => "txt",
             Vue => "vue",
             Xml => "xml",
             Yaml => "yaml",
@@ -100,17 +397,144 @@ impl CodeHighlighter {
     }
 }
 
+/// A highlighter that uses syntect.
+#[derive(Clone)]
+struct SyntectHighlighter {
+    theme: &'static Theme,
+    syntaxes: &'static SyntaxSet,
+}
+
+impl SyntectHighlighter {
+    fn find_syntax(&self, language: &CodeLanguage) -> &'static SyntaxReference {
+        let syntax = match language {
+            // Languages we don't know about may still be supported by the loaded syntaxes.
+            CodeLanguage::Unknown(name) if !name.is_empty() => self.syntaxes.find_syntax_by_token(name),
+            _ => None,
+        };
+        syntax
+            .or_else(|| self.syntaxes.find_syntax_by_extension(CodeHighlighter::language_extension(language)))
+            .unwrap_or_else(|| self.syntaxes.find_syntax_plain_text())
+    }
+}
+
+impl Highlighter for SyntectHighlighter {
+    fn highlight_lines<'a>(&self, code: &'a str, language: &CodeLanguage) -> Option<Vec<Vec<(Style, &'a str)>>> {
+        let syntax = self.find_syntax(language);
+        let mut highlight_lines = HighlightLines::new(syntax, self.theme);
+        let lines = LinesWithEndings::from(code)
+            .map(|line| highlight_lines.highlight_line(line, self.syntaxes).unwrap())
+            .collect();
+        Some(lines)
+    }
+}
+
+/// Identifies a piece of code in the highlight cache.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct HighlightKey {
+    code_hash: u64,
+    language: CodeLanguage,
+    theme_name: String,
+    tree_sitter: bool,
+}
+
+/// The way a line of code is styled on top of its syntax highlighting.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) enum LineStyle {
+    /// The line is displayed as is.
+    #[default]
+    Normal,
+
+    /// The line stands out from the rest.
+    Emphasized,
+
+    /// The line is displayed with reduced intensity.
+    Dimmed,
+
+    /// The line was added or removed in a diff and is displayed with the given background color.
+    ///
+    /// The line's first non whitespace character is the `+` or `-` marker, which isn't highlighted as code.
+    Changed(Color),
+}
+
 /// A line of highlighted code.
 pub(crate) struct CodeLine<'a> {
     /// The original line of code.
     pub(crate) original: &'a str,
 
-    /// The formatted line of code.
+    /// The styled pieces of text that make up this line.
     ///
-    /// This uses terminal escape codes internally and is ready to be printed.
-    pub(crate) formatted: String,
+    /// These don't contain any escape codes so they can be mapped into any output format. Use
+    /// [ColorDepth::escape](super::ansi::ColorDepth::escape) to turn them into something that can be printed in a
+    /// terminal.
+    pub(crate) spans: Vec<StyledText>,
+}
+
+/// The syntaxes and themes in a `bat` cache directory.
+struct BatAssets {
+    syntaxes: SyntaxSet,
+    themes: ThemeSet,
+}
+
+impl BatAssets {
+    fn load(path: &Path) -> Result<Self, LoadAssetsError> {
+        let read = |name: &str| {
+            let path = path.join(name);
+            fs::read(&path).map_err(|e| LoadAssetsError::Io(path, e))
+        };
+        let invalid = |name: &str| LoadAssetsError::Invalid(path.join(name));
+
+        // Depending on the version, bat stores these either compressed or uncompressed.
+        let contents = read("syntaxes.bin")?;
+        let syntaxes = from_uncompressed_data(&contents)
+            .or_else(|_| from_reader(contents.as_slice()))
+            .map_err(|_| invalid("syntaxes.bin"))?;
+
+        // Newer versions of bat store each theme compressed individually so they can be loaded lazily.
+        let contents = read("themes.bin")?;
+        let themes = match from_uncompressed_data::<LazyThemeSet>(&contents) {
+            Ok(lazy_themes) => {
+                let mut themes = BTreeMap::new();
+                for (name, theme) in lazy_themes.themes {
+                    let theme = from_reader(theme.serialized.as_slice()).map_err(|_| invalid("themes.bin"))?;
+                    themes.insert(name, theme);
+                }
+                ThemeSet { themes }
+            }
+            Err(_) => from_reader(contents.as_slice()).map_err(|_| invalid("themes.bin"))?,
+        };
+        Ok(Self { syntaxes, themes })
+    }
+}
+
+/// The format bat uses to store themes.
+#[derive(Deserialize)]
+struct LazyThemeSet {
+    themes: BTreeMap<String, LazyTheme>,
+}
+
+#[derive(Deserialize)]
+struct LazyTheme {
+    serialized: Vec<u8>,
+}
+
+/// An error loading syntaxes and themes.
+#[derive(Debug, thiserror::Error)]
+pub enum LoadAssetsError {
+    #[error("io error reading {0}: {1}")]
+    Io(PathBuf, io::Error),
+
+    #[error("invalid assets in {0}")]
+    Invalid(PathBuf),
+
+    #[error("failed to load syntaxes in {0}: {1}")]
+    Syntaxes(PathBuf, LoadingError),
 }
 
+/// Tree-sitter highlighting was requested but presenterm was built without it.
+#[derive(Debug, thiserror::Error)]
+#[error("tree-sitter highlighting is not available, presenterm needs to be built with the 'tree-sitter' feature")]
+pub struct TreeSitterUnavailable;
+
 /// A theme could not be found.
 #[derive(Debug, thiserror::Error)]
 #[error("theme not found")]
@@ -121,6 +545,149 @@ mod test {
     use super::*;
     use strum::IntoEnumIterator;
 
+    fn highlighter() -> CodeHighlighter {
+        CodeHighlighter::new("base16-ocean.dark").unwrap()
+    }
+
+    fn has_background(line: &CodeLine, color: Color) -> bool {
+        line.spans.iter().any(|span| span.style.colors.background == Some(color))
+    }
+
+    #[test]
+    fn emphasized_lines() {
+        let highlighter = highlighter();
+        let style = |index| if index == 1 { LineStyle::Emphasized } else { LineStyle::Normal };
+        let lines = highlighter.highlight("a\nb\nc\n", &CodeLanguage::Unknown(String::new()), style);
+        let plain = highlighter.highlight("a\nb\nc\n", &CodeLanguage::Unknown(String::new()), |_| LineStyle::Normal);
+        assert_eq!(lines[0].spans, plain[0].spans);
+        assert_ne!(lines[1].spans, plain[1].spans);
+        assert_eq!(lines[2].spans, plain[2].spans);
+    }
+
+    #[test]
+    fn dimmed_lines() {
+        let highlighter = highlighter();
+        let style = |index| if index == 0 { LineStyle::Dimmed } else { LineStyle::Normal };
+        let lines = highlighter.highlight("a\nb\n", &CodeLanguage::Unknown(String::new()), style);
+        let plain = highlighter.highlight("a\nb\n", &CodeLanguage::Unknown(String::new()), |_| LineStyle::Normal);
+        assert_ne!(lines[0].spans, plain[0].spans);
+        assert_eq!(lines[1].spans, plain[1].spans);
+    }
+
+    #[test]
+    fn changed_lines() {
+        let highlighter = highlighter();
+        let style = |index| if index == 0 { LineStyle::Changed(Color::new(1, 2, 3)) } else { LineStyle::Normal };
+        let lines = highlighter.highlight("  + let a = 1;\n  let b = 2;\n", &CodeLanguage::Rust, style);
+        assert!(has_background(&lines[0], Color::new(1, 2, 3)));
+        assert!(lines[0].spans.iter().any(|span| span.text.contains('+')));
+        assert!(!has_background(&lines[1], Color::new(1, 2, 3)));
+
+        // The marker doesn't affect how the rest of the line is highlighted.
+        let plain = highlighter.highlight("    let a = 1;\n", &CodeLanguage::Rust, |_| LineStyle::Normal);
+        let keyword_color = |line: &CodeLine| {
+            line.spans.iter().find(|span| span.text == "let").and_then(|span| span.style.colors.foreground)
+        };
+        assert!(keyword_color(&lines[0]).is_some());
+        assert_eq!(keyword_color(&lines[0]), keyword_color(&plain[0]));
+    }
+
+    #[test]
+    fn background_override() {
+        let highlighter = highlighter().with_background(Color::new(1, 2, 3));
+        let lines = highlighter.highlight("let a = 1;\n", &CodeLanguage::Rust, |_| LineStyle::Normal);
+        assert!(has_background(&lines[0], Color::new(1, 2, 3)));
+        assert_eq!(highlighter.default_colors().background, Some(Color::new(1, 2, 3)));
+    }
+
+    #[test]
+    fn styled_spans() {
+        let highlighter = highlighter();
+        let lines = highlighter.highlight("let a = 1;\n", &CodeLanguage::Rust, |_| LineStyle::Normal);
+        let text: String = lines[0].spans.iter().map(|span| span.text.as_str()).collect();
+        assert_eq!(text, "let a = 1;\n");
+
+        let style = |text: &str| lines[0].spans.iter().find(|span| span.text == text).map(|span| span.style.clone());
+        let keyword = style("let").expect("no keyword");
+        let number = style("1").expect("no number");
+        assert!(keyword.colors.foreground.is_some());
+        assert_ne!(keyword.colors.foreground, number.colors.foreground);
+    }
+
+    #[test]
+    fn cached_highlights() {
+        let highlighter = highlighter();
+        let code = "fn cached_highlights() {}\n";
+        let first = highlighter.cached_highlight_lines(code, &CodeLanguage::Rust);
+        let second = highlighter.cached_highlight_lines(code, &CodeLanguage::Rust);
+        assert!(Arc::ptr_eq(&first, &second));
+
+        // The same code in a different language is highlighted differently.
+        let other = highlighter.cached_highlight_lines(code, &CodeLanguage::Go);
+        assert!(!Arc::ptr_eq(&first, &other));
+    }
+
+    #[test]
+    fn blend_colors() {
+        let foreground = SyntectColor { r: 200, g: 100, b: 0, a: 255 };
+        let background = SyntectColor { r: 0, g: 0, b: 50, a: 255 };
+        assert_eq!(CodeHighlighter::blend(foreground, background), SyntectColor { r: 100, g: 50, b: 25, a: 255 });
+    }
+
+    #[test]
+    fn load_bat_assets() {
+        let directory = tempfile::tempdir().expect("no temp dir");
+        fs::copy("syntaxes/syntaxes.bin", directory.path().join("syntaxes.bin")).expect("copy failed");
+        let theme = THEMES.themes.get("base16-ocean.dark").unwrap();
+        let serialized = syntect::dumps::dump_binary(theme);
+        let mut themes = Vec::new();
+        // A map with a single entry, the way bincode serializes it.
+        themes.extend(1_u64.to_le_bytes());
+        themes.extend(bincode::serialize("potato").unwrap());
+        themes.extend(bincode::serialize(&serialized).unwrap());
+        fs::write(directory.path().join("themes.bin"), themes).expect("write failed");
+
+        let assets = BatAssets::load(directory.path()).expect("load failed");
+        assert!(assets.themes.themes.contains_key("potato"));
+        assert!(assets.syntaxes.find_syntax_by_extension("rs").is_some());
+    }
+
+    #[test]
+    fn missing_bat_assets() {
+        let directory = tempfile::tempdir().expect("no temp dir");
+        let result = BatAssets::load(directory.path());
+        assert!(matches!(result, Err(LoadAssetsError::Io(..))));
+    }
+
+    #[test]
+    fn load_custom_syntaxes() {
+        let directory = tempfile::tempdir().expect("no temp dir");
+        let definition = r"%YAML 1.2
+---
+name: Potato
+file_extensions: [potato]
+scope: source.potato
+contexts:
+  main:
+    - match: '\bpotato\b'
+      scope: keyword.potato
+";
+        fs::write(directory.path().join("potato.sublime-syntax"), definition).expect("write failed");
+
+        let syntaxes = CodeHighlighter::merge_syntaxes(&SYNTAX_SET, directory.path()).expect("load failed");
+        assert!(syntaxes.find_syntax_by_token("potato").is_some());
+        // The existing ones are still there.
+        assert!(syntaxes.find_syntax_by_token("rs").is_some());
+    }
+
+    #[test]
+    fn invalid_custom_syntaxes() {
+        let directory = tempfile::tempdir().expect("no temp dir");
+        fs::write(directory.path().join("broken.sublime-syntax"), "potato: [").expect("write failed");
+        let result = CodeHighlighter::merge_syntaxes(&SYNTAX_SET, directory.path());
+        assert!(matches!(result, Err(LoadAssetsError::Syntaxes(..))));
+    }
+
     #[test]
     fn language_extensions_exist() {
         for language in CodeLanguage::iter() {
@@ -129,4 +696,11 @@ mod test {
             assert!(syntax.is_some(), "extension {extension} for {language:?} not found");
         }
     }
+
+    #[test]
+    fn fish_syntax() {
+        let extension = CodeHighlighter::language_extension(&CodeLanguage::Shell("fish".into()));
+        let syntax = SYNTAX_SET.find_syntax_by_extension(extension).expect("no syntax");
+        assert_eq!(syntax.name, "Fish");
+    }
 }
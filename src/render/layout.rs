@@ -16,11 +16,16 @@ impl Layout {
         self
     }
 
+    /// Whether wrapped lines should be stretched to take up the entire width.
+    pub(crate) fn justifies(&self) -> bool {
+        matches!(self.alignment, Alignment::Justify { .. })
+    }
+
     pub(crate) fn compute(&self, dimensions: &WindowSize, text_length: u16) -> Positioning {
         let max_line_length;
         let mut start_column;
         match &self.alignment {
-            Alignment::Left { margin } => {
+            Alignment::Left { margin } | Alignment::Justify { margin } => {
                 let margin = margin.as_characters(dimensions.columns);
                 // Ignore the margin if it's larger than the screen: we can't satisfy it so we
                 // might as well not do anything about it.
@@ -160,6 +165,11 @@ mod test {
         10,
         Positioning{ max_line_length: 60, start_column: 20 }
     )]
+    #[case::justify_some_margin(
+        Alignment::Justify{ margin: Margin::Fixed(5) },
+        10,
+        Positioning{ max_line_length: 90, start_column: 5 }
+    )]
     fn layout(#[case] alignment: Alignment, #[case] length: u16, #[case] expected: Positioning) {
         let dimensions = WindowSize { rows: 0, columns: 100, width: 0, height: 0, has_pixels: true };
         let positioning = Layout::new(alignment).compute(&dimensions, length);
@@ -1,35 +1,198 @@
-use crate::render::properties::WindowSize;
-use image::{DynamicImage, ImageError};
-use std::{fmt::Debug, io, rc::Rc};
+use crate::{presentation::ImagePosition, render::properties::WindowSize};
+use crossterm::{cursor::MoveTo, QueueableCommand};
+use image::{
+    imageops::{self, FilterType},
+    DynamicImage, ImageError, RgbaImage,
+};
+use serde::Deserialize;
+use std::{env, fmt::Debug, io, rc::Rc};
 use viuer::ViuError;
 
-use super::properties::CursorPosition;
+use super::{properties::CursorPosition, sixel::encode_sixel, terminal::base64_encode};
 
 /// An image.
 ///
-/// This stores the image in an [std::rc::Rc] so it's cheap to clone.
+/// This stores the image in an [std::rc::Rc] so it's cheap to clone. The original encoded contents are kept around
+/// as some protocols take the image as is.
 #[derive(Clone, PartialEq)]
-pub(crate) struct Image(Rc<DynamicImage>);
+pub(crate) struct Image {
+    decoded: Rc<DynamicImage>,
+    contents: Rc<[u8]>,
+}
 
 impl Debug for Image {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Image<{}x{}>", self.0.width(), self.0.height())
+        write!(f, "Image<{}x{}>", self.decoded.width(), self.decoded.height())
     }
 }
 
 impl Image {
     /// Construct a new image from a byte sequence.
     pub(crate) fn new(contents: &[u8]) -> Result<Self, InvalidImage> {
-        let contents = image::load_from_memory(contents)?;
-        let contents = Rc::new(contents);
-        Ok(Self(contents))
+        let decoded = image::load_from_memory(contents)?;
+        Ok(Self { decoded: Rc::new(decoded), contents: contents.into() })
+    }
+
+    /// Get a darker version of this image, where every color is scaled by the given brightness between 0 and 1.
+    pub(crate) fn dimmed(&self, brightness: f64) -> Self {
+        let mut rgba = self.decoded.to_rgba8();
+        for pixel in rgba.pixels_mut() {
+            for channel in &mut pixel.0[..3] {
+                *channel = (*channel as f64 * brightness) as u8;
+            }
+        }
+        let contents = encode_bmp(&rgba);
+        Self { decoded: Rc::new(DynamicImage::ImageRgba8(rgba)), contents: contents.into() }
+    }
+}
+
+/// Encode an image as an uncompressed 24 bit BMP.
+///
+/// This is used for images we modify ourselves, as every protocol that takes encoded images supports this format.
+fn encode_bmp(image: &RgbaImage) -> Vec<u8> {
+    const HEADERS_SIZE: u32 = 54;
+    let (width, height) = image.dimensions();
+    let stride = (width * 3 + 3) & !3;
+    let pixels_size = stride * height;
+    let mut output = Vec::with_capacity((HEADERS_SIZE + pixels_size) as usize);
+    output.extend_from_slice(b"BM");
+    for value in [HEADERS_SIZE + pixels_size, 0, HEADERS_SIZE, 40, width, height] {
+        output.extend_from_slice(&value.to_le_bytes());
+    }
+    // 1 plane, 24 bits per pixel.
+    output.extend_from_slice(&1_u16.to_le_bytes());
+    output.extend_from_slice(&24_u16.to_le_bytes());
+    // No compression, the size of the pixels, 72 DPI in both directions, and no palette.
+    for value in [0, pixels_size, 2835, 2835, 0, 0] {
+        output.extend_from_slice(&value.to_le_bytes());
+    }
+    // Rows go from the bottom to the top.
+    for row in image.rows().rev() {
+        for pixel in row {
+            let [red, green, blue, _] = pixel.0;
+            output.extend_from_slice(&[blue, green, red]);
+        }
+        output.resize(output.len() + (stride - width * 3) as usize, 0);
+    }
+    output
+}
+
+/// The way images are drawn in the terminal.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageProtocol {
+    /// Use the kitty graphics protocol, which draws images in their full resolution.
+    Kitty,
+
+    /// Use iTerm2's inline images protocol, which draws images in their full resolution.
+    Iterm2,
+
+    /// Use sixel graphics, which draws images using a palette of up to 256 colors.
+    Sixel,
+
+    /// Approximate images using unicode blocks.
+    #[default]
+    AsciiBlocks,
+}
+
+impl ImageProtocol {
+    /// Detect the protocol to use based on the terminal we're running in.
+    ///
+    /// This needs the terminal to be in raw mode as it may need to query it to find out if it supports sixel.
+    pub(crate) fn detect() -> Self {
+        match Self::from_environment(|name| env::var(name).ok()) {
+            Self::AsciiBlocks if query_sixel_support() => Self::Sixel,
+            protocol => protocol,
+        }
+    }
+
+    fn from_environment<F>(variable: F) -> Self
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let is = |name, value| variable(name).is_some_and(|actual| actual == value);
+        if is("TERM", "xterm-kitty") || variable("KITTY_WINDOW_ID").is_some() {
+            Self::Kitty
+        } else if is("TERM_PROGRAM", "iTerm.app") || is("LC_TERMINAL", "iTerm2") || is("TERM_PROGRAM", "WezTerm") {
+            Self::Iterm2
+        } else {
+            Self::AsciiBlocks
+        }
     }
 }
 
+/// Ask the terminal whether it supports sixel graphics via its primary device attributes.
+///
+/// A reply is either read in full or not at all: crossterm's event reader discards complete replies, so one that only
+/// arrives after we stopped waiting for it doesn't show up as key presses.
+#[cfg(unix)]
+fn query_sixel_support() -> bool {
+    use std::{io::Write, os::fd::AsRawFd};
+
+    let mut stdout = io::stdout();
+    if write!(stdout, "\x1b[c").and_then(|_| stdout.flush()).is_err() {
+        return false;
+    }
+    let stdin = io::stdin().as_raw_fd();
+    let read_byte = |timeout_millis| {
+        let mut poll_fd = libc::pollfd { fd: stdin, events: libc::POLLIN, revents: 0 };
+        if unsafe { libc::poll(&mut poll_fd, 1, timeout_millis) } <= 0 {
+            return None;
+        }
+        let mut byte = 0_u8;
+        let read = unsafe { libc::read(stdin, &mut byte as *mut u8 as *mut libc::c_void, 1) };
+        (read == 1).then_some(byte)
+    };
+    // Terminals that don't reply to this at all would otherwise leave us waiting forever.
+    let Some(first) = read_byte(200) else {
+        return false;
+    };
+    // Once the reply starts coming in, wait a lot longer for the rest of it so none of it is left behind.
+    let mut response = vec![first];
+    while !response.ends_with(b"c") && response.len() < 64 {
+        let Some(byte) = read_byte(2000) else {
+            return false;
+        };
+        response.push(byte);
+    }
+    supports_sixel(&String::from_utf8_lossy(&response))
+}
+
+#[cfg(not(unix))]
+fn query_sixel_support() -> bool {
+    false
+}
+
+/// Check whether a primary device attributes response, like `\x1b[?62;4;22c`, includes sixel support.
+fn supports_sixel(response: &str) -> bool {
+    let Some(attributes) = response.strip_prefix("\x1b[?").and_then(|response| response.strip_suffix('c')) else {
+        return false;
+    };
+    attributes.split(';').any(|attribute| attribute == "4")
+}
+
 /// A media render.
-pub(crate) struct MediaRender;
+#[derive(Clone)]
+pub(crate) struct MediaRender {
+    protocol: ImageProtocol,
+}
 
 impl MediaRender {
+    /// Construct a new render that draws images using the given protocol.
+    pub(crate) fn new(protocol: ImageProtocol) -> Self {
+        Self { protocol }
+    }
+
+    /// Remove any images drawn in the terminal.
+    ///
+    /// This is only needed for protocols where images aren't removed by clearing the screen.
+    pub(crate) fn clear_images<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        if self.protocol == ImageProtocol::Kitty {
+            write!(writer, "\x1b_Ga=d,d=A,q=2\x1b\\")?;
+        }
+        Ok(())
+    }
+
     /// Draw an image.
     ///
     /// This will use the current terminal size and try to render the image where the cursor is
@@ -38,25 +201,47 @@ impl MediaRender {
     ///
     /// In case the image does not fit, it will be resized to fit the screen, preserving the aspect
     /// ratio.
-    pub(crate) fn draw_image(
+    pub(crate) fn draw_image<W: io::Write>(
         &self,
         image: &Image,
         position: CursorPosition,
+        image_position: ImagePosition,
         dimensions: &WindowSize,
+        writer: &mut W,
     ) -> Result<(), RenderImageError> {
+        let area = Self::image_area(image, position, image_position, dimensions)?;
+        self.draw(image, &area, dimensions, writer)
+    }
+
+    /// Get the number of rows an image takes when drawn at the given position.
+    pub(crate) fn image_rows(
+        image: &Image,
+        position: CursorPosition,
+        dimensions: &WindowSize,
+    ) -> Result<u16, RenderImageError> {
+        let area = Self::image_area(image, position, ImagePosition::default(), dimensions)?;
+        Ok(area.rows as u16)
+    }
+
+    fn image_area(
+        image: &Image,
+        position: CursorPosition,
+        image_position: ImagePosition,
+        dimensions: &WindowSize,
+    ) -> Result<ImageArea, RenderImageError> {
         if !dimensions.has_pixels {
             return Err(RenderImageError::NoWindowSize);
         }
-        let image = &image.0;
+        let decoded = &image.decoded;
 
         // Compute the image's width in columns by translating pixels -> columns.
         let column_in_pixels = dimensions.pixels_per_column();
         let column_margin = (dimensions.columns as f64 * 0.95) as u32;
-        let mut width_in_columns = (image.width() as f64 / column_in_pixels) as u32;
+        let mut width_in_columns = (decoded.width() as f64 / column_in_pixels) as u32;
 
         // Do the same for its height.
         let row_in_pixels = dimensions.pixels_per_row();
-        let height_in_rows = (image.height() as f64 / row_in_pixels) as u32;
+        let mut height_in_rows = (decoded.height() as f64 / row_in_pixels) as u32;
 
         // If the image doesn't fit vertically, shrink it.
         let available_height = dimensions.rows.saturating_sub(position.row) as u32;
@@ -65,24 +250,166 @@ impl MediaRender {
             // need to shrink the height.
             let shrink_ratio = available_height as f64 / height_in_rows as f64;
             width_in_columns = (width_in_columns as f64 * shrink_ratio) as u32;
+            height_in_rows = available_height;
         }
         // Don't go too far wide.
-        let width_in_columns = width_in_columns.min(column_margin);
-
-        // Draw it in the middle
-        let start_column = dimensions.columns / 2 - (width_in_columns / 2) as u16;
-        let start_column = start_column + position.column;
-        let config = viuer::Config {
-            width: Some(width_in_columns),
-            x: start_column,
-            y: position.row as i16,
-            ..Default::default()
+        if width_in_columns > column_margin {
+            height_in_rows = (height_in_rows as f64 * column_margin as f64 / width_in_columns as f64) as u32;
+            width_in_columns = column_margin;
+        }
+
+        let start_column = match image_position {
+            ImagePosition::Left => 0,
+            ImagePosition::Center => dimensions.columns / 2 - (width_in_columns / 2) as u16,
+            ImagePosition::Right => dimensions.columns.saturating_sub(width_in_columns as u16),
+        };
+        let area = ImageArea {
+            column: start_column + position.column,
+            row: position.row,
+            columns: width_in_columns.max(1),
+            rows: height_in_rows.max(1),
+            background: false,
+        };
+        Ok(area)
+    }
+
+    /// Draw an image behind everything else, stretching it so it covers the entire window.
+    pub(crate) fn draw_background<W: io::Write>(
+        &self,
+        image: &Image,
+        dimensions: &WindowSize,
+        writer: &mut W,
+    ) -> Result<(), RenderImageError> {
+        if !dimensions.has_pixels {
+            return Err(RenderImageError::NoWindowSize);
+        }
+        let area = ImageArea {
+            column: 0,
+            row: 0,
+            columns: dimensions.columns as u32,
+            rows: dimensions.rows as u32,
+            background: true,
+        };
+        self.draw(image, &area, dimensions, writer)
+    }
+
+    fn draw<W: io::Write>(
+        &self,
+        image: &Image,
+        area: &ImageArea,
+        dimensions: &WindowSize,
+        writer: &mut W,
+    ) -> Result<(), RenderImageError> {
+        let ImageArea { column, row, columns, rows, background } = *area;
+        match self.protocol {
+            ImageProtocol::Kitty => {
+                writer.queue(MoveTo(column, row))?;
+                let rgba = image.decoded.to_rgba8();
+                let placement = KittyPlacement {
+                    width: rgba.width(),
+                    height: rgba.height(),
+                    columns,
+                    rows,
+                    below_text: background,
+                };
+                write_kitty_image(writer, &placement, rgba.as_raw())?;
+                writer.flush()?;
+            }
+            ImageProtocol::Iterm2 => {
+                writer.queue(MoveTo(column, row))?;
+                write_iterm2_image(writer, columns, rows, !background, &image.contents)?;
+                writer.flush()?;
+            }
+            ImageProtocol::Sixel => {
+                writer.queue(MoveTo(column, row))?;
+                let width = (columns as f64 * dimensions.pixels_per_column()) as u32;
+                let height = (rows as f64 * dimensions.pixels_per_row()) as u32;
+                let resized = imageops::resize(&image.decoded.to_rgba8(), width, height, FilterType::Triangle);
+                writer.write_all(encode_sixel(&resized).as_bytes())?;
+                writer.flush()?;
+            }
+            ImageProtocol::AsciiBlocks => {
+                // viuer writes straight into stdout so anything we've queued needs to go first.
+                writer.flush()?;
+                let config = viuer::Config {
+                    width: Some(columns),
+                    height: background.then_some(rows),
+                    x: column,
+                    y: row as i16,
+                    use_kitty: false,
+                    use_iterm: false,
+                    ..Default::default()
+                };
+                viuer::print(&image.decoded, &config)?;
+            }
         };
-        viuer::print(image, &config)?;
         Ok(())
     }
 }
 
+/// The part of the window an image is drawn on.
+struct ImageArea {
+    column: u16,
+    row: u16,
+    columns: u32,
+    rows: u32,
+    background: bool,
+}
+
+/// Where and how big an image drawn using the kitty graphics protocol is.
+struct KittyPlacement {
+    width: u32,
+    height: u32,
+    columns: u32,
+    rows: u32,
+    below_text: bool,
+}
+
+/// The maximum size of the payload in every escape sequence, as defined by the kitty graphics protocol.
+const KITTY_CHUNK_SIZE: usize = 4096;
+
+/// Write an RGBA image using the kitty graphics protocol, scaled to fit the given number of columns and rows.
+fn write_kitty_image<W: io::Write>(writer: &mut W, placement: &KittyPlacement, pixels: &[u8]) -> io::Result<()> {
+    let KittyPlacement { width, height, columns, rows, below_text } = placement;
+    // A negative z-index places the image below the text in the cells it covers.
+    let z_index = if *below_text { -1 } else { 0 };
+    let payload = base64_encode(pixels);
+    let mut chunks = payload.as_bytes().chunks(KITTY_CHUNK_SIZE).peekable();
+    let mut first = true;
+    while let Some(chunk) = chunks.next() {
+        let more = u8::from(chunks.peek().is_some());
+        // base64 output is always ascii.
+        let chunk = std::str::from_utf8(chunk).expect("invalid base64");
+        if first {
+            write!(
+                writer,
+                "\x1b_Ga=T,f=32,s={width},v={height},c={columns},r={rows},z={z_index},q=2,m={more};{chunk}\x1b\\"
+            )?;
+            first = false;
+        } else {
+            write!(writer, "\x1b_Gm={more};{chunk}\x1b\\")?;
+        }
+    }
+    Ok(())
+}
+
+/// Write an encoded image using iTerm2's inline images protocol, scaled to fit the given number of columns and rows.
+fn write_iterm2_image<W: io::Write>(
+    writer: &mut W,
+    columns: u32,
+    rows: u32,
+    preserve_aspect_ratio: bool,
+    contents: &[u8],
+) -> io::Result<()> {
+    let size = contents.len();
+    let preserve_aspect_ratio = u8::from(preserve_aspect_ratio);
+    let payload = base64_encode(contents);
+    write!(
+        writer,
+        "\x1b]1337;File=inline=1;size={size};width={columns};height={rows};preserveAspectRatio={preserve_aspect_ratio}:{payload}\x07"
+    )
+}
+
 /// An invalid image.
 #[derive(thiserror::Error, Debug)]
 #[error("invalid image: {0}")]
@@ -104,3 +431,84 @@ pub enum RenderImageError {
     #[error("no window size support in terminal")]
     NoWindowSize,
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::kitty_term(&[("TERM", "xterm-kitty")], ImageProtocol::Kitty)]
+    #[case::kitty_window(&[("TERM", "xterm-256color"), ("KITTY_WINDOW_ID", "1")], ImageProtocol::Kitty)]
+    #[case::iterm2(&[("TERM_PROGRAM", "iTerm.app")], ImageProtocol::Iterm2)]
+    #[case::iterm2_over_ssh(&[("LC_TERMINAL", "iTerm2")], ImageProtocol::Iterm2)]
+    #[case::wezterm(&[("TERM_PROGRAM", "WezTerm")], ImageProtocol::Iterm2)]
+    #[case::other(&[("TERM", "xterm-256color")], ImageProtocol::AsciiBlocks)]
+    fn detect_protocol(#[case] variables: &[(&str, &str)], #[case] expected: ImageProtocol) {
+        let lookup = |name: &str| variables.iter().find(|(key, _)| *key == name).map(|(_, value)| value.to_string());
+        assert_eq!(ImageProtocol::from_environment(lookup), expected);
+    }
+
+    #[rstest]
+    #[case::supported("\x1b[?62;4;22c", true)]
+    #[case::vt340("\x1b[?63;1;2;4;6;9;15;22c", true)]
+    #[case::unsupported("\x1b[?62;22c", false)]
+    #[case::similar_attribute("\x1b[?64;44c", false)]
+    #[case::garbage("potato", false)]
+    fn sixel_support(#[case] response: &str, #[case] expected: bool) {
+        assert_eq!(supports_sixel(response), expected);
+    }
+
+    #[test]
+    fn bmp_encoding() {
+        let image = RgbaImage::from_fn(2, 2, |column, row| image::Rgba([column as u8, row as u8, 7, 255]));
+        let output = encode_bmp(&image);
+        // Every row takes 6 bytes, padded to 8.
+        assert_eq!(output.len(), 54 + 16);
+        assert_eq!(&output[..2], b"BM");
+        assert_eq!(&output[2..6], &70_u32.to_le_bytes());
+        // The bottom row goes first and every pixel is written as BGR.
+        assert_eq!(&output[54..], &[7, 1, 0, 7, 1, 1, 0, 0, 7, 0, 0, 7, 0, 1, 0, 0]);
+    }
+
+    #[test]
+    fn dimmed_image() {
+        let image = RgbaImage::from_pixel(1, 1, image::Rgba([200, 100, 50, 255]));
+        let image = Image { decoded: Rc::new(DynamicImage::ImageRgba8(image)), contents: Rc::new([]) };
+        let dimmed = image.dimmed(0.5);
+        assert_eq!(dimmed.decoded.to_rgba8().get_pixel(0, 0).0, [100, 50, 25, 255]);
+        assert!(dimmed.contents.starts_with(b"BM"));
+    }
+
+    #[test]
+    fn iterm2_image() {
+        let mut output = Vec::new();
+        write_iterm2_image(&mut output, 10, 5, true, b"foo").expect("write failed");
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(output, "\x1b]1337;File=inline=1;size=3;width=10;height=5;preserveAspectRatio=1:Zm9v\x07");
+    }
+
+    #[test]
+    fn kitty_single_chunk() {
+        let placement = KittyPlacement { width: 1, height: 1, columns: 2, rows: 3, below_text: false };
+        let mut output = Vec::new();
+        write_kitty_image(&mut output, &placement, &[255, 0, 0, 255]).expect("write failed");
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(output, "\x1b_Ga=T,f=32,s=1,v=1,c=2,r=3,z=0,q=2,m=0;/wAA/w==\x1b\\");
+    }
+
+    #[test]
+    fn kitty_multiple_chunks() {
+        let placement = KittyPlacement { width: 64, height: 64, columns: 10, rows: 5, below_text: true };
+        let pixels = vec![0; 64 * 64 * 4];
+        let mut output = Vec::new();
+        write_kitty_image(&mut output, &placement, &pixels).expect("write failed");
+        let output = String::from_utf8(output).unwrap();
+        let sequences: Vec<_> = output.split_terminator("\x1b\\").collect();
+        // 16384 bytes turn into 21848 bytes of base64.
+        assert_eq!(sequences.len(), 6);
+        assert!(sequences[0].starts_with("\x1b_Ga=T,f=32,s=64,v=64,c=10,r=5,z=-1,q=2,m=1;"));
+        assert!(sequences[1].starts_with("\x1b_Gm=1;"));
+        assert!(sequences[5].starts_with("\x1b_Gm=0;"));
+    }
+}
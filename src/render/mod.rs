@@ -1,8 +1,15 @@
+pub(crate) mod ansi;
+pub(crate) mod chart;
 pub(crate) mod draw;
 pub(crate) mod engine;
+pub(crate) mod figlet;
 pub(crate) mod highlighting;
 pub(crate) mod layout;
 pub(crate) mod media;
 pub(crate) mod properties;
+pub(crate) mod qr;
+pub(crate) mod sixel;
 pub(crate) mod terminal;
 pub(crate) mod text;
+#[cfg(feature = "tree-sitter")]
+pub(crate) mod tree_sitter;
@@ -0,0 +1,93 @@
+use qrcode::types::QrError;
+
+/// The number of light modules around the code, which scanners need to find it.
+const QUIET_ZONE: usize = 2;
+
+/// A QR code, ready to be drawn using unicode half blocks.
+///
+/// Every character cell holds 2 modules stacked vertically so modules are roughly square.
+#[derive(Clone, Debug)]
+pub(crate) struct QrCode {
+    width: usize,
+    modules: Vec<bool>,
+}
+
+impl QrCode {
+    /// Encode a piece of data as a QR code.
+    pub(crate) fn encode(data: &str) -> Result<Self, QrError> {
+        let code = qrcode::QrCode::new(data)?;
+        let modules = code.to_colors().into_iter().map(|color| color == qrcode::Color::Dark).collect();
+        Ok(Self { width: code.width(), modules })
+    }
+
+    /// Get the largest scale the code can be drawn at while fitting in the given number of columns and rows.
+    pub(crate) fn fitting_scale(&self, columns: usize, rows: usize) -> Option<usize> {
+        let size = self.width + QUIET_ZONE * 2;
+        let scale = (columns / size).min(rows * 2 / size);
+        (scale > 0).then_some(scale)
+    }
+
+    /// Draw this code, where every module takes `scale` columns and half as many rows.
+    ///
+    /// Dark modules are drawn using the foreground color and light ones using the background color.
+    pub(crate) fn rows(&self, scale: usize) -> Vec<String> {
+        let size = (self.width + QUIET_ZONE * 2) * scale;
+        let is_dark = |column: usize, row: usize| {
+            let (column, row) = (column / scale, row / scale);
+            let inside = (QUIET_ZONE..self.width + QUIET_ZONE).contains(&column)
+                && (QUIET_ZONE..self.width + QUIET_ZONE).contains(&row);
+            inside && self.modules[(row - QUIET_ZONE) * self.width + column - QUIET_ZONE]
+        };
+        (0..size)
+            .step_by(2)
+            .map(|row| {
+                (0..size)
+                    .map(|column| match (is_dark(column, row), row + 1 < size && is_dark(column, row + 1)) {
+                        (true, true) => '█',
+                        (true, false) => '▀',
+                        (false, true) => '▄',
+                        (false, false) => ' ',
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    #[test]
+    fn encode() {
+        let code = QrCode::encode("https://example.com").expect("encode failed");
+        // The smallest version is 21 modules wide, and this needs the next one.
+        assert_eq!(code.width, 25);
+        let rows = code.rows(1);
+        assert_eq!(rows.len(), 15);
+        assert!(rows.iter().all(|row| row.chars().count() == 29));
+        // The quiet zone is blank and is followed by the top of the finder patterns.
+        assert_eq!(rows[0], " ".repeat(29));
+        assert!(rows[1].starts_with("  █▀▀▀▀▀█ "), "unexpected row: {}", rows[1]);
+    }
+
+    #[test]
+    fn scaled() {
+        let code = QrCode::encode("hi").expect("encode failed");
+        let rows = code.rows(2);
+        assert_eq!(rows.len(), 25);
+        assert!(rows.iter().all(|row| row.chars().count() == 50));
+        assert!(rows[2].starts_with("    ██████████████  "), "unexpected row: {}", rows[2]);
+    }
+
+    #[rstest]
+    #[case::too_small(20, 50, None)]
+    #[case::exact(25, 13, Some(1))]
+    #[case::limited_by_rows(100, 25, Some(2))]
+    #[case::limited_by_columns(75, 100, Some(3))]
+    fn fitting_scale(#[case] columns: usize, #[case] rows: usize, #[case] expected: Option<usize>) {
+        let code = QrCode::encode("hi").expect("encode failed");
+        assert_eq!(code.fitting_scale(columns, rows), expected);
+    }
+}
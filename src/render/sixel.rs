@@ -0,0 +1,140 @@
+use image::RgbaImage;
+use std::fmt::Write;
+
+/// The number of levels every channel is quantized into, giving us a palette of 216 colors.
+const LEVELS: u8 = 6;
+
+/// Pixels with an alpha below this are treated as transparent.
+const ALPHA_THRESHOLD: u8 = 128;
+
+/// Encode an image as sixel graphics.
+///
+/// Colors are quantized into a fixed palette and transparent pixels are left untouched so whatever is behind them
+/// remains visible.
+pub(crate) fn encode_sixel(image: &RgbaImage) -> String {
+    let (width, height) = image.dimensions();
+    let indexes: Vec<Option<u8>> = image.pixels().map(|pixel| palette_index(pixel.0)).collect();
+
+    let mut output = String::new();
+    // Using 1 as the second parameter makes pixels we don't draw transparent.
+    let _ = write!(output, "\x1bP0;1;0q\"1;1;{width};{height}");
+    let mut used = [false; (LEVELS as usize).pow(3)];
+    for index in indexes.iter().flatten() {
+        used[*index as usize] = true;
+    }
+    for (index, _) in used.iter().enumerate().filter(|(_, used)| **used) {
+        let [red, green, blue] = palette_color(index as u8).map(|level| level as u32 * 100 / 255);
+        let _ = write!(output, "#{index};2;{red};{green};{blue}");
+    }
+
+    let width = width as usize;
+    for band_start in (0..height as usize).step_by(6) {
+        let band_end = (band_start + 6).min(height as usize);
+        let mut band_used = [false; (LEVELS as usize).pow(3)];
+        for row in band_start..band_end {
+            for index in indexes[row * width..(row + 1) * width].iter().flatten() {
+                band_used[*index as usize] = true;
+            }
+        }
+        for (color, _) in band_used.iter().enumerate().filter(|(_, used)| **used) {
+            let sixels = (0..width).map(|column| {
+                let mut bits = 0;
+                for row in band_start..band_end {
+                    if indexes[row * width + column] == Some(color as u8) {
+                        bits |= 1 << (row - band_start);
+                    }
+                }
+                (b'?' + bits) as char
+            });
+            let _ = write!(output, "#{color}");
+            write_run_length_encoded(&mut output, sixels);
+            // Go back to the start of the band so the next color can be drawn on top.
+            output.push('$');
+        }
+        output.push('-');
+    }
+    output.push_str("\x1b\\");
+    output
+}
+
+fn write_run_length_encoded<I: Iterator<Item = char>>(output: &mut String, sixels: I) {
+    let mut runs: Vec<(char, usize)> = Vec::new();
+    for sixel in sixels {
+        match runs.last_mut() {
+            Some((last, count)) if *last == sixel => *count += 1,
+            _ => runs.push((sixel, 1)),
+        };
+    }
+    // Nothing is drawn at the end of the row so there's no need to write it.
+    if runs.last().is_some_and(|(sixel, _)| *sixel == '?') {
+        runs.pop();
+    }
+    for (sixel, count) in runs {
+        if count > 3 {
+            let _ = write!(output, "!{count}{sixel}");
+        } else {
+            for _ in 0..count {
+                output.push(sixel);
+            }
+        }
+    }
+}
+
+fn palette_index([red, green, blue, alpha]: [u8; 4]) -> Option<u8> {
+    if alpha < ALPHA_THRESHOLD {
+        return None;
+    }
+    let level = |value: u8| ((value as u32 * (LEVELS as u32 - 1) + 127) / 255) as u8;
+    Some(level(red) * LEVELS * LEVELS + level(green) * LEVELS + level(blue))
+}
+
+fn palette_color(index: u8) -> [u8; 3] {
+    let step = 255 / (LEVELS - 1);
+    [index / (LEVELS * LEVELS), index / LEVELS % LEVELS, index % LEVELS].map(|level| level * step)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use image::Rgba;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::black([0, 0, 0, 255], Some(0))]
+    #[case::white([255, 255, 255, 255], Some(215))]
+    #[case::red([255, 0, 0, 255], Some(180))]
+    #[case::almost_red([250, 10, 0, 255], Some(180))]
+    #[case::transparent([255, 0, 0, 0], None)]
+    fn quantize(#[case] pixel: [u8; 4], #[case] expected: Option<u8>) {
+        assert_eq!(palette_index(pixel), expected);
+    }
+
+    #[test]
+    fn palette_roundtrip() {
+        for index in 0..216 {
+            let [red, green, blue] = palette_color(index);
+            assert_eq!(palette_index([red, green, blue, 255]), Some(index));
+        }
+    }
+
+    #[test]
+    fn encode() {
+        // The first 2 rows are red, the third one is transparent.
+        let image = RgbaImage::from_fn(5, 3, |_, row| match row {
+            0 | 1 => Rgba([255, 0, 0, 255]),
+            _ => Rgba([0, 0, 0, 0]),
+        });
+        let output = encode_sixel(&image);
+        assert_eq!(output, "\x1bP0;1;0q\"1;1;5;3#180;2;100;0;0#180!5B$-\x1b\\");
+    }
+
+    #[test]
+    fn encode_multiple_colors() {
+        let image = RgbaImage::from_fn(2, 1, |column, _| match column {
+            0 => Rgba([0, 0, 0, 255]),
+            _ => Rgba([255, 255, 255, 255]),
+        });
+        let output = encode_sixel(&image);
+        assert_eq!(output, "\x1bP0;1;0q\"1;1;2;1#0;2;0;0;0#215;2;100;100;100#0@$#215?@$-\x1b\\");
+    }
+}
@@ -15,6 +15,7 @@ where
 {
     writer: W,
     pub(crate) cursor_row: u16,
+    owns_terminal: bool,
 }
 
 impl<W: io::Write> Terminal<W> {
@@ -23,7 +24,7 @@ impl<W: io::Write> Terminal<W> {
         writer.queue(cursor::Hide)?;
         writer.queue(terminal::EnterAlternateScreen)?;
 
-        Ok(Self { writer, cursor_row: 0 })
+        Ok(Self { writer, cursor_row: 0, owns_terminal: true })
     }
 
     pub(crate) fn move_to(&mut self, column: u16, row: u16) -> io::Result<()> {
@@ -81,20 +82,100 @@ impl<W: io::Write> Terminal<W> {
         Ok(())
     }
 
+    /// Give up control of the terminal so another process can use it.
+    pub(crate) fn suspend(&mut self) -> io::Result<()> {
+        self.writer.queue(terminal::LeaveAlternateScreen)?;
+        self.writer.queue(cursor::Show)?;
+        self.writer.flush()?;
+        terminal::disable_raw_mode()
+    }
+
+    /// Take back control of the terminal after a call to [Terminal::suspend].
+    pub(crate) fn resume(&mut self) -> io::Result<()> {
+        terminal::enable_raw_mode()?;
+        self.writer.queue(terminal::EnterAlternateScreen)?;
+        self.writer.queue(cursor::Hide)?;
+        self.writer.flush()
+    }
+
+    /// Copy some text into the system clipboard.
+    ///
+    /// This uses the OSC 52 escape sequence so it works as long as the terminal supports it, even over ssh.
+    pub(crate) fn copy_to_clipboard(&mut self, text: &str) -> io::Result<()> {
+        write!(self.writer, "\x1b]52;c;{}\x07", base64_encode(text.as_bytes()))?;
+        Ok(())
+    }
+
     pub(crate) fn sync_cursor_row(&mut self) -> io::Result<()> {
         self.cursor_row = CursorPosition::current()?.row;
         Ok(())
     }
 }
 
+impl Terminal<io::Sink> {
+    /// Construct a terminal that discards anything written to it, starting at the given row.
+    ///
+    /// This is used to figure out where things would be drawn without actually drawing them.
+    pub(crate) fn sink(cursor_row: u16) -> Self {
+        Self { writer: io::sink(), cursor_row, owns_terminal: false }
+    }
+}
+
+impl<W: io::Write> io::Write for Terminal<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writer.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
 impl<W> Drop for Terminal<W>
 where
     W: io::Write,
 {
     fn drop(&mut self) {
+        if !self.owns_terminal {
+            return;
+        }
         let _ = self.writer.queue(terminal::LeaveAlternateScreen);
         let _ = self.writer.queue(cursor::Show);
         let _ = self.writer.flush();
         let _ = terminal::disable_raw_mode();
     }
 }
+
+pub(crate) fn base64_encode(input: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut output = String::with_capacity(input.len().div_ceil(3) * 4);
+    for chunk in input.chunks(3) {
+        let bytes = [chunk[0], chunk.get(1).copied().unwrap_or(0), chunk.get(2).copied().unwrap_or(0)];
+        let value = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for index in 0..4 {
+            if index <= chunk.len() {
+                let position = (value >> (18 - index * 6)) & 0x3f;
+                output.push(ALPHABET[position as usize] as char);
+            } else {
+                output.push('=');
+            }
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::empty("", "")]
+    #[case::one_byte("f", "Zg==")]
+    #[case::two_bytes("fo", "Zm8=")]
+    #[case::three_bytes("foo", "Zm9v")]
+    #[case::longer("echo hello\n", "ZWNobyBoZWxsbwo=")]
+    fn base64(#[case] input: &str, #[case] expected: &str) {
+        assert_eq!(base64_encode(input.as_bytes()), expected);
+    }
+}
@@ -1,16 +1,21 @@
 use super::terminal::Terminal;
 use crate::{
-    markdown::text::WeightedLine,
+    markdown::{
+        elements::StyledText,
+        text::{char_width, TextWidth, WeightedLine},
+    },
     render::{
         draw::{RenderError, RenderResult},
         layout::{Layout, Positioning},
         properties::WindowSize,
     },
     style::{Colors, TextStyle},
+    theme::OutputOverflow,
 };
-use std::io;
+use std::{io, mem};
 
 const MINIMUM_LINE_LENGTH: u16 = 10;
+const CONTINUATION_MARKER: &str = "↪ ";
 
 /// Draws text on the screen.
 ///
@@ -18,6 +23,7 @@ const MINIMUM_LINE_LENGTH: u16 = 10;
 pub(crate) struct TextDrawer<'a> {
     line: &'a WeightedLine,
     positioning: Positioning,
+    justify: bool,
     default_colors: &'a Colors,
 }
 
@@ -34,7 +40,7 @@ impl<'a> TextDrawer<'a> {
         if text_length > positioning.max_line_length && positioning.max_line_length <= MINIMUM_LINE_LENGTH {
             Err(RenderError::TerminalTooSmall)
         } else {
-            Ok(Self { line, positioning, default_colors })
+            Ok(Self { line, positioning, justify: layout.justifies(), default_colors })
         }
     }
 
@@ -47,15 +53,32 @@ impl<'a> TextDrawer<'a> {
     {
         let Positioning { max_line_length, start_column } = self.positioning;
 
-        for (line_index, line) in self.line.split(max_line_length as usize).enumerate() {
+        let lines: Vec<_> = self.line.split(max_line_length as usize).collect();
+        let line_count = lines.len();
+        for (line_index, line) in lines.into_iter().enumerate() {
             terminal.move_to_column(start_column)?;
             if line_index > 0 {
                 terminal.move_down(1)?;
             }
-            for chunk in line {
-                let (text, style) = chunk.into_parts();
+            // The last line in a justified paragraph is left as is.
+            let texts: Vec<_> = line.iter().map(|chunk| chunk.clone().into_parts().0).collect();
+            let texts = match self.justify && line_index + 1 < line_count {
+                true => justify(&texts, max_line_length as usize),
+                false => texts.into_iter().map(String::from).collect(),
+            };
+            for (chunk, text) in line.into_iter().zip(texts) {
+                // Links within the presentation are followed by us rather than the terminal.
+                let url = chunk.link().filter(|link| !link.starts_with('#'));
+                let (_, style) = chunk.into_parts();
                 let text = style.apply(text);
-                terminal.print_styled_line(text)?;
+                match url {
+                    Some(url) => {
+                        terminal.print_line(&format!("\x1b]8;;{url}\x1b\\"))?;
+                        terminal.print_styled_line(text)?;
+                        terminal.print_line("\x1b]8;;\x1b\\")?;
+                    }
+                    None => terminal.print_styled_line(text)?,
+                };
 
                 // Crossterm resets colors if any attributes are set so let's just re-apply colors
                 // if the format has anything on it at all.
@@ -67,3 +90,178 @@ impl<'a> TextDrawer<'a> {
         Ok(())
     }
 }
+
+/// Stretch the pieces of text that make up a line so that together they take up the given width.
+///
+/// This is done by widening the spaces between words, the leftmost ones getting an extra space if they can't all be
+/// widened evenly. Trailing spaces are dropped.
+fn justify(texts: &[&str], width: usize) -> Vec<String> {
+    let mut texts = texts.to_vec();
+    for text in texts.iter_mut().rev() {
+        *text = text.trim_end();
+        if !text.is_empty() {
+            break;
+        }
+    }
+    let gaps: usize = texts.iter().map(|text| text.matches(' ').count()).sum();
+    let text_width: usize = texts.iter().map(|text| text.width()).sum();
+    let extra = width.saturating_sub(text_width);
+    if gaps == 0 || extra == 0 {
+        return texts.into_iter().map(String::from).collect();
+    }
+    let mut gap_index = 0;
+    texts
+        .into_iter()
+        .map(|text| {
+            let mut output = String::with_capacity(text.len() + extra);
+            for c in text.chars() {
+                output.push(c);
+                if c == ' ' {
+                    let widening = extra / gaps + usize::from(gap_index < extra % gaps);
+                    for _ in 0..widening {
+                        output.push(' ');
+                    }
+                    gap_index += 1;
+                }
+            }
+            output
+        })
+        .collect()
+}
+
+/// Split a line of styled text into lines that fit within the given number of columns.
+///
+/// Each of these is returned along with its width.
+pub(crate) fn wrap_styled_line(
+    chunks: Vec<StyledText>,
+    columns: usize,
+    overflow: &OutputOverflow,
+) -> Vec<(Vec<StyledText>, usize)> {
+    let columns = columns.max(1);
+    let total_width = styled_line_width(&chunks);
+    let truncate = matches!(overflow, OutputOverflow::Truncate) && total_width > columns;
+    // Leave room for the ellipsis.
+    let max_width = if truncate { columns - 1 } else { columns };
+    let mut lines = Vec::new();
+    let mut line = Vec::new();
+    let mut width = 0;
+    for StyledText { text, style, .. } in chunks {
+        let mut piece = String::new();
+        let mut previous = None;
+        for c in text.chars() {
+            let char_width = char_width(c, previous);
+            previous = Some(c);
+            if width + char_width > max_width && width > 0 {
+                push_styled(&mut line, mem::take(&mut piece), &style);
+                if truncate {
+                    line.push(StyledText::new("…", style));
+                    lines.push((line, width + 1));
+                    return lines;
+                }
+                lines.push((mem::take(&mut line), width));
+                width = 0;
+            }
+            piece.push(c);
+            width += char_width;
+        }
+        push_styled(&mut line, piece, &style);
+    }
+    if width > 0 {
+        lines.push((line, width));
+    }
+    lines
+}
+
+/// Split a line of preformatted text that doesn't fit within the given number of columns.
+///
+/// When wrapping, every line after the first one starts with a marker, using the colors the original line starts with,
+/// to make it clear it continues the previous one.
+pub(crate) fn split_preformatted_line(
+    spans: &[StyledText],
+    columns: usize,
+    overflow: &OutputOverflow,
+) -> Vec<(Vec<StyledText>, usize)> {
+    let colors = spans.first().map(|span| span.style.colors.clone()).unwrap_or_default();
+    let marker_width = match overflow {
+        OutputOverflow::Wrap => CONTINUATION_MARKER.width(),
+        OutputOverflow::Truncate => 0,
+    };
+    let mut lines = wrap_styled_line(spans.to_vec(), columns.saturating_sub(marker_width), overflow);
+    for (line, width) in lines.iter_mut().skip(1) {
+        line.insert(0, StyledText::new(CONTINUATION_MARKER, TextStyle::default().colors(colors.clone())));
+        *width += marker_width;
+    }
+    lines
+}
+
+/// Get the width of a line made up of styled pieces of text.
+pub(crate) fn styled_line_width(spans: &[StyledText]) -> usize {
+    spans.iter().map(|span| span.text.width()).sum()
+}
+
+fn push_styled(line: &mut Vec<StyledText>, text: String, style: &TextStyle) {
+    if !text.is_empty() {
+        line.push(StyledText::new(text, style.clone()));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{render::ansi::AnsiSplitter, style::Color};
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::even(&["a b c"], 7, &["a  b  c"])]
+    #[case::uneven(&["a b c"], 8, &["a   b  c"])]
+    #[case::across_chunks(&["a ", "b", " c"], 7, &["a  ", "b", "  c"])]
+    #[case::trailing_spaces(&["a b ", "  "], 5, &["a   b", ""])]
+    #[case::no_gaps(&["abc"], 10, &["abc"])]
+    #[case::too_wide(&["a bcd"], 3, &["a bcd"])]
+    fn justify_line(#[case] texts: &[&str], #[case] width: usize, #[case] expected: &[&str]) {
+        assert_eq!(justify(texts, width), expected);
+    }
+
+    fn plain(line: &[StyledText]) -> String {
+        line.iter().map(|span| span.text.as_str()).collect()
+    }
+
+    #[test]
+    fn wrap_styled_output() {
+        let chunks = AnsiSplitter::new(Colors::default()).split_line("ab\x1b[1mcde");
+        let lines = wrap_styled_line(chunks, 3, &OutputOverflow::Wrap);
+        let lengths: Vec<_> = lines.iter().map(|(_, length)| *length).collect();
+        assert_eq!(lengths, &[3, 2]);
+        let expected = [StyledText::new("ab", TextStyle::default()), StyledText::new("c", TextStyle::default().bold())];
+        assert_eq!(lines[0].0, expected);
+    }
+
+    #[rstest]
+    #[case::fits("abc", OutputOverflow::Wrap, &[("abc", 3)])]
+    #[case::wrap("abcdefg", OutputOverflow::Wrap, &[("abcd", 4), ("efg", 3)])]
+    #[case::wrap_wide("苹果苹果苹", OutputOverflow::Wrap, &[("苹果", 4), ("苹果", 4), ("苹", 2)])]
+    #[case::truncate("abcdefg", OutputOverflow::Truncate, &[("abc…", 4)])]
+    #[case::truncate_fits("abcd", OutputOverflow::Truncate, &[("abcd", 4)])]
+    #[case::truncate_wide("苹果苹果", OutputOverflow::Truncate, &[("苹…", 3)])]
+    fn wrap_output(#[case] input: &str, #[case] overflow: OutputOverflow, #[case] expected: &[(&str, usize)]) {
+        let chunks = vec![StyledText::from(input.to_string())];
+        let lines = wrap_styled_line(chunks, 4, &overflow);
+        let lines: Vec<_> = lines.iter().map(|(line, width)| (plain(line), *width)).collect();
+        let expected: Vec<_> = expected.iter().map(|(line, width)| (line.to_string(), *width)).collect();
+        assert_eq!(lines, expected);
+    }
+
+    #[rstest]
+    #[case::wrap(OutputOverflow::Wrap, &["abcde", "↪ fghij"])]
+    #[case::truncate(OutputOverflow::Truncate, &["abcdef…"])]
+    fn split_preformatted(#[case] overflow: OutputOverflow, #[case] expected: &[&str]) {
+        let spans = AnsiSplitter::new(Colors::default()).split_line("\x1b[38;2;1;2;3mabcdefghij");
+        let lines = split_preformatted_line(&spans, 7, &overflow);
+        let texts: Vec<_> = lines.iter().map(|(line, _)| plain(line)).collect();
+        assert_eq!(texts, expected);
+        for (line, width) in &lines {
+            assert!(*width <= 7);
+            assert_eq!(line[0].style.colors.foreground, Some(Color::new(1, 2, 3)), "{line:?}");
+        }
+    }
+}
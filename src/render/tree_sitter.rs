@@ -0,0 +1,154 @@
+use super::highlighting::Highlighter;
+use crate::markdown::elements::CodeLanguage;
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use syntect::{
+    highlighting::{Highlighter as ThemeHighlighter, Style, Theme},
+    parsing::Scope,
+};
+use tree_sitter::Language;
+use tree_sitter_highlight::{HighlightConfiguration, HighlightEvent};
+
+/// The tree-sitter highlight names we recognize along with the textmate scope used to style each of them.
+///
+/// Using textmate scopes lets us style code using the same themes syntect uses.
+const HIGHLIGHT_NAMES: &[(&str, &str)] = &[
+    ("attribute", "entity.other.attribute-name"),
+    ("comment", "comment"),
+    ("constant", "constant"),
+    ("constant.builtin", "constant.language"),
+    ("constructor", "entity.name.type"),
+    ("escape", "constant.character.escape"),
+    ("function", "entity.name.function"),
+    ("function.builtin", "support.function"),
+    ("function.macro", "entity.name.function.macro"),
+    ("function.method", "entity.name.function"),
+    ("keyword", "keyword"),
+    ("label", "entity.name.label"),
+    ("number", "constant.numeric"),
+    ("operator", "keyword.operator"),
+    ("property", "variable.other.member"),
+    ("punctuation", "punctuation"),
+    ("punctuation.bracket", "punctuation.section"),
+    ("punctuation.delimiter", "punctuation.separator"),
+    ("string", "string"),
+    ("string.special", "string.regexp"),
+    ("tag", "entity.name.tag"),
+    ("type", "entity.name.type"),
+    ("type.builtin", "storage.type"),
+    ("variable", "variable"),
+    ("variable.builtin", "variable.language"),
+    ("variable.parameter", "variable.parameter"),
+];
+
+static CONFIGURATIONS: Lazy<HashMap<CodeLanguage, HighlightConfiguration>> = Lazy::new(|| {
+    let languages = [
+        (CodeLanguage::C, tree_sitter_c::language(), tree_sitter_c::HIGHLIGHT_QUERY, ""),
+        (CodeLanguage::Go, tree_sitter_go::language(), tree_sitter_go::HIGHLIGHT_QUERY, ""),
+        (CodeLanguage::Java, tree_sitter_java::language(), tree_sitter_java::HIGHLIGHT_QUERY, ""),
+        (
+            CodeLanguage::JavaScript,
+            tree_sitter_javascript::language(),
+            tree_sitter_javascript::HIGHLIGHT_QUERY,
+            tree_sitter_javascript::LOCALS_QUERY,
+        ),
+        (CodeLanguage::Json, tree_sitter_json::language(), tree_sitter_json::HIGHLIGHT_QUERY, ""),
+        (CodeLanguage::Python, tree_sitter_python::language(), tree_sitter_python::HIGHLIGHT_QUERY, ""),
+        (CodeLanguage::Rust, tree_sitter_rust::language(), tree_sitter_rust::HIGHLIGHT_QUERY, ""),
+    ];
+    let names: Vec<_> = HIGHLIGHT_NAMES.iter().map(|(name, _)| *name).collect();
+    languages
+        .into_iter()
+        .map(|(code_language, language, highlights, locals): (_, Language, _, _)| {
+            let mut configuration =
+                HighlightConfiguration::new(language, highlights, "", locals).expect("invalid tree-sitter queries");
+            configuration.configure(&names);
+            (code_language, configuration)
+        })
+        .collect()
+});
+
+/// A highlighter that uses tree-sitter.
+#[derive(Clone)]
+pub(crate) struct TreeSitterHighlighter {
+    styles: Vec<Style>,
+    default_style: Style,
+}
+
+impl TreeSitterHighlighter {
+    /// Construct a new highlighter that uses the styles in the given theme.
+    pub(crate) fn new(theme: &Theme) -> Self {
+        let highlighter = ThemeHighlighter::new(theme);
+        let styles = HIGHLIGHT_NAMES
+            .iter()
+            .map(|(_, scope)| {
+                let scope = Scope::new(scope).expect("invalid scope");
+                highlighter.style_for_stack(&[scope])
+            })
+            .collect();
+        Self { styles, default_style: highlighter.get_default() }
+    }
+}
+
+impl Highlighter for TreeSitterHighlighter {
+    fn highlight_lines<'a>(&self, code: &'a str, language: &CodeLanguage) -> Option<Vec<Vec<(Style, &'a str)>>> {
+        let configuration = CONFIGURATIONS.get(language)?;
+        let mut highlighter = tree_sitter_highlight::Highlighter::new();
+        let events = highlighter.highlight(configuration, code.as_bytes(), None, |_| None).ok()?;
+        let mut styles = Vec::new();
+        let mut lines = vec![Vec::new()];
+        for event in events {
+            match event.ok()? {
+                HighlightEvent::HighlightStart(highlight) => styles.push(self.styles[highlight.0]),
+                HighlightEvent::HighlightEnd => {
+                    styles.pop();
+                }
+                HighlightEvent::Source { start, end } => {
+                    let style = styles.last().copied().unwrap_or(self.default_style);
+                    // Split these at line endings so every line gets its own pieces.
+                    for text in code[start..end].split_inclusive('\n') {
+                        lines.last_mut().expect("no lines").push((style, text));
+                        if text.ends_with('\n') {
+                            lines.push(Vec::new());
+                        }
+                    }
+                }
+            }
+        }
+        // There's always an empty line at the end unless the code doesn't end in a line break.
+        if lines.last().is_some_and(Vec::is_empty) {
+            lines.pop();
+        }
+        Some(lines)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use syntect::highlighting::ThemeSet;
+
+    fn highlighter() -> TreeSitterHighlighter {
+        let themes = ThemeSet::load_defaults();
+        TreeSitterHighlighter::new(&themes.themes["base16-ocean.dark"])
+    }
+
+    #[test]
+    fn highlight_lines() {
+        let code = "fn main() {\n    let a = \"hi\";\n}\n";
+        let lines = highlighter().highlight_lines(code, &CodeLanguage::Rust).expect("not supported");
+        assert_eq!(lines.len(), 3);
+        for (line, expected) in lines.iter().zip(code.lines()) {
+            let text: String = line.iter().map(|(_, text)| *text).collect();
+            assert_eq!(text.trim_end_matches('\n'), expected);
+        }
+        // Keywords and strings are styled differently.
+        let style = |text: &str| lines.iter().flatten().find(|(_, piece)| *piece == text).map(|(style, _)| *style);
+        assert_ne!(style("fn"), style("\"hi\""));
+    }
+
+    #[test]
+    fn unsupported_language() {
+        assert!(highlighter().highlight_lines("foo", &CodeLanguage::Zig).is_none());
+    }
+}
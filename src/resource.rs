@@ -3,7 +3,7 @@ use crate::{
     theme::{LoadThemeError, PresentationTheme},
 };
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     fs, io,
     path::{Path, PathBuf},
 };
@@ -16,6 +16,7 @@ pub struct Resources {
     base_path: PathBuf,
     images: HashMap<PathBuf, Image>,
     themes: HashMap<PathBuf, PresentationTheme>,
+    external_files: HashSet<PathBuf>,
 }
 
 impl Resources {
@@ -23,7 +24,17 @@ impl Resources {
     ///
     /// Any relative paths will be assumed to be relative to the given base.
     pub fn new<P: Into<PathBuf>>(base_path: P) -> Self {
-        Self { base_path: base_path.into(), images: Default::default(), themes: Default::default() }
+        Self {
+            base_path: base_path.into(),
+            images: Default::default(),
+            themes: Default::default(),
+            external_files: Default::default(),
+        }
+    }
+
+    /// Get the path relative paths are resolved against.
+    pub(crate) fn base_path(&self) -> &Path {
+        &self.base_path
     }
 
     /// Get the image at the given path.
@@ -51,6 +62,21 @@ impl Resources {
         Ok(theme)
     }
 
+    /// Get the contents of the text file at the given path.
+    ///
+    /// These are not cached as they're expected to change while the presentation is being worked on.
+    pub(crate) fn external_text_file<P: AsRef<Path>>(&mut self, path: P) -> Result<String, LoadFileError> {
+        let path = self.base_path.join(path);
+        // Keep track of it even if reading fails so creating it later on triggers a reload.
+        self.external_files.insert(path.clone());
+        fs::read_to_string(&path).map_err(|e| LoadFileError(path, e))
+    }
+
+    /// Get the paths of all external files that were loaded.
+    pub(crate) fn external_files(&self) -> impl Iterator<Item = &Path> {
+        self.external_files.iter().map(PathBuf::as_path)
+    }
+
     /// Clears all resources.
     pub(crate) fn clear(&mut self) {
         self.images.clear();
@@ -58,6 +84,11 @@ impl Resources {
     }
 }
 
+/// An error loading a file.
+#[derive(thiserror::Error, Debug)]
+#[error("io error reading {0}: {1}")]
+pub struct LoadFileError(PathBuf, io::Error);
+
 /// An error loading an image.
 #[derive(thiserror::Error, Debug)]
 pub enum LoadImageError {
@@ -1,12 +1,42 @@
 use crossterm::style::Stylize;
 use hex::{FromHex, FromHexError};
+use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
 use serde_with::{DeserializeFromStr, SerializeDisplay};
 use std::{
+    env,
     fmt::{self, Display},
     str::FromStr,
 };
 
+static STRIKETHROUGH_SUPPORTED: Lazy<bool> = Lazy::new(|| {
+    let term = env::var("TERM").ok();
+    let term_program = env::var("TERM_PROGRAM").ok();
+    supports_strikethrough(term.as_deref(), term_program.as_deref())
+});
+
+static HYPERLINKS_SUPPORTED: Lazy<bool> = Lazy::new(|| {
+    let term = env::var("TERM").ok();
+    let term_program = env::var("TERM_PROGRAM").ok();
+    supports_hyperlinks(term.as_deref(), term_program.as_deref())
+});
+
+/// Figure out whether a terminal can cross out text based on the `TERM` and `TERM_PROGRAM` environment variables.
+fn supports_strikethrough(term: Option<&str>, term_program: Option<&str>) -> bool {
+    !matches!(term, Some("linux" | "vt100" | "vt220" | "ansi")) && term_program != Some("Apple_Terminal")
+}
+
+/// Figure out whether a terminal can display OSC 8 hyperlinks based on the `TERM` and `TERM_PROGRAM` environment
+/// variables.
+fn supports_hyperlinks(term: Option<&str>, term_program: Option<&str>) -> bool {
+    !matches!(term, Some("linux" | "vt100" | "vt220" | "ansi" | "dumb")) && term_program != Some("Apple_Terminal")
+}
+
+/// Check whether the terminal we're running in can display OSC 8 hyperlinks.
+pub(crate) fn hyperlinks_supported() -> bool {
+    *HYPERLINKS_SUPPORTED
+}
+
 /// The style of a piece of text.
 #[derive(Clone, Debug, Default, PartialEq, Eq)]
 pub(crate) struct TextStyle {
@@ -39,12 +69,27 @@ impl TextStyle {
         self
     }
 
+    /// Add underline to this style.
+    pub(crate) fn underlined(mut self) -> Self {
+        self.flags |= TextFormatFlags::Underlined as u8;
+        self
+    }
+
     /// Indicate this is a link.
     pub(crate) fn link(mut self) -> Self {
         self.flags |= TextFormatFlags::Link as u8;
         self
     }
 
+    /// Set whether this text is in focus.
+    pub(crate) fn set_focused(&mut self, focused: bool) {
+        if focused {
+            self.flags |= TextFormatFlags::Focused as u8;
+        } else {
+            self.flags &= !(TextFormatFlags::Focused as u8);
+        }
+    }
+
     /// Set the colors for this text style.
     pub(crate) fn colors(mut self, colors: Colors) -> Self {
         self.colors = colors;
@@ -71,11 +116,21 @@ impl TextStyle {
         self.flags & TextFormatFlags::Strikethrough as u8 != 0
     }
 
+    /// Check whether this text style is underlined.
+    pub(crate) fn is_underlined(&self) -> bool {
+        self.flags & TextFormatFlags::Underlined as u8 != 0
+    }
+
     /// Check whether this text is a link.
     pub(crate) fn is_link(&self) -> bool {
         self.flags & TextFormatFlags::Link as u8 != 0
     }
 
+    /// Check whether this text is in focus.
+    pub(crate) fn is_focused(&self) -> bool {
+        self.flags & TextFormatFlags::Focused as u8 != 0
+    }
+
     /// Merge this style with another one.
     pub(crate) fn merge(&mut self, other: &TextStyle) {
         self.flags |= other.flags;
@@ -94,11 +149,18 @@ impl TextStyle {
             styled = styled.italic();
         }
         if self.is_strikethrough() {
-            styled = styled.crossed_out();
+            // Dim it instead if it can't be crossed out so it's at least distinguishable from the rest.
+            styled = if *STRIKETHROUGH_SUPPORTED { styled.crossed_out() } else { styled.dim() };
+        }
+        if self.is_underlined() {
+            styled = styled.underlined();
         }
         if self.is_link() {
             styled = styled.italic().underlined();
         }
+        if self.is_focused() {
+            styled = styled.reverse();
+        }
         if let Some(color) = self.colors.background {
             styled = styled.on(color.into());
         }
@@ -116,6 +178,8 @@ enum TextFormatFlags {
     Code = 4,
     Strikethrough = 8,
     Link = 16,
+    Underlined = 32,
+    Focused = 64,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, SerializeDisplay, DeserializeFromStr)]
@@ -125,6 +189,14 @@ impl Color {
     pub(crate) fn new(r: u8, g: u8, b: u8) -> Self {
         Self(crossterm::style::Color::Rgb { r, g, b })
     }
+
+    /// Get the red, green, and blue components of this color.
+    pub(crate) fn as_rgb(&self) -> Option<(u8, u8, u8)> {
+        match self.0 {
+            crossterm::style::Color::Rgb { r, g, b } => Some((r, g, b)),
+            _ => None,
+        }
+    }
 }
 
 impl FromStr for Color {
@@ -177,10 +249,29 @@ pub(crate) struct ParseColorError(#[from] FromHexError);
 #[cfg(test)]
 mod test {
     use super::*;
+    use rstest::rstest;
 
     #[test]
     fn color_serde() {
         let color: Color = "beef42".parse().unwrap();
         assert_eq!(color.to_string(), "beef42");
     }
+
+    #[rstest]
+    #[case::unknown(None, None, true)]
+    #[case::xterm(Some("xterm-256color"), None, true)]
+    #[case::linux_console(Some("linux"), None, false)]
+    #[case::apple_terminal(Some("xterm-256color"), Some("Apple_Terminal"), false)]
+    fn strikethrough_support(#[case] term: Option<&str>, #[case] term_program: Option<&str>, #[case] expected: bool) {
+        assert_eq!(supports_strikethrough(term, term_program), expected);
+    }
+
+    #[rstest]
+    #[case::unknown(None, None, true)]
+    #[case::xterm(Some("xterm-256color"), None, true)]
+    #[case::dumb(Some("dumb"), None, false)]
+    #[case::apple_terminal(Some("xterm-256color"), Some("Apple_Terminal"), false)]
+    fn hyperlink_support(#[case] term: Option<&str>, #[case] term_program: Option<&str>, #[case] expected: bool) {
+        assert_eq!(supports_hyperlinks(term, term_program), expected);
+    }
 }
@@ -1,6 +1,9 @@
-use crate::style::Colors;
+use crate::{
+    presentation::ColumnLayout,
+    style::{Color, Colors},
+};
 use serde::{Deserialize, Serialize};
-use std::{fs, io, path::Path};
+use std::{collections::BTreeMap, fs, io, path::Path};
 
 include!(concat!(env!("OUT_DIR"), "/themes.rs"));
 
@@ -23,14 +26,38 @@ pub struct PresentationTheme {
     #[serde(default)]
     pub(crate) inline_code: InlineCodeStyle,
 
+    /// The style for paragraphs.
+    #[serde(default)]
+    pub(crate) paragraph: ParagraphStyle,
+
+    /// The style for strikethrough text.
+    #[serde(default)]
+    pub(crate) strikethrough: StrikethroughStyle,
+
     /// The style for a table.
     #[serde(default)]
-    pub(crate) table: Option<Alignment>,
+    pub(crate) table: TableStyle,
 
     /// The style for a block quote.
     #[serde(default)]
     pub(crate) block_quote: BlockQuoteStyle,
 
+    /// The style for lists.
+    #[serde(default)]
+    pub(crate) list: ListStyle,
+
+    /// The style for the checkboxes in task lists.
+    #[serde(default)]
+    pub(crate) task_list: TaskListStyle,
+
+    /// The style for code that's rendered into images via typst.
+    #[serde(default)]
+    pub(crate) typst: TypstStyle,
+
+    /// The style for mermaid diagrams that are rendered into images.
+    #[serde(default)]
+    pub(crate) mermaid: MermaidStyle,
+
     /// The default style.
     #[serde(rename = "default", default)]
     pub(crate) default_style: DefaultStyle,
@@ -43,9 +70,41 @@ pub struct PresentationTheme {
     #[serde(default)]
     pub(crate) intro_slide: IntroSlideStyle,
 
+    /// The style of the closing slide.
+    #[serde(default)]
+    pub(crate) closing_slide: ClosingSlideStyle,
+
     /// The style of the presentation footer.
     #[serde(default)]
     pub(crate) footer: FooterStyle,
+
+    /// The style of images.
+    #[serde(default)]
+    pub(crate) image: ImageStyle,
+
+    /// The layouts slides can use, by name.
+    #[serde(default)]
+    pub(crate) layouts: BTreeMap<String, SlideLayout>,
+
+    /// The style of column layouts.
+    #[serde(default)]
+    pub(crate) column_layout: ColumnLayoutStyle,
+
+    /// The style of countdown timers.
+    #[serde(default)]
+    pub(crate) timer: TimerStyle,
+
+    /// The style of big text.
+    #[serde(default)]
+    pub(crate) big_text: BigTextStyle,
+
+    /// The style of QR codes.
+    #[serde(default)]
+    pub(crate) qr: QrStyle,
+
+    /// The style of charts.
+    #[serde(default)]
+    pub(crate) chart: ChartStyle,
 }
 
 impl PresentationTheme {
@@ -84,13 +143,19 @@ impl PresentationTheme {
             Heading4 => &self.headings.h4.alignment,
             Heading5 => &self.headings.h5.alignment,
             Heading6 => &self.headings.h6.alignment,
-            Paragraph | List => &None,
+            Paragraph => &self.paragraph.alignment,
+            List => &None,
             Code => &self.code.alignment,
             PresentationTitle => &self.intro_slide.title.alignment,
             PresentationSubTitle => &self.intro_slide.subtitle.alignment,
             PresentationAuthor => &self.intro_slide.author.alignment,
-            Table => &self.table,
+            ClosingSlideMessage => &self.closing_slide.message.alignment,
+            ClosingSlideDetails => &self.closing_slide.details.alignment,
+            Table => &self.table.alignment,
             BlockQuote => &self.block_quote.alignment,
+            BigText => &self.big_text.alignment,
+            QrCode => &self.qr.alignment,
+            Chart => &self.chart.alignment,
         };
         alignment.clone().unwrap_or_default()
     }
@@ -166,6 +231,357 @@ pub(crate) struct HeadingStyle {
     pub(crate) colors: Colors,
 }
 
+/// The style of a table.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub(crate) struct TableStyle {
+    /// The alignment.
+    #[serde(flatten, default)]
+    pub(crate) alignment: Option<Alignment>,
+
+    /// The characters used to draw the table's borders.
+    #[serde(default)]
+    pub(crate) border: Option<TableBorder>,
+
+    /// The colors to be used for the table's borders.
+    #[serde(default)]
+    pub(crate) border_colors: Colors,
+
+    /// The style for the table's header.
+    #[serde(default)]
+    pub(crate) header: TableHeaderStyle,
+}
+
+impl TableStyle {
+    /// Get the characters used to draw the table's borders, which are sharp ones by default.
+    pub(crate) fn border(&self) -> TableBorder {
+        self.border.unwrap_or_default()
+    }
+}
+
+/// The style of a table's header.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub(crate) struct TableHeaderStyle {
+    /// The colors to be used.
+    #[serde(default)]
+    pub(crate) colors: Colors,
+}
+
+/// The characters used to draw a table's borders.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum TableBorder {
+    /// Straight lines with sharp corners, e.g. `┌─┬─┐`.
+    #[default]
+    Sharp,
+
+    /// Straight lines with rounded corners, e.g. `╭─┬─╮`.
+    Rounded,
+
+    /// Double lines, e.g. `╔═╦═╗`.
+    Double,
+
+    /// Plain ASCII characters, e.g. `+-+-+`.
+    Ascii,
+
+    /// Only the lines between columns and below the header.
+    Minimal,
+}
+
+/// The style of lists.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub(crate) struct ListStyle {
+    /// The bullets used in unordered lists, one for each nesting level.
+    ///
+    /// Items nested deeper than the number of bullets use the last one.
+    #[serde(default)]
+    pub(crate) bullets: Option<Vec<String>>,
+
+    /// The colors used for the bullets and numbers in lists, one for each nesting level.
+    ///
+    /// Items nested deeper than the number of colors use the last one.
+    #[serde(default)]
+    pub(crate) colors: Option<Vec<Colors>>,
+
+    /// The way ordered lists are numbered, one for each nesting level.
+    ///
+    /// Items nested deeper than the number of entries use the last one.
+    #[serde(default)]
+    pub(crate) numbering: Option<Vec<ListNumbering>>,
+
+    /// The suffix used after the number in ordered lists, e.g. `)`.
+    ///
+    /// If not set, the one used in the markdown is used.
+    #[serde(default)]
+    pub(crate) number_suffix: Option<String>,
+}
+
+impl ListStyle {
+    const DEFAULT_BULLETS: [&'static str; 3] = ["•", "◦", "▪"];
+
+    /// Get the bullet used for unordered list items at the given nesting level.
+    pub(crate) fn bullet(&self, depth: u8) -> &str {
+        match &self.bullets {
+            Some(bullets) if !bullets.is_empty() => &bullets[usize::from(depth).min(bullets.len() - 1)],
+            _ => Self::DEFAULT_BULLETS[usize::from(depth).min(Self::DEFAULT_BULLETS.len() - 1)],
+        }
+    }
+
+    /// Get the way ordered list items at the given nesting level are numbered.
+    pub(crate) fn numbering(&self, depth: u8) -> ListNumbering {
+        let numbering = self.numbering.as_deref().unwrap_or_default();
+        numbering.get(usize::from(depth)).or(numbering.last()).cloned().unwrap_or_default()
+    }
+
+    /// Get the colors used for the bullets and numbers of list items at the given nesting level.
+    pub(crate) fn colors(&self, depth: u8) -> Colors {
+        let colors = self.colors.as_deref().unwrap_or_default();
+        colors.get(usize::from(depth)).or(colors.last()).cloned().unwrap_or_default()
+    }
+}
+
+/// The way ordered lists are numbered.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ListNumbering {
+    /// `1`, `2`, `3`, etc.
+    #[default]
+    Arabic,
+
+    /// `i`, `ii`, `iii`, etc.
+    LowerRoman,
+
+    /// `I`, `II`, `III`, etc.
+    UpperRoman,
+
+    /// `a`, `b`, `c`, etc, followed by `aa`, `ab`, etc.
+    LowerAlpha,
+
+    /// `A`, `B`, `C`, etc, followed by `AA`, `AB`, etc.
+    UpperAlpha,
+}
+
+impl ListNumbering {
+    /// Format a number, starting at 1.
+    pub(crate) fn format(&self, number: usize) -> String {
+        match self {
+            Self::Arabic => number.to_string(),
+            Self::LowerRoman => Self::roman(number).to_lowercase(),
+            Self::UpperRoman => Self::roman(number),
+            Self::LowerAlpha => Self::alpha(number).to_lowercase(),
+            Self::UpperAlpha => Self::alpha(number),
+        }
+    }
+
+    fn roman(mut number: usize) -> String {
+        const SYMBOLS: [(usize, &str); 13] = [
+            (1000, "M"),
+            (900, "CM"),
+            (500, "D"),
+            (400, "CD"),
+            (100, "C"),
+            (90, "XC"),
+            (50, "L"),
+            (40, "XL"),
+            (10, "X"),
+            (9, "IX"),
+            (5, "V"),
+            (4, "IV"),
+            (1, "I"),
+        ];
+        let mut output = String::new();
+        for (value, symbol) in SYMBOLS {
+            while number >= value {
+                output.push_str(symbol);
+                number -= value;
+            }
+        }
+        output
+    }
+
+    fn alpha(mut number: usize) -> String {
+        let mut output = Vec::new();
+        while number > 0 {
+            number -= 1;
+            output.push(b'A' + (number % 26) as u8);
+            number /= 26;
+        }
+        output.iter().rev().map(|c| *c as char).collect()
+    }
+}
+
+/// The style of the checkboxes in task lists.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub(crate) struct TaskListStyle {
+    /// The style for items that are checked.
+    #[serde(default)]
+    pub(crate) checked: TaskListItemStyle,
+
+    /// The style for items that aren't checked.
+    #[serde(default)]
+    pub(crate) unchecked: TaskListItemStyle,
+}
+
+impl TaskListStyle {
+    /// Get the glyph used for items that are checked.
+    pub(crate) fn checked_glyph(&self) -> &str {
+        self.checked.glyph.as_deref().unwrap_or("☑")
+    }
+
+    /// Get the glyph used for items that aren't checked.
+    pub(crate) fn unchecked_glyph(&self) -> &str {
+        self.unchecked.glyph.as_deref().unwrap_or("☐")
+    }
+}
+
+/// The style of a task list item's checkbox.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub(crate) struct TaskListItemStyle {
+    /// The glyph used instead of the list item's bullet.
+    #[serde(default)]
+    pub(crate) glyph: Option<String>,
+
+    /// The colors to be used for the glyph.
+    #[serde(default)]
+    pub(crate) colors: Colors,
+}
+
+/// The style of code rendered into images via typst, which includes LaTeX formulas.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub(crate) struct TypstStyle {
+    /// The colors to be used.
+    ///
+    /// Any color not set here falls back to the ones in the default style.
+    #[serde(default)]
+    pub(crate) colors: Colors,
+}
+
+/// The style of mermaid diagrams rendered into images.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub(crate) struct MermaidStyle {
+    /// The mermaid theme to use, e.g. `dark` or `forest`.
+    #[serde(default)]
+    pub(crate) theme: Option<String>,
+
+    /// The background color to use, e.g. `transparent` or `#1e1e1e`.
+    #[serde(default)]
+    pub(crate) background: Option<String>,
+}
+
+/// A layout a slide can use via the `layout` comment command.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub(crate) struct SlideLayout {
+    /// The alignment for everything in the slide.
+    #[serde(flatten, default)]
+    pub(crate) alignment: Option<Alignment>,
+
+    /// Whether to place the slide's contents starting at the vertical center of the screen.
+    #[serde(default)]
+    pub(crate) jump_to_middle: Option<bool>,
+
+    /// The widths of the columns the slide is split into, if any.
+    #[serde(default)]
+    pub(crate) columns: Option<ColumnLayout>,
+
+    /// The names of the regions in the slide, one per column, which content can be placed in via the `region`
+    /// comment command.
+    #[serde(default)]
+    pub(crate) regions: Option<Vec<String>>,
+
+    /// The style of each of the slide's columns, which overrides the one in the theme's column layout style.
+    #[serde(default)]
+    pub(crate) column_styles: Option<Vec<ColumnStyle>>,
+}
+
+impl SlideLayout {
+    /// Whether to place the slide's contents starting at the vertical center of the screen, which it isn't by default.
+    pub(crate) fn jump_to_middle(&self) -> bool {
+        self.jump_to_middle.unwrap_or(false)
+    }
+}
+
+/// The style of column layouts.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub(crate) struct ColumnLayoutStyle {
+    /// The character used to draw a vertical line between columns.
+    ///
+    /// No line is drawn if this isn't set.
+    #[serde(default)]
+    pub(crate) separator: Option<String>,
+
+    /// The colors of the line between columns.
+    #[serde(default)]
+    pub(crate) separator_colors: Colors,
+
+    /// The style of each column, by index.
+    #[serde(default)]
+    pub(crate) columns: Option<Vec<ColumnStyle>>,
+}
+
+impl ColumnLayoutStyle {
+    /// Get the style for the column at the given index.
+    pub(crate) fn column(&self, index: usize) -> ColumnStyle {
+        self.columns.as_ref().and_then(|columns| columns.get(index)).cloned().unwrap_or_default()
+    }
+}
+
+/// The style of a single column in a column layout.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub(crate) struct ColumnStyle {
+    /// The background color that fills the column.
+    #[serde(default)]
+    pub(crate) background: Option<Color>,
+
+    /// The number of columns to leave between the column's edges and its contents.
+    #[serde(default)]
+    pub(crate) padding: Option<u8>,
+}
+
+/// The style of images.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub(crate) struct ImageStyle {
+    /// The style of the caption displayed below images.
+    #[serde(default)]
+    pub(crate) caption: ImageCaptionStyle,
+}
+
+/// The style of an image's caption.
+///
+/// Captions are built out of an image's alt text.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub(crate) struct ImageCaptionStyle {
+    /// Whether to display captions below images.
+    #[serde(default)]
+    pub(crate) enabled: Option<bool>,
+
+    /// Whether to display captions in italics.
+    #[serde(default)]
+    pub(crate) italics: Option<bool>,
+
+    /// The colors to be used.
+    #[serde(default)]
+    pub(crate) colors: Colors,
+}
+
+impl ImageCaptionStyle {
+    /// Whether to display captions below images, which they are by default.
+    pub(crate) fn enabled(&self) -> bool {
+        self.enabled.unwrap_or(true)
+    }
+
+    /// Whether to display captions in italics, which they aren't by default.
+    pub(crate) fn italics(&self) -> bool {
+        self.italics.unwrap_or(false)
+    }
+}
+
+/// The style of a paragraph.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub(crate) struct ParagraphStyle {
+    /// The alignment.
+    #[serde(flatten, default)]
+    pub(crate) alignment: Option<Alignment>,
+}
+
 /// The style of a block quote.
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
 pub(crate) struct BlockQuoteStyle {
@@ -179,7 +595,42 @@ pub(crate) struct BlockQuoteStyle {
     #[serde(default)]
     pub(crate) prefix: Option<String>,
 
+    /// The colors to be used for the prefix.
+    ///
+    /// Anything not set here falls back to the block quote's colors.
+    #[serde(default)]
+    pub(crate) prefix_colors: Colors,
+
+    /// Whether to display the quoted text in italics.
+    #[serde(default)]
+    pub(crate) italics: Option<bool>,
+
+    /// The colors to be used.
+    #[serde(default)]
+    pub(crate) colors: Colors,
+
+    /// The style of the line the quote is attributed in.
+    #[serde(default)]
+    pub(crate) attribution: BlockQuoteAttributionStyle,
+}
+
+impl BlockQuoteStyle {
+    /// Whether to display the quoted text in italics, which it isn't by default.
+    pub(crate) fn italics(&self) -> bool {
+        self.italics.unwrap_or(false)
+    }
+}
+
+/// The style of the attribution in a block quote.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub(crate) struct BlockQuoteAttributionStyle {
+    /// The prefix to be added before the author, `— ` by default.
+    #[serde(default)]
+    pub(crate) prefix: Option<String>,
+
     /// The colors to be used.
+    ///
+    /// Anything not set here falls back to the block quote's colors.
     #[serde(default)]
     pub(crate) colors: Colors,
 }
@@ -207,9 +658,61 @@ pub(crate) struct DefaultStyle {
     #[serde(default, with = "serde_yaml::with::singleton_map")]
     pub(crate) margin: Option<Margin>,
 
+    /// The maximum width of a slide's contents.
+    ///
+    /// The contents are centered on the screen if the margin leaves more than this.
+    #[serde(default, with = "serde_yaml::with::singleton_map")]
+    pub(crate) max_width: Option<Margin>,
+
+    /// The number of rows to leave at the top of every slide.
+    #[serde(default)]
+    pub(crate) padding_top: Option<u8>,
+
+    /// The number of rows to leave between the slide's contents and the bottom of the screen.
+    #[serde(default)]
+    pub(crate) padding_bottom: Option<u8>,
+
     /// The colors to be used.
     #[serde(default)]
     pub(crate) colors: Colors,
+
+    /// How slide contents are aligned vertically.
+    #[serde(default)]
+    pub(crate) vertical_alignment: Option<VerticalAlignment>,
+}
+
+impl DefaultStyle {
+    /// Get how slide contents are aligned vertically, which is at the top by default.
+    pub(crate) fn vertical_alignment(&self) -> VerticalAlignment {
+        self.vertical_alignment.unwrap_or_default()
+    }
+}
+
+/// Vertical alignment for a slide's contents.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum VerticalAlignment {
+    /// Place the contents at the top of the slide.
+    #[default]
+    Top,
+
+    /// Place the contents in the vertical center of the slide.
+    Center,
+
+    /// Place the contents at the bottom of the slide.
+    Bottom,
+}
+
+/// The style for the closing slide.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub(crate) struct ClosingSlideStyle {
+    /// The style of the message line.
+    #[serde(default)]
+    pub(crate) message: BasicStyle,
+
+    /// The style of the author, handle, and URL lines.
+    #[serde(default)]
+    pub(crate) details: BasicStyle,
 }
 
 /// A simple style.
@@ -254,6 +757,13 @@ pub(crate) enum Alignment {
         #[serde(default)]
         minimum_size: u16,
     },
+
+    /// Left alignment where every wrapped line except the last one is stretched to take up the entire width.
+    Justify {
+        /// The margin before and after any text.
+        #[serde(default)]
+        margin: Margin,
+    },
 }
 
 impl Default for Alignment {
@@ -285,13 +795,13 @@ pub(crate) enum FooterStyle {
     /// Use a template to generate the footer.
     Template {
         /// The template for the text to be put on the left.
-        left: Option<String>,
+        left: Option<FooterTemplate>,
 
         /// The template for the text to be put on the center.
-        center: Option<String>,
+        center: Option<FooterTemplate>,
 
         /// The template for the text to be put on the right.
-        right: Option<String>,
+        right: Option<FooterTemplate>,
 
         /// The colors to be used.
         #[serde(default)]
@@ -303,19 +813,105 @@ pub(crate) enum FooterStyle {
         /// The character that will be used for the progress bar.
         character: Option<char>,
 
+        /// The character that will be used for the part of the bar that hasn't been reached yet.
+        remaining_character: Option<char>,
+
+        /// The character used to mark where every section starts in the bar.
+        section_tick: Option<char>,
+
+        /// The number of rows the progress bar takes.
+        thickness: Option<u16>,
+
+        /// Where the progress bar is placed.
+        #[serde(default)]
+        position: ProgressBarPosition,
+
         /// The colors to be used.
         #[serde(default)]
         colors: Colors,
+
+        /// The colors to be used for the part of the bar that hasn't been reached yet.
+        #[serde(default)]
+        remaining_colors: Colors,
     },
 
     /// No footer.
     Empty,
 }
 
+impl FooterStyle {
+    /// The number of rows the footer takes at the top of the screen.
+    pub(crate) fn top_rows(&self) -> u16 {
+        match self {
+            Self::ProgressBar { position: ProgressBarPosition::Top, thickness, .. } => thickness.unwrap_or(1),
+            _ => 0,
+        }
+    }
+
+    /// The number of rows the footer takes at the bottom of the screen.
+    pub(crate) fn bottom_rows(&self) -> u16 {
+        match self {
+            Self::ProgressBar { position: ProgressBarPosition::Bottom, thickness, .. } => thickness.unwrap_or(1),
+            Self::ProgressBar { .. } | Self::Empty => 0,
+            Self::Template { .. } => 1,
+        }
+    }
+}
+
+/// Where a progress bar is placed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ProgressBarPosition {
+    /// At the top of the screen.
+    Top,
+
+    /// At the bottom of the screen.
+    #[default]
+    Bottom,
+}
+
+/// A template for one of the segments in a footer.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+pub(crate) enum FooterTemplate {
+    /// A template that uses the footer's colors.
+    Plain(String),
+
+    /// A template that has its own colors.
+    Styled {
+        /// The template itself.
+        template: String,
+
+        /// The colors to be used for this segment, on top of the footer's colors.
+        #[serde(default)]
+        colors: Colors,
+    },
+}
+
+impl FooterTemplate {
+    /// Get the template text.
+    pub(crate) fn template(&self) -> &str {
+        match self {
+            Self::Plain(template) | Self::Styled { template, .. } => template,
+        }
+    }
+
+    /// Get the colors for this segment, falling back to the given footer colors.
+    pub(crate) fn colors(&self, footer_colors: &Colors) -> Colors {
+        match self {
+            Self::Plain(_) => footer_colors.clone(),
+            Self::Styled { colors, .. } => Colors {
+                foreground: colors.foreground.or(footer_colors.foreground),
+                background: colors.background.or(footer_colors.background),
+            },
+        }
+    }
+}
+
 impl Default for FooterStyle {
     fn default() -> Self {
         Self::Template {
-            left: Some("{current_slide} / {total_slides}".to_string()),
+            left: Some(FooterTemplate::Plain("{current_slide} / {total_slides}".to_string())),
             center: None,
             right: None,
             colors: Colors::default(),
@@ -337,6 +933,95 @@ pub(crate) struct CodeBlockStyle {
     /// The syntect theme name to use.
     #[serde(default)]
     pub(crate) theme_name: Option<String>,
+
+    /// The background color to use instead of the syntect theme's.
+    #[serde(default)]
+    pub(crate) background_color: Option<Color>,
+
+    /// What to do with lines that are too long to fit.
+    #[serde(default)]
+    pub(crate) overflow: Option<OutputOverflow>,
+
+    /// How lines are displayed when only some of them are highlighted.
+    #[serde(default)]
+    pub(crate) highlight_mode: Option<LineHighlightMode>,
+
+    /// The background colors for lines that were added or removed in code that's displayed as a diff.
+    #[serde(default)]
+    pub(crate) diff_colors: DiffColors,
+
+    /// The style for the bar above code blocks that have a title.
+    #[serde(default)]
+    pub(crate) title: CodeTitleStyle,
+}
+
+impl CodeBlockStyle {
+    /// Get what to do with lines that are too long to fit, which is wrapping them by default.
+    pub(crate) fn overflow(&self) -> OutputOverflow {
+        self.overflow.clone().unwrap_or_default()
+    }
+
+    /// Get how lines are displayed when only some of them are highlighted, which is emphasizing them by default.
+    pub(crate) fn highlight_mode(&self) -> LineHighlightMode {
+        self.highlight_mode.unwrap_or_default()
+    }
+}
+
+/// The style for a code block's title.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub(crate) struct CodeTitleStyle {
+    /// The colors to be used.
+    ///
+    /// Any color not set here falls back to the ones used for the code itself.
+    #[serde(default)]
+    pub(crate) colors: Colors,
+
+    /// Whether code loaded from a file uses the file's path as its title unless it has one.
+    #[serde(default)]
+    pub(crate) file_names: Option<bool>,
+}
+
+impl CodeTitleStyle {
+    /// Whether code loaded from a file uses the file's path as its title, which is the default.
+    pub(crate) fn file_names(&self) -> bool {
+        self.file_names.unwrap_or(true)
+    }
+}
+
+/// The background colors for lines that were added or removed in a diff.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub(crate) struct DiffColors {
+    /// The background color for added lines.
+    #[serde(default)]
+    pub(crate) added: Option<Color>,
+
+    /// The background color for removed lines.
+    #[serde(default)]
+    pub(crate) removed: Option<Color>,
+}
+
+impl DiffColors {
+    /// Get the background color for added lines, falling back to a dark green.
+    pub(crate) fn added(&self) -> Color {
+        self.added.unwrap_or(Color::new(0x1f, 0x44, 0x1f))
+    }
+
+    /// Get the background color for removed lines, falling back to a dark red.
+    pub(crate) fn removed(&self) -> Color {
+        self.removed.unwrap_or(Color::new(0x4f, 0x1f, 0x1f))
+    }
+}
+
+/// How lines are displayed when only some of them in a piece of code are highlighted.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum LineHighlightMode {
+    /// Emphasize the highlighted lines and leave the rest untouched.
+    #[default]
+    Emphasize,
+
+    /// Emphasize the highlighted lines and dim the rest.
+    Dim,
 }
 
 /// The style for the output of a code execution block.
@@ -345,6 +1030,49 @@ pub(crate) struct ExecutionOutputBlockStyle {
     /// The colors to be used.
     #[serde(default)]
     pub(crate) colors: Colors,
+
+    /// The colors to be used for anything the process writes to stderr.
+    ///
+    /// Any color not set here falls back to the ones in `colors`.
+    #[serde(default)]
+    pub(crate) stderr_colors: Colors,
+
+    /// The maximum number of output lines to display at once.
+    ///
+    /// Any output that doesn't fit can be scrolled through.
+    #[serde(default)]
+    pub(crate) max_height: Option<u16>,
+
+    /// What to do with output lines that are too long to fit.
+    #[serde(default)]
+    pub(crate) overflow: Option<OutputOverflow>,
+}
+
+impl ExecutionOutputBlockStyle {
+    /// Get the colors for anything written to stderr, falling back to the ones in `colors`.
+    pub(crate) fn stderr_colors(&self) -> Colors {
+        Colors {
+            foreground: self.stderr_colors.foreground.or(self.colors.foreground),
+            background: self.stderr_colors.background.or(self.colors.background),
+        }
+    }
+
+    /// Get what to do with output lines that are too long to fit, which is wrapping them by default.
+    pub(crate) fn overflow(&self) -> OutputOverflow {
+        self.overflow.clone().unwrap_or_default()
+    }
+}
+
+/// What to do with lines that are too long to fit.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum OutputOverflow {
+    /// Wrap them into as many lines as needed.
+    #[default]
+    Wrap,
+
+    /// Cut them off, ending them with an ellipsis.
+    Truncate,
 }
 
 /// The style for inline code.
@@ -353,6 +1081,78 @@ pub(crate) struct InlineCodeStyle {
     /// The colors to be used.
     #[serde(default)]
     pub(crate) colors: Colors,
+
+    /// The characters to add before and after inline code, e.g. a space so its background extends past the text.
+    #[serde(default)]
+    pub(crate) padding: Option<String>,
+}
+
+/// The style for strikethrough text.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub(crate) struct StrikethroughStyle {
+    /// The colors to be used.
+    ///
+    /// These make strikethrough text stand out even in terminals that can't cross it out.
+    #[serde(default)]
+    pub(crate) colors: Colors,
+}
+
+/// The style for countdown timers.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub(crate) struct TimerStyle {
+    /// The colors to be used while the timer is counting down.
+    #[serde(default)]
+    pub(crate) colors: Colors,
+
+    /// The colors to be used once the timer is done.
+    #[serde(default)]
+    pub(crate) finished_colors: Colors,
+}
+
+/// The style for text drawn using large glyphs.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub(crate) struct BigTextStyle {
+    /// The alignment.
+    #[serde(flatten, default)]
+    pub(crate) alignment: Option<Alignment>,
+
+    /// The colors to be used.
+    #[serde(default)]
+    pub(crate) colors: Colors,
+}
+
+/// The style for QR codes.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub(crate) struct QrStyle {
+    /// The alignment.
+    #[serde(flatten, default)]
+    pub(crate) alignment: Option<Alignment>,
+
+    /// The colors to be used, where the foreground is used for dark modules and the background for light ones.
+    ///
+    /// These default to black and white since not every scanner can read codes with inverted colors.
+    #[serde(default)]
+    pub(crate) colors: Colors,
+}
+
+/// The style for charts.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub(crate) struct ChartStyle {
+    /// The alignment.
+    #[serde(flatten, default)]
+    pub(crate) alignment: Option<Alignment>,
+
+    /// The colors to be used for bars and lines.
+    #[serde(default)]
+    pub(crate) colors: Colors,
+
+    /// The colors to be used for the title, labels, and axes.
+    #[serde(default)]
+    pub(crate) axis_colors: Colors,
+
+    /// The number of rows line charts use, not counting the x axis.
+    #[serde(default)]
+    pub(crate) height: Option<u16>,
 }
 
 /// Vertical/horizontal padding.
@@ -413,8 +1213,13 @@ pub(crate) enum ElementType {
     PresentationTitle,
     PresentationSubTitle,
     PresentationAuthor,
+    ClosingSlideMessage,
+    ClosingSlideDetails,
     Table,
     BlockQuote,
+    BigText,
+    QrCode,
+    Chart,
 }
 
 /// Where to position the author's name in the intro slide.
@@ -442,6 +1247,7 @@ pub enum LoadThemeError {
 #[cfg(test)]
 mod test {
     use super::*;
+    use rstest::rstest;
 
     #[test]
     fn validate_themes() {
@@ -454,4 +1260,84 @@ mod test {
             assert!(merged.is_ok(), "theme '{theme_name}' can't be merged: {}", merged.unwrap_err());
         }
     }
+
+    #[test]
+    fn overrides_keep_unrelated_settings() {
+        let theme: PresentationTheme = serde_yaml::from_str(
+            r#"
+table:
+  border: rounded
+code:
+  highlight_mode: dim
+  overflow: truncate
+execution_output:
+  overflow: truncate
+block_quote:
+  italics: true
+image:
+  caption:
+    enabled: false
+layouts:
+  title:
+    jump_to_middle: true
+default:
+  vertical_alignment: center
+column_layout:
+  columns:
+    - padding: 2
+"#,
+        )
+        .expect("invalid theme");
+        let overrides: PresentationTheme =
+            serde_yaml::from_str("default:\n  colors:\n    foreground: ff0000").expect("invalid overrides");
+        let theme = merge_struct::merge(&theme, &overrides).expect("merge failed");
+        assert_eq!(theme.table.border(), TableBorder::Rounded);
+        assert_eq!(theme.code.highlight_mode(), LineHighlightMode::Dim);
+        assert_eq!(theme.code.overflow(), OutputOverflow::Truncate);
+        assert_eq!(theme.execution_output.overflow(), OutputOverflow::Truncate);
+        assert!(theme.block_quote.italics());
+        assert!(!theme.image.caption.enabled());
+        assert!(theme.layouts["title"].jump_to_middle());
+        assert_eq!(theme.default_style.vertical_alignment(), VerticalAlignment::Center);
+        assert_eq!(theme.column_layout.column(0).padding, Some(2));
+    }
+
+    #[test]
+    fn list_style_per_depth() {
+        let style: ListStyle = serde_yaml::from_str(
+            r#"
+bullets: ["-", "+"]
+colors:
+  - foreground: "ff0000"
+"#,
+        )
+        .expect("invalid style");
+        let bullets: Vec<_> = (0..4).map(|depth| style.bullet(depth)).collect();
+        assert_eq!(bullets, &["-", "+", "+", "+"]);
+        assert_eq!(style.colors(3).foreground, Some(Color::new(255, 0, 0)));
+
+        let style = ListStyle::default();
+        let bullets: Vec<_> = (0..4).map(|depth| style.bullet(depth)).collect();
+        assert_eq!(bullets, &["•", "◦", "▪", "▪"]);
+        assert_eq!(style.colors(0), Colors::default());
+    }
+
+    #[test]
+    fn paragraph_alignment() {
+        let theme: PresentationTheme =
+            serde_yaml::from_str("paragraph:\n  alignment: justify\n  margin:\n    fixed: 2").expect("invalid theme");
+        let alignment = theme.alignment(&ElementType::Paragraph);
+        assert!(matches!(alignment, Alignment::Justify { margin: Margin::Fixed(2) }), "unexpected {alignment:?}");
+    }
+
+    #[rstest]
+    #[case::arabic(ListNumbering::Arabic, 12, "12")]
+    #[case::lower_roman(ListNumbering::LowerRoman, 14, "xiv")]
+    #[case::upper_roman(ListNumbering::UpperRoman, 1994, "MCMXCIV")]
+    #[case::lower_alpha(ListNumbering::LowerAlpha, 3, "c")]
+    #[case::upper_alpha_wraps(ListNumbering::UpperAlpha, 28, "AB")]
+    #[case::upper_alpha_last(ListNumbering::UpperAlpha, 26, "Z")]
+    fn list_numbering(#[case] numbering: ListNumbering, #[case] number: usize, #[case] expected: &str) {
+        assert_eq!(numbering.format(number), expected);
+    }
 }
@@ -0,0 +1,407 @@
+//! Rendering of code into images by using third party tools.
+
+use crate::{
+    config::{LatexConfig, MermaidConfig, TypstConfig},
+    style::{Color, Colors},
+    theme::MermaidStyle,
+};
+use std::{
+    env, fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process::{self, Command, Stdio},
+};
+
+/// The pixels per inch used when rendering typst code if none is configured.
+const DEFAULT_TYPST_PPI: u32 = 300;
+
+/// The scale used when rendering mermaid diagrams if none is configured.
+const DEFAULT_MERMAID_SCALE: u32 = 2;
+
+/// Renders code into images by using third party tools.
+///
+/// Rendered images are cached in a directory by a hash of everything used to render them so each piece of code is
+/// only rendered once, even across runs.
+#[derive(Clone, Debug)]
+pub struct ThirdPartyRender {
+    typst: TypstConfig,
+    latex: LatexConfig,
+    mermaid: MermaidConfig,
+    cache_directory: PathBuf,
+}
+
+impl Default for ThirdPartyRender {
+    fn default() -> Self {
+        Self::new(Default::default(), Default::default(), Default::default())
+    }
+}
+
+impl ThirdPartyRender {
+    /// Construct a new render that caches images in the user's cache directory.
+    pub fn new(typst: TypstConfig, latex: LatexConfig, mermaid: MermaidConfig) -> Self {
+        Self { typst, latex, mermaid, cache_directory: Self::default_cache_directory() }
+    }
+
+    /// Get the default cache directory, following the XDG base directory spec.
+    fn default_cache_directory() -> PathBuf {
+        let base = match env::var_os("XDG_CACHE_HOME") {
+            Some(path) if !path.is_empty() => PathBuf::from(path),
+            _ => match env::var_os("HOME") {
+                Some(home) if !home.is_empty() => PathBuf::from(home).join(".cache"),
+                _ => env::temp_dir(),
+            },
+        };
+        base.join("presenterm")
+    }
+
+    /// Use the given directory to cache rendered images.
+    pub fn with_cache_directory<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.cache_directory = path.into();
+        self
+    }
+
+    /// Render a piece of code into an image, returning the path to it.
+    pub(crate) fn render(&self, request: RenderRequest) -> Result<PathBuf, ThirdPartyRenderError> {
+        match request {
+            RenderRequest::Typst { input, colors } => self.render_typst(input, colors),
+            RenderRequest::Latex { input, colors } => self.render_latex(input, colors),
+            RenderRequest::Mermaid { input, style } => self.render_mermaid(input, style),
+            RenderRequest::Graphviz { input } => {
+                self.render_command(input, "dot", &["dot", "-Tpng", "-o", "$OUT", "$FILE"])
+            }
+            RenderRequest::D2 { input } => self.render_command(input, "d2", &["d2", "$FILE", "$OUT"]),
+        }
+    }
+
+    fn render_typst(&self, input: &str, colors: &Colors) -> Result<PathBuf, ThirdPartyRenderError> {
+        let document = Self::typst_document(input, colors);
+        let ppi = self.typst.ppi.unwrap_or(DEFAULT_TYPST_PPI).to_string();
+        self.render_cached(&[&document, &ppi], "typ", |input_path, output_path| {
+            let command = ["typst", "compile", "--format", "png", "--ppi", &ppi];
+            let mut arguments: Vec<_> = command.iter().map(|argument| argument.to_string()).collect();
+            arguments.extend([input_path.display().to_string(), output_path.display().to_string()]);
+            Self::run(&arguments, None).map(|_| ())
+        })
+    }
+
+    fn render_latex(&self, input: &str, colors: &Colors) -> Result<PathBuf, ThirdPartyRenderError> {
+        let Some(command) = &self.latex.command else {
+            // Let pandoc turn it into typst so it looks the same as any typst code.
+            let arguments = ["pandoc", "--from", "latex", "--to", "typst"].map(String::from);
+            let output = Self::run(&arguments, Some(&format!("$$\n{input}\n$$")))?;
+            let typst = String::from_utf8_lossy(&output);
+            return self.render_typst(&typst, colors);
+        };
+        self.render_command(input, "tex", command)
+    }
+
+    /// Render by running a command where `$FILE` is replaced with the input path and `$OUT` with the output path.
+    fn render_command<S: AsRef<str>>(
+        &self,
+        input: &str,
+        extension: &str,
+        command: &[S],
+    ) -> Result<PathBuf, ThirdPartyRenderError> {
+        let key = command.iter().map(AsRef::as_ref).collect::<Vec<_>>().join(" ");
+        self.render_cached(&[input, &key], extension, |input_path, output_path| {
+            let arguments: Vec<_> = command
+                .iter()
+                .map(|argument| {
+                    argument
+                        .as_ref()
+                        .replace("$FILE", &input_path.to_string_lossy())
+                        .replace("$OUT", &output_path.to_string_lossy())
+                })
+                .collect();
+            Self::run(&arguments, None).map(|_| ())
+        })
+    }
+
+    fn render_mermaid(&self, input: &str, style: &MermaidStyle) -> Result<PathBuf, ThirdPartyRenderError> {
+        let command = self.mermaid.command.as_deref().unwrap_or("mmdc");
+        let scale = self.mermaid.scale.unwrap_or(DEFAULT_MERMAID_SCALE).to_string();
+        let theme = style.theme.as_deref().unwrap_or("default");
+        let background = style.background.as_deref().unwrap_or("transparent");
+        self.render_cached(&[input, command, &scale, theme, background], "mmd", |input_path, output_path| {
+            let arguments = [
+                command,
+                "-i",
+                &input_path.to_string_lossy(),
+                "-o",
+                &output_path.to_string_lossy(),
+                "-s",
+                &scale,
+                "-t",
+                theme,
+                "-b",
+                background,
+            ]
+            .map(String::from);
+            Self::run(&arguments, None).map(|_| ())
+        })
+    }
+
+    /// Render something unless it was already rendered before.
+    ///
+    /// `key` contains everything that affects how the image looks. The first entry in it is written to a file with the
+    /// given extension and `render` is called with its path and the path the image must be written to.
+    fn render_cached<F>(&self, key: &[&str], extension: &str, render: F) -> Result<PathBuf, ThirdPartyRenderError>
+    where
+        F: FnOnce(&Path, &Path) -> Result<(), ThirdPartyRenderError>,
+    {
+        let name = format!("{:016x}", Self::hash_key(key));
+        self.create_cache_directory()?;
+        let output_path = self.cache_directory.join(format!("{name}.png"));
+        if output_path.exists() {
+            return Ok(output_path);
+        }
+        let input_path = self.cache_directory.join(format!("{name}.{extension}"));
+        fs::write(&input_path, key.first().unwrap_or(&""))?;
+        // Render somewhere else first so a render that fails halfway doesn't leave a broken image in the cache.
+        let partial_path = self.cache_directory.join(format!("{name}.{}.partial.png", process::id()));
+        let result = render(&input_path, &partial_path).and_then(|_| match partial_path.exists() {
+            true => Ok(fs::rename(&partial_path, &output_path)?),
+            false => Err(ThirdPartyRenderError::NoImage(output_path.clone())),
+        });
+        if result.is_err() {
+            let _ = fs::remove_file(&partial_path);
+        }
+        result.map(|_| output_path)
+    }
+
+    /// Hash the key for an image using FNV-1a which, unlike the standard library's hasher, always yields the same hash
+    /// so images rendered in a previous run can be found.
+    fn hash_key(key: &[&str]) -> u64 {
+        let mut hash: u64 = 0xcbf29ce484222325;
+        // Every part is terminated by a byte that can't show up in a string so `["ab", "c"]` and `["a", "bc"]` differ.
+        for byte in key.iter().flat_map(|part| part.bytes().chain([0xff])) {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+        hash
+    }
+
+    /// Create the cache directory, making sure nobody else can tamper with the images in it.
+    fn create_cache_directory(&self) -> Result<(), ThirdPartyRenderError> {
+        let mut builder = fs::DirBuilder::new();
+        builder.recursive(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+
+            builder.mode(0o700).create(&self.cache_directory)?;
+            // The directory may have existed before so check it's ours and only we can write to it.
+            let metadata = fs::metadata(&self.cache_directory)?;
+            let owned = metadata.uid() == unsafe { libc::getuid() };
+            if !owned || metadata.mode() & 0o022 != 0 {
+                return Err(ThirdPartyRenderError::InsecureCacheDirectory(self.cache_directory.clone()));
+            }
+        }
+        #[cfg(not(unix))]
+        builder.create(&self.cache_directory)?;
+        Ok(())
+    }
+
+    fn typst_document(input: &str, colors: &Colors) -> String {
+        let color = |color: Option<Color>| match color.and_then(|color| color.as_rgb()) {
+            Some((r, g, b)) => format!("rgb({r}, {g}, {b})"),
+            None => "none".into(),
+        };
+        let fill = color(colors.background);
+        let mut document = format!("#set page(width: auto, height: auto, margin: 0.5em, fill: {fill})\n");
+        if colors.foreground.is_some() {
+            document.push_str(&format!("#set text(fill: {})\n", color(colors.foreground)));
+        }
+        document.push_str(input);
+        document
+    }
+
+    fn run(arguments: &[String], stdin: Option<&str>) -> Result<Vec<u8>, ThirdPartyRenderError> {
+        let (program, arguments) = arguments.split_first().ok_or(ThirdPartyRenderError::EmptyCommand)?;
+        let mut child = Command::new(program)
+            .args(arguments)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| ThirdPartyRenderError::Spawn(program.clone(), e))?;
+        if let Some(input) = stdin {
+            child.stdin.take().expect("no stdin").write_all(input.as_bytes())?;
+        }
+        drop(child.stdin.take());
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            let error = String::from_utf8_lossy(&output.stderr).trim().to_string();
+            return Err(ThirdPartyRenderError::CommandFailed(program.clone(), error));
+        }
+        Ok(output.stdout)
+    }
+}
+
+/// A request to render a piece of code into an image.
+pub(crate) enum RenderRequest<'a> {
+    /// Render typst code using the given colors.
+    Typst { input: &'a str, colors: &'a Colors },
+
+    /// Render a LaTeX formula using the given colors.
+    Latex { input: &'a str, colors: &'a Colors },
+
+    /// Render a mermaid diagram using the given style.
+    Mermaid { input: &'a str, style: &'a MermaidStyle },
+
+    /// Render a graphviz diagram.
+    Graphviz { input: &'a str },
+
+    /// Render a d2 diagram.
+    D2 { input: &'a str },
+}
+
+/// An error when rendering code into an image.
+#[derive(thiserror::Error, Debug)]
+pub enum ThirdPartyRenderError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+
+    #[error("failed to run '{0}' (is it installed?): {1}")]
+    Spawn(String, io::Error),
+
+    #[error("'{0}' failed: {1}")]
+    CommandFailed(String, String),
+
+    #[error("render command is empty")]
+    EmptyCommand,
+
+    #[error("no image was written to {0}")]
+    NoImage(PathBuf),
+
+    #[error("cache directory {0} can be written to by other users")]
+    InsecureCacheDirectory(PathBuf),
+}
+
+impl ThirdPartyRenderError {
+    /// Whether this error was caused by the tool used to render not being installed.
+    pub(crate) fn is_missing_tool(&self) -> bool {
+        matches!(self, Self::Spawn(_, e) if e.kind() == io::ErrorKind::NotFound)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn typst_document() {
+        let colors = Colors { background: Some(Color::new(1, 2, 3)), foreground: Some(Color::new(4, 5, 6)) };
+        let document = ThirdPartyRender::typst_document("$ x^2 $", &colors);
+        let expected = "#set page(width: auto, height: auto, margin: 0.5em, fill: rgb(1, 2, 3))\n\
+                        #set text(fill: rgb(4, 5, 6))\n\
+                        $ x^2 $";
+        assert_eq!(document, expected);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn latex_command() {
+        let directory = tempfile::tempdir().expect("no temp dir");
+        let image_path = directory.path().join("source.png");
+        image::RgbaImage::new(2, 2).save(&image_path).expect("failed to write image");
+        let command = vec!["cp".into(), image_path.display().to_string(), "$OUT".into()];
+        let latex = LatexConfig { command: Some(command) };
+        let render = ThirdPartyRender::new(Default::default(), latex, Default::default())
+            .with_cache_directory(directory.path().join("cache"));
+
+        let colors = Colors::default();
+        let request = || RenderRequest::Latex { input: "x^2", colors: &colors };
+        let path = render.render(request()).expect("render failed");
+        assert!(path.starts_with(directory.path().join("cache")));
+        assert!(path.exists());
+
+        // The second time around the cached image is used so the command isn't run.
+        fs::remove_file(&image_path).expect("failed to remove image");
+        assert_eq!(render.render(request()).expect("render failed"), path);
+    }
+
+    #[test]
+    fn hash_key() {
+        assert_eq!(ThirdPartyRender::hash_key(&["x^2", "pandoc"]), 0xd93a680b2b699308);
+        assert_ne!(ThirdPartyRender::hash_key(&["ab", "c"]), ThirdPartyRender::hash_key(&["a", "bc"]));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn partial_render_not_cached() {
+        let directory = tempfile::tempdir().expect("no temp dir");
+        let command = ["sh", "-c", "printf partial > '$OUT'; exit 1"].map(String::from).to_vec();
+        let latex = LatexConfig { command: Some(command) };
+        let render =
+            ThirdPartyRender::new(Default::default(), latex, Default::default()).with_cache_directory(directory.path());
+        let result = render.render(RenderRequest::Latex { input: "x^2", colors: &Colors::default() });
+        assert!(matches!(result, Err(ThirdPartyRenderError::CommandFailed(..))));
+
+        let images: Vec<_> = fs::read_dir(directory.path())
+            .expect("failed to list cache")
+            .map(|entry| entry.expect("failed to read entry").path())
+            .filter(|path| path.extension().is_some_and(|extension| extension == "png"))
+            .collect();
+        assert!(images.is_empty(), "images left behind: {images:?}");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn failed_command() {
+        let directory = tempfile::tempdir().expect("no temp dir");
+        let latex = LatexConfig { command: Some(vec!["false".into()]) };
+        let render =
+            ThirdPartyRender::new(Default::default(), latex, Default::default()).with_cache_directory(directory.path());
+        let result = render.render(RenderRequest::Latex { input: "x^2", colors: &Colors::default() });
+        assert!(matches!(result, Err(ThirdPartyRenderError::CommandFailed(..))));
+    }
+
+    #[test]
+    fn missing_tool() {
+        let directory = tempfile::tempdir().expect("no temp dir");
+        let latex = LatexConfig { command: Some(vec!["presenterm-surely-not-installed".into()]) };
+        let render =
+            ThirdPartyRender::new(Default::default(), latex, Default::default()).with_cache_directory(directory.path());
+        let error = render
+            .render(RenderRequest::Latex { input: "x^2", colors: &Colors::default() })
+            .expect_err("render succeeded");
+        assert!(error.is_missing_tool());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn insecure_cache_directory() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let directory = tempfile::tempdir().expect("no temp dir");
+        let cache_directory = directory.path().join("cache");
+        fs::create_dir(&cache_directory).expect("failed to create directory");
+        fs::set_permissions(&cache_directory, fs::Permissions::from_mode(0o777)).expect("failed to set permissions");
+
+        let latex = LatexConfig { command: Some(vec!["true".into()]) };
+        let render =
+            ThirdPartyRender::new(Default::default(), latex, Default::default()).with_cache_directory(&cache_directory);
+        let result = render.render(RenderRequest::Latex { input: "x^2", colors: &Colors::default() });
+        assert!(matches!(result, Err(ThirdPartyRenderError::InsecureCacheDirectory(_))));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn mermaid_command() {
+        let directory = tempfile::tempdir().expect("no temp dir");
+        // Write a script that behaves like mmdc by writing an image to the path after `-o`.
+        let script_path = directory.path().join("mmdc");
+        fs::write(&script_path, "#!/bin/sh\nwhile [ \"$1\" != \"-o\" ]; do shift; done\nprintf png > \"$2\"\n")
+            .expect("failed to write script");
+        Command::new("chmod").arg("+x").arg(&script_path).status().expect("chmod failed");
+
+        let mermaid = MermaidConfig { command: Some(script_path.display().to_string()), scale: None };
+        let render = ThirdPartyRender::new(Default::default(), Default::default(), mermaid)
+            .with_cache_directory(directory.path().join("cache"));
+        let style = MermaidStyle::default();
+        let path =
+            render.render(RenderRequest::Mermaid { input: "graph TD; A-->B", style: &style }).expect("render failed");
+        assert_eq!(fs::read_to_string(path).expect("no image"), "png");
+    }
+}
@@ -0,0 +1,333 @@
+use crate::{
+    input::source::WidgetInput,
+    markdown::{
+        elements::StyledText,
+        text::{TextWidth, WeightedText},
+    },
+    presentation::{AsRenderOperations, RenderOnDemand, RenderOnDemandState, RenderOperation},
+    render::{figlet::big_text, properties::WindowSize},
+    style::{Colors, TextStyle},
+    theme::{Alignment, Margin, TimerStyle},
+};
+use std::{
+    cell::RefCell,
+    time::{Duration, Instant},
+};
+
+/// How long the timer flashes for once it's done.
+const FLASH_DURATION: Duration = Duration::from_secs(3);
+
+/// How long every step in the flash lasts.
+const FLASH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// A countdown timer that's started, paused, and reset by the user.
+#[derive(Debug)]
+pub(crate) struct CountdownTimer {
+    duration: Duration,
+    colors: Colors,
+    finished_colors: Colors,
+    phase: RefCell<TimerPhase>,
+}
+
+impl CountdownTimer {
+    /// Construct a new timer that counts down from the given duration.
+    pub(crate) fn new(duration: Duration, style: &TimerStyle) -> Self {
+        Self {
+            duration,
+            colors: style.colors.clone(),
+            finished_colors: style.finished_colors.clone(),
+            phase: Default::default(),
+        }
+    }
+
+    fn remaining(&self, now: Instant) -> Duration {
+        match *self.phase.borrow() {
+            TimerPhase::Idle => self.duration,
+            TimerPhase::Running { started_at, remaining } => {
+                remaining.saturating_sub(now.saturating_duration_since(started_at))
+            }
+            TimerPhase::Paused { remaining } => remaining,
+            TimerPhase::Finished { .. } => Duration::ZERO,
+        }
+    }
+
+    fn update(&self, now: Instant) {
+        let mut phase = self.phase.borrow_mut();
+        if let TimerPhase::Running { started_at, remaining } = *phase {
+            if now.saturating_duration_since(started_at) >= remaining {
+                *phase = TimerPhase::Finished { at: started_at + remaining };
+            }
+        }
+    }
+
+    /// Start the timer if it's not running or pause it if it is.
+    fn toggle(&self, now: Instant) -> bool {
+        self.update(now);
+        let remaining = self.remaining(now);
+        let mut phase = self.phase.borrow_mut();
+        *phase = match *phase {
+            TimerPhase::Idle | TimerPhase::Paused { .. } => TimerPhase::Running { started_at: now, remaining },
+            TimerPhase::Running { .. } => TimerPhase::Paused { remaining },
+            TimerPhase::Finished { .. } => return false,
+        };
+        true
+    }
+
+    fn state(&self, now: Instant) -> RenderOnDemandState {
+        self.update(now);
+        match *self.phase.borrow() {
+            TimerPhase::Idle => RenderOnDemandState::NotStarted,
+            TimerPhase::Running { .. } => RenderOnDemandState::Rendering,
+            TimerPhase::Paused { .. } => RenderOnDemandState::Rendered,
+            // Keep rendering while flashing so every step in it gets drawn.
+            TimerPhase::Finished { at } if now.saturating_duration_since(at) < FLASH_DURATION => {
+                RenderOnDemandState::Rendering
+            }
+            TimerPhase::Finished { .. } => RenderOnDemandState::Rendered,
+        }
+    }
+
+    fn visible(&self, now: Instant) -> bool {
+        match *self.phase.borrow() {
+            TimerPhase::Finished { at } => {
+                let elapsed = now.saturating_duration_since(at);
+                elapsed >= FLASH_DURATION || (elapsed.as_millis() / FLASH_INTERVAL.as_millis()) & 1 == 0
+            }
+            _ => true,
+        }
+    }
+
+    fn render_operations_at(&self, now: Instant, dimensions: &WindowSize) -> Vec<RenderOperation> {
+        self.update(now);
+        let text = format_remaining(self.remaining(now));
+        let (colors, status) = match *self.phase.borrow() {
+            TimerPhase::Finished { .. } => (&self.finished_colors, ""),
+            TimerPhase::Paused { .. } => (&self.colors, "paused"),
+            _ => (&self.colors, ""),
+        };
+        let rows = match big_text(&text) {
+            Some(rows) if rows.iter().all(|row| row.width() <= dimensions.columns as usize) => rows,
+            _ => vec![text],
+        };
+        let visible = self.visible(now);
+        let alignment = Alignment::Center { minimum_size: 0, minimum_margin: Margin::Fixed(0) };
+        let mut operations = Vec::new();
+        for row in rows.into_iter().chain([status.to_string()]) {
+            if visible && !row.is_empty() {
+                let text = WeightedText::from(StyledText::new(row, TextStyle::default().colors(colors.clone())));
+                operations.push(RenderOperation::RenderText { line: vec![text].into(), alignment: alignment.clone() });
+            }
+            operations.push(RenderOperation::RenderLineBreak);
+        }
+        operations
+    }
+}
+
+impl AsRenderOperations for CountdownTimer {
+    fn as_render_operations(&self, dimensions: &WindowSize) -> Vec<RenderOperation> {
+        self.render_operations_at(Instant::now(), dimensions)
+    }
+}
+
+impl RenderOnDemand for CountdownTimer {
+    fn start_render(&self) -> bool {
+        self.toggle(Instant::now())
+    }
+
+    fn renders_automatically(&self) -> bool {
+        false
+    }
+
+    fn runs_code(&self) -> bool {
+        false
+    }
+
+    fn render_step(&self) -> Option<u32> {
+        None
+    }
+
+    fn poll_state(&self) -> RenderOnDemandState {
+        self.state(Instant::now())
+    }
+
+    fn abort_render(&self) -> bool {
+        // A running timer shouldn't prevent exiting the presentation.
+        false
+    }
+
+    fn reset_render(&self) -> bool {
+        let previous = self.phase.replace(TimerPhase::Idle);
+        !matches!(previous, TimerPhase::Idle)
+    }
+
+    fn render_error(&self) -> Option<String> {
+        None
+    }
+
+    fn scroll(&self, _lines: isize) -> bool {
+        false
+    }
+
+    fn toggle_follow(&self) -> bool {
+        false
+    }
+
+    fn captures_input(&self) -> bool {
+        false
+    }
+
+    fn send_input(&self, _input: &WidgetInput) -> bool {
+        false
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+enum TimerPhase {
+    #[default]
+    Idle,
+    Running {
+        started_at: Instant,
+        remaining: Duration,
+    },
+    Paused {
+        remaining: Duration,
+    },
+    Finished {
+        at: Instant,
+    },
+}
+
+/// Parse a duration like `5m`, `90s`, `1h30m`, or `45`, the latter being a number of seconds.
+pub(crate) fn parse_duration(text: &str) -> Option<Duration> {
+    let text = text.trim();
+    if let Ok(seconds) = text.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let mut seconds = 0;
+    let mut rest = text;
+    while !rest.is_empty() {
+        let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+        let value: u64 = rest[..digits].parse().ok()?;
+        let multiplier = match rest[digits..].chars().next()? {
+            'h' => 3600,
+            'm' => 60,
+            's' => 1,
+            _ => return None,
+        };
+        seconds += value * multiplier;
+        rest = rest[digits + 1..].trim_start();
+    }
+    (!text.is_empty()).then(|| Duration::from_secs(seconds))
+}
+
+fn format_remaining(remaining: Duration) -> String {
+    // Round up so the timer only shows zero once it's done.
+    let seconds = remaining.as_millis().div_ceil(1000);
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    match hours {
+        0 => format!("{minutes:02}:{seconds:02}"),
+        _ => format!("{hours}:{minutes:02}:{seconds:02}"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    fn timer(seconds: u64) -> CountdownTimer {
+        CountdownTimer::new(Duration::from_secs(seconds), &Default::default())
+    }
+
+    #[rstest]
+    #[case::seconds("90s", Some(90))]
+    #[case::minutes("5m", Some(300))]
+    #[case::combined("1h 30m 15s", Some(5415))]
+    #[case::bare("45", Some(45))]
+    #[case::unknown_unit("5d", None)]
+    #[case::no_number("m", None)]
+    #[case::no_unit("5m30", None)]
+    #[case::empty("", None)]
+    fn parse(#[case] input: &str, #[case] expected: Option<u64>) {
+        assert_eq!(parse_duration(input), expected.map(Duration::from_secs));
+    }
+
+    #[rstest]
+    #[case::zero(0, "00:00")]
+    #[case::partial_second(1, "00:01")]
+    #[case::minutes(299_500, "05:00")]
+    #[case::hours(3_661_000, "1:01:01")]
+    fn format(#[case] milliseconds: u64, #[case] expected: &str) {
+        assert_eq!(format_remaining(Duration::from_millis(milliseconds)), expected);
+    }
+
+    fn rendered_texts(timer: &CountdownTimer, columns: u16) -> Vec<String> {
+        let dimensions = WindowSize { rows: 10, columns, width: 0, height: 0, has_pixels: false };
+        timer
+            .render_operations_at(Instant::now(), &dimensions)
+            .into_iter()
+            .filter_map(|operation| match operation {
+                RenderOperation::RenderText { line, .. } => {
+                    Some(line.iter_texts().map(|text| text.text.text.clone()).collect::<String>())
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn big_digits() {
+        let texts = rendered_texts(&timer(300), 80);
+        assert_eq!(texts, big_text("05:00").expect("no rows"));
+    }
+
+    #[test]
+    fn start_pause_resume() {
+        let timer = timer(10);
+        let start = Instant::now();
+        assert!(matches!(timer.state(start), RenderOnDemandState::NotStarted));
+
+        assert!(timer.toggle(start));
+        assert!(matches!(timer.state(start), RenderOnDemandState::Rendering));
+        assert_eq!(timer.remaining(start + Duration::from_secs(3)), Duration::from_secs(7));
+
+        assert!(timer.toggle(start + Duration::from_secs(3)));
+        assert!(matches!(timer.state(start + Duration::from_secs(60)), RenderOnDemandState::Rendered));
+        assert_eq!(timer.remaining(start + Duration::from_secs(60)), Duration::from_secs(7));
+
+        let resumed = start + Duration::from_secs(60);
+        assert!(timer.toggle(resumed));
+        assert_eq!(timer.remaining(resumed + Duration::from_secs(2)), Duration::from_secs(5));
+    }
+
+    #[test]
+    fn finish() {
+        let timer = timer(10);
+        let start = Instant::now();
+        timer.toggle(start);
+
+        let done = start + Duration::from_secs(10);
+        assert!(matches!(timer.state(done), RenderOnDemandState::Rendering));
+        assert_eq!(timer.remaining(done), Duration::ZERO);
+        assert!(timer.visible(done));
+        assert!(!timer.visible(done + FLASH_INTERVAL));
+        assert!(matches!(timer.state(done + FLASH_DURATION), RenderOnDemandState::Rendered));
+        assert!(timer.visible(done + FLASH_DURATION));
+        assert!(!timer.toggle(done + FLASH_DURATION));
+    }
+
+    #[test]
+    fn reset() {
+        let timer = timer(10);
+        assert!(!timer.reset_render());
+        timer.toggle(Instant::now());
+        assert!(timer.reset_render());
+        assert!(matches!(timer.state(Instant::now()), RenderOnDemandState::NotStarted));
+    }
+
+    #[test]
+    fn narrow_terminal() {
+        let texts = rendered_texts(&timer(300), 10);
+        assert_eq!(texts, &["05:00"]);
+    }
+}
@@ -0,0 +1,195 @@
+use crate::{
+    input::source::WidgetInput,
+    presentation::{AsRenderOperations, RenderOnDemand, RenderOnDemandState, RenderOperation},
+    render::properties::WindowSize,
+};
+use std::{cell::Cell, time::Instant};
+
+/// A block of text that's revealed one character at a time once it becomes visible.
+///
+/// Everything in the block is displayed at once until it starts, like when exporting a presentation.
+#[derive(Debug)]
+pub(crate) struct Typewriter {
+    operations: Vec<RenderOperation>,
+    characters_per_second: u32,
+    characters: usize,
+    started_at: Cell<Option<Instant>>,
+}
+
+impl Typewriter {
+    /// Construct a new typewriter that reveals the text in the given operations.
+    pub(crate) fn new(operations: Vec<RenderOperation>, characters_per_second: u32) -> Self {
+        let characters = operations
+            .iter()
+            .map(|operation| match operation {
+                RenderOperation::RenderText { line, .. } => line.characters(),
+                _ => 0,
+            })
+            .sum();
+        Self {
+            operations,
+            characters_per_second: characters_per_second.max(1),
+            characters,
+            started_at: Cell::new(None),
+        }
+    }
+
+    fn revealed(&self, now: Instant) -> usize {
+        match self.started_at.get() {
+            Some(started_at) => {
+                let elapsed = now.saturating_duration_since(started_at);
+                let revealed = elapsed.as_millis() * self.characters_per_second as u128 / 1000;
+                revealed.min(self.characters as u128) as usize
+            }
+            None => self.characters,
+        }
+    }
+
+    fn state(&self, now: Instant) -> RenderOnDemandState {
+        match self.started_at.get() {
+            None => RenderOnDemandState::NotStarted,
+            Some(_) if self.revealed(now) < self.characters => RenderOnDemandState::Rendering,
+            Some(_) => RenderOnDemandState::Rendered,
+        }
+    }
+
+    fn render_operations_at(&self, now: Instant) -> Vec<RenderOperation> {
+        let mut remaining = self.revealed(now);
+        let mut operations = Vec::new();
+        for operation in &self.operations {
+            match operation {
+                RenderOperation::RenderText { line, alignment } => {
+                    let characters = line.characters();
+                    let line = match remaining >= characters {
+                        true => line.clone(),
+                        false => line.typed(remaining),
+                    };
+                    remaining = remaining.saturating_sub(characters);
+                    operations.push(RenderOperation::RenderText { line, alignment: alignment.clone() });
+                }
+                _ => operations.push(operation.clone()),
+            };
+        }
+        operations
+    }
+}
+
+impl AsRenderOperations for Typewriter {
+    fn as_render_operations(&self, _dimensions: &WindowSize) -> Vec<RenderOperation> {
+        self.render_operations_at(Instant::now())
+    }
+}
+
+impl RenderOnDemand for Typewriter {
+    fn start_render(&self) -> bool {
+        if self.started_at.get().is_some() {
+            return false;
+        }
+        self.started_at.set(Some(Instant::now()));
+        true
+    }
+
+    fn renders_automatically(&self) -> bool {
+        true
+    }
+
+    fn runs_code(&self) -> bool {
+        false
+    }
+
+    fn render_step(&self) -> Option<u32> {
+        None
+    }
+
+    fn poll_state(&self) -> RenderOnDemandState {
+        self.state(Instant::now())
+    }
+
+    fn abort_render(&self) -> bool {
+        false
+    }
+
+    fn reset_render(&self) -> bool {
+        self.started_at.take().is_some()
+    }
+
+    fn render_error(&self) -> Option<String> {
+        None
+    }
+
+    fn scroll(&self, _lines: isize) -> bool {
+        false
+    }
+
+    fn toggle_follow(&self) -> bool {
+        false
+    }
+
+    fn captures_input(&self) -> bool {
+        false
+    }
+
+    fn send_input(&self, _input: &WidgetInput) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::markdown::{elements::StyledText, text::WeightedText};
+    use std::time::Duration;
+
+    fn typing_time(characters: u64, characters_per_second: u64) -> Duration {
+        Duration::from_millis(characters * 1000 / characters_per_second)
+    }
+
+    fn texts(operations: &[RenderOperation]) -> Vec<String> {
+        operations
+            .iter()
+            .filter_map(|operation| match operation {
+                RenderOperation::RenderText { line, .. } => {
+                    Some(line.iter_texts().map(|text| text.text.text.as_str()).collect())
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn typewriter() -> Typewriter {
+        let text = |text: &str| RenderOperation::RenderText {
+            line: vec![WeightedText::from(StyledText::from(text))].into(),
+            alignment: Default::default(),
+        };
+        let operations = vec![text("hello"), RenderOperation::RenderLineBreak, text("world")];
+        Typewriter::new(operations, 10)
+    }
+
+    #[test]
+    fn reveal() {
+        let typewriter = typewriter();
+        let start = Instant::now();
+        assert!(matches!(typewriter.state(start), RenderOnDemandState::NotStarted));
+        assert_eq!(texts(&typewriter.render_operations_at(start)), &["hello", "world"]);
+
+        typewriter.started_at.set(Some(start));
+        assert!(matches!(typewriter.state(start), RenderOnDemandState::Rendering));
+        assert_eq!(texts(&typewriter.render_operations_at(start)), &["     ", "     "]);
+        assert_eq!(texts(&typewriter.render_operations_at(start + typing_time(3, 10))), &["hel  ", "     "]);
+        assert_eq!(texts(&typewriter.render_operations_at(start + typing_time(7, 10))), &["hello", "wo   "]);
+
+        let done = start + typing_time(10, 10);
+        assert!(matches!(typewriter.state(done), RenderOnDemandState::Rendered));
+        assert_eq!(texts(&typewriter.render_operations_at(done)), &["hello", "world"]);
+    }
+
+    #[test]
+    fn start_and_reset() {
+        let typewriter = typewriter();
+        assert!(!typewriter.reset_render());
+        assert!(typewriter.start_render());
+        assert!(!typewriter.start_render());
+        assert!(typewriter.reset_render());
+        assert!(matches!(typewriter.poll_state(), RenderOnDemandState::NotStarted));
+    }
+}
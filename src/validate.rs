@@ -0,0 +1,159 @@
+use crate::{
+    builder::{BuildError, ExecutionCache, PresentationBuilder, PresentationBuilderOptions},
+    input::source::WidgetInput,
+    markdown::parse::ParseError,
+    presentation::{Presentation, RenderOnDemand, RenderOnDemandState, RenderOperation},
+    CodeHighlighter, MarkdownParser, PresentationTheme, Resources,
+};
+use std::{fmt, fs, io, path::Path, thread, time::Duration};
+
+/// Executes every piece of code marked for execution in a presentation and reports any that fail.
+pub struct SnippetValidator<'a> {
+    parser: MarkdownParser<'a>,
+    default_theme: &'a PresentationTheme,
+    default_highlighter: CodeHighlighter,
+    resources: Resources,
+    options: PresentationBuilderOptions,
+}
+
+impl<'a> SnippetValidator<'a> {
+    /// Construct a new validator.
+    pub fn new(
+        parser: MarkdownParser<'a>,
+        default_theme: &'a PresentationTheme,
+        default_highlighter: CodeHighlighter,
+        resources: Resources,
+        options: PresentationBuilderOptions,
+    ) -> Self {
+        Self { parser, default_theme, default_highlighter, resources, options }
+    }
+
+    /// Validate all the snippets in the given presentation.
+    ///
+    /// Snippets are executed one at a time and this blocks until all of them finish.
+    pub fn validate(&mut self, presentation_path: &Path) -> Result<Vec<SnippetFailure>, ValidateError> {
+        let content = fs::read_to_string(presentation_path).map_err(ValidateError::Read)?;
+        let elements = self.parser.parse(&content)?;
+        let mut execution_cache = ExecutionCache::default();
+        let presentation = PresentationBuilder::new(
+            self.default_highlighter.clone(),
+            self.default_theme,
+            &mut self.resources,
+            &mut execution_cache,
+            self.options.clone(),
+        )
+        .build(elements)?;
+        Ok(Self::validate_presentation(&presentation))
+    }
+
+    fn validate_presentation(presentation: &Presentation) -> Vec<SnippetFailure> {
+        let mut failures = Vec::new();
+        for (index, slide) in presentation.iter_slides().enumerate() {
+            let operations = slide.iter_chunks().flat_map(|chunk| chunk.iter_operations());
+            for operation in operations {
+                let RenderOperation::RenderOnDemand(operation) = operation else {
+                    continue;
+                };
+                if let Some(message) = Self::run(operation.as_ref()) {
+                    failures.push(SnippetFailure { slide: index + 1, message });
+                }
+            }
+        }
+        failures
+    }
+
+    fn run(operation: &dyn RenderOnDemand) -> Option<String> {
+        if !operation.runs_code() || !operation.start_render() {
+            return None;
+        }
+        // Nobody is going to type anything so let it know there's no input.
+        if operation.captures_input() {
+            operation.send_input(&WidgetInput::EndOfInput);
+        }
+        while !matches!(operation.poll_state(), RenderOnDemandState::Rendered) {
+            thread::sleep(Duration::from_millis(50));
+        }
+        operation.render_error()
+    }
+}
+
+/// A snippet that failed validation.
+#[derive(Clone, Debug)]
+pub struct SnippetFailure {
+    /// The slide the snippet is in, starting at 1.
+    pub slide: usize,
+
+    /// A description of the failure.
+    pub message: String,
+}
+
+impl fmt::Display for SnippetFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut lines = self.message.lines();
+        write!(f, "snippet in slide {} failed: {}", self.slide, lines.next().unwrap_or_default())?;
+        for line in lines {
+            write!(f, "\n    {line}")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ValidateError {
+    #[error("failed to read presentation: {0}")]
+    Read(io::Error),
+
+    #[error("failed to parse presentation: {0}")]
+    Parse(#[from] ParseError),
+
+    #[error("failed to build presentation: {0}")]
+    Build(#[from] BuildError),
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use comrak::Arena;
+
+    fn validate(input: &str) -> Vec<SnippetFailure> {
+        let arena = Arena::new();
+        let elements = MarkdownParser::new(&arena).parse(input).expect("parse failed");
+        let theme = PresentationTheme::default();
+        let mut resources = Resources::new("/tmp");
+        let mut execution_cache = ExecutionCache::default();
+        let presentation = PresentationBuilder::new(
+            CodeHighlighter::new("base16-ocean.dark").unwrap(),
+            &theme,
+            &mut resources,
+            &mut execution_cache,
+            Default::default(),
+        )
+        .build(elements)
+        .expect("build failed");
+        SnippetValidator::validate_presentation(&presentation)
+    }
+
+    #[test]
+    fn failures_are_reported() {
+        let input = r"
+```sh +exec
+echo hi
+```
+
+<!-- end_slide -->
+
+```sh
+exit 1
+```
+
+```sh +exec
+echo bye >&2
+exit 3
+```
+";
+        let failures = validate(input);
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].slide, 2);
+        assert_eq!(failures[0].message, "exit: 3\nbye");
+    }
+}
@@ -0,0 +1,251 @@
+use crate::presentation::PresentationMetadata;
+use std::{
+    collections::BTreeMap,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// The variables that can be used in slide text and footer templates via `{{name}}` placeholders.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Variables(BTreeMap<String, String>);
+
+impl Variables {
+    /// Construct the variables defined by a presentation's front matter, along with the built-in ones.
+    ///
+    /// Any value under `variables` in the front matter that's a string, number, or boolean can be used as a variable,
+    /// as well as the presentation's title, sub-title, author, and date. The date defaults to today's date.
+    pub(crate) fn new(metadata: &PresentationMetadata) -> Self {
+        let mut variables = BTreeMap::new();
+        for (name, value) in &metadata.variables {
+            let value = match value {
+                serde_yaml::Value::String(value) => value.clone(),
+                serde_yaml::Value::Number(value) => value.to_string(),
+                serde_yaml::Value::Bool(value) => value.to_string(),
+                _ => continue,
+            };
+            variables.insert(name.clone(), value);
+        }
+        let fields = [("title", &metadata.title), ("sub_title", &metadata.sub_title), ("author", &metadata.author)];
+        for (name, value) in fields {
+            if let Some(value) = value {
+                variables.insert(name.into(), value.clone());
+            }
+        }
+        let today = current_date();
+        variables.insert("date".into(), metadata.date.clone().unwrap_or_else(|| today.clone()));
+        variables.insert("current_date".into(), today);
+        Self(variables)
+    }
+
+    /// Set the value for a variable.
+    pub(crate) fn insert<S: Into<String>>(&mut self, name: &str, value: S) {
+        self.0.insert(name.into(), value.into());
+    }
+
+    /// Get the value for a variable.
+    pub(crate) fn get(&self, name: &str) -> Option<&str> {
+        self.0.get(name).map(String::as_str)
+    }
+
+    /// Replace every `{{name}}` placeholder in the given text with the value of that variable.
+    ///
+    /// Placeholders for unknown variables are left as they are.
+    pub(crate) fn interpolate(&self, text: &str) -> String {
+        self.interpolate_with_legacy(text, &[])
+    }
+
+    /// Like [Variables::interpolate], but `{name}` placeholders are replaced too for the given variables.
+    ///
+    /// Everything is replaced in a single pass so placeholders within the values of variables are left as they are.
+    pub(crate) fn interpolate_with_legacy(&self, text: &str, legacy: &[&str]) -> String {
+        let mut output = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(start) = rest.find('{') {
+            output.push_str(&rest[..start]);
+            let candidate = &rest[start..];
+            match self.placeholder(candidate, legacy) {
+                Some((length, value)) => {
+                    output.push_str(value);
+                    rest = &candidate[length..];
+                }
+                None => {
+                    output.push('{');
+                    rest = &candidate[1..];
+                }
+            }
+        }
+        output.push_str(rest);
+        output
+    }
+
+    /// Whether the given text has a `{{name}}` placeholder for a variable.
+    pub(crate) fn is_used(text: &str, name: &str) -> bool {
+        text.split("{{").skip(1).any(|rest| rest.split_once("}}").is_some_and(|(inner, _)| inner.trim() == name))
+    }
+
+    /// Get the value for the placeholder at the start of the given text, along with the placeholder's length.
+    fn placeholder(&self, text: &str, legacy: &[&str]) -> Option<(usize, &str)> {
+        if let Some(inner) = text.strip_prefix("{{") {
+            let end = inner.find("}}")?;
+            let value = self.get(inner[..end].trim())?;
+            return Some((end + 4, value));
+        }
+        let inner = text.strip_prefix('{')?;
+        let name = &inner[..inner.find('}')?];
+        let value = legacy.contains(&name).then(|| self.get(name)).flatten()?;
+        Some((name.len() + 2, value))
+    }
+}
+
+/// Get today's local date formatted as `YYYY-MM-DD`.
+fn current_date() -> String {
+    let LocalTime { year, month, day, .. } = local_time();
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// Get the current local time formatted as `HH:MM`.
+pub(crate) fn current_time() -> String {
+    let LocalTime { hour, minute, .. } = local_time();
+    format!("{hour:02}:{minute:02}")
+}
+
+/// Get the number of minutes since the unix epoch, which changes every time the wall clock's minute does.
+pub(crate) fn current_minute() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|duration| duration.as_secs() / 60).unwrap_or(0)
+}
+
+/// A point in time down to the minute.
+struct LocalTime {
+    year: i64,
+    month: u32,
+    day: u32,
+    hour: u32,
+    minute: u32,
+}
+
+#[cfg(unix)]
+fn local_time() -> LocalTime {
+    // SAFETY: `localtime_r` only writes into the `tm` we own and returns null on failure.
+    unsafe {
+        let now = libc::time(std::ptr::null_mut());
+        let mut tm: libc::tm = std::mem::zeroed();
+        if libc::localtime_r(&now, &mut tm).is_null() {
+            return utc_time();
+        }
+        LocalTime {
+            year: i64::from(tm.tm_year) + 1900,
+            month: tm.tm_mon as u32 + 1,
+            day: tm.tm_mday as u32,
+            hour: tm.tm_hour as u32,
+            minute: tm.tm_min as u32,
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn local_time() -> LocalTime {
+    utc_time()
+}
+
+fn utc_time() -> LocalTime {
+    let minutes = current_minute();
+    let (year, month, day) = civil_from_days((minutes / (24 * 60)) as i64);
+    let minutes = minutes % (24 * 60);
+    LocalTime { year, month, day, hour: (minutes / 60) as u32, minute: (minutes % 60) as u32 }
+}
+
+/// Convert a number of days since the unix epoch into a year, month, and day.
+///
+/// See <https://howardhinnant.github.io/date_algorithms.html#civil_from_days>.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    fn variables() -> Variables {
+        let metadata = r"
+title: hi
+author: bob
+venue: Nowhere
+variables:
+  event: RustConf
+  year: 2024
+  featured: true
+  tags: [a, b]";
+        let metadata: PresentationMetadata = serde_yaml::from_str(metadata).expect("invalid metadata");
+        Variables::new(&metadata)
+    }
+
+    #[rstest]
+    #[case::none("hello", "hello")]
+    #[case::single("by {{author}}", "by bob")]
+    #[case::spaces("{{ event }} {{year}}", "RustConf 2024")]
+    #[case::boolean("{{featured}}", "true")]
+    #[case::unknown("{{potato}} {{title}}", "{{potato}} hi")]
+    #[case::not_a_list("{{tags}}", "{{tags}}")]
+    #[case::not_a_variable("{{venue}}", "{{venue}}")]
+    #[case::unclosed("{{author", "{{author")]
+    #[case::single_braces("{author}", "{author}")]
+    fn interpolate(#[case] input: &str, #[case] expected: &str) {
+        assert_eq!(variables().interpolate(input), expected);
+    }
+
+    #[rstest]
+    #[case::legacy("{author} {{event}}", "bob RustConf")]
+    #[case::not_legacy("{event}", "{event}")]
+    #[case::value_placeholders("{{braces}}", "{author}")]
+    fn interpolate_with_legacy(#[case] input: &str, #[case] expected: &str) {
+        let mut variables = variables();
+        variables.insert("braces", "{author}");
+        assert_eq!(variables.interpolate_with_legacy(input, &["author"]), expected);
+    }
+
+    #[rstest]
+    #[case::used("it's {{ clock }}", true)]
+    #[case::other_variable("it's {{clock_time}}", false)]
+    #[case::single_braces("it's {clock}", false)]
+    fn is_used(#[case] text: &str, #[case] expected: bool) {
+        assert_eq!(Variables::is_used(text, "clock"), expected);
+    }
+
+    #[rstest]
+    #[case::front_matter("date: 2024-09-10", "2024-09-10")]
+    #[case::today("title: hi", &current_date())]
+    fn date_variable(#[case] metadata: &str, #[case] expected: &str) {
+        let metadata: PresentationMetadata = serde_yaml::from_str(metadata).expect("invalid metadata");
+        assert_eq!(Variables::new(&metadata).interpolate("{{date}}"), expected);
+    }
+
+    #[test]
+    fn current_date_variable() {
+        let date = variables().interpolate("{{current_date}}");
+        assert_eq!(date.len(), 10, "unexpected date: {date}");
+    }
+
+    #[test]
+    fn time() {
+        let time = current_time();
+        assert_eq!(time.len(), 5, "unexpected time: {time}");
+        assert_eq!(&time[2..3], ":");
+    }
+
+    #[rstest]
+    #[case::epoch(0, (1970, 1, 1))]
+    #[case::leap_day(19782, (2024, 2, 29))]
+    #[case::end_of_year(20088, (2024, 12, 31))]
+    fn days_to_date(#[case] days: i64, #[case] expected: (i64, u32, u32)) {
+        assert_eq!(civil_from_days(days), expected);
+    }
+}